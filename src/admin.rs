@@ -0,0 +1,282 @@
+use crate::config::LoadOptions;
+use crate::control::{self, ControlCommand, ControlRequest, ControlResponse};
+use crate::engine::FlareSync;
+use crate::errors::FlareSyncError;
+use crate::history::{self, HistoryEntry};
+use crate::logging::EVENTS_TARGET;
+use crate::status::RuntimeStatus;
+use axum::extract::{Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+
+const DEFAULT_HISTORY_LIMIT: usize = 20;
+pub const MIN_TOKEN_LENGTH: usize = 16;
+
+/// Where the admin API listens and the bearer token every request must carry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdminConfig {
+    pub bind_address: SocketAddr,
+    pub token: String,
+}
+
+#[derive(Clone)]
+struct AdminState {
+    engine: FlareSync,
+    load_options: LoadOptions,
+    token: Arc<str>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DomainQuery {
+    domain: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    domain: Option<String>,
+    limit: Option<usize>,
+}
+
+/// Serves the admin API on `listener` until the task is dropped.
+pub async fn serve(
+    listener: TcpListener,
+    engine: FlareSync,
+    load_options: LoadOptions,
+    token: &str,
+) -> Result<(), FlareSyncError> {
+    let state = AdminState {
+        engine,
+        load_options,
+        token: Arc::from(token),
+    };
+    let app = Router::new()
+        .route("/status", get(status))
+        .route("/history", get(history))
+        .route("/sync", post(sync))
+        .route("/pause", post(pause))
+        .route("/resume", post(resume))
+        .route("/reload", post(reload))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authorize))
+        .with_state(state);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn authorize(State(state): State<AdminState>, request: Request, next: Next) -> Response {
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match presented {
+        Some(token) if constant_time_eq(token.as_bytes(), state.token.as_bytes()) => {
+            next.run(request).await
+        }
+        _ => {
+            warn!(
+                "Rejected admin API request to {} without a valid token",
+                request.uri().path()
+            );
+            (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+                Json(ControlResponse {
+                    ok: false,
+                    message: Some("Missing or invalid bearer token".to_string()),
+                    status: None,
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+async fn status(State(state): State<AdminState>) -> Json<RuntimeStatus> {
+    Json(state.engine.status().borrow().clone())
+}
+
+async fn history(
+    State(state): State<AdminState>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<HistoryEntry>>, (StatusCode, Json<ControlResponse>)> {
+    let config = state.engine.config();
+    let entries = history::load(&config).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ControlResponse {
+                ok: false,
+                message: Some(format!("Failed to read history: {}", e)),
+                status: None,
+            }),
+        )
+    })?;
+    let limit = query.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+    Ok(Json(
+        history::query(&entries, query.domain.as_deref(), limit)
+            .into_iter()
+            .cloned()
+            .collect(),
+    ))
+}
+
+async fn sync(State(state): State<AdminState>) -> (StatusCode, Json<ControlResponse>) {
+    run_command(&state, ControlCommand::SyncNow.into())
+}
+
+async fn pause(
+    State(state): State<AdminState>,
+    Query(query): Query<DomainQuery>,
+) -> (StatusCode, Json<ControlResponse>) {
+    run_command(
+        &state,
+        ControlRequest {
+            command: ControlCommand::Pause,
+            domain: query.domain,
+        },
+    )
+}
+
+async fn resume(
+    State(state): State<AdminState>,
+    Query(query): Query<DomainQuery>,
+) -> (StatusCode, Json<ControlResponse>) {
+    run_command(
+        &state,
+        ControlRequest {
+            command: ControlCommand::Resume,
+            domain: query.domain,
+        },
+    )
+}
+
+async fn reload(State(state): State<AdminState>) -> (StatusCode, Json<ControlResponse>) {
+    run_command(&state, ControlCommand::ReloadConfig.into())
+}
+
+fn run_command(state: &AdminState, request: ControlRequest) -> (StatusCode, Json<ControlResponse>) {
+    info!(target: EVENTS_TARGET, "Admin API command received: {}", request);
+    let response = control::execute(&state.engine, &state.load_options, &request);
+    let code = if response.ok {
+        StatusCode::OK
+    } else {
+        StatusCode::CONFLICT
+    };
+    (code, Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[tokio::test]
+    async fn test_admin_api_requires_token_and_controls_engine() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let test_dir = std::env::temp_dir().join(format!(
+            "flaresync_admin_test_{}_{}",
+            std::process::id(),
+            unique
+        ));
+        let history_path = test_dir.join("history.jsonl");
+        history::append(
+            &history_path,
+            &HistoryEntry::updated("example.com", "203.0.113.1", "203.0.113.2"),
+        )
+        .unwrap();
+        let config = Config::builder()
+            .api_token("test_token")
+            .zone("test_zone_id")
+            .domains(["example.com"])
+            .status_file_path(test_dir.join("status.json"))
+            .history_file_path(&history_path)
+            .build()
+            .unwrap();
+        let engine = FlareSync::new(config).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let token = "0123456789abcdef";
+        tokio::spawn(serve(
+            listener,
+            engine.clone(),
+            LoadOptions::default(),
+            token,
+        ));
+
+        let client = reqwest::Client::new();
+        let response = client.get(format!("{}/status", base)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let response = client
+            .get(format!("{}/status", base))
+            .bearer_auth("wrong")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let status: RuntimeStatus = client
+            .get(format!("{}/status", base))
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert!(!status.paused);
+
+        let history: Vec<HistoryEntry> = client
+            .get(format!("{}/history?domain=example.com&limit=5", base))
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].new_ip, "203.0.113.2");
+
+        let response = client
+            .post(format!("{}/pause", base))
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert!(engine.is_paused());
+        let response = client
+            .post(format!("{}/sync", base))
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CONFLICT);
+
+        let response = client
+            .post(format!("{}/pause?domain=example.com", base))
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert!(engine.paused_domains().contains("example.com"));
+
+        let _ = std::fs::remove_dir_all(test_dir);
+    }
+}
@@ -0,0 +1,322 @@
+use crate::cloudflare::DnsRecord;
+use crate::errors::FlareSyncError;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt;
+
+const SUPPORTED_TYPES: [&str; 4] = ["A", "AAAA", "CNAME", "TXT"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DesiredRecord {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub content: String,
+    #[serde(default = "auto_ttl")]
+    pub ttl: u32,
+    #[serde(default)]
+    pub proxied: bool,
+}
+
+fn auto_ttl() -> u32 {
+    1
+}
+
+impl DesiredRecord {
+    pub fn to_record(&self, id: &str) -> DnsRecord {
+        DnsRecord {
+            id: id.to_string(),
+            name: self.name.clone(),
+            content: self.content.clone(),
+            record_type: self.record_type.clone(),
+            proxied: self.proxied,
+            ttl: self.ttl,
+            modified_on: None,
+        }
+    }
+
+    fn normalized(mut self) -> Self {
+        self.name = self.name.trim_end_matches('.').to_ascii_lowercase();
+        self.record_type = self.record_type.to_ascii_uppercase();
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum RecordChange {
+    Create(DesiredRecord),
+    Update {
+        current: DnsRecord,
+        desired: DesiredRecord,
+    },
+}
+
+impl fmt::Display for RecordChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordChange::Create(desired) => write!(
+                f,
+                "+ {} {} {} ttl={} proxied={}",
+                desired.record_type, desired.name, desired.content, desired.ttl, desired.proxied
+            ),
+            RecordChange::Update { current, desired } => write!(
+                f,
+                "~ {} {} {} ttl={} proxied={} -> {} ttl={} proxied={}",
+                desired.record_type,
+                desired.name,
+                current.content,
+                current.ttl,
+                current.proxied,
+                desired.content,
+                desired.ttl,
+                desired.proxied
+            ),
+        }
+    }
+}
+
+pub fn parse_json(input: &str) -> Result<Vec<DesiredRecord>, FlareSyncError> {
+    let records: Vec<DesiredRecord> = serde_json::from_str(input)?;
+    Ok(records.into_iter().map(DesiredRecord::normalized).collect())
+}
+
+fn absolute_name(name: &str, origin: &str) -> String {
+    match name {
+        "@" => origin.to_string(),
+        name if name.ends_with('.') => name.trim_end_matches('.').to_string(),
+        name if origin.is_empty() => name.to_string(),
+        name => format!("{}.{}", name, origin),
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Parses A, AAAA, CNAME, and TXT records from a BIND zone file. Other record types and
+/// parenthesized multi-line records (such as the SOA) are skipped.
+pub fn parse_zone_file(input: &str) -> Result<Vec<DesiredRecord>, FlareSyncError> {
+    let mut origin = String::new();
+    let mut default_ttl = auto_ttl();
+    let mut previous_name: Option<String> = None;
+    let mut records = Vec::new();
+    let mut in_parens = false;
+
+    for (index, raw_line) in input.lines().enumerate() {
+        let proxied = raw_line.contains("cf-proxied:true");
+        let line = strip_comment(raw_line);
+        if in_parens {
+            in_parens = !line.contains(')');
+            continue;
+        }
+        if line.contains('(') {
+            in_parens = !line.contains(')');
+            continue;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let invalid = |reason: &str| {
+            FlareSyncError::Config(format!("Zone file line {}: {}", index + 1, reason))
+        };
+        let mut tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens[0] {
+            "$ORIGIN" => {
+                let value = tokens
+                    .get(1)
+                    .ok_or_else(|| invalid("$ORIGIN needs a name"))?;
+                origin = value.trim_end_matches('.').to_ascii_lowercase();
+                continue;
+            }
+            "$TTL" => {
+                let value = tokens.get(1).ok_or_else(|| invalid("$TTL needs a value"))?;
+                default_ttl = value.parse().map_err(|_| invalid("invalid $TTL"))?;
+                continue;
+            }
+            _ => {}
+        }
+
+        let name = if line.starts_with([' ', '\t']) {
+            previous_name
+                .clone()
+                .ok_or_else(|| invalid("record without a name"))?
+        } else {
+            absolute_name(&tokens.remove(0).to_ascii_lowercase(), &origin)
+        };
+        previous_name = Some(name.clone());
+
+        let mut ttl = default_ttl;
+        if let Some(value) = tokens.first().and_then(|token| token.parse::<u32>().ok()) {
+            ttl = value;
+            tokens.remove(0);
+        }
+        if tokens
+            .first()
+            .is_some_and(|token| token.eq_ignore_ascii_case("IN"))
+        {
+            tokens.remove(0);
+        }
+        if tokens.len() < 2 {
+            return Err(invalid("expected a record type and content"));
+        }
+        let record_type = tokens.remove(0).to_ascii_uppercase();
+        if !SUPPORTED_TYPES.contains(&record_type.as_str()) {
+            continue;
+        }
+        let content = tokens.join(" ");
+        let content = match record_type.as_str() {
+            "CNAME" => absolute_name(&content, &origin),
+            "TXT" => content
+                .strip_prefix('"')
+                .and_then(|content| content.strip_suffix('"'))
+                .filter(|content| !content.contains('"'))
+                .map(str::to_string)
+                .unwrap_or(content),
+            _ => content,
+        };
+        records.push(DesiredRecord {
+            name,
+            record_type,
+            content,
+            ttl,
+            proxied,
+        });
+    }
+    Ok(records)
+}
+
+pub fn plan(desired: &[DesiredRecord], existing: &[DnsRecord]) -> Vec<RecordChange> {
+    let mut claimed: HashSet<&str> = HashSet::new();
+    let mut changes = Vec::new();
+    let same_slot = |record: &DnsRecord, desired: &DesiredRecord| {
+        record.name.eq_ignore_ascii_case(&desired.name)
+            && record
+                .record_type
+                .eq_ignore_ascii_case(&desired.record_type)
+    };
+
+    // Exact content matches first, so reordering a multi-value set does not rewrite it.
+    let mut unmatched = Vec::new();
+    for record in desired {
+        let exact = existing.iter().find(|current| {
+            same_slot(current, record)
+                && current.content == record.content
+                && !claimed.contains(current.id.as_str())
+        });
+        match exact {
+            Some(current) => {
+                claimed.insert(&current.id);
+                if current.ttl != record.ttl || current.proxied != record.proxied {
+                    changes.push(RecordChange::Update {
+                        current: current.clone(),
+                        desired: record.clone(),
+                    });
+                }
+            }
+            None => unmatched.push(record),
+        }
+    }
+    for record in unmatched {
+        let reusable = existing
+            .iter()
+            .find(|current| same_slot(current, record) && !claimed.contains(current.id.as_str()));
+        match reusable {
+            Some(current) => {
+                claimed.insert(&current.id);
+                changes.push(RecordChange::Update {
+                    current: current.clone(),
+                    desired: record.clone(),
+                });
+            }
+            None => changes.push(RecordChange::Create(record.clone())),
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_zone_file_resolves_names() {
+        let zone = r#"
+$ORIGIN example.com.
+$TTL 3600
+@	3600	IN	SOA	ns1.example.com. admin.example.com. (
+        2024010101 ; serial
+        7200 3600 1209600 3600 )
+home	1	IN	A	203.0.113.7 ; cf_tags=cf-proxied:true
+	300	IN	AAAA	2001:db8::7
+www.example.com.	IN	CNAME	home
+home	IN	TXT	"v=spf1; -all"
+@	IN	MX	10 mail.example.com.
+"#;
+        let records = parse_zone_file(zone).unwrap();
+        let summary: Vec<(&str, &str, &str, u32, bool)> = records
+            .iter()
+            .map(|record| {
+                (
+                    record.name.as_str(),
+                    record.record_type.as_str(),
+                    record.content.as_str(),
+                    record.ttl,
+                    record.proxied,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("home.example.com", "A", "203.0.113.7", 1, true),
+                ("home.example.com", "AAAA", "2001:db8::7", 300, false),
+                ("www.example.com", "CNAME", "home.example.com", 3600, false),
+                ("home.example.com", "TXT", "v=spf1; -all", 3600, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_creates_and_updates_only_differences() {
+        let current = |id: &str, record_type: &str, content: &str| DnsRecord {
+            id: id.to_string(),
+            name: "home.example.com".to_string(),
+            content: content.to_string(),
+            record_type: record_type.to_string(),
+            proxied: false,
+            ttl: 1,
+            modified_on: None,
+        };
+        let desired = parse_json(
+            r#"[
+                {"name": "home.example.com", "type": "A", "content": "203.0.113.7"},
+                {"name": "home.example.com", "type": "AAAA", "content": "2001:db8::8", "ttl": 300},
+                {"name": "home.example.com", "type": "TXT", "content": "hello"}
+            ]"#,
+        )
+        .unwrap();
+        let existing = [
+            current("1", "A", "203.0.113.7"),
+            current("2", "AAAA", "2001:db8::7"),
+        ];
+
+        let changes = plan(&desired, &existing);
+        assert_eq!(changes.len(), 2);
+        assert!(matches!(
+            &changes[0],
+            RecordChange::Update { current, desired } if current.id == "2" && desired.ttl == 300
+        ));
+        assert!(
+            matches!(&changes[1], RecordChange::Create(desired) if desired.record_type == "TXT")
+        );
+    }
+}
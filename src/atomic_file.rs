@@ -0,0 +1,119 @@
+use crate::errors::FlareSyncError;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Replaces `path` with `contents` through a synced temporary file in the same directory,
+/// so neither readers nor a crash ever leave a partial file behind. Missing parent
+/// directories are created.
+pub fn write(path: &Path, contents: &[u8]) -> Result<(), FlareSyncError> {
+    write_with(path, contents, false)
+}
+
+/// Like [`write`], for a file readable only by the owner.
+pub fn write_private(path: &Path, contents: &[u8]) -> Result<(), FlareSyncError> {
+    write_with(path, contents, true)
+}
+
+fn write_with(path: &Path, contents: &[u8], private: bool) -> Result<(), FlareSyncError> {
+    #[cfg(not(unix))]
+    let _ = private;
+    let parent = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty());
+    if let Some(parent) = parent {
+        fs::create_dir_all(parent)?;
+    }
+
+    let temp_path = temporary_path(path);
+    let write_result = (|| -> io::Result<()> {
+        let mut open_options = OpenOptions::new();
+        open_options.write(true).create_new(true);
+        #[cfg(unix)]
+        if private {
+            open_options.mode(0o600);
+        }
+        let mut temp_file = open_options.open(&temp_path)?;
+        temp_file.write_all(contents)?;
+        temp_file.sync_all()?;
+        drop(temp_file);
+        fs::rename(&temp_path, path)?;
+        sync_dir(parent.unwrap_or(Path::new(".")))
+    })();
+
+    if write_result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    Ok(write_result?)
+}
+
+/// Makes a rename in `dir` durable.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+fn temporary_path(path: &Path) -> PathBuf {
+    let unique = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    let file_name = path
+        .file_name()
+        .and_then(|value| value.to_str())
+        .unwrap_or("flaresync");
+    path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        file_name,
+        std::process::id(),
+        unique
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_write_replaces_file_without_leaving_temporary_files() {
+        let dir =
+            &std::env::temp_dir().join(format!("flaresync_atomic_file_{}", std::process::id()));
+        let _ = fs::remove_dir_all(dir);
+        let path = dir.join("nested").join("state.json");
+
+        write(&path, b"{\"first\":true}").unwrap();
+        write_private(&path, b"{\"second\":true}").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"{\"second\":true}");
+        #[cfg(unix)]
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        let leftovers = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .ends_with(".tmp")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+
+        fs::create_dir_all(dir.join("taken")).unwrap();
+        assert!(write(&dir.join("taken"), b"{}").is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,1337 @@
+use crate::atomic_file;
+use crate::cloudflare::{self, sanitize_filename_component, DnsRecord};
+use crate::config::Config;
+#[cfg(feature = "sqlite")]
+use crate::database::Database;
+use crate::encryption::{self, EncryptionKey};
+use crate::errors::FlareSyncError;
+use crate::gzip;
+use crate::hashing;
+use crate::journal::{self, JournalEntry};
+use crate::logging::EVENTS_TARGET;
+use crate::s3::{self, S3Config};
+use chrono::{Local, NaiveDateTime};
+use reqwest::Client as ReqwestClient;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::Hash;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub const DEFAULT_BACKUP_DIR: &str = "backups";
+const TIMESTAMP_LEN: usize = "20240101_120000_000000000".len();
+const TIMESTAMP_FORMAT: &str = "%Y%m%d_%H%M%S_%f";
+
+const FORMAT_NAME: &str = "flaresync-backup";
+/// The backup format this build writes. Version 1 files are a bare record without the
+/// envelope; version 2 wraps the record in [`BackupDocument`]; version 3 adds its checksum,
+/// which every later version requires, and version 4 the zone.
+pub const FORMAT_VERSION: u64 = 4;
+
+/// Whether a snapshot holds a record as it was before FlareSync changed it, which is what
+/// restores use, or as Cloudflare returned it after the change.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotKind {
+    #[default]
+    Before,
+    After,
+}
+
+impl SnapshotKind {
+    const ALL: [SnapshotKind; 2] = [SnapshotKind::Before, SnapshotKind::After];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SnapshotKind::Before => "before",
+            SnapshotKind::After => "after",
+        }
+    }
+
+    fn suffix(self, compressed: bool, encrypted: bool) -> String {
+        let base = match self {
+            SnapshotKind::Before => "_backup.json",
+            SnapshotKind::After => "_applied.json",
+        };
+        format!(
+            "{}{}{}",
+            base,
+            if compressed { ".gz" } else { "" },
+            if encrypted { ".enc" } else { "" }
+        )
+    }
+}
+
+/// The zone a backed-up record belongs to, so the backup can be restored even after the
+/// configuration changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZoneInfo {
+    pub id: String,
+    /// Unknown when the API token cannot read the zone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// The contents of a backup file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupDocument {
+    pub format: String,
+    pub version: u64,
+    #[serde(default)]
+    pub kind: SnapshotKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone: Option<ZoneInfo>,
+    pub record: DnsRecord,
+    /// The SHA-256 of the document as written, without this field, or its HMAC-SHA256 under
+    /// the backup encryption key when one is configured. Backups written before version 3
+    /// have none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+}
+
+impl BackupDocument {
+    pub fn new(
+        record: &DnsRecord,
+        kind: SnapshotKind,
+        zone: Option<&ZoneInfo>,
+        key: Option<&EncryptionKey>,
+    ) -> Self {
+        let mut document = Self {
+            format: FORMAT_NAME.to_string(),
+            version: FORMAT_VERSION,
+            kind,
+            zone: zone.cloned(),
+            record: record.clone(),
+            checksum: None,
+        };
+        let value = serde_json::to_value(&document).expect("backup documents serialize");
+        document.checksum = Some(checksum(&value, key));
+        document
+    }
+
+    /// Parses a backup of any format version up to [`FORMAT_VERSION`], upgrading older
+    /// ones one version at a time. A backup that does not match its checksum, or lacks one
+    /// from version 3 on, is an error. `key` verifies checksums written with encryption.
+    pub fn from_json(json: &[u8], key: Option<&EncryptionKey>) -> Result<Self, FlareSyncError> {
+        Self::from_value(serde_json::from_slice(json)?, key)
+    }
+
+    pub(crate) fn from_value(
+        mut value: Value,
+        key: Option<&EncryptionKey>,
+    ) -> Result<Self, FlareSyncError> {
+        // The checksum covers the document as written, so it is checked before upgrading.
+        verify_checksum(&value, key)?;
+        loop {
+            let version = match value.get("format") {
+                Some(format) if format != FORMAT_NAME => {
+                    return Err(FlareSyncError::Config(format!(
+                        "Not a FlareSync backup (format {})",
+                        format
+                    )))
+                }
+                Some(_) => value
+                    .get("version")
+                    .and_then(Value::as_u64)
+                    .ok_or_else(|| {
+                        FlareSyncError::Config("Backup has no format version".to_string())
+                    })?,
+                None => 1,
+            };
+            value = match version {
+                1 => serde_json::json!({
+                    "format": FORMAT_NAME,
+                    "version": 2,
+                    "record": value,
+                }),
+                2 | 3 => {
+                    value["version"] = (version + 1).into();
+                    value
+                }
+                FORMAT_VERSION => return Ok(serde_json::from_value(value)?),
+                _ => {
+                    return Err(FlareSyncError::Config(format!(
+                        "Backup format version {} is newer than this FlareSync supports ({})",
+                        version, FORMAT_VERSION
+                    )))
+                }
+            };
+        }
+    }
+}
+
+const SHA256_PREFIX: &str = "sha256:";
+const HMAC_PREFIX: &str = "hmac-sha256:";
+
+/// The checksum of a backup document without its `checksum` field, as compact JSON with
+/// sorted keys: an HMAC-SHA256 under `key` if given, so that only the key holder can
+/// write a matching checksum, or else a SHA-256.
+pub(crate) fn checksum(document: &Value, key: Option<&EncryptionKey>) -> String {
+    let mut unsigned = document.clone();
+    if let Some(object) = unsigned.as_object_mut() {
+        object.remove("checksum");
+    }
+    let json = serde_json::to_vec(&unsigned).expect("JSON values serialize");
+    match key {
+        Some(key) => format!("{}{}", HMAC_PREFIX, hashing::hex(&key.mac(&json))),
+        None => format!("{}{}", SHA256_PREFIX, hashing::sha256(&json)),
+    }
+}
+
+fn verify_checksum(document: &Value, key: Option<&EncryptionKey>) -> Result<(), FlareSyncError> {
+    let expected = match document.get("checksum") {
+        Some(expected) => expected.as_str().unwrap_or_default(),
+        None => {
+            let version = document
+                .get("format")
+                .and(document.get("version"))
+                .and_then(Value::as_u64);
+            return match version {
+                Some(version) if version >= 3 => Err(FlareSyncError::Config(format!(
+                    "Backup format version {} has no checksum; the backup was modified",
+                    version
+                ))),
+                _ => Ok(()),
+            };
+        }
+    };
+    let actual = if expected.starts_with(HMAC_PREFIX) {
+        let key = key.ok_or_else(|| {
+            FlareSyncError::Config(
+                "Backup checksum needs the encryption key; set BACKUP_ENCRYPTION_KEY or BACKUP_ENCRYPTION_KEY_FILE"
+                    .to_string(),
+            )
+        })?;
+        checksum(document, Some(key))
+    } else {
+        checksum(document, None)
+    };
+    if expected != actual {
+        return Err(FlareSyncError::Config(
+            "Backup checksum does not match; the backup is corrupted or was modified".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// How snapshots are stored in the backup directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackupMode {
+    /// One timestamped file per snapshot.
+    #[default]
+    Files,
+    /// One line per snapshot, appended to a journal per record.
+    Journal,
+}
+
+impl BackupMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BackupMode::Files => "files",
+            BackupMode::Journal => "journal",
+        }
+    }
+}
+
+impl FromStr for BackupMode {
+    type Err = FlareSyncError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "files" => Ok(BackupMode::Files),
+            "journal" => Ok(BackupMode::Journal),
+            _ => Err(FlareSyncError::Config(
+                "BACKUP_MODE must be files or journal".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BackupRetention {
+    pub keep_last: Option<usize>,
+    pub max_age: Option<Duration>,
+}
+
+impl BackupRetention {
+    pub fn is_enabled(&self) -> bool {
+        self.keep_last.is_some() || self.max_age.is_some()
+    }
+}
+
+/// Where record backups go. Every code path that changes a record writes its backup
+/// through one of these, so they all honor `BACKUP_DIR`, `BACKUP_COMPRESS`,
+/// `BACKUP_MODE`, `DATABASE_PATH`, the encryption key, and the offsite bucket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupStore {
+    pub dir: PathBuf,
+    pub compress: bool,
+    pub mode: BackupMode,
+    /// Keeps new backups in this SQLite database instead of `dir`.
+    pub database: Option<PathBuf>,
+    /// Encrypts backup files and their offsite copies with this key.
+    pub encryption: Option<EncryptionKey>,
+    pub offsite: Option<S3Config>,
+    /// The zone recorded in each backup.
+    pub zone: Option<ZoneInfo>,
+}
+
+impl BackupStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            compress: false,
+            mode: BackupMode::Files,
+            database: None,
+            encryption: None,
+            offsite: None,
+            zone: None,
+        }
+    }
+
+    pub fn encrypted(mut self, encryption: Option<EncryptionKey>) -> Self {
+        self.encryption = encryption;
+        self
+    }
+
+    pub fn database(mut self, database: Option<PathBuf>) -> Self {
+        self.database = database;
+        self
+    }
+
+    pub fn mode(mut self, mode: BackupMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn compressed(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    pub fn offsite(mut self, offsite: Option<S3Config>) -> Self {
+        self.offsite = offsite;
+        self
+    }
+
+    pub fn zone(mut self, zone: Option<ZoneInfo>) -> Self {
+        self.zone = zone;
+        self
+    }
+
+    /// Looks up the name of the store's zone, if it is not known yet, to record it in
+    /// new backups.
+    pub async fn resolve_zone_name(mut self, client: &ReqwestClient, api_token: &str) -> Self {
+        if let Some(zone) = self.zone.as_mut().filter(|zone| zone.name.is_none()) {
+            zone.name = cloudflare::zone_name(client, api_token, &zone.id).await;
+        }
+        self
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(&config.backup_dir)
+            .compressed(config.backup_compress)
+            .mode(config.backup_mode)
+            .database(config.database_path.clone())
+            .encrypted(config.backup_encryption_key.clone())
+            .offsite(config.offsite_backup.clone())
+            .zone(Some(ZoneInfo {
+                id: config.zone_id.clone(),
+                name: None,
+            }))
+    }
+
+    /// Backs up `record` before it is changed.
+    pub async fn save(
+        &self,
+        client: &ReqwestClient,
+        record: &DnsRecord,
+    ) -> Result<PathBuf, FlareSyncError> {
+        self.store(client, record, SnapshotKind::Before).await
+    }
+
+    /// Backs up each of `records` as they are now, whether or not a change is pending.
+    pub async fn save_all(
+        &self,
+        client: &ReqwestClient,
+        records: &[DnsRecord],
+    ) -> Result<Vec<PathBuf>, FlareSyncError> {
+        let mut paths = Vec::with_capacity(records.len());
+        for record in records {
+            paths.push(self.save(client, record).await?);
+        }
+        Ok(paths)
+    }
+
+    /// Saves `record` as Cloudflare returned it after a change. A failure is only logged,
+    /// since the change itself already succeeded.
+    pub async fn save_applied(&self, client: &ReqwestClient, record: &DnsRecord) {
+        if let Err(e) = self.store(client, record, SnapshotKind::After).await {
+            warn!(
+                target: EVENTS_TARGET,
+                "Failed to save the updated record of {}: {}", record.name, e
+            );
+        }
+    }
+
+    /// Writes `record` like [`BackupStore::write`], then uploads it to the offsite bucket
+    /// if one is configured, as a backup file even in journal mode. A failed upload is
+    /// logged but does not fail the backup, since the local copy already exists.
+    async fn store(
+        &self,
+        client: &ReqwestClient,
+        record: &DnsRecord,
+        kind: SnapshotKind,
+    ) -> Result<PathBuf, FlareSyncError> {
+        let (path, name, contents) = self.write_file(record, kind)?;
+        if let Some(offsite) = &self.offsite {
+            match s3::upload(client, offsite, &name, contents).await {
+                Ok(key) => info!("Uploaded backup of {} to {}", record.name, key),
+                Err(e) => warn!(
+                    target: EVENTS_TARGET,
+                    "Failed to upload backup of {} to S3 bucket {}: {}",
+                    record.name,
+                    offsite.bucket,
+                    e
+                ),
+            }
+        }
+        Ok(path)
+    }
+
+    /// Writes `record` to a new timestamped file, readable only by the owner and gzipped
+    /// when `compress` is set, or appends it to the record's journal or the database, and
+    /// returns the path written. The directory is created if needed.
+    pub fn write(&self, record: &DnsRecord, kind: SnapshotKind) -> Result<PathBuf, FlareSyncError> {
+        self.write_file(record, kind).map(|(path, _, _)| path)
+    }
+
+    /// Also returns the name and contents of the backup file, for the offsite copy.
+    fn write_file(
+        &self,
+        record: &DnsRecord,
+        kind: SnapshotKind,
+    ) -> Result<(PathBuf, String, Vec<u8>), FlareSyncError> {
+        fs::create_dir_all(&self.dir).map_err(|e| {
+            io_context(
+                e,
+                format!("Failed to create backup directory {}", self.dir.display()),
+            )
+        })?;
+
+        let timestamp = Local::now().format(TIMESTAMP_FORMAT);
+        let safe_name = sanitize_filename_component(&record.name);
+        let name = format!(
+            "{}_{}{}",
+            timestamp,
+            safe_name,
+            kind.suffix(self.compress, self.encryption.is_some())
+        );
+        let key = self.encryption.as_ref();
+        let json = serde_json::to_string_pretty(&BackupDocument::new(
+            record,
+            kind,
+            self.zone.as_ref(),
+            key,
+        ))?;
+        let mut contents = if self.compress {
+            gzip::compress(json.as_bytes())
+        } else {
+            json.into_bytes()
+        };
+        if let Some(key) = key {
+            contents = encryption::encrypt(key, &contents)?;
+        }
+
+        let path = match (&self.database, self.mode) {
+            #[cfg(feature = "sqlite")]
+            (Some(database), _) => {
+                Database::open(database)?.insert_backup(
+                    &JournalEntry::new(record, kind, self.zone.as_ref(), key),
+                    key,
+                )?;
+                database.clone()
+            }
+            (_, BackupMode::Files) => {
+                let path = self.dir.join(&name);
+                atomic_file::write_private(&path, &contents).map_err(|e| match e {
+                    FlareSyncError::Io(e) => {
+                        io_context(e, format!("Failed to write backup {}", path.display()))
+                    }
+                    e => e,
+                })?;
+                path
+            }
+            (_, BackupMode::Journal) => {
+                let path = journal::path(&self.dir, &record.name);
+                let entry = JournalEntry::new(record, kind, self.zone.as_ref(), key);
+                journal::append(&path, &entry, key).map_err(|e| match e {
+                    FlareSyncError::Io(e) => io_context(
+                        e,
+                        format!("Failed to append to backup journal {}", path.display()),
+                    ),
+                    e => e,
+                })?;
+                path
+            }
+        };
+
+        match kind {
+            SnapshotKind::Before => info!("DNS record backup created for {}", record.name),
+            SnapshotKind::After => info!("Updated DNS record saved for {}", record.name),
+        }
+        Ok((path, name, contents))
+    }
+}
+
+fn io_context(err: io::Error, context: String) -> FlareSyncError {
+    FlareSyncError::Io(io::Error::new(err.kind(), format!("{}: {}", context, err)))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupFile {
+    pub path: PathBuf,
+    pub timestamp: String,
+    pub name: String,
+    pub kind: SnapshotKind,
+}
+
+impl BackupFile {
+    fn parse(path: PathBuf) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?;
+        let (kind, stem) = SnapshotKind::ALL.into_iter().find_map(|kind| {
+            [(true, true), (true, false), (false, true), (false, false)]
+                .into_iter()
+                .find_map(|(compressed, encrypted)| {
+                    file_name.strip_suffix(kind.suffix(compressed, encrypted).as_str())
+                })
+                .map(|stem| (kind, stem))
+        })?;
+        if stem.len() <= TIMESTAMP_LEN + 1 || !stem.is_char_boundary(TIMESTAMP_LEN) {
+            return None;
+        }
+        let (timestamp, name) = stem.split_at(TIMESTAMP_LEN);
+        let name = name.strip_prefix('_')?;
+        let is_timestamp = timestamp.chars().all(|c| c.is_ascii_digit() || c == '_');
+        if !is_timestamp {
+            return None;
+        }
+        Some(Self {
+            timestamp: timestamp.to_string(),
+            name: name.to_string(),
+            kind,
+            path,
+        })
+    }
+
+    pub fn is_for(&self, domain: &str) -> bool {
+        self.name == sanitize_filename_component(domain)
+    }
+
+    fn created_at(&self) -> Option<NaiveDateTime> {
+        NaiveDateTime::parse_from_str(&self.timestamp, TIMESTAMP_FORMAT).ok()
+    }
+}
+
+pub fn list(dir: &Path) -> Result<Vec<BackupFile>, FlareSyncError> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut backups = Vec::new();
+    for entry in entries {
+        if let Some(backup) = BackupFile::parse(entry?.path()) {
+            backups.push(backup);
+        }
+    }
+    backups.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    Ok(backups)
+}
+
+/// How one backup fared in [`BackupStore::verify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Integrity {
+    /// The backup matches its checksum.
+    Verified,
+    /// The backup reads correctly but predates checksums.
+    Unchecked,
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupCheck {
+    pub source: String,
+    pub integrity: Integrity,
+}
+
+impl BackupCheck {
+    pub(crate) fn new(source: String, document: Result<BackupDocument, FlareSyncError>) -> Self {
+        let integrity = match document {
+            Ok(document) if document.checksum.is_some() => Integrity::Verified,
+            Ok(_) => Integrity::Unchecked,
+            Err(FlareSyncError::Config(message)) => Integrity::Failed(message),
+            Err(e) => Integrity::Failed(e.to_string()),
+        };
+        Self { source, integrity }
+    }
+}
+
+/// A pre-update backup read back for a restore.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// The backup file, or the journal and when the entry was saved.
+    pub source: String,
+    pub saved_at: Option<NaiveDateTime>,
+    pub zone: Option<ZoneInfo>,
+    pub record: DnsRecord,
+}
+
+impl Snapshot {
+    /// Refuses a backup taken in another zone than `configured_zone_id` unless
+    /// `allow_other_zone` is set, since restoring it writes to a zone this configuration
+    /// does not manage.
+    pub fn check_zone(
+        &self,
+        configured_zone_id: &str,
+        allow_other_zone: bool,
+    ) -> Result<(), FlareSyncError> {
+        match &self.zone {
+            Some(zone) if zone.id != configured_zone_id && !allow_other_zone => {
+                Err(FlareSyncError::Config(format!(
+                    "{} ({}) was backed up from zone {}, not the configured zone {}; pass --allow-other-zone to restore it there",
+                    self.record.name,
+                    self.source,
+                    zone.name.as_deref().unwrap_or(&zone.id),
+                    configured_zone_id
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The newest pre-update backup of `domain` whose record satisfies `matches`, from either
+/// a backup file or the domain's journal.
+fn newest_before(
+    dir: &Path,
+    key: Option<&EncryptionKey>,
+    domain: &str,
+    matches: impl Fn(&DnsRecord) -> bool,
+) -> Result<Option<Snapshot>, FlareSyncError> {
+    let mut newest = None;
+    for backup in list(dir)?.into_iter().rev() {
+        if backup.kind != SnapshotKind::Before || !backup.is_for(domain) {
+            continue;
+        }
+        let document = read(&backup.path, key)?;
+        if matches(&document.record) {
+            newest = Some(Snapshot {
+                source: backup.path.display().to_string(),
+                saved_at: backup.created_at(),
+                zone: document.zone,
+                record: document.record,
+            });
+            break;
+        }
+    }
+
+    let journal_path = journal::path(dir, domain);
+    let entry = journal::read(&journal_path, key)?
+        .into_iter()
+        .rev()
+        .find(|entry| {
+            entry.document.kind == SnapshotKind::Before && matches(&entry.document.record)
+        });
+    if let Some(entry) = entry {
+        let saved_at = entry.created_at();
+        if newest
+            .as_ref()
+            .is_none_or(|snapshot: &Snapshot| saved_at > snapshot.saved_at)
+        {
+            newest = Some(Snapshot {
+                source: format!("{} (saved {})", journal_path.display(), entry.saved_at),
+                saved_at,
+                zone: entry.document.zone,
+                record: entry.document.record,
+            });
+        }
+    }
+    Ok(newest)
+}
+
+impl BackupStore {
+    /// Reads a backup like [`read`], decrypting it with the store's key if needed.
+    pub fn read(&self, path: &Path) -> Result<BackupDocument, FlareSyncError> {
+        read(path, self.encryption.as_ref())
+    }
+
+    /// The newest pre-update backup of `domain` in the backup directory or the database.
+    pub fn latest(&self, domain: &str) -> Result<Option<Snapshot>, FlareSyncError> {
+        self.newest_before(domain, &|_| true)
+    }
+
+    /// The newest backup of `domain` whose record content is `content`.
+    pub fn latest_with_content(
+        &self,
+        domain: &str,
+        content: &str,
+    ) -> Result<Option<DnsRecord>, FlareSyncError> {
+        Ok(self
+            .newest_before(domain, &|record| record.content == content)?
+            .map(|snapshot| snapshot.record))
+    }
+
+    fn newest_before(
+        &self,
+        domain: &str,
+        matches: &dyn Fn(&DnsRecord) -> bool,
+    ) -> Result<Option<Snapshot>, FlareSyncError> {
+        let newest = newest_before(&self.dir, self.encryption.as_ref(), domain, matches)?;
+        #[cfg(feature = "sqlite")]
+        if let Some(database) = &self.database {
+            let stored = Database::open(database)?.newest_before(
+                domain,
+                self.encryption.as_ref(),
+                matches,
+            )?;
+            if let Some(stored) = stored.filter(|stored| {
+                newest
+                    .as_ref()
+                    .is_none_or(|newest| stored.saved_at > newest.saved_at)
+            }) {
+                return Ok(Some(stored));
+            }
+        }
+        Ok(newest)
+    }
+
+    /// Checks every backup file, journal entry, and database backup against its checksum.
+    pub fn verify(&self) -> Result<Vec<BackupCheck>, FlareSyncError> {
+        let mut checks = Vec::new();
+        for backup in list(&self.dir)? {
+            checks.push(BackupCheck::new(
+                backup.path.display().to_string(),
+                read_document(&backup.path, self.encryption.as_ref()),
+            ));
+        }
+        for path in journal::list(&self.dir)? {
+            checks.extend(journal::verify(&path, self.encryption.as_ref())?);
+        }
+        #[cfg(feature = "sqlite")]
+        if let Some(database) = &self.database {
+            checks.extend(Database::open(database)?.verify_backups(self.encryption.as_ref())?);
+        }
+        Ok(checks)
+    }
+
+    /// Deletes the backup files, journal entries, and database backups `retention`
+    /// expires and returns a description of each.
+    pub fn prune(
+        &self,
+        retention: BackupRetention,
+        dry_run: bool,
+    ) -> Result<Vec<String>, FlareSyncError> {
+        if !retention.is_enabled() {
+            return Ok(Vec::new());
+        }
+        let now = Local::now().naive_local();
+        let mut removed = Vec::new();
+        for backup in expired(list(&self.dir)?, retention, now) {
+            if !dry_run {
+                fs::remove_file(&backup.path)?;
+            }
+            removed.push(backup.path.display().to_string());
+        }
+        for path in journal::list(&self.dir)? {
+            removed.extend(journal::prune(
+                &path,
+                self.encryption.as_ref(),
+                retention,
+                now,
+                dry_run,
+            )?);
+        }
+        #[cfg(feature = "sqlite")]
+        if let Some(database) = &self.database {
+            removed.extend(Database::open(database)?.prune_backups(retention, now, dry_run)?);
+        }
+        Ok(removed)
+    }
+}
+
+fn expired(
+    backups: Vec<BackupFile>,
+    retention: BackupRetention,
+    now: NaiveDateTime,
+) -> Vec<BackupFile> {
+    expired_by(
+        backups,
+        |backup| (backup.name.clone(), backup.kind),
+        BackupFile::created_at,
+        retention,
+        now,
+    )
+}
+
+/// The snapshots in `items`, which are ordered oldest first, that `retention` removes.
+/// Snapshots are grouped by `group`, and the newest of each group is always kept.
+pub(crate) fn expired_by<T, K: Eq + Hash>(
+    items: Vec<T>,
+    group: impl Fn(&T) -> K,
+    created_at: impl Fn(&T) -> Option<NaiveDateTime>,
+    retention: BackupRetention,
+    now: NaiveDateTime,
+) -> Vec<T> {
+    let cutoff = retention
+        .max_age
+        .and_then(|max_age| chrono::Duration::from_std(max_age).ok())
+        .and_then(|max_age| now.checked_sub_signed(max_age));
+    let keep_last = retention.keep_last.unwrap_or(usize::MAX).max(1);
+    let mut newer_in_group: HashMap<K, usize> = HashMap::new();
+    let mut expired = vec![false; items.len()];
+    for (index, item) in items.iter().enumerate().rev() {
+        let newer = newer_in_group.entry(group(item)).or_default();
+        let position = *newer;
+        *newer += 1;
+        if position == 0 {
+            continue;
+        }
+        let too_old = match (cutoff, created_at(item)) {
+            (Some(cutoff), Some(created_at)) => created_at < cutoff,
+            _ => false,
+        };
+        expired[index] = position >= keep_last || too_old;
+    }
+    items
+        .into_iter()
+        .zip(expired)
+        .filter_map(|(item, expired)| expired.then_some(item))
+        .collect()
+}
+
+/// Reads a backup of any supported format version, written with or without compression;
+/// gzip data is recognized by its header rather than the file name. For a journal, this
+/// is its newest pre-update entry. Encrypted backups,
+/// also recognized by their header, need `key`. A backup that does not match its checksum
+/// is an error.
+pub fn read(path: &Path, key: Option<&EncryptionKey>) -> Result<BackupDocument, FlareSyncError> {
+    let failed = |e: &dyn std::fmt::Display| {
+        FlareSyncError::Config(format!("Failed to read backup {}: {}", path.display(), e))
+    };
+    if journal::is_journal(path) {
+        return journal::read(path, key)?
+            .into_iter()
+            .rev()
+            .find(|entry| entry.document.kind == SnapshotKind::Before)
+            .map(|entry| entry.document)
+            .ok_or_else(|| failed(&"the journal has no backups"));
+    }
+    read_document(path, key).map_err(|e| match e {
+        FlareSyncError::Config(message) => failed(&message),
+        FlareSyncError::Io(e) => failed(&e),
+        e => failed(&e),
+    })
+}
+
+fn read_document(
+    path: &Path,
+    key: Option<&EncryptionKey>,
+) -> Result<BackupDocument, FlareSyncError> {
+    let mut contents = fs::read(path)?;
+    if encryption::is_encrypted(&contents) {
+        let key = key.ok_or_else(|| {
+            FlareSyncError::Config(
+                "it is encrypted; set BACKUP_ENCRYPTION_KEY or BACKUP_ENCRYPTION_KEY_FILE"
+                    .to_string(),
+            )
+        })?;
+        contents = encryption::decrypt(key, &contents)?;
+    }
+    if gzip::is_gzip(&contents) {
+        contents = gzip::decompress(&contents)?;
+    }
+    BackupDocument::from_json(&contents, key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+
+    fn record(name: &str, content: &str) -> DnsRecord {
+        DnsRecord {
+            id: "1".to_string(),
+            name: name.to_string(),
+            content: content.to_string(),
+            record_type: "A".to_string(),
+            proxied: false,
+            ttl: 120,
+            modified_on: None,
+        }
+    }
+
+    #[test]
+    fn test_store_writes_private_backup_to_configured_dir() {
+        let dir =
+            &std::env::temp_dir().join(format!("flaresync_backup_store_{}", std::process::id()));
+        let _ = fs::remove_dir_all(dir);
+        let record = record("test.com", "127.0.0.1");
+
+        let path = BackupStore::new(dir.join("nested"))
+            .write(&record, SnapshotKind::Before)
+            .unwrap();
+        let backups = list(&dir.join("nested")).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].path, path);
+        assert!(backups[0].is_for("test.com"));
+        assert_eq!(read(&path, None).unwrap().record.id, "1");
+        #[cfg(unix)]
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        let compressed = BackupStore::new(dir.join("nested"))
+            .compressed(true)
+            .write(&record, SnapshotKind::Before)
+            .unwrap();
+        assert!(compressed.to_string_lossy().ends_with("_backup.json.gz"));
+        assert!(gzip::is_gzip(&fs::read(&compressed).unwrap()));
+        assert_eq!(
+            read(&compressed, None).unwrap().record.content,
+            record.content
+        );
+        let backups = list(&dir.join("nested")).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert!(backups.iter().all(|backup| backup.is_for("test.com")));
+
+        let applied = BackupStore::new(dir.join("nested"))
+            .write(&record, SnapshotKind::After)
+            .unwrap();
+        assert!(applied.to_string_lossy().ends_with("_applied.json"));
+        let backups = list(&dir.join("nested")).unwrap();
+        assert_eq!(backups.len(), 3);
+        assert_eq!(backups[2].kind, SnapshotKind::After);
+        assert_eq!(
+            BackupStore::new(dir.join("nested"))
+                .latest("test.com")
+                .unwrap()
+                .unwrap()
+                .source,
+            compressed.display().to_string()
+        );
+
+        let key = EncryptionKey::parse("AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=").unwrap();
+        let store = BackupStore::new(dir.join("nested"))
+            .compressed(true)
+            .encrypted(Some(key));
+        let encrypted = store.write(&record, SnapshotKind::Before).unwrap();
+        assert!(encrypted.to_string_lossy().ends_with("_backup.json.gz.enc"));
+        assert!(encryption::is_encrypted(&fs::read(&encrypted).unwrap()));
+        assert_eq!(list(&dir.join("nested")).unwrap().len(), 4);
+        assert_eq!(
+            store.read(&encrypted).unwrap().record.content,
+            record.content
+        );
+        assert!(read(&encrypted, None)
+            .unwrap_err()
+            .to_string()
+            .contains("it is encrypted"));
+        assert_eq!(
+            store.latest("test.com").unwrap().unwrap().source,
+            encrypted.display().to_string()
+        );
+
+        fs::write(dir.join("file"), "").unwrap();
+        let err = BackupStore::new(dir.join("file"))
+            .write(&record, SnapshotKind::Before)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Failed to create backup directory"));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_restore_keeps_the_zone_and_refuses_another() {
+        let dir =
+            &std::env::temp_dir().join(format!("flaresync_backup_zone_{}", std::process::id()));
+        let _ = fs::remove_dir_all(dir);
+        let record = record("test.com", "127.0.0.1");
+        let zone = ZoneInfo {
+            id: "home_zone_id".to_string(),
+            name: Some("test.com".to_string()),
+        };
+        let store = BackupStore::new(dir).zone(Some(zone.clone()));
+        let path = store.write(&record, SnapshotKind::Before).unwrap();
+        assert_eq!(store.read(&path).unwrap().zone, Some(zone.clone()));
+
+        let snapshot = store.latest("test.com").unwrap().unwrap();
+        assert_eq!(snapshot.zone, Some(zone));
+        assert_eq!(snapshot.record, record);
+        assert!(snapshot.check_zone("home_zone_id", false).is_ok());
+        let err = snapshot.check_zone("office_zone_id", false).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("was backed up from zone test.com, not the configured zone office_zone_id"));
+        assert!(snapshot.check_zone("office_zone_id", true).is_ok());
+
+        let unzoned = Snapshot {
+            zone: None,
+            ..snapshot
+        };
+        assert!(unzoned.check_zone("office_zone_id", false).is_ok());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_save_all_snapshots_every_record() {
+        let dir =
+            &std::env::temp_dir().join(format!("flaresync_backup_all_{}", std::process::id()));
+        let _ = fs::remove_dir_all(dir);
+        let records = [
+            record("home.example.com", "203.0.113.1"),
+            record("www.example.com", "203.0.113.2"),
+        ];
+
+        let store = BackupStore::new(dir);
+        let paths = store
+            .save_all(&ReqwestClient::new(), &records)
+            .await
+            .unwrap();
+        assert_eq!(paths.len(), 2);
+        let backups = list(dir).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert!(backups
+            .iter()
+            .all(|backup| backup.kind == SnapshotKind::Before));
+        for record in &records {
+            assert_eq!(&store.latest(&record.name).unwrap().unwrap().record, record);
+        }
+        assert!(store
+            .save_all(&ReqwestClient::new(), &[])
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(list(dir).unwrap().len(), 2);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_save_applied_keeps_the_written_record_beside_the_backup() {
+        let dir =
+            &std::env::temp_dir().join(format!("flaresync_backup_applied_{}", std::process::id()));
+        let _ = fs::remove_dir_all(dir);
+        let client = ReqwestClient::new();
+        let before = record("test.com", "203.0.113.1");
+        let after = DnsRecord {
+            content: "203.0.113.2".to_string(),
+            modified_on: Some("2024-03-01T12:00:00Z".to_string()),
+            ..before.clone()
+        };
+
+        let store = BackupStore::new(dir.join("files"));
+        store.save(&client, &before).await.unwrap();
+        store.save_applied(&client, &after).await;
+        let backups = list(&dir.join("files")).unwrap();
+        assert_eq!(
+            backups.iter().map(|backup| backup.kind).collect::<Vec<_>>(),
+            [SnapshotKind::Before, SnapshotKind::After]
+        );
+        assert_eq!(store.read(&backups[1].path).unwrap().record, after);
+        assert_eq!(store.latest("test.com").unwrap().unwrap().record, before);
+
+        let journal = BackupStore::new(dir.join("journal")).mode(BackupMode::Journal);
+        journal.save(&client, &before).await.unwrap();
+        journal.save_applied(&client, &after).await;
+        let entries =
+            journal::read(&journal::path(&dir.join("journal"), "test.com"), None).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].document.kind, SnapshotKind::After);
+        assert_eq!(entries[1].document.record, after);
+        assert_eq!(journal.latest("test.com").unwrap().unwrap().record, before);
+
+        fs::write(dir.join("file"), "").unwrap();
+        BackupStore::new(dir.join("file"))
+            .save_applied(&client, &after)
+            .await;
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_document_reads_legacy_and_rejects_newer_formats() {
+        let legacy = br#"{"id":"1","name":"test.com","content":"127.0.0.1","type":"A","proxied":true,"ttl":1}"#;
+        let document = BackupDocument::from_json(legacy, None).unwrap();
+        assert_eq!(document.version, FORMAT_VERSION);
+        assert_eq!(document.kind, SnapshotKind::Before);
+        assert_eq!(document.checksum, None);
+        assert_eq!(document.record.name, "test.com");
+        assert!(document.record.proxied);
+
+        let parse =
+            |value: &Value| BackupDocument::from_json(&serde_json::to_vec(value).unwrap(), None);
+        let mut version_3 = serde_json::to_value(BackupDocument::new(
+            &document.record,
+            SnapshotKind::After,
+            None,
+            None,
+        ))
+        .unwrap();
+        version_3["version"] = 3.into();
+        version_3["checksum"] = checksum(&version_3, None).into();
+        let upgraded = parse(&version_3).unwrap();
+        assert_eq!(upgraded.version, FORMAT_VERSION);
+        assert_eq!(upgraded.zone, None);
+        assert_eq!(upgraded.kind, SnapshotKind::After);
+        assert!(upgraded.checksum.is_some());
+
+        let mut stripped = version_3.clone();
+        stripped.as_object_mut().unwrap().remove("checksum");
+        assert!(parse(&stripped)
+            .unwrap_err()
+            .to_string()
+            .contains("Backup format version 3 has no checksum"));
+        assert!(parse(&serde_json::to_value(&document).unwrap()).is_err());
+
+        let mut newer = version_3.clone();
+        newer["version"] = (FORMAT_VERSION + 1).into();
+        newer["checksum"] = checksum(&newer, None).into();
+        assert!(parse(&newer)
+            .unwrap_err()
+            .to_string()
+            .contains("newer than this FlareSync supports"));
+        assert!(BackupDocument::from_json(br#"{"format":"other","version":1}"#, None).is_err());
+    }
+
+    #[test]
+    fn test_checksum_is_keyed_with_the_encryption_key() {
+        let key = EncryptionKey::parse("AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=").unwrap();
+        let other = EncryptionKey::parse("HxwdHhsaGRgXFhUUExIREA8ODQwLCgkIBwYFBAMCAQA=").unwrap();
+        let record = record("test.com", "127.0.0.1");
+        let document = BackupDocument::new(&record, SnapshotKind::Before, None, Some(&key));
+        assert!(document
+            .checksum
+            .as_deref()
+            .is_some_and(|checksum| checksum.starts_with("hmac-sha256:")));
+        let json = serde_json::to_vec(&document).unwrap();
+
+        assert_eq!(
+            BackupDocument::from_json(&json, Some(&key)).unwrap(),
+            document
+        );
+        assert!(BackupDocument::from_json(&json, None)
+            .unwrap_err()
+            .to_string()
+            .contains("needs the encryption key"));
+        assert!(BackupDocument::from_json(&json, Some(&other))
+            .unwrap_err()
+            .to_string()
+            .contains("checksum does not match"));
+
+        let mut rehashed: Value = serde_json::from_slice(&json).unwrap();
+        rehashed["record"]["content"] = "127.0.0.2".into();
+        rehashed["checksum"] = checksum(&rehashed, None).into();
+        assert!(BackupDocument::from_json(&serde_json::to_vec(&rehashed).unwrap(), None).is_ok());
+        rehashed["checksum"] = checksum(&rehashed, Some(&other)).into();
+        assert!(
+            BackupDocument::from_json(&serde_json::to_vec(&rehashed).unwrap(), Some(&key)).is_err()
+        );
+    }
+
+    #[test]
+    fn test_journal_and_database_backups_are_encrypted_with_a_key() {
+        let dir = std::env::temp_dir().join(format!(
+            "flaresync_encrypted_backups_{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        let key = EncryptionKey::parse("AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=").unwrap();
+        let record = record("test.com", "198.51.100.7");
+
+        let journal = BackupStore::new(&dir)
+            .mode(BackupMode::Journal)
+            .encrypted(Some(key.clone()));
+        let path = journal.write(&record, SnapshotKind::Before).unwrap();
+        let line = fs::read_to_string(&path).unwrap();
+        assert!(line.starts_with("{\"encrypted\":"));
+        assert!(!line.contains("198.51.100.7"));
+        assert_eq!(journal.latest("test.com").unwrap().unwrap().record, record);
+        assert_eq!(read(&path, Some(&key)).unwrap().record, record);
+        assert!(read(&path, None).is_err());
+        assert_eq!(journal.verify().unwrap()[0].integrity, Integrity::Verified);
+
+        #[cfg(feature = "sqlite")]
+        {
+            let database = BackupStore::new(&dir)
+                .database(Some(dir.join("flaresync.db")))
+                .encrypted(Some(key));
+            let path = database.write(&record, SnapshotKind::Before).unwrap();
+            let contents = fs::read(&path).unwrap();
+            assert!(!contents.windows(12).any(|window| window == b"198.51.100.7"));
+            assert!(Database::open(&path)
+                .unwrap()
+                .newest_before("test.com", None, |_| true)
+                .unwrap_err()
+                .to_string()
+                .contains("it is encrypted"));
+            assert_eq!(database.latest("test.com").unwrap().unwrap().record, record);
+            assert!(database
+                .verify()
+                .unwrap()
+                .iter()
+                .all(|check| check.integrity == Integrity::Verified));
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_verify_detects_modified_backups() {
+        let dir =
+            &std::env::temp_dir().join(format!("flaresync_backup_verify_{}", std::process::id()));
+        let _ = fs::remove_dir_all(dir);
+        let record = record("test.com", "127.0.0.1");
+        let zone = ZoneInfo {
+            id: "zone-1".to_string(),
+            name: Some("test.com".to_string()),
+        };
+        let store = BackupStore::new(dir).zone(Some(zone.clone()));
+        let path = store.write(&record, SnapshotKind::Before).unwrap();
+        let document = read(&path, None).unwrap();
+        assert_eq!(document.zone, Some(zone.clone()));
+        assert_eq!(
+            document,
+            BackupDocument::new(&record, SnapshotKind::Before, Some(&zone), None)
+        );
+        assert!(document
+            .checksum
+            .as_deref()
+            .is_some_and(|checksum| checksum.starts_with("sha256:")));
+        fs::write(
+            dir.join("20240101_120000_000000000_test.com_backup.json"),
+            serde_json::to_vec(&record).unwrap(),
+        )
+        .unwrap();
+        let journal_path = journal::path(dir, "test.com");
+        journal::append(
+            &journal_path,
+            &JournalEntry::new(&record, SnapshotKind::After, Some(&zone), None),
+            None,
+        )
+        .unwrap();
+        let integrity = |checks: Vec<BackupCheck>| -> Vec<Integrity> {
+            checks.into_iter().map(|check| check.integrity).collect()
+        };
+        assert_eq!(
+            integrity(store.verify().unwrap()),
+            vec![
+                Integrity::Unchecked,
+                Integrity::Verified,
+                Integrity::Verified
+            ]
+        );
+
+        let tampered = fs::read_to_string(&path)
+            .unwrap()
+            .replace("127.0.0.1", "127.0.0.2");
+        fs::write(&path, tampered).unwrap();
+        assert!(read(&path, None)
+            .unwrap_err()
+            .to_string()
+            .contains("checksum does not match"));
+        let tampered = fs::read_to_string(&journal_path)
+            .unwrap()
+            .replace("\"ttl\":120", "\"ttl\":60");
+        fs::write(&journal_path, tampered).unwrap();
+        let checks = store.verify().unwrap();
+        assert!(matches!(checks[1].integrity, Integrity::Failed(_)));
+        assert!(matches!(checks[2].integrity, Integrity::Failed(_)));
+        assert!(checks[2].source.ends_with("line 1"));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_latest_backup_per_domain() {
+        let dir =
+            &std::env::temp_dir().join(format!("flaresync_backup_latest_{}", std::process::id()));
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+        for name in [
+            "20240101_120000_000000001_home.example.com_backup.json",
+            "20240301_120000_000000001_home.example.com_backup.json",
+            "20240201_120000_000000001_home.example.com_backup.json",
+            "20240401_120000_000000001_www.example.com_backup.json",
+            "notes.txt",
+        ] {
+            fs::write(
+                dir.join(name),
+                r#"{"id":"1","name":"example.com","content":"203.0.113.1","type":"A","proxied":false,"ttl":1}"#,
+            )
+            .unwrap();
+        }
+
+        assert_eq!(list(dir).unwrap().len(), 4);
+        let store = BackupStore::new(dir);
+        let latest = store.latest("home.example.com").unwrap().unwrap();
+        assert!(latest
+            .source
+            .ends_with("20240301_120000_000000001_home.example.com_backup.json"));
+        assert_eq!(
+            latest.saved_at.unwrap().format("%Y%m%d").to_string(),
+            "20240301"
+        );
+        assert!(store.latest("missing.example.com").unwrap().is_none());
+        assert!(list(&dir.join("absent")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_retention_keeps_newest_per_record() {
+        let backup = |timestamp: &str, name: &str| BackupFile {
+            path: PathBuf::from(format!("{}_{}_backup.json", timestamp, name)),
+            timestamp: timestamp.to_string(),
+            name: name.to_string(),
+            kind: SnapshotKind::Before,
+        };
+        let backups = vec![
+            backup("20240101_120000_000000000", "a.example.com"),
+            backup("20240201_120000_000000000", "a.example.com"),
+            backup("20240301_120000_000000000", "a.example.com"),
+            backup("20240101_120000_000000000", "b.example.com"),
+        ];
+        let now =
+            NaiveDateTime::parse_from_str("20240315_120000_000000000", TIMESTAMP_FORMAT).unwrap();
+        let names = |expired: Vec<BackupFile>| -> Vec<String> {
+            expired
+                .into_iter()
+                .map(|backup| format!("{} {}", backup.timestamp, backup.name))
+                .collect()
+        };
+
+        let keep_two = BackupRetention {
+            keep_last: Some(2),
+            max_age: None,
+        };
+        assert_eq!(
+            names(expired(backups.clone(), keep_two, now)),
+            ["20240101_120000_000000000 a.example.com"]
+        );
+
+        let thirty_days = BackupRetention {
+            keep_last: None,
+            max_age: Some(Duration::from_secs(30 * 86400)),
+        };
+        assert_eq!(
+            names(expired(backups, thirty_days, now)),
+            [
+                "20240101_120000_000000000 a.example.com",
+                "20240201_120000_000000000 a.example.com"
+            ]
+        );
+    }
+}
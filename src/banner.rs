@@ -0,0 +1,93 @@
+use crate::config::Config;
+use crate::logging::EVENTS_TARGET;
+use crate::prefetch::PrefetchPolicy;
+use tracing::info;
+
+/// The optional features `config` turns on, by name.
+pub fn features(config: &Config) -> Vec<String> {
+    let mut features: Vec<String> = config
+        .notification_channels
+        .iter()
+        .map(|channel| format!("notify:{}", channel.notifier.name()))
+        .collect();
+    let enabled = [
+        ("health", config.health_bind_address.is_some()),
+        ("control", config.control_socket.is_some()),
+        ("admin", config.admin.is_some()),
+        ("dyndns", config.dyndns.is_some()),
+        ("statsd", config.statsd.is_some()),
+        ("otel", config.otlp_endpoint.is_some()),
+        ("sentry", config.sentry_dsn.is_some()),
+        ("syslog", config.syslog_target.is_some()),
+        ("s3", config.offsite_backup.is_some()),
+        ("heartbeat", config.heartbeat_url.is_some()),
+        ("summary", config.summary_period.is_some()),
+        ("on_change", config.on_change.is_some()),
+        ("adaptive", config.adaptive_min_interval.is_some()),
+        ("prefetch", config.prefetch != PrefetchPolicy::Off),
+        ("exit_after_failures", config.exit_after_failures.is_some()),
+    ];
+    features.extend(
+        enabled
+            .into_iter()
+            .filter(|(_, on)| *on)
+            .map(|(name, _)| name.to_string()),
+    );
+    features
+}
+
+/// Logs one event with the version, where the configuration came from, what is synced
+/// and how often, and the enabled features, so a deployment can be checked from its
+/// first log lines.
+pub fn log(config: &Config, source: &str) {
+    let domain_intervals: Vec<String> = config
+        .domain_intervals
+        .iter()
+        .map(|(domain_name, interval)| format!("{}={}s", domain_name, interval.as_secs()))
+        .collect();
+    let features = features(config);
+    info!(
+        target: EVENTS_TARGET,
+        version = env!("CARGO_PKG_VERSION"),
+        config_source = %source,
+        zone = %config.zone_id,
+        domains = %config.domain_names.join(","),
+        interval_seconds = config.update_interval.as_secs(),
+        domain_intervals = %domain_intervals.join(","),
+        paused = %config.paused_domains.iter().cloned().collect::<Vec<_>>().join(","),
+        features = %features.join(","),
+        "Starting FlareSync {}: {} domains in zone {}, every {:?}",
+        env!("CARGO_PKG_VERSION"),
+        config.domain_names.len(),
+        config.zone_id,
+        config.update_interval
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_features_lists_enabled_integrations() {
+        let config = Config::builder()
+            .api_token("test_token")
+            .zone("test_zone_id")
+            .domains(["example.com"])
+            .build()
+            .unwrap();
+        assert!(features(&config).is_empty());
+
+        let config = Config::builder()
+            .api_token("test_token")
+            .zone("test_zone_id")
+            .domains(["example.com"])
+            .health_bind_address("127.0.0.1:8080".parse().unwrap())
+            .adaptive_min_interval(Duration::from_secs(60))
+            .prefetch(PrefetchPolicy::Warn)
+            .build()
+            .unwrap();
+        assert_eq!(features(&config), ["health", "adaptive", "prefetch"]);
+    }
+}
@@ -0,0 +1,441 @@
+use crate::config::DEFAULT_UPDATE_INTERVAL_MINUTES;
+#[cfg(unix)]
+use crate::control::ControlCommand;
+use clap::{Parser, Subcommand};
+use clap_complete::Shell;
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "flaresync",
+    version,
+    about = "Keep Cloudflare DNS records in sync with your public IP"
+)]
+pub struct Cli {
+    /// Path to a TOML config file (defaults to FLARESYNC_CONFIG when set)
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Named profile to use from the config file (defaults to FLARESYNC_PROFILE when set)
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
+    /// Reject unknown config keys and misspelled environment variables
+    #[arg(long, global = true)]
+    pub strict: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the sync loop (default)
+    Run,
+    /// Run a single sync cycle and exit
+    Update {
+        /// Write the current IP to every record even if it already matches
+        #[arg(long)]
+        force: bool,
+    },
+    /// Interactively create a config file by picking a zone and domains
+    Init {
+        /// Where to write the config file
+        #[arg(long, default_value = "flaresync.toml")]
+        output: PathBuf,
+        /// Where to write the API token referenced by the config file
+        #[arg(long, default_value = "flaresync.token")]
+        token_file: PathBuf,
+        /// Overwrite existing files
+        #[arg(long)]
+        force: bool,
+    },
+    /// Write the current environment configuration to a TOML config file
+    MigrateConfig {
+        /// Where to write the config file
+        #[arg(long, default_value = "flaresync.toml")]
+        output: PathBuf,
+        /// Where to write the API token referenced by the config file
+        #[arg(long, default_value = "flaresync.token")]
+        token_file: PathBuf,
+        /// Overwrite existing files
+        #[arg(long)]
+        force: bool,
+    },
+    /// Show recorded DNS updates, newest first
+    History {
+        /// Only show entries for this domain
+        #[arg(long)]
+        domain: Option<String>,
+        /// Maximum number of entries to show
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Show a live dashboard of the running instance's status and events
+    Watch {
+        /// Number of recent events to show
+        #[arg(long, default_value_t = 10)]
+        events: usize,
+    },
+    /// Show the public IP, per-domain sync state, and recent errors
+    Status {
+        /// Print the status as JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show runtime statistics from the status file
+    Stats,
+    /// Back up the current A records of every configured domain
+    Backup,
+    /// Restore DNS records from backup files
+    Restore {
+        /// Backup file to restore
+        #[arg(required_unless_present = "latest", conflicts_with = "latest")]
+        file: Option<PathBuf>,
+        /// Restore the newest backup of each configured domain
+        #[arg(long)]
+        latest: bool,
+        /// With --latest, only restore this domain
+        #[arg(long, requires = "latest")]
+        domain: Option<String>,
+        /// Show what would change without writing to Cloudflare
+        #[arg(long)]
+        dry_run: bool,
+        /// Restore a backup taken in another zone than CLOUDFLARE_ZONE_ID into that zone
+        #[arg(long)]
+        allow_other_zone: bool,
+    },
+    /// Undo the most recent FlareSync update of each configured domain
+    Rollback {
+        /// Only roll back this domain
+        #[arg(long)]
+        domain: Option<String>,
+        /// Show what would change without writing to Cloudflare
+        #[arg(long)]
+        dry_run: bool,
+        /// Roll back even if the record was changed after the update
+        #[arg(long)]
+        force: bool,
+    },
+    /// Delete backups outside BACKUP_KEEP / BACKUP_MAX_AGE_DAYS
+    PruneBackups {
+        /// List the backups that would be deleted without deleting them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check every backup against its checksum to detect corrupted or modified snapshots
+    VerifyBackups,
+    /// Send a test event through every configured notification channel
+    TestNotify {
+        /// Only test channels of this type (e.g. discord, email)
+        #[arg(long)]
+        channel: Option<String>,
+    },
+    /// Check that the API token can read and edit the configured records
+    Verify {
+        /// Print the check results as JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Run all diagnostics and print a checklist for support requests
+    Doctor,
+    /// Export every record in the zone as a BIND zone file
+    Export {
+        /// Write the zone file here instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Overwrite the output file if it exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Create or update the configured domains' records from a JSON or zone file
+    Import {
+        /// JSON array of records (`.json`) or a BIND zone file
+        file: PathBuf,
+        /// Show the planned changes without applying them
+        #[arg(long)]
+        dry_run: bool,
+        /// Apply the changes without asking for confirmation
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
+    /// Detect and print the public IP that would be published
+    Ip {
+        /// Also print what each IP source returned
+        #[arg(long, short)]
+        verbose: bool,
+        /// Print the result as JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show the record changes the next sync would make, without applying them
+    Diff {
+        /// Print the pending changes as JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show the Cloudflare DNS records of every configured domain
+    List {
+        /// Print the records as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write a systemd service, or a service and timer, that runs this binary
+    Install {
+        /// Run one-shot updates from a timer instead of the long-running sync loop
+        #[arg(long)]
+        timer: bool,
+        /// Write a launchd property list for macOS instead of systemd units
+        #[arg(long)]
+        launchd: bool,
+        /// Minutes between timer runs
+        #[arg(long, default_value_t = DEFAULT_UPDATE_INTERVAL_MINUTES, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+        /// Directory to write the unit files to [default: /etc/systemd/system, or
+        /// /Library/LaunchDaemons with --launchd]
+        #[arg(long)]
+        unit_dir: Option<PathBuf>,
+        /// User to run the service as
+        #[arg(long)]
+        user: Option<String>,
+        /// Print the unit files instead of writing them
+        #[arg(long)]
+        dry_run: bool,
+        /// Overwrite existing unit files
+        #[arg(long)]
+        force: bool,
+    },
+    /// Exit 0 when the last sync succeeded recently and 1 otherwise, for container health checks
+    Healthcheck {
+        /// Query this readiness URL (e.g. http://127.0.0.1:8080/readyz) instead of reading the status file
+        #[arg(long)]
+        url: Option<String>,
+    },
+    /// Send a command to the running daemon over CONTROL_SOCKET
+    #[cfg(unix)]
+    Control {
+        #[arg(value_enum)]
+        command: ControlCommand,
+        /// Pause or resume only this domain
+        domain: Option<String>,
+    },
+    /// Install, remove, or run FlareSync as a Windows service
+    #[cfg(windows)]
+    Service {
+        #[command(subcommand)]
+        action: ServiceAction,
+    },
+    /// Print a shell completion script to stdout
+    Completions {
+        /// Shell to generate completions for
+        shell: Shell,
+    },
+}
+
+#[cfg(windows)]
+#[derive(Debug, Subcommand)]
+pub enum ServiceAction {
+    /// Register a service that starts the sync loop automatically with Windows
+    Install {
+        /// Start the service right after installing it
+        #[arg(long)]
+        start: bool,
+    },
+    /// Stop the service and remove it
+    Uninstall,
+    /// Run the sync loop under the service control manager (used by the installed service)
+    #[command(hide = true)]
+    Run {
+        /// Directory to resolve relative paths such as `.env` and `status/` against
+        #[arg(long)]
+        working_directory: Option<PathBuf>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_defaults_to_run() {
+        let cli = Cli::parse_from(["flaresync"]);
+        assert!(cli.command.is_none());
+        assert!(cli.config.is_none());
+        assert!(cli.profile.is_none());
+    }
+
+    #[test]
+    fn test_cli_parses_profile_selector() {
+        let cli = Cli::parse_from([
+            "flaresync",
+            "--config",
+            "flaresync.toml",
+            "run",
+            "--profile",
+            "home",
+        ]);
+        assert_eq!(cli.config, Some(PathBuf::from("flaresync.toml")));
+        assert_eq!(cli.profile.as_deref(), Some("home"));
+        assert!(matches!(cli.command, Some(Command::Run)));
+    }
+
+    #[test]
+    fn test_cli_parses_migrate_config() {
+        let cli = Cli::parse_from([
+            "flaresync",
+            "migrate-config",
+            "--output",
+            "/etc/flaresync.toml",
+            "--force",
+        ]);
+        match cli.command {
+            Some(Command::MigrateConfig {
+                output,
+                token_file,
+                force,
+            }) => {
+                assert_eq!(output, PathBuf::from("/etc/flaresync.toml"));
+                assert_eq!(token_file, PathBuf::from("flaresync.token"));
+                assert!(force);
+            }
+            other => panic!("expected migrate-config, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cli_parses_history_filters() {
+        let cli = Cli::parse_from(["flaresync", "history", "--domain", "example.com"]);
+        match cli.command {
+            Some(Command::History { domain, limit }) => {
+                assert_eq!(domain.as_deref(), Some("example.com"));
+                assert_eq!(limit, 20);
+            }
+            other => panic!("expected history, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cli_parses_forced_update() {
+        let cli = Cli::parse_from(["flaresync", "update", "--force"]);
+        assert!(matches!(cli.command, Some(Command::Update { force: true })));
+    }
+
+    #[test]
+    fn test_cli_parses_restore_selection() {
+        let cli = Cli::parse_from([
+            "flaresync",
+            "restore",
+            "--latest",
+            "--domain",
+            "example.com",
+            "--dry-run",
+        ]);
+        match cli.command {
+            Some(Command::Restore {
+                file,
+                latest,
+                domain,
+                dry_run,
+                allow_other_zone,
+            }) => {
+                assert!(file.is_none());
+                assert!(latest && dry_run);
+                assert!(!allow_other_zone);
+                assert_eq!(domain.as_deref(), Some("example.com"));
+            }
+            other => panic!("expected restore, got {:?}", other),
+        }
+        assert!(Cli::try_parse_from(["flaresync", "restore"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_generates_completions() {
+        let cli = Cli::parse_from(["flaresync", "completions", "zsh"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Completions { shell: Shell::Zsh })
+        ));
+
+        let mut script = Vec::new();
+        clap_complete::generate(
+            Shell::Bash,
+            &mut <Cli as clap::CommandFactory>::command(),
+            "flaresync",
+            &mut script,
+        );
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("prune-backups"));
+        assert!(script.contains("--dry-run"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cli_parses_control_command() {
+        let cli = Cli::parse_from(["flaresync", "control", "reload-config"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Control {
+                command: ControlCommand::ReloadConfig,
+                domain: None,
+            })
+        ));
+        let cli = Cli::parse_from(["flaresync", "control", "pause", "example.com"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Control {
+                command: ControlCommand::Pause,
+                domain: Some(domain),
+            }) if domain == "example.com"
+        ));
+        assert!(Cli::try_parse_from(["flaresync", "control", "restart"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parses_json_output_format() {
+        let cli = Cli::parse_from(["flaresync", "list", "--json"]);
+        assert!(matches!(cli.command, Some(Command::List { json: true })));
+        for command in ["status", "diff", "verify"] {
+            let cli = Cli::parse_from(["flaresync", command, "--json"]);
+            assert!(matches!(
+                cli.command,
+                Some(Command::Status { json: true })
+                    | Some(Command::Diff { json: true })
+                    | Some(Command::Verify { json: true })
+            ));
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_cli_parses_service_commands() {
+        let cli = Cli::parse_from(["flaresync", "service", "install", "--start"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Service {
+                action: ServiceAction::Install { start: true },
+            })
+        ));
+        let cli = Cli::parse_from(["flaresync", "service", "uninstall"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Service {
+                action: ServiceAction::Uninstall,
+            })
+        ));
+        let cli = Cli::parse_from([
+            "flaresync",
+            "service",
+            "run",
+            "--working-directory",
+            r"C:\ProgramData\FlareSync",
+        ]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Service {
+                action: ServiceAction::Run {
+                    working_directory: Some(dir),
+                },
+            }) if dir == PathBuf::from(r"C:\ProgramData\FlareSync")
+        ));
+    }
+}
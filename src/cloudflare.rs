@@ -1,19 +1,25 @@
+use crate::apply::RecordChange;
+use crate::backup::BackupStore;
 use crate::errors::FlareSyncError;
-use log::{info, warn};
-use reqwest::Client as ReqwestClient;
+use crate::metrics;
+use crate::rate_limit;
+use reqwest::{Client as ReqwestClient, RequestBuilder};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::fs::{self, OpenOptions};
-use std::io::Write;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::net::Ipv4Addr;
-#[cfg(unix)]
-use std::os::unix::fs::OpenOptionsExt;
-use std::path::Path;
+use std::sync::Mutex;
 use std::time::Duration;
 use tokio::time;
+use tracing::{debug, info, warn};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+pub const HTTP_LOG_TARGET: &str = "flaresync::http";
+
+static ZONE_NAMES: Mutex<BTreeMap<String, Option<String>>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct DnsRecord {
     pub id: String,
     pub name: String,
@@ -22,6 +28,14 @@ pub struct DnsRecord {
     pub record_type: String,
     pub proxied: bool,
     pub ttl: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_on: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Zone {
+    pub id: String,
+    pub name: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -40,10 +54,16 @@ pub struct CloudflareResponse<T> {
     pub result: T,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DnsUpdateStatus {
-    Updated,
-    Unchanged,
+    Updated {
+        previous_content: String,
+        proxied: bool,
+        record: DnsRecord,
+    },
+    Unchanged {
+        record: DnsRecord,
+    },
     Missing,
 }
 
@@ -58,6 +78,16 @@ fn is_transient_cloudflare_error(err: &FlareSyncError) -> bool {
     }
 }
 
+/// Whether a failed create may be sent again. A create that failed without a response,
+/// such as one that timed out, may still have created the record, so among those only a
+/// failure to connect is retried.
+fn is_retryable_create_error(err: &FlareSyncError) -> bool {
+    match err {
+        FlareSyncError::Network(e) if e.status().is_none() => e.is_connect(),
+        err => is_transient_cloudflare_error(err),
+    }
+}
+
 fn cloudflare_errors_look_transient(errors: &[Value]) -> bool {
     errors.iter().any(|error| {
         let code = error.get("code").and_then(|v| v.as_i64());
@@ -124,7 +154,18 @@ where
     })
 }
 
-async fn retry_cloudflare<T, F, Fut>(mut f: F) -> Result<T, FlareSyncError>
+async fn retry_cloudflare<T, F, Fut>(f: F) -> Result<T, FlareSyncError>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, FlareSyncError>>,
+{
+    retry_cloudflare_if(is_transient_cloudflare_error, f).await
+}
+
+async fn retry_cloudflare_if<T, F, Fut>(
+    is_retryable: fn(&FlareSyncError) -> bool,
+    mut f: F,
+) -> Result<T, FlareSyncError>
 where
     F: FnMut() -> Fut,
     Fut: std::future::Future<Output = Result<T, FlareSyncError>>,
@@ -137,13 +178,17 @@ where
         match f().await {
             Ok(result) => return Ok(result),
             Err(e) => {
-                if !is_transient_cloudflare_error(&e) || retries >= max_retries {
+                if !is_retryable(&e) || retries >= max_retries {
                     return Err(e);
                 }
                 warn!(
                     "Cloudflare request failed: {}. Retrying in {:?}...",
                     e, wait_time
                 );
+                metrics::record_cloudflare_retry(match e {
+                    FlareSyncError::CloudflareTransient(_) => "api",
+                    _ => "http",
+                });
                 time::sleep(wait_time).await;
                 retries += 1;
                 wait_time *= 2;
@@ -155,7 +200,7 @@ where
     }
 }
 
-fn sanitize_filename_component(input: &str) -> String {
+pub(crate) fn sanitize_filename_component(input: &str) -> String {
     let mut sanitized: String = input
         .chars()
         .map(|c| {
@@ -177,86 +222,543 @@ fn sanitize_filename_component(input: &str) -> String {
     sanitized
 }
 
-async fn get_dns_record(
+async fn send_cloudflare_request(
+    client: &ReqwestClient,
+    request: RequestBuilder,
+) -> Result<CloudflareEnvelope, FlareSyncError> {
+    let request = request.build()?;
+    debug!(
+        target: HTTP_LOG_TARGET,
+        method = %request.method(),
+        url = %request.url(),
+        body = %request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(String::from_utf8_lossy)
+            .unwrap_or_default(),
+        "Cloudflare request"
+    );
+
+    rate_limit::acquire().await;
+    let resp = client.execute(request).await?;
+    let status = resp.status();
+    let status_error = resp.error_for_status_ref().err();
+    let body = resp.text().await?;
+    debug!(
+        target: HTTP_LOG_TARGET,
+        status = status.as_u16(),
+        body = %body,
+        "Cloudflare response"
+    );
+
+    if let Some(e) = status_error {
+        return Err(e.into());
+    }
+    Ok(serde_json::from_str(&body)?)
+}
+
+async fn fetch_dns_records(
     client: &ReqwestClient,
     api_token: &str,
     zone_id: &str,
     domain_name: &str,
-) -> Result<Option<DnsRecord>, FlareSyncError> {
+    record_type: Option<&str>,
+) -> Result<Vec<DnsRecord>, FlareSyncError> {
     let response: CloudflareResponse<Vec<DnsRecord>> = retry_cloudflare(|| async {
-        let resp = client
+        let mut request = client
             .get(format!(
                 "https://api.cloudflare.com/client/v4/zones/{}/dns_records",
                 zone_id
             ))
-            .query(&[("type", "A"), ("name", domain_name)])
+            .query(&[("name", domain_name)])
             .header("Authorization", format!("Bearer {}", api_token))
-            .header("Content-Type", "application/json")
-            .send()
-            .await?
-            .error_for_status()?;
-        let envelope: CloudflareEnvelope = resp.json().await?;
+            .header("Content-Type", "application/json");
+        if let Some(record_type) = record_type {
+            request = request.query(&[("type", record_type)]);
+        }
+        let envelope = send_cloudflare_request(client, request).await?;
         parse_cloudflare_response(envelope, "fetching", domain_name)
     })
     .await?;
 
-    Ok(response.result.into_iter().next())
+    Ok(response.result)
 }
 
-async fn update_dns_record(
+pub async fn list_zones(
+    client: &ReqwestClient,
+    api_token: &str,
+) -> Result<Vec<Zone>, FlareSyncError> {
+    let response: CloudflareResponse<Vec<Zone>> = retry_cloudflare(|| async {
+        let request = client
+            .get("https://api.cloudflare.com/client/v4/zones")
+            .query(&[("per_page", "50")])
+            .header("Authorization", format!("Bearer {}", api_token))
+            .header("Content-Type", "application/json");
+        let envelope = send_cloudflare_request(client, request).await?;
+        parse_cloudflare_response(envelope, "listing", "zones")
+    })
+    .await?;
+
+    Ok(response.result)
+}
+
+async fn fetch_zone(
     client: &ReqwestClient,
     api_token: &str,
     zone_id: &str,
-    record: &DnsRecord,
-    current_ip: &Ipv4Addr,
-) -> Result<(), FlareSyncError> {
-    let _response: CloudflareResponse<DnsRecord> = retry_cloudflare(|| async {
-        let resp = client
+) -> Result<Zone, FlareSyncError> {
+    let response: CloudflareResponse<Zone> = retry_cloudflare(|| async {
+        let request = client
+            .get(format!(
+                "https://api.cloudflare.com/client/v4/zones/{}",
+                zone_id
+            ))
+            .header("Authorization", format!("Bearer {}", api_token))
+            .header("Content-Type", "application/json");
+        let envelope = send_cloudflare_request(client, request).await?;
+        parse_cloudflare_response(envelope, "fetching", zone_id)
+    })
+    .await?;
+
+    Ok(response.result)
+}
+
+/// The name of the zone `zone_id`, looked up once per process. `None`, also remembered
+/// unless the failure was transient, when the zone cannot be read, e.g. because the
+/// token lacks Zone Read permission.
+pub async fn zone_name(client: &ReqwestClient, api_token: &str, zone_id: &str) -> Option<String> {
+    if let Some(name) = ZONE_NAMES.lock().unwrap().get(zone_id) {
+        return name.clone();
+    }
+    let name = match fetch_zone(client, api_token, zone_id).await {
+        Ok(zone) => Some(zone.name),
+        Err(e) if is_transient_cloudflare_error(&e) => {
+            debug!("Could not look up the name of zone {}: {}", zone_id, e);
+            return None;
+        }
+        Err(e) => {
+            debug!("Could not look up the name of zone {}: {}", zone_id, e);
+            None
+        }
+    };
+    ZONE_NAMES
+        .lock()
+        .unwrap()
+        .insert(zone_id.to_string(), name.clone());
+    name
+}
+
+pub async fn list_zone_a_records(
+    client: &ReqwestClient,
+    api_token: &str,
+    zone_id: &str,
+) -> Result<Vec<DnsRecord>, FlareSyncError> {
+    let response: CloudflareResponse<Vec<DnsRecord>> = retry_cloudflare(|| async {
+        let request = client
+            .get(format!(
+                "https://api.cloudflare.com/client/v4/zones/{}/dns_records",
+                zone_id
+            ))
+            .query(&[("type", "A"), ("per_page", "500")])
+            .header("Authorization", format!("Bearer {}", api_token))
+            .header("Content-Type", "application/json");
+        let envelope = send_cloudflare_request(client, request).await?;
+        parse_cloudflare_response(envelope, "listing", zone_id)
+    })
+    .await?;
+
+    Ok(response.result)
+}
+
+pub async fn get_dns_record(
+    client: &ReqwestClient,
+    api_token: &str,
+    zone_id: &str,
+    domain_name: &str,
+) -> Result<Option<DnsRecord>, FlareSyncError> {
+    let records = fetch_dns_records(client, api_token, zone_id, domain_name, Some("A")).await?;
+    Ok(records.into_iter().next())
+}
+
+pub async fn list_dns_records(
+    client: &ReqwestClient,
+    api_token: &str,
+    zone_id: &str,
+    domain_name: &str,
+) -> Result<Vec<DnsRecord>, FlareSyncError> {
+    fetch_dns_records(client, api_token, zone_id, domain_name, None).await
+}
+
+async fn put_dns_record(
+    client: &ReqwestClient,
+    api_token: &str,
+    zone_id: &str,
+    record_id: &str,
+    desired: &DnsRecord,
+) -> Result<DnsRecord, FlareSyncError> {
+    let response: CloudflareResponse<DnsRecord> = retry_cloudflare(|| async {
+        let request = client
             .put(format!(
                 "https://api.cloudflare.com/client/v4/zones/{}/dns_records/{}",
-                zone_id, record.id
+                zone_id, record_id
             ))
             .header("Authorization", format!("Bearer {}", api_token))
             .header("Content-Type", "application/json")
             .json(&serde_json::json!({
-                "type": "A",
-                "name": record.name,
-                "content": current_ip.to_string(),
-                "ttl": record.ttl,
-                "proxied": record.proxied
-            }))
-            .send()
-            .await?
-            .error_for_status()?;
-        let envelope: CloudflareEnvelope = resp.json().await?;
-        parse_cloudflare_response(envelope, "updating", &record.name)
+                "type": desired.record_type,
+                "name": desired.name,
+                "content": desired.content,
+                "ttl": desired.ttl,
+                "proxied": desired.proxied
+            }));
+        let envelope = send_cloudflare_request(client, request).await?;
+        parse_cloudflare_response(envelope, "updating", &desired.name)
     })
     .await?;
+    Ok(response.result)
+}
+
+async fn update_dns_record(
+    client: &ReqwestClient,
+    api_token: &str,
+    zone_id: &str,
+    record: &DnsRecord,
+    current_ip: &Ipv4Addr,
+) -> Result<DnsRecord, FlareSyncError> {
+    let desired = DnsRecord {
+        content: current_ip.to_string(),
+        ..record.clone()
+    };
+    let updated = put_dns_record(client, api_token, zone_id, &record.id, &desired).await?;
 
     info!("DNS record for {} updated successfully!", record.name);
+    Ok(updated)
+}
+
+async fn create_dns_record(
+    client: &ReqwestClient,
+    api_token: &str,
+    zone_id: &str,
+    desired: &DnsRecord,
+) -> Result<(), FlareSyncError> {
+    let _response: CloudflareResponse<DnsRecord> =
+        retry_cloudflare_if(is_retryable_create_error, || async {
+            let request = client
+                .post(format!(
+                    "https://api.cloudflare.com/client/v4/zones/{}/dns_records",
+                    zone_id
+                ))
+                .header("Authorization", format!("Bearer {}", api_token))
+                .header("Content-Type", "application/json")
+                .json(&serde_json::json!({
+                    "type": desired.record_type,
+                    "name": desired.name,
+                    "content": desired.content,
+                    "ttl": desired.ttl,
+                    "proxied": desired.proxied
+                }));
+            let envelope = send_cloudflare_request(client, request).await?;
+            parse_cloudflare_response(envelope, "creating", &desired.name)
+        })
+        .await?;
     Ok(())
 }
 
-fn backup_dns_record(record: &DnsRecord) -> Result<(), FlareSyncError> {
-    let backup_dir = Path::new("backups");
-    fs::create_dir_all(backup_dir)?;
+pub async fn apply_record_change(
+    client: &ReqwestClient,
+    api_token: &str,
+    zone_id: &str,
+    change: &RecordChange,
+    backups: &BackupStore,
+) -> Result<(), FlareSyncError> {
+    match change {
+        RecordChange::Create(desired) => {
+            create_dns_record(client, api_token, zone_id, &desired.to_record("")).await?;
+            info!(
+                "DNS record {} {} created",
+                desired.record_type, desired.name
+            );
+        }
+        RecordChange::Update { current, desired } => {
+            backups.save(client, current).await?;
+            let updated = put_dns_record(
+                client,
+                api_token,
+                zone_id,
+                &current.id,
+                &desired.to_record(&current.id),
+            )
+            .await?;
+            backups.save_applied(client, &updated).await;
+            info!(
+                "DNS record {} {} updated",
+                desired.record_type, desired.name
+            );
+        }
+    }
+    Ok(())
+}
 
-    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S_%f");
-    let safe_name = sanitize_filename_component(&record.name);
-    let filename = format!("{}_{}_backup.json", timestamp, safe_name);
-    let backup_path = backup_dir.join(filename);
+pub async fn snapshot_dns_records(
+    client: &ReqwestClient,
+    api_token: &str,
+    zone_id: &str,
+    domain_name: &str,
+    backups: &BackupStore,
+) -> Result<Vec<DnsRecord>, FlareSyncError> {
+    let records = fetch_dns_records(client, api_token, zone_id, domain_name, Some("A")).await?;
+    backups.save_all(client, &records).await?;
+    Ok(records)
+}
 
-    let mut open_options = OpenOptions::new();
-    open_options.write(true).create_new(true);
-    #[cfg(unix)]
-    open_options.mode(0o600);
+pub async fn restore_dns_record(
+    client: &ReqwestClient,
+    api_token: &str,
+    zone_id: &str,
+    backup: &DnsRecord,
+    backups: &BackupStore,
+    dry_run: bool,
+) -> Result<DnsRecord, FlareSyncError> {
+    let current = fetch_dns_records(
+        client,
+        api_token,
+        zone_id,
+        &backup.name,
+        Some(&backup.record_type),
+    )
+    .await?
+    .into_iter()
+    .next()
+    .ok_or_else(|| {
+        FlareSyncError::Cloudflare(format!(
+            "No {} record named {} exists to restore",
+            backup.record_type, backup.name
+        ))
+    })?;
 
-    let mut file = open_options.open(backup_path)?;
-    let json = serde_json::to_string_pretty(record)?;
-    file.write_all(json.as_bytes())?;
+    if !dry_run {
+        backups.save(client, &current).await?;
+        let restored = put_dns_record(client, api_token, zone_id, &current.id, backup).await?;
+        backups.save_applied(client, &restored).await;
+        info!("DNS record for {} restored from backup", backup.name);
+    }
+    Ok(current)
+}
 
-    info!("DNS record backup created for {}", record.name);
-    Ok(())
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccessCheck {
+    pub name: String,
+    pub problem: Option<String>,
+}
+
+async fn probe(
+    client: &ReqwestClient,
+    request: RequestBuilder,
+) -> Result<(u16, Value), FlareSyncError> {
+    let request = request.build()?;
+    rate_limit::acquire().await;
+    let resp = client.execute(request).await?;
+    let status = resp.status().as_u16();
+    let body = resp.text().await?;
+    Ok((status, serde_json::from_str(&body).unwrap_or(Value::Null)))
+}
+
+fn api_error_messages(body: &Value) -> String {
+    let messages: Vec<String> = body
+        .get("errors")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|error| match (error.get("code"), error.get("message")) {
+            (Some(code), Some(Value::String(message))) => format!("{} {}", code, message),
+            _ => error.to_string(),
+        })
+        .collect();
+    if messages.is_empty() {
+        "no error details".to_string()
+    } else {
+        messages.join("; ")
+    }
+}
+
+fn permission_problem(status: u16, body: &Value, permission: &str) -> Option<String> {
+    match status {
+        200..=299 => None,
+        401 | 403 => Some(format!(
+            "token lacks the {} permission for this zone ({})",
+            permission,
+            api_error_messages(body)
+        )),
+        _ => Some(format!("HTTP {}: {}", status, api_error_messages(body))),
+    }
+}
+
+fn export_error(status: u16, body: &str, zone_id: &str) -> FlareSyncError {
+    let details = api_error_messages(&serde_json::from_str(body).unwrap_or(Value::Null));
+    let message = format!("HTTP {} exporting zone {}: {}", status, zone_id, details);
+    if status == 429 || status >= 500 {
+        FlareSyncError::CloudflareTransient(message)
+    } else {
+        FlareSyncError::Cloudflare(message)
+    }
+}
+
+pub async fn export_zone_file(
+    client: &ReqwestClient,
+    api_token: &str,
+    zone_id: &str,
+) -> Result<String, FlareSyncError> {
+    retry_cloudflare(|| async {
+        let request = client
+            .get(format!(
+                "https://api.cloudflare.com/client/v4/zones/{}/dns_records/export",
+                zone_id
+            ))
+            .header("Authorization", format!("Bearer {}", api_token))
+            .build()?;
+        rate_limit::acquire().await;
+        let resp = client.execute(request).await?;
+        let status = resp.status().as_u16();
+        let body = resp.text().await?;
+        if !(200..300).contains(&status) {
+            return Err(export_error(status, &body, zone_id));
+        }
+        Ok(body)
+    })
+    .await
+}
+
+pub async fn verify_access(
+    client: &ReqwestClient,
+    api_token: &str,
+    zone_id: &str,
+    domain_names: &[String],
+) -> Result<Vec<AccessCheck>, FlareSyncError> {
+    let authorized = |request: RequestBuilder| {
+        request
+            .header("Authorization", format!("Bearer {}", api_token))
+            .header("Content-Type", "application/json")
+    };
+    let mut checks = Vec::new();
+
+    let (status, body) = probe(
+        client,
+        authorized(client.get("https://api.cloudflare.com/client/v4/user/tokens/verify")),
+    )
+    .await?;
+    let token_status = body["result"]["status"].as_str().unwrap_or("unknown");
+    let problem = match status {
+        200 if token_status == "active" => None,
+        200 => Some(format!("token is {}", token_status)),
+        _ => Some(format!(
+            "token was rejected (HTTP {}: {})",
+            status,
+            api_error_messages(&body)
+        )),
+    };
+    let token_ok = problem.is_none();
+    checks.push(AccessCheck {
+        name: "API token is valid and active".to_string(),
+        problem,
+    });
+    if !token_ok {
+        return Ok(checks);
+    }
+
+    let records_url = format!(
+        "https://api.cloudflare.com/client/v4/zones/{}/dns_records",
+        zone_id
+    );
+    let (status, body) = probe(
+        client,
+        authorized(client.get(&records_url).query(&[("per_page", "1")])),
+    )
+    .await?;
+    let problem = permission_problem(status, &body, "Zone → DNS → Read");
+    let can_read = problem.is_none();
+    checks.push(AccessCheck {
+        name: format!("Read DNS records in zone {}", zone_id),
+        problem,
+    });
+
+    // Editing a record ID that cannot exist proves edit access without changing anything:
+    // Cloudflare answers 404 when the token may edit and 403 when it may not.
+    let (status, body) = probe(
+        client,
+        authorized(
+            client
+                .patch(format!("{}/{}", records_url, "0".repeat(32)))
+                .json(&serde_json::json!({})),
+        ),
+    )
+    .await?;
+    let problem = match status {
+        401 | 403 => permission_problem(status, &body, "Zone → DNS → Edit"),
+        _ => None,
+    };
+    checks.push(AccessCheck {
+        name: format!("Edit DNS records in zone {}", zone_id),
+        problem,
+    });
+
+    if can_read {
+        for domain_name in domain_names {
+            let problem = match get_dns_record(client, api_token, zone_id, domain_name).await {
+                Ok(Some(_)) => None,
+                Ok(None) => Some(format!(
+                    "no A record named {} exists; FlareSync only updates existing records",
+                    domain_name
+                )),
+                Err(e) => Some(e.to_string()),
+            };
+            checks.push(AccessCheck {
+                name: format!("A record for {}", domain_name),
+                problem,
+            });
+        }
+    }
+    Ok(checks)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PendingChange {
+    pub domain: String,
+    pub current: String,
+    pub desired: String,
+    pub proxied: bool,
+    pub ttl: u32,
+}
+
+impl fmt::Display for PendingChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ttl = match self.ttl {
+            1 => "auto".to_string(),
+            ttl => ttl.to_string(),
+        };
+        write!(
+            f,
+            "{}: {} → {} [{}, ttl={}]",
+            self.domain,
+            self.current,
+            self.desired,
+            if self.proxied { "proxied" } else { "dns only" },
+            ttl
+        )
+    }
+}
+
+/// The update a sync cycle would make to `record`, or `None` if it already matches.
+pub fn pending_change(record: &DnsRecord, current_ip: &Ipv4Addr) -> Option<PendingChange> {
+    if record.content == current_ip.to_string() {
+        return None;
+    }
+    Some(PendingChange {
+        domain: record.name.clone(),
+        current: record.content.clone(),
+        desired: current_ip.to_string(),
+        proxied: record.proxied,
+        ttl: record.ttl,
+    })
 }
 
 pub async fn check_and_update_ip(
@@ -265,87 +767,259 @@ pub async fn check_and_update_ip(
     zone_id: &str,
     domain_name: &str,
     current_ip: &Ipv4Addr,
+    force: bool,
+    backups: &BackupStore,
 ) -> Result<DnsUpdateStatus, FlareSyncError> {
     info!("Checking DNS for domain: {}", domain_name);
+    match get_dns_record(client, api_token, zone_id, domain_name).await? {
+        Some(record) => {
+            sync_dns_record(
+                client, api_token, zone_id, record, current_ip, force, backups,
+            )
+            .await
+        }
+        None => {
+            warn!("No matching DNS record found for {}.", domain_name);
+            Ok(DnsUpdateStatus::Missing)
+        }
+    }
+}
 
-    if let Some(record) = get_dns_record(client, api_token, zone_id, domain_name).await? {
-        info!(
-            "Current Cloudflare DNS record IP for {}: {}",
-            domain_name, record.content
-        );
+/// Brings `record`, an A record already fetched, to `current_ip`.
+pub async fn sync_dns_record(
+    client: &ReqwestClient,
+    api_token: &str,
+    zone_id: &str,
+    record: DnsRecord,
+    current_ip: &Ipv4Addr,
+    force: bool,
+    backups: &BackupStore,
+) -> Result<DnsUpdateStatus, FlareSyncError> {
+    let domain_name = record.name.clone();
+    info!(
+        "Current Cloudflare DNS record IP for {}: {}",
+        domain_name, record.content
+    );
 
-        if record.content != current_ip.to_string() {
-            info!("IP for {} has changed. Updating DNS record...", domain_name);
-            backup_dns_record(&record)?;
-            update_dns_record(client, api_token, zone_id, &record, current_ip).await?;
-            Ok(DnsUpdateStatus::Updated)
+    if record.content != current_ip.to_string() || force {
+        if record.content == current_ip.to_string() {
+            info!(
+                "IP for {} hasn't changed. Forcing DNS record update...",
+                domain_name
+            );
         } else {
-            info!("IP for {} hasn't changed. No update needed.", domain_name);
-            Ok(DnsUpdateStatus::Unchanged)
+            info!(
+                old_ip = %record.content,
+                new_ip = %current_ip,
+                "IP for {} has changed. Updating DNS record...",
+                domain_name
+            );
         }
+        backups.save(client, &record).await?;
+        let updated = update_dns_record(client, api_token, zone_id, &record, current_ip).await?;
+        backups.save_applied(client, &updated).await;
+        Ok(DnsUpdateStatus::Updated {
+            previous_content: record.content,
+            proxied: record.proxied,
+            record: updated,
+        })
     } else {
-        warn!("No matching DNS record found for {}.", domain_name);
-        Ok(DnsUpdateStatus::Missing)
+        info!("IP for {} hasn't changed. No update needed.", domain_name);
+        Ok(DnsUpdateStatus::Unchanged { record })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
-    #[cfg(unix)]
-    use std::os::unix::fs::PermissionsExt;
-    use std::path::Path;
 
     #[test]
-    fn test_backup_dns_record() {
-        let _guard = crate::test_support::global_lock();
+    fn test_permission_problem_names_missing_permission() {
+        let body = serde_json::json!({
+            "success": false,
+            "errors": [{ "code": 10000, "message": "Authentication error" }]
+        });
+        assert_eq!(
+            permission_problem(403, &body, "Zone → DNS → Edit").as_deref(),
+            Some(
+                "token lacks the Zone → DNS → Edit permission for this zone (10000 Authentication error)"
+            )
+        );
+        assert_eq!(
+            permission_problem(200, &Value::Null, "Zone → DNS → Read"),
+            None
+        );
+        assert_eq!(
+            permission_problem(400, &Value::Null, "Zone → DNS → Read").as_deref(),
+            Some("HTTP 400: no error details")
+        );
+    }
 
-        let record = DnsRecord {
-            id: "1".to_string(),
-            name: "test.com".to_string(),
-            content: "127.0.0.1".to_string(),
-            record_type: "A".to_string(),
-            proxied: false,
-            ttl: 120,
-        };
+    #[tokio::test]
+    async fn test_request_and_response_bodies_are_logged_to_the_http_target() {
+        use axum::routing::put;
+        use std::io::{self, Write};
+        use std::sync::Arc;
+        use tracing_subscriber::layer::SubscriberExt;
+        use tracing_subscriber::Layer;
 
-        // Create a temporary directory for the test
-        let test_dir = Path::new("target/test_output");
-        fs::create_dir_all(test_dir).unwrap();
-        let original_cwd = std::env::current_dir().unwrap();
-        std::env::set_current_dir(test_dir).unwrap();
-
-        let result = backup_dns_record(&record);
-        assert!(result.is_ok());
-
-        let backup_dir = Path::new("backups");
-        assert!(backup_dir.exists());
-
-        let mut found = false;
-        for entry in fs::read_dir(backup_dir).unwrap() {
-            let entry = entry.unwrap();
-            let path = entry.path();
-            if path.is_file() && path.to_str().unwrap().contains("test.com_backup.json") {
-                #[cfg(unix)]
-                assert_eq!(
-                    fs::metadata(&path).unwrap().permissions().mode() & 0o777,
-                    0o600
-                );
+        #[derive(Clone, Default)]
+        struct Captured(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for Captured {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
 
-                let content = fs::read_to_string(path).unwrap();
-                let backed_up_record: DnsRecord = serde_json::from_str(&content).unwrap();
-                assert_eq!(backed_up_record.id, record.id);
-                found = true;
-                break;
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
             }
         }
 
-        // Cleanup
-        std::env::set_current_dir(original_cwd).unwrap();
-        fs::remove_dir_all(test_dir).unwrap();
+        let app = axum::Router::new().route(
+            "/dns_records/1",
+            put(|| async { r#"{"success":true,"errors":[],"messages":[],"result":null}"# }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/dns_records/1", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let captured = Captured::default();
+        let sink = captured.clone();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(move || sink.clone())
+                .with_filter(
+                    tracing_subscriber::filter::Targets::new()
+                        .with_target(HTTP_LOG_TARGET, tracing::Level::DEBUG),
+                ),
+        );
+        let _default = tracing::subscriber::set_default(subscriber);
+
+        let client = ReqwestClient::new();
+        let request = client
+            .put(&url)
+            .header("Authorization", "Bearer secret-token")
+            .json(&serde_json::json!({ "content": "203.0.113.9" }));
+        let envelope = send_cloudflare_request(&client, request).await.unwrap();
+        assert!(envelope.success);
+
+        let logged = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(logged.contains(&format!(
+            r#"Cloudflare request method=PUT url={} body={{"content":"203.0.113.9"}}"#,
+            url
+        )));
+        assert!(logged.contains(r#"Cloudflare response status=200 body={"success":true"#));
+        assert!(!logged.contains("secret-token"));
+    }
+
+    #[tokio::test]
+    async fn test_creates_are_not_retried_after_a_timeout() {
+        use axum::routing::post;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let app = axum::Router::new().route(
+            "/dns_records",
+            post(move || async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                time::sleep(Duration::from_secs(5)).await;
+                r#"{"success":true,"errors":[],"messages":[],"result":null}"#
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/dns_records", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
 
-        assert!(found, "Backup file was not found");
+        let client = ReqwestClient::builder()
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        let result = retry_cloudflare_if(is_retryable_create_error, || async {
+            send_cloudflare_request(&client, client.post(&url)).await
+        })
+        .await;
+        let err = result.unwrap_err();
+        assert!(matches!(&err, FlareSyncError::Network(e) if e.is_timeout()));
+        assert!(is_transient_cloudflare_error(&err));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        let refused = client
+            .post("http://127.0.0.1:1/dns_records")
+            .send()
+            .await
+            .unwrap_err();
+        assert!(is_retryable_create_error(&FlareSyncError::Network(refused)));
+    }
+
+    #[test]
+    fn test_export_error_classifies_status() {
+        let body =
+            r#"{"success":false,"errors":[{"code":10000,"message":"Authentication error"}]}"#;
+        assert!(matches!(
+            export_error(403, body, "zone"),
+            FlareSyncError::Cloudflare(message)
+                if message == "HTTP 403 exporting zone zone: 10000 Authentication error"
+        ));
+        assert!(matches!(
+            export_error(503, "<html>", "zone"),
+            FlareSyncError::CloudflareTransient(_)
+        ));
+    }
+
+    #[test]
+    fn test_pending_change_display() {
+        let record = DnsRecord {
+            id: "1".to_string(),
+            name: "www.example.com".to_string(),
+            content: "203.0.113.4".to_string(),
+            record_type: "A".to_string(),
+            proxied: true,
+            ttl: 1,
+            modified_on: None,
+        };
+        let change = pending_change(&record, &Ipv4Addr::new(198, 51, 100, 7)).unwrap();
+        assert_eq!(
+            change.to_string(),
+            "www.example.com: 203.0.113.4 → 198.51.100.7 [proxied, ttl=auto]"
+        );
+        assert_eq!(
+            pending_change(&record, &Ipv4Addr::new(203, 0, 113, 4)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_pending_change_and_access_check_serialize_for_json_output() {
+        let change = PendingChange {
+            domain: "www.example.com".to_string(),
+            current: "203.0.113.4".to_string(),
+            desired: "198.51.100.7".to_string(),
+            proxied: true,
+            ttl: 1,
+        };
+        assert_eq!(
+            serde_json::to_value(&change).unwrap(),
+            serde_json::json!({
+                "domain": "www.example.com",
+                "current": "203.0.113.4",
+                "desired": "198.51.100.7",
+                "proxied": true,
+                "ttl": 1
+            })
+        );
+        let check = AccessCheck {
+            name: "Token is active".to_string(),
+            problem: Some("token is disabled".to_string()),
+        };
+        assert_eq!(
+            serde_json::to_value(&check).unwrap(),
+            serde_json::json!({ "name": "Token is active", "problem": "token is disabled" })
+        );
     }
 
     #[test]
@@ -1,10 +1,253 @@
+use crate::admin::{self, AdminConfig};
+use crate::backup::{self, BackupMode, BackupRetention};
+use crate::dyndns::DynDnsConfig;
+use crate::encryption::EncryptionKey;
 use crate::errors::FlareSyncError;
+use crate::hooks::ChangeHook;
+use crate::log_rotation::RotationPolicy;
+use crate::logging::LogFormat;
+use crate::metrics::StatsdFormat;
+use crate::notify::{
+    self, AppriseConfig, DiscordConfig, EmailConfig, EventFilter, GotifyConfig, MatrixConfig,
+    MessageTemplates, MqttConfig, NotificationChannel, NotificationMode, NotifierConfig,
+    NtfyConfig, PushoverConfig, SignalConfig, SlackConfig, TeamsConfig, TelegramConfig,
+    WebhookConfig,
+};
+use crate::prefetch::PrefetchPolicy;
+use crate::rate_limit;
+use crate::s3::{self, S3Config};
+use crate::scheduler::{OverlapPolicy, StartupPolicy};
+use crate::summary::SummaryPeriod;
+use crate::syslog::{self, SyslogTarget};
+use std::collections::{BTreeMap, BTreeSet};
 use std::env;
-use std::path::PathBuf;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-const DEFAULT_UPDATE_INTERVAL_MINUTES: u64 = 5;
+pub const DEFAULT_UPDATE_INTERVAL_MINUTES: u64 = 5;
 const DEFAULT_STATUS_FILE_PATH: &str = "status/flaresync-status.json";
+const DEFAULT_HISTORY_FILE_PATH: &str = "status/flaresync-history.jsonl";
+const DEFAULT_LOCK_FILE_PATH: &str = "status/flaresync.lock";
+const DEFAULT_PID_FILE_PATH: &str = "status/flaresync.pid";
+const DEFAULT_LOG_LEVEL: &str = "info";
+const DEFAULT_SYSLOG_FACILITY: u8 = 3;
+const DEFAULT_LOG_MAX_SIZE_MB: u64 = 10;
+const DEFAULT_LOG_RETENTION: usize = 5;
+const CONFIG_FILE_ENV: &str = "FLARESYNC_CONFIG";
+const PROFILE_ENV: &str = "FLARESYNC_PROFILE";
+const PROFILES_KEY: &str = "profiles";
+
+pub const CONFIG_KEYS: &[&str] = &[
+    "CLOUDFLARE_API_TOKEN",
+    "CLOUDFLARE_API_TOKEN_FILE",
+    "CLOUDFLARE_ZONE_ID",
+    "CLOUDFLARE_RATE_LIMIT",
+    "DOMAIN_NAME",
+    "UPDATE_INTERVAL",
+    "DOMAIN_INTERVALS",
+    "PAUSED_DOMAINS",
+    "OVERLAP_POLICY",
+    "EXIT_AFTER_FAILURES",
+    "ADAPTIVE_MIN_INTERVAL",
+    "STARTUP_DELAY",
+    "STARTUP_SPLAY",
+    "SKIP_INITIAL_CHECK",
+    "PREFETCH_RECORDS",
+    "STATUS_FILE_PATH",
+    "HISTORY_FILE_PATH",
+    "LOCK_FILE_PATH",
+    "PID_FILE_PATH",
+    "STATE_FILE_PATH",
+    "DATABASE_PATH",
+    "FLARESYNC_STRICT",
+    "FLARESYNC_JOBS",
+    "HEALTH_BIND_ADDRESS",
+    "CONTROL_SOCKET",
+    "ADMIN_BIND_ADDRESS",
+    "ADMIN_TOKEN",
+    "DYNDNS_BIND_ADDRESS",
+    "DYNDNS_USERNAME",
+    "DYNDNS_PASSWORD",
+    "LOG_FORMAT",
+    "LOG_LEVEL",
+    "LOG_FILE",
+    "LOG_MAX_SIZE_MB",
+    "LOG_MAX_AGE_HOURS",
+    "LOG_RETENTION",
+    "LOG_JOURNALD",
+    "LOG_EVENTLOG",
+    "LOG_HTTP_BODIES",
+    "LOG_QUIET",
+    "OTLP_ENDPOINT",
+    "SYSLOG_ADDRESS",
+    "SYSLOG_FACILITY",
+    "STATSD_ADDRESS",
+    "STATSD_PREFIX",
+    "STATSD_FORMAT",
+    "HEARTBEAT_URL",
+    "SUMMARY_INTERVAL",
+    "SENTRY_DSN",
+    "ON_CHANGE_COMMAND",
+    "ON_CHANGE_TIMEOUT",
+    "BACKUP_DIR",
+    "BACKUP_COMPRESS",
+    "BACKUP_MODE",
+    "BACKUP_ENCRYPTION_KEY",
+    "BACKUP_ENCRYPTION_KEY_FILE",
+    "S3_ENDPOINT",
+    "S3_BUCKET",
+    "S3_REGION",
+    "S3_ACCESS_KEY_ID",
+    "S3_SECRET_ACCESS_KEY",
+    "S3_PREFIX",
+    "BACKUP_KEEP",
+    "BACKUP_MAX_AGE_DAYS",
+    "NOTIFY_TEMPLATE_UPDATED",
+    "NOTIFY_TEMPLATE_FAILED",
+    "NOTIFY_TEMPLATE_RECOVERED",
+    "NOTIFY_RETRY_ATTEMPTS",
+    "NOTIFY_MODE",
+    "NOTIFY_REMINDER_HOURS",
+    "WEBHOOK_URL",
+    "WEBHOOK_METHOD",
+    "WEBHOOK_HEADERS",
+    "WEBHOOK_BODY_TEMPLATE",
+    "WEBHOOK_EVENTS",
+    "WEBHOOK_FAILURE_THRESHOLD",
+    "DISCORD_WEBHOOK_URL",
+    "DISCORD_FAILURE_THRESHOLD",
+    "DISCORD_EVENTS",
+    "TELEGRAM_BOT_TOKEN",
+    "TELEGRAM_CHAT_ID",
+    "TELEGRAM_FAILURE_THRESHOLD",
+    "TELEGRAM_EVENTS",
+    "SLACK_WEBHOOK_URL",
+    "SLACK_EVENTS",
+    "SLACK_FAILURE_THRESHOLD",
+    "SMTP_HOST",
+    "SMTP_PORT",
+    "SMTP_TLS",
+    "SMTP_USERNAME",
+    "SMTP_PASSWORD",
+    "SMTP_FROM",
+    "SMTP_TO",
+    "SMTP_FAILURE_THRESHOLD",
+    "SMTP_EVENTS",
+    "NTFY_URL",
+    "NTFY_TOKEN",
+    "NTFY_PRIORITY",
+    "NTFY_TAGS",
+    "NTFY_EVENTS",
+    "NTFY_FAILURE_THRESHOLD",
+    "PUSHOVER_APP_TOKEN",
+    "PUSHOVER_USER_KEY",
+    "PUSHOVER_FAILURE_THRESHOLD",
+    "PUSHOVER_EVENTS",
+    "GOTIFY_URL",
+    "GOTIFY_APP_TOKEN",
+    "GOTIFY_PRIORITY",
+    "GOTIFY_FAILURE_THRESHOLD",
+    "GOTIFY_EVENTS",
+    "APPRISE_URL",
+    "APPRISE_URLS",
+    "APPRISE_TAG",
+    "APPRISE_EVENTS",
+    "APPRISE_FAILURE_THRESHOLD",
+    "MQTT_URL",
+    "MQTT_USERNAME",
+    "MQTT_PASSWORD",
+    "MQTT_TOPIC",
+    "MQTT_CLIENT_ID",
+    "MQTT_HA_DISCOVERY",
+    "MQTT_HA_DISCOVERY_PREFIX",
+    "MQTT_EVENTS",
+    "MQTT_FAILURE_THRESHOLD",
+    "MATRIX_HOMESERVER",
+    "MATRIX_ACCESS_TOKEN",
+    "MATRIX_ROOM_ID",
+    "MATRIX_FAILURE_THRESHOLD",
+    "MATRIX_EVENTS",
+    "TEAMS_WEBHOOK_URL",
+    "TEAMS_EVENTS",
+    "TEAMS_FAILURE_THRESHOLD",
+    "SIGNAL_API_URL",
+    "SIGNAL_NUMBER",
+    "SIGNAL_RECIPIENTS",
+    "SIGNAL_EVENTS",
+    "SIGNAL_FAILURE_THRESHOLD",
+];
+
+/// Settings that hold credentials. Each can instead be read from the file named by the same
+/// key with `_FILE` appended, and `migrate-config` moves them into such files rather than
+/// the config file.
+pub const SECRET_KEYS: &[&str] = &[
+    "CLOUDFLARE_API_TOKEN",
+    "ADMIN_TOKEN",
+    "DYNDNS_PASSWORD",
+    "HEARTBEAT_URL",
+    "SENTRY_DSN",
+    "BACKUP_ENCRYPTION_KEY",
+    "S3_SECRET_ACCESS_KEY",
+    "WEBHOOK_URL",
+    "WEBHOOK_HEADERS",
+    "DISCORD_WEBHOOK_URL",
+    "TELEGRAM_BOT_TOKEN",
+    "SLACK_WEBHOOK_URL",
+    "SMTP_PASSWORD",
+    "NTFY_TOKEN",
+    "PUSHOVER_APP_TOKEN",
+    "PUSHOVER_USER_KEY",
+    "GOTIFY_APP_TOKEN",
+    "APPRISE_URLS",
+    "MQTT_PASSWORD",
+    "MATRIX_ACCESS_TOKEN",
+    "TEAMS_WEBHOOK_URL",
+];
+
+const ENV_ONLY_KEYS: &[&str] = &[CONFIG_FILE_ENV, PROFILE_ENV];
+const STRICT_ENV_PREFIXES: &[&str] = &["FLARESYNC_", "CLOUDFLARE_"];
+
+#[derive(Debug, Default, Clone)]
+pub struct LoadOptions {
+    pub config_path: Option<PathBuf>,
+    pub profile: Option<String>,
+    pub strict: bool,
+}
+
+impl LoadOptions {
+    /// Where [`Config::load`] reads settings from, for log messages.
+    pub fn describe(&self) -> String {
+        let config_path = self
+            .config_path
+            .clone()
+            .or_else(|| env::var(CONFIG_FILE_ENV).ok().map(PathBuf::from));
+        let profile = self.profile.clone().or_else(|| env::var(PROFILE_ENV).ok());
+        let mut sources = Vec::new();
+        match (config_path, profile) {
+            (Some(path), Some(profile)) => {
+                sources.push(format!("{} (profile {})", path.display(), profile))
+            }
+            (Some(path), None) => sources.push(path.display().to_string()),
+            (None, _) => {}
+        }
+        if Path::new(".env").is_file() {
+            sources.push(".env".to_string());
+        }
+        sources.push("environment".to_string());
+        sources.join(" + ")
+    }
+}
+
+/// One of the sync jobs `run` drives at the same time: a configuration and the options
+/// that load it again on reload.
+#[derive(Debug)]
+pub struct Job {
+    pub name: Option<String>,
+    pub load_options: LoadOptions,
+    pub config: Config,
+}
 
 #[derive(Debug)]
 pub struct Config {
@@ -12,24 +255,1349 @@ pub struct Config {
     pub zone_id: String,
     pub domain_names: Vec<String>,
     pub update_interval: Duration,
+    pub domain_intervals: BTreeMap<String, Duration>,
+    pub paused_domains: BTreeSet<String>,
+    pub overlap_policy: OverlapPolicy,
+    pub exit_after_failures: Option<u64>,
+    pub adaptive_min_interval: Option<Duration>,
+    pub cloudflare_rate_limit: u32,
+    pub startup: StartupPolicy,
+    pub prefetch: PrefetchPolicy,
     pub status_file_path: PathBuf,
+    pub history_file_path: PathBuf,
+    pub lock_file_path: PathBuf,
+    pub pid_file_path: PathBuf,
+    pub state_file_path: PathBuf,
+    pub database_path: Option<PathBuf>,
+    pub health_bind_address: Option<SocketAddr>,
+    pub control_socket: Option<PathBuf>,
+    pub admin: Option<AdminConfig>,
+    pub dyndns: Option<DynDnsConfig>,
+    pub log_format: LogFormat,
+    pub log_level: String,
+    pub log_file: Option<PathBuf>,
+    pub log_rotation: RotationPolicy,
+    pub log_journald: Option<bool>,
+    pub log_eventlog: Option<bool>,
+    pub log_http_bodies: bool,
+    pub log_quiet: bool,
+    pub otlp_endpoint: Option<String>,
+    pub syslog_target: Option<SyslogTarget>,
+    pub syslog_facility: u8,
+    pub statsd: Option<StatsdConfig>,
+    pub heartbeat_url: Option<String>,
+    pub summary_period: Option<SummaryPeriod>,
+    pub sentry_dsn: Option<String>,
+    pub on_change: Option<ChangeHook>,
+    pub backup_dir: PathBuf,
+    pub backup_compress: bool,
+    pub backup_mode: BackupMode,
+    pub backup_encryption_key: Option<EncryptionKey>,
+    pub offsite_backup: Option<S3Config>,
+    pub backup_retention: BackupRetention,
+    pub notification_channels: Vec<NotificationChannel>,
+    pub notification_templates: MessageTemplates,
+    pub notification_retry_attempts: u32,
+    pub notification_mode: NotificationMode,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatsdConfig {
+    pub address: String,
+    pub prefix: String,
+    pub format: StatsdFormat,
+}
+
+#[derive(Debug, Default)]
+pub struct ConfigBuilder {
+    api_token: Option<String>,
+    zone_id: Option<String>,
+    domain_names: Vec<String>,
+    update_interval: Option<Duration>,
+    domain_intervals: Vec<(String, Duration)>,
+    paused_domains: Vec<String>,
+    overlap_policy: OverlapPolicy,
+    exit_after_failures: Option<u64>,
+    adaptive_min_interval: Option<Duration>,
+    cloudflare_rate_limit: Option<u32>,
+    startup: StartupPolicy,
+    prefetch: PrefetchPolicy,
+    status_file_path: Option<PathBuf>,
+    history_file_path: Option<PathBuf>,
+    lock_file_path: Option<PathBuf>,
+    pid_file_path: Option<PathBuf>,
+    state_file_path: Option<PathBuf>,
+    database_path: Option<PathBuf>,
+    health_bind_address: Option<SocketAddr>,
+    control_socket: Option<PathBuf>,
+    admin: Option<AdminConfig>,
+    dyndns: Option<DynDnsConfig>,
+    log_format: LogFormat,
+    log_level: Option<String>,
+    log_file: Option<PathBuf>,
+    log_rotation: Option<RotationPolicy>,
+    log_journald: Option<bool>,
+    log_eventlog: Option<bool>,
+    log_http_bodies: bool,
+    log_quiet: bool,
+    otlp_endpoint: Option<String>,
+    syslog_target: Option<SyslogTarget>,
+    syslog_facility: Option<u8>,
+    statsd: Option<StatsdConfig>,
+    heartbeat_url: Option<String>,
+    summary_period: Option<SummaryPeriod>,
+    sentry_dsn: Option<String>,
+    on_change: Option<ChangeHook>,
+    backup_dir: Option<PathBuf>,
+    backup_compress: bool,
+    backup_mode: BackupMode,
+    backup_encryption_key: Option<EncryptionKey>,
+    offsite_backup: Option<S3Config>,
+    backup_retention: BackupRetention,
+    notification_channels: Vec<NotificationChannel>,
+    notification_templates: MessageTemplates,
+    notification_retry_attempts: Option<u32>,
+    notification_mode: NotificationMode,
+}
+
+impl ConfigBuilder {
+    pub fn api_token(mut self, api_token: impl Into<String>) -> Self {
+        self.api_token = Some(api_token.into());
+        self
+    }
+
+    pub fn zone(mut self, zone_id: impl Into<String>) -> Self {
+        self.zone_id = Some(zone_id.into());
+        self
+    }
+
+    pub fn domain(mut self, domain_name: impl Into<String>) -> Self {
+        self.domain_names.push(domain_name.into());
+        self
+    }
+
+    pub fn domains<I, S>(mut self, domain_names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.domain_names
+            .extend(domain_names.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn update_interval(mut self, update_interval: Duration) -> Self {
+        self.update_interval = Some(update_interval);
+        self
+    }
+
+    pub fn domain_interval(mut self, domain_name: impl Into<String>, interval: Duration) -> Self {
+        self.domain_intervals.push((domain_name.into(), interval));
+        self
+    }
+
+    pub fn paused_domain(mut self, domain_name: impl Into<String>) -> Self {
+        self.paused_domains.push(domain_name.into());
+        self
+    }
+
+    pub fn overlap_policy(mut self, overlap_policy: OverlapPolicy) -> Self {
+        self.overlap_policy = overlap_policy;
+        self
+    }
+
+    /// Makes `run` return an error once this many cycles in a row have failed, so a
+    /// supervisor can restart the daemon or alert someone.
+    pub fn exit_after_failures(mut self, failures: u64) -> Self {
+        self.exit_after_failures = Some(failures);
+        self
+    }
+
+    /// Polls every `min_interval` after the public IP changes, then doubles the interval
+    /// after each cycle without a change until it is back at the configured interval.
+    pub fn adaptive_min_interval(mut self, min_interval: Duration) -> Self {
+        self.adaptive_min_interval = Some(min_interval);
+        self
+    }
+
+    pub fn cloudflare_rate_limit(mut self, requests_per_minute: u32) -> Self {
+        self.cloudflare_rate_limit = Some(requests_per_minute);
+        self
+    }
+
+    pub fn startup(mut self, startup: StartupPolicy) -> Self {
+        self.startup = startup;
+        self
+    }
+
+    /// Fetches every record once before the first cycle; see [`PrefetchPolicy`].
+    pub fn prefetch(mut self, prefetch: PrefetchPolicy) -> Self {
+        self.prefetch = prefetch;
+        self
+    }
+
+    pub fn status_file_path(mut self, status_file_path: impl Into<PathBuf>) -> Self {
+        self.status_file_path = Some(status_file_path.into());
+        self
+    }
+
+    pub fn history_file_path(mut self, history_file_path: impl Into<PathBuf>) -> Self {
+        self.history_file_path = Some(history_file_path.into());
+        self
+    }
+
+    pub fn control_socket(mut self, control_socket: impl Into<PathBuf>) -> Self {
+        self.control_socket = Some(control_socket.into());
+        self
+    }
+
+    pub fn admin(mut self, admin: AdminConfig) -> Self {
+        self.admin = Some(admin);
+        self
+    }
+
+    pub fn dyndns(mut self, dyndns: DynDnsConfig) -> Self {
+        self.dyndns = Some(dyndns);
+        self
+    }
+
+    pub fn lock_file_path(mut self, lock_file_path: impl Into<PathBuf>) -> Self {
+        self.lock_file_path = Some(lock_file_path.into());
+        self
+    }
+
+    pub fn pid_file_path(mut self, pid_file_path: impl Into<PathBuf>) -> Self {
+        self.pid_file_path = Some(pid_file_path.into());
+        self
+    }
+
+    pub fn state_file_path(mut self, state_file_path: impl Into<PathBuf>) -> Self {
+        self.state_file_path = Some(state_file_path.into());
+        self
+    }
+
+    /// Keeps state, history, and backups in a SQLite database instead of separate files.
+    pub fn database_path(mut self, database_path: impl Into<PathBuf>) -> Self {
+        self.database_path = Some(database_path.into());
+        self
+    }
+
+    pub fn health_bind_address(mut self, health_bind_address: SocketAddr) -> Self {
+        self.health_bind_address = Some(health_bind_address);
+        self
+    }
+
+    pub fn log_format(mut self, log_format: LogFormat) -> Self {
+        self.log_format = log_format;
+        self
+    }
+
+    pub fn log_level(mut self, log_level: impl Into<String>) -> Self {
+        self.log_level = Some(log_level.into());
+        self
+    }
+
+    pub fn log_file(mut self, log_file: impl Into<PathBuf>) -> Self {
+        self.log_file = Some(log_file.into());
+        self
+    }
+
+    pub fn log_rotation(mut self, log_rotation: RotationPolicy) -> Self {
+        self.log_rotation = Some(log_rotation);
+        self
+    }
+
+    pub fn log_journald(mut self, log_journald: bool) -> Self {
+        self.log_journald = Some(log_journald);
+        self
+    }
+
+    pub fn log_eventlog(mut self, log_eventlog: bool) -> Self {
+        self.log_eventlog = Some(log_eventlog);
+        self
+    }
+
+    pub fn log_http_bodies(mut self, log_http_bodies: bool) -> Self {
+        self.log_http_bodies = log_http_bodies;
+        self
+    }
+
+    pub fn log_quiet(mut self, log_quiet: bool) -> Self {
+        self.log_quiet = log_quiet;
+        self
+    }
+
+    pub fn otlp_endpoint(mut self, otlp_endpoint: impl Into<String>) -> Self {
+        self.otlp_endpoint = Some(otlp_endpoint.into());
+        self
+    }
+
+    pub fn syslog(mut self, target: SyslogTarget, facility: u8) -> Self {
+        self.syslog_target = Some(target);
+        self.syslog_facility = Some(facility);
+        self
+    }
+
+    pub fn statsd(mut self, statsd: StatsdConfig) -> Self {
+        self.statsd = Some(statsd);
+        self
+    }
+
+    pub fn heartbeat_url(mut self, heartbeat_url: impl Into<String>) -> Self {
+        self.heartbeat_url = Some(heartbeat_url.into());
+        self
+    }
+
+    pub fn summary_period(mut self, summary_period: SummaryPeriod) -> Self {
+        self.summary_period = Some(summary_period);
+        self
+    }
+
+    pub fn sentry_dsn(mut self, sentry_dsn: impl Into<String>) -> Self {
+        self.sentry_dsn = Some(sentry_dsn.into());
+        self
+    }
+
+    pub fn on_change(mut self, hook: ChangeHook) -> Self {
+        self.on_change = Some(hook);
+        self
+    }
+
+    pub fn backup_dir(mut self, backup_dir: impl Into<PathBuf>) -> Self {
+        self.backup_dir = Some(backup_dir.into());
+        self
+    }
+
+    pub fn backup_compress(mut self, compress: bool) -> Self {
+        self.backup_compress = compress;
+        self
+    }
+
+    pub fn backup_mode(mut self, mode: BackupMode) -> Self {
+        self.backup_mode = mode;
+        self
+    }
+
+    pub fn backup_encryption_key(mut self, key: EncryptionKey) -> Self {
+        self.backup_encryption_key = Some(key);
+        self
+    }
+
+    pub fn offsite_backup(mut self, offsite: S3Config) -> Self {
+        self.offsite_backup = Some(offsite);
+        self
+    }
+
+    pub fn backup_retention(mut self, retention: BackupRetention) -> Self {
+        self.backup_retention = retention;
+        self
+    }
+
+    pub fn notification_channel(mut self, channel: NotificationChannel) -> Self {
+        self.notification_channels.push(channel);
+        self
+    }
+
+    pub fn notification_templates(mut self, templates: MessageTemplates) -> Self {
+        self.notification_templates = templates;
+        self
+    }
+
+    pub fn notification_retry_attempts(mut self, attempts: u32) -> Self {
+        self.notification_retry_attempts = Some(attempts);
+        self
+    }
+
+    pub fn notification_mode(mut self, mode: NotificationMode) -> Self {
+        self.notification_mode = mode;
+        self
+    }
+
+    pub fn build(self) -> Result<Config, FlareSyncError> {
+        let api_token = self
+            .api_token
+            .filter(|token| !token.trim().is_empty())
+            .ok_or_else(|| FlareSyncError::Config("API token must be set".to_string()))?;
+        let zone_id = self
+            .zone_id
+            .filter(|zone| !zone.trim().is_empty())
+            .ok_or_else(|| FlareSyncError::Config("Zone ID must be set".to_string()))?;
+
+        let domain_names: Vec<String> = self
+            .domain_names
+            .iter()
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(normalize_domain_name)
+            .collect::<Result<_, _>>()?;
+        if domain_names.is_empty() {
+            return Err(FlareSyncError::Config(
+                "At least one non-empty domain must be configured".to_string(),
+            ));
+        }
+
+        let update_interval = self
+            .update_interval
+            .unwrap_or(Duration::from_secs(DEFAULT_UPDATE_INTERVAL_MINUTES * 60));
+        if update_interval < Duration::from_secs(60) {
+            return Err(FlareSyncError::Config(
+                "Update interval must be at least 1 minute".to_string(),
+            ));
+        }
+
+        let mut domain_intervals = BTreeMap::new();
+        for (domain_name, interval) in self.domain_intervals {
+            let domain_name = normalize_domain_name(domain_name.trim())?;
+            if !domain_names.contains(&domain_name) {
+                return Err(FlareSyncError::Config(format!(
+                    "Interval set for {}, which is not a configured domain",
+                    domain_name
+                )));
+            }
+            if interval < Duration::from_secs(60) {
+                return Err(FlareSyncError::Config(format!(
+                    "Update interval for {} must be at least 1 minute",
+                    domain_name
+                )));
+            }
+            domain_intervals.insert(domain_name, interval);
+        }
+        let mut paused_domains = BTreeSet::new();
+        for domain_name in self.paused_domains {
+            let domain_name = normalize_domain_name(domain_name.trim())?;
+            if !domain_names.contains(&domain_name) {
+                return Err(FlareSyncError::Config(format!(
+                    "{} is paused but is not a configured domain",
+                    domain_name
+                )));
+            }
+            paused_domains.insert(domain_name);
+        }
+        if self
+            .admin
+            .as_ref()
+            .is_some_and(|admin| admin.token.chars().count() < admin::MIN_TOKEN_LENGTH)
+        {
+            return Err(FlareSyncError::Config(format!(
+                "The admin API token must be at least {} characters",
+                admin::MIN_TOKEN_LENGTH
+            )));
+        }
+        if self
+            .adaptive_min_interval
+            .is_some_and(|interval| interval < Duration::from_secs(60))
+        {
+            return Err(FlareSyncError::Config(
+                "Adaptive minimum interval must be at least 1 minute".to_string(),
+            ));
+        }
+        if self.exit_after_failures == Some(0) {
+            return Err(FlareSyncError::Config(
+                "Failure threshold must be at least 1 cycle".to_string(),
+            ));
+        }
+        if self.cloudflare_rate_limit == Some(0) {
+            return Err(FlareSyncError::Config(
+                "Cloudflare rate limit must be at least 1 request per minute".to_string(),
+            ));
+        }
+        #[cfg(not(feature = "sqlite"))]
+        if self.database_path.is_some() {
+            return Err(FlareSyncError::Config(
+                "DATABASE_PATH requires a build with --features sqlite".to_string(),
+            ));
+        }
+        if self.backup_mode == BackupMode::Journal && self.backup_compress {
+            return Err(FlareSyncError::Config(
+                "BACKUP_COMPRESS cannot be used with BACKUP_MODE=journal".to_string(),
+            ));
+        }
+
+        let status_file_path = self
+            .status_file_path
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_STATUS_FILE_PATH));
+        Ok(Config {
+            api_token,
+            zone_id,
+            domain_names,
+            update_interval,
+            domain_intervals,
+            paused_domains,
+            overlap_policy: self.overlap_policy,
+            exit_after_failures: self.exit_after_failures,
+            adaptive_min_interval: self.adaptive_min_interval,
+            cloudflare_rate_limit: self
+                .cloudflare_rate_limit
+                .unwrap_or(rate_limit::DEFAULT_REQUESTS_PER_MINUTE),
+            startup: self.startup,
+            prefetch: self.prefetch,
+            state_file_path: self
+                .state_file_path
+                .unwrap_or_else(|| status_file_path.with_extension("state.json")),
+            database_path: self.database_path,
+            status_file_path,
+            history_file_path: self
+                .history_file_path
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_HISTORY_FILE_PATH)),
+            lock_file_path: self
+                .lock_file_path
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_LOCK_FILE_PATH)),
+            pid_file_path: self
+                .pid_file_path
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_PID_FILE_PATH)),
+            health_bind_address: self.health_bind_address,
+            control_socket: self.control_socket,
+            admin: self.admin,
+            dyndns: self.dyndns,
+            log_format: self.log_format,
+            log_level: self
+                .log_level
+                .unwrap_or_else(|| DEFAULT_LOG_LEVEL.to_string()),
+            log_file: self.log_file,
+            log_rotation: self.log_rotation.unwrap_or(RotationPolicy {
+                max_size_bytes: Some(DEFAULT_LOG_MAX_SIZE_MB * 1024 * 1024),
+                max_age: None,
+                retention: DEFAULT_LOG_RETENTION,
+            }),
+            log_journald: self.log_journald,
+            log_eventlog: self.log_eventlog,
+            log_http_bodies: self.log_http_bodies,
+            log_quiet: self.log_quiet,
+            otlp_endpoint: self.otlp_endpoint,
+            syslog_target: self.syslog_target,
+            syslog_facility: self.syslog_facility.unwrap_or(DEFAULT_SYSLOG_FACILITY),
+            statsd: self.statsd,
+            heartbeat_url: self.heartbeat_url,
+            summary_period: self.summary_period,
+            sentry_dsn: self.sentry_dsn,
+            on_change: self.on_change,
+            backup_dir: self
+                .backup_dir
+                .unwrap_or_else(|| PathBuf::from(backup::DEFAULT_BACKUP_DIR)),
+            backup_compress: self.backup_compress,
+            backup_mode: self.backup_mode,
+            backup_encryption_key: self.backup_encryption_key,
+            offsite_backup: self.offsite_backup,
+            backup_retention: self.backup_retention,
+            notification_channels: self.notification_channels,
+            notification_templates: self.notification_templates,
+            notification_retry_attempts: self
+                .notification_retry_attempts
+                .unwrap_or(notify::DEFAULT_RETRY_ATTEMPTS),
+            notification_mode: self.notification_mode,
+        })
+    }
+}
+
+pub(crate) fn normalize_domain_name(domain_name: &str) -> Result<String, FlareSyncError> {
+    let (wildcard, name) = match domain_name.strip_prefix("*.") {
+        Some(rest) => (true, rest),
+        None => (false, domain_name),
+    };
+
+    let ascii = idna::domain_to_ascii(name).map_err(|e| {
+        FlareSyncError::Config(format!("Invalid domain name '{}': {}", domain_name, e))
+    })?;
+    if ascii.is_empty() || ascii.split('.').any(|label| label.is_empty()) {
+        return Err(FlareSyncError::Config(format!(
+            "Invalid domain name '{}': empty label",
+            domain_name
+        )));
+    }
+
+    if wildcard {
+        Ok(format!("*.{}", ascii))
+    } else {
+        Ok(ascii)
+    }
+}
+
+#[derive(Debug, Default)]
+struct ConfigSource {
+    file_values: BTreeMap<String, String>,
+    unknown_file_keys: Vec<String>,
+    /// Secrets read from their `_FILE` settings, by key.
+    secret_files: BTreeMap<String, String>,
+}
+
+impl ConfigSource {
+    fn load(options: &LoadOptions) -> Result<Self, FlareSyncError> {
+        dotenvy::dotenv().ok();
+
+        let config_path = options
+            .config_path
+            .clone()
+            .or_else(|| env::var(CONFIG_FILE_ENV).ok().map(PathBuf::from));
+        let profile = options
+            .profile
+            .clone()
+            .or_else(|| env::var(PROFILE_ENV).ok());
+        let (file_values, unknown_file_keys) = match (config_path, profile) {
+            (Some(path), profile) => read_config_file(&path, profile.as_deref())?,
+            (None, Some(profile)) => {
+                return Err(FlareSyncError::Config(format!(
+                    "Profile '{}' requires a config file",
+                    profile
+                )))
+            }
+            (None, None) => (BTreeMap::new(), Vec::new()),
+        };
+
+        let mut source = Self {
+            file_values,
+            unknown_file_keys,
+            secret_files: BTreeMap::new(),
+        };
+        for &key in SECRET_KEYS {
+            let file_key = format!("{}_FILE", key);
+            let Some(path) = source.get(&file_key).filter(|_| source.get(key).is_none()) else {
+                continue;
+            };
+            let secret = fs::read_to_string(&path).map_err(|e| {
+                FlareSyncError::Config(format!("Failed to read {} {}: {}", file_key, path, e))
+            })?;
+            source
+                .secret_files
+                .insert(key.to_string(), secret.trim().to_string());
+        }
+        Ok(source)
+    }
+
+    fn strict(&self, options: &LoadOptions) -> Result<bool, FlareSyncError> {
+        if options.strict {
+            return Ok(true);
+        }
+        match self.get("FLARESYNC_STRICT") {
+            Some(value) => parse_bool("FLARESYNC_STRICT", &value),
+            None => Ok(false),
+        }
+    }
+
+    fn check_unknown_keys(&self) -> Result<(), FlareSyncError> {
+        let mut problems: Vec<String> = self
+            .unknown_file_keys
+            .iter()
+            .map(|key| {
+                let name = key.rsplit('.').next().unwrap_or(key);
+                describe_unknown_key(key, "config file", &name.to_ascii_uppercase())
+            })
+            .collect();
+
+        let mut env_names: Vec<String> = env::vars_os()
+            .filter_map(|(name, _)| name.into_string().ok())
+            .filter(|name| is_unknown_env_key(name))
+            .collect();
+        env_names.sort();
+        problems.extend(
+            env_names
+                .iter()
+                .map(|name| describe_unknown_key(name, "environment", name)),
+        );
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(FlareSyncError::Config(format!(
+                "Strict mode rejected unknown settings: {}",
+                problems.join("; ")
+            )))
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<String> {
+        env::var(key)
+            .ok()
+            .or_else(|| self.file_values.get(&key.to_ascii_lowercase()).cloned())
+            .or_else(|| self.secret_files.get(key).cloned())
+    }
+}
+
+/// Whether `key` names a setting, including the `_FILE` variant of a secret.
+fn is_config_key(key: &str) -> bool {
+    CONFIG_KEYS.contains(&key)
+        || key
+            .strip_suffix("_FILE")
+            .is_some_and(|secret| SECRET_KEYS.contains(&secret))
+}
+
+fn is_known_key(key: &str) -> bool {
+    is_config_key(key) || ENV_ONLY_KEYS.contains(&key)
+}
+
+fn is_unknown_env_key(name: &str) -> bool {
+    if is_known_key(name) {
+        return false;
+    }
+    STRICT_ENV_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+        || CONFIG_KEYS.iter().any(|key| name.starts_with(key))
+}
+
+fn describe_unknown_key(key: &str, origin: &str, lookup: &str) -> String {
+    match suggest_key(lookup) {
+        Some(suggestion) => format!(
+            "unknown key '{}' in {} (did you mean '{}'?)",
+            key, origin, suggestion
+        ),
+        None => format!("unknown key '{}' in {}", key, origin),
+    }
+}
+
+fn suggest_key(name: &str) -> Option<&'static str> {
+    CONFIG_KEYS
+        .iter()
+        .copied()
+        .filter(|key| name.starts_with(key) || key.starts_with(name))
+        .max_by_key(|key| key.len())
+        .or_else(|| {
+            CONFIG_KEYS
+                .iter()
+                .copied()
+                .map(|key| (key, edit_distance(name, key)))
+                .filter(|(key, distance)| *distance <= key.len() / 3)
+                .min_by_key(|(_, distance)| *distance)
+                .map(|(key, _)| key)
+        })
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+pub(crate) fn parse_bool(key: &str, value: &str) -> Result<bool, FlareSyncError> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Ok(true),
+        "false" | "0" | "no" | "off" => Ok(false),
+        _ => Err(FlareSyncError::Config(format!(
+            "{} must be true or false",
+            key
+        ))),
+    }
+}
+
+fn unknown_table_keys(table: &toml::Table, prefix: &str) -> Vec<String> {
+    table
+        .keys()
+        .filter(|key| !is_config_key(&key.to_ascii_uppercase()))
+        .map(|key| format!("{}{}", prefix, key))
+        .collect()
+}
+
+type FileValues = (BTreeMap<String, String>, Vec<String>);
+
+fn read_config_file(path: &Path, profile: Option<&str>) -> Result<FileValues, FlareSyncError> {
+    let contents = fs::read_to_string(path).map_err(|e| {
+        FlareSyncError::Config(format!(
+            "Failed to read config file {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    let mut table: toml::Table = toml::from_str(&contents).map_err(|e| {
+        FlareSyncError::Config(format!("Invalid config file {}: {}", path.display(), e))
+    })?;
+
+    let profiles = match table.remove(PROFILES_KEY) {
+        Some(toml::Value::Table(profiles)) => profiles,
+        Some(_) => {
+            return Err(FlareSyncError::Config(format!(
+                "Invalid config file {}: {} must be a table",
+                path.display(),
+                PROFILES_KEY
+            )))
+        }
+        None => toml::Table::new(),
+    };
+
+    let mut unknown_keys = unknown_table_keys(&table, "");
+    for (name, profile_table) in &profiles {
+        if let toml::Value::Table(profile_table) = profile_table {
+            unknown_keys.extend(unknown_table_keys(
+                profile_table,
+                &format!("{}.{}.", PROFILES_KEY, name),
+            ));
+        }
+    }
+
+    let mut values = table_to_values(table)?;
+    if let Some(profile) = profile {
+        let profile_table = match profiles.get(profile) {
+            Some(toml::Value::Table(profile_table)) => profile_table.clone(),
+            Some(_) => {
+                return Err(FlareSyncError::Config(format!(
+                    "Profile '{}' in {} must be a table",
+                    profile,
+                    path.display()
+                )))
+            }
+            None => {
+                let available: Vec<&str> = profiles.keys().map(String::as_str).collect();
+                return Err(FlareSyncError::Config(format!(
+                    "Profile '{}' not found in {} (available: {})",
+                    profile,
+                    path.display(),
+                    if available.is_empty() {
+                        "none".to_string()
+                    } else {
+                        available.join(", ")
+                    }
+                )));
+            }
+        };
+        values.extend(table_to_values(profile_table)?);
+    }
+
+    Ok((values, unknown_keys))
+}
+
+fn table_to_values(table: toml::Table) -> Result<BTreeMap<String, String>, FlareSyncError> {
+    table
+        .into_iter()
+        .map(|(key, value)| {
+            let value = toml_value_to_string(&key, value)?;
+            Ok((key.to_ascii_lowercase(), value))
+        })
+        .collect()
+}
+
+fn toml_value_to_string(key: &str, value: toml::Value) -> Result<String, FlareSyncError> {
+    match value {
+        toml::Value::String(s) => Ok(s),
+        toml::Value::Integer(i) => Ok(i.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        toml::Value::Boolean(b) => Ok(b.to_string()),
+        toml::Value::Array(items) => items
+            .into_iter()
+            .map(|item| toml_value_to_string(key, item))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|items| items.join(",")),
+        _ => Err(FlareSyncError::Config(format!(
+            "Unsupported value for config key {}",
+            key
+        ))),
+    }
+}
+
+fn parse_u64(source: &ConfigSource, key: &str) -> Result<Option<u64>, FlareSyncError> {
+    source
+        .get(key)
+        .map(|value| {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| FlareSyncError::Config(format!("{} must be a number", key)))
+        })
+        .transpose()
+}
+
+fn rotation_policy_from_source(source: &ConfigSource) -> Result<RotationPolicy, FlareSyncError> {
+    let max_size_mb = parse_u64(source, "LOG_MAX_SIZE_MB")?.unwrap_or(DEFAULT_LOG_MAX_SIZE_MB);
+    let max_size_bytes =
+        match max_size_mb {
+            0 => None,
+            mb => Some(mb.checked_mul(1024 * 1024).ok_or_else(|| {
+                FlareSyncError::Config("LOG_MAX_SIZE_MB is too large".to_string())
+            })?),
+        };
+    let max_age = match parse_u64(source, "LOG_MAX_AGE_HOURS")? {
+        None | Some(0) => None,
+        Some(hours) => Some(Duration::from_secs(hours.checked_mul(3600).ok_or_else(
+            || FlareSyncError::Config("LOG_MAX_AGE_HOURS is too large".to_string()),
+        )?)),
+    };
+    let retention = parse_u64(source, "LOG_RETENTION")?
+        .map(|retention| retention as usize)
+        .unwrap_or(DEFAULT_LOG_RETENTION);
+
+    Ok(RotationPolicy {
+        max_size_bytes,
+        max_age,
+        retention,
+    })
+}
+
+fn parse_failure_threshold(
+    source: &ConfigSource,
+    key: &str,
+) -> Result<Option<u32>, FlareSyncError> {
+    Ok(
+        parse_u64(source, key)?
+            .map(|threshold| u32::try_from(threshold).unwrap_or(u32::MAX).max(1)),
+    )
+}
+
+fn notification_channel(
+    source: &ConfigSource,
+    prefix: &str,
+    notifier: NotifierConfig,
+    default_failure_threshold: Option<u32>,
+) -> Result<NotificationChannel, FlareSyncError> {
+    let mut filter = EventFilter::new(default_failure_threshold);
+    if let Some(events) = source.get(&format!("{}_EVENTS", prefix)) {
+        filter.events = notify::parse_event_kinds(&events)?;
+    }
+    if let Some(threshold) =
+        parse_failure_threshold(source, &format!("{}_FAILURE_THRESHOLD", prefix))?
+    {
+        filter.failure_threshold = Some(threshold);
+    }
+    filter.reminder_interval = parse_u64(source, "NOTIFY_REMINDER_HOURS")?
+        .filter(|hours| *hours > 0)
+        .map(|hours| Duration::from_secs(hours.saturating_mul(3600)));
+    Ok(NotificationChannel { notifier, filter })
+}
+
+fn notification_channels_from_source(
+    source: &ConfigSource,
+) -> Result<Vec<NotificationChannel>, FlareSyncError> {
+    let threshold = Some(notify::DEFAULT_FAILURE_THRESHOLD);
+    let mut channels = Vec::new();
+    if let Some(url) = source.get("WEBHOOK_URL") {
+        let mut webhook = WebhookConfig::new(url);
+        if let Some(method) = source.get("WEBHOOK_METHOD") {
+            webhook.method = WebhookConfig::parse_method(&method)?;
+        }
+        if let Some(headers) = source.get("WEBHOOK_HEADERS") {
+            webhook.headers = WebhookConfig::parse_headers(&headers)?;
+        }
+        webhook.body_template = source.get("WEBHOOK_BODY_TEMPLATE");
+        channels.push(notification_channel(
+            source,
+            "WEBHOOK",
+            NotifierConfig::Webhook(webhook),
+            None,
+        )?);
+    }
+    if let Some(webhook_url) = source.get("DISCORD_WEBHOOK_URL") {
+        channels.push(notification_channel(
+            source,
+            "DISCORD",
+            NotifierConfig::Discord(DiscordConfig::new(webhook_url)),
+            threshold,
+        )?);
+    }
+    if let Some(bot_token) = source.get("TELEGRAM_BOT_TOKEN") {
+        let chat_id = source.get("TELEGRAM_CHAT_ID").ok_or_else(|| {
+            FlareSyncError::Config(
+                "TELEGRAM_CHAT_ID must be set when TELEGRAM_BOT_TOKEN is set".to_string(),
+            )
+        })?;
+        channels.push(notification_channel(
+            source,
+            "TELEGRAM",
+            NotifierConfig::Telegram(TelegramConfig::new(bot_token, chat_id)),
+            threshold,
+        )?);
+    }
+    if let Some(webhook_url) = source.get("SLACK_WEBHOOK_URL") {
+        channels.push(notification_channel(
+            source,
+            "SLACK",
+            NotifierConfig::Slack(SlackConfig::new(webhook_url)),
+            None,
+        )?);
+    }
+    if let Some(host) = source.get("SMTP_HOST") {
+        let from = source.get("SMTP_FROM").ok_or_else(|| {
+            FlareSyncError::Config("SMTP_FROM must be set when SMTP_HOST is set".to_string())
+        })?;
+        let to = source
+            .get("SMTP_TO")
+            .unwrap_or_default()
+            .split([',', ';'])
+            .map(str::trim)
+            .filter(|address| !address.is_empty())
+            .map(str::to_string)
+            .collect();
+        let mut email = EmailConfig::new(host, from, to);
+        if let Some(port) = parse_u64(source, "SMTP_PORT")? {
+            email.port = Some(u16::try_from(port).map_err(|_| {
+                FlareSyncError::Config("SMTP_PORT must be a valid port number".to_string())
+            })?);
+        }
+        if let Some(tls) = source.get("SMTP_TLS") {
+            email.tls = tls.parse()?;
+        }
+        email.username = source.get("SMTP_USERNAME");
+        email.password = source.get("SMTP_PASSWORD");
+        email.validate()?;
+        channels.push(notification_channel(
+            source,
+            "SMTP",
+            NotifierConfig::Email(email),
+            threshold,
+        )?);
+    }
+    if let Some(topic_url) = source.get("NTFY_URL") {
+        let mut ntfy = NtfyConfig::new(topic_url);
+        ntfy.access_token = source.get("NTFY_TOKEN");
+        if let Some(priority) = source.get("NTFY_PRIORITY") {
+            ntfy.priority = Some(NtfyConfig::parse_priority(&priority)?);
+        }
+        if let Some(tags) = source.get("NTFY_TAGS") {
+            ntfy.tags = NtfyConfig::parse_tags(&tags);
+        }
+        channels.push(notification_channel(
+            source,
+            "NTFY",
+            NotifierConfig::Ntfy(ntfy),
+            None,
+        )?);
+    }
+    if let Some(app_token) = source.get("PUSHOVER_APP_TOKEN") {
+        let user_key = source.get("PUSHOVER_USER_KEY").ok_or_else(|| {
+            FlareSyncError::Config(
+                "PUSHOVER_USER_KEY must be set when PUSHOVER_APP_TOKEN is set".to_string(),
+            )
+        })?;
+        channels.push(notification_channel(
+            source,
+            "PUSHOVER",
+            NotifierConfig::Pushover(PushoverConfig::new(app_token, user_key)),
+            threshold,
+        )?);
+    }
+    if let Some(server_url) = source.get("GOTIFY_URL") {
+        let app_token = source.get("GOTIFY_APP_TOKEN").ok_or_else(|| {
+            FlareSyncError::Config(
+                "GOTIFY_APP_TOKEN must be set when GOTIFY_URL is set".to_string(),
+            )
+        })?;
+        let mut gotify = GotifyConfig::new(server_url, app_token);
+        if let Some(priority) = parse_u64(source, "GOTIFY_PRIORITY")? {
+            gotify.priority = u8::try_from(priority).unwrap_or(u8::MAX).min(10);
+        }
+        channels.push(notification_channel(
+            source,
+            "GOTIFY",
+            NotifierConfig::Gotify(gotify),
+            threshold,
+        )?);
+    }
+    if let Some(endpoint) = source.get("APPRISE_URL") {
+        let mut apprise = AppriseConfig::new(endpoint);
+        apprise.urls = source.get("APPRISE_URLS");
+        apprise.tag = source.get("APPRISE_TAG");
+        channels.push(notification_channel(
+            source,
+            "APPRISE",
+            NotifierConfig::Apprise(apprise),
+            None,
+        )?);
+    }
+    if let Some(url) = source.get("MQTT_URL") {
+        let mut mqtt = MqttConfig::from_url(&url)?;
+        mqtt.username = source.get("MQTT_USERNAME");
+        mqtt.password = source.get("MQTT_PASSWORD");
+        if let Some(topic) = source.get("MQTT_TOPIC") {
+            mqtt.topic = topic;
+        }
+        if let Some(client_id) = source.get("MQTT_CLIENT_ID") {
+            mqtt.client_id = client_id;
+        }
+        if let Some(enabled) = source.get("MQTT_HA_DISCOVERY") {
+            if parse_bool("MQTT_HA_DISCOVERY", &enabled)? {
+                mqtt.discovery_prefix = Some(
+                    source
+                        .get("MQTT_HA_DISCOVERY_PREFIX")
+                        .unwrap_or_else(|| notify::DEFAULT_HA_DISCOVERY_PREFIX.to_string()),
+                );
+            }
+        }
+        channels.push(notification_channel(
+            source,
+            "MQTT",
+            NotifierConfig::Mqtt(mqtt),
+            None,
+        )?);
+    }
+    if let Some(homeserver) = source.get("MATRIX_HOMESERVER") {
+        let required = |key: &str| {
+            source.get(key).ok_or_else(|| {
+                FlareSyncError::Config(format!("{} must be set when MATRIX_HOMESERVER is set", key))
+            })
+        };
+        let matrix = MatrixConfig::new(
+            homeserver,
+            required("MATRIX_ACCESS_TOKEN")?,
+            required("MATRIX_ROOM_ID")?,
+        );
+        matrix.send_url("validate")?;
+        channels.push(notification_channel(
+            source,
+            "MATRIX",
+            NotifierConfig::Matrix(matrix),
+            threshold,
+        )?);
+    }
+    if let Some(webhook_url) = source.get("TEAMS_WEBHOOK_URL") {
+        channels.push(notification_channel(
+            source,
+            "TEAMS",
+            NotifierConfig::Teams(TeamsConfig::new(webhook_url)),
+            threshold,
+        )?);
+    }
+    if let Some(api_url) = source.get("SIGNAL_API_URL") {
+        let number = source.get("SIGNAL_NUMBER").ok_or_else(|| {
+            FlareSyncError::Config(
+                "SIGNAL_NUMBER must be set when SIGNAL_API_URL is set".to_string(),
+            )
+        })?;
+        let recipients: Vec<String> = source
+            .get("SIGNAL_RECIPIENTS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|recipient| !recipient.is_empty())
+            .map(str::to_string)
+            .collect();
+        if recipients.is_empty() {
+            return Err(FlareSyncError::Config(
+                "SIGNAL_RECIPIENTS must be set when SIGNAL_API_URL is set".to_string(),
+            ));
+        }
+        channels.push(notification_channel(
+            source,
+            "SIGNAL",
+            NotifierConfig::Signal(SignalConfig::new(api_url, number, recipients)),
+            threshold,
+        )?);
+    }
+    Ok(channels)
+}
+
+fn read_api_token(source: &ConfigSource) -> Result<String, FlareSyncError> {
+    source.get("CLOUDFLARE_API_TOKEN").ok_or_else(|| {
+        FlareSyncError::Config(
+            "CLOUDFLARE_API_TOKEN or CLOUDFLARE_API_TOKEN_FILE must be set".to_string(),
+        )
+    })
+}
+
+fn read_backup_encryption_key(
+    source: &ConfigSource,
+) -> Result<Option<EncryptionKey>, FlareSyncError> {
+    source
+        .get("BACKUP_ENCRYPTION_KEY")
+        .map(|key| EncryptionKey::parse(&key))
+        .transpose()
+}
+
+pub struct ConfigMigration {
+    pub config_toml: String,
+    /// Secrets to write, readable only by the owner, to the files the config refers to.
+    pub secret_files: Vec<(PathBuf, String)>,
 }
 
 impl Config {
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
     pub fn from_env() -> Result<Self, FlareSyncError> {
-        dotenvy::dotenv().ok();
+        Self::load(&LoadOptions::default())
+    }
+
+    pub fn interval_for(&self, domain_name: &str) -> Duration {
+        self.domain_intervals
+            .get(domain_name)
+            .copied()
+            .unwrap_or(self.update_interval)
+    }
+
+    /// The shortest interval of any domain, which bounds how long the loop sleeps.
+    pub fn cycle_interval(&self) -> Duration {
+        self.domain_names
+            .iter()
+            .map(|domain_name| self.interval_for(domain_name))
+            .min()
+            .unwrap_or(self.update_interval)
+    }
+
+    /// The longest interval of any domain, where adaptive polling settles.
+    pub fn max_interval(&self) -> Duration {
+        self.domain_names
+            .iter()
+            .map(|domain_name| self.interval_for(domain_name))
+            .max()
+            .unwrap_or(self.update_interval)
+    }
+
+    pub fn secrets(&self) -> Vec<String> {
+        let mut secrets = vec![self.api_token.clone()];
+        secrets.extend(self.sentry_dsn.clone());
+        secrets.extend(self.heartbeat_url.clone());
+        secrets.extend(self.admin.as_ref().map(|admin| admin.token.clone()));
+        secrets.extend(self.dyndns.as_ref().map(|dyndns| dyndns.password.clone()));
+        secrets.extend(
+            self.backup_encryption_key
+                .as_ref()
+                .map(EncryptionKey::encoded),
+        );
+        secrets.extend(
+            self.offsite_backup
+                .as_ref()
+                .map(|offsite| offsite.secret_access_key.clone()),
+        );
+        for channel in &self.notification_channels {
+            secrets.extend(channel.notifier.secrets());
+        }
+        secrets
+    }
+
+    pub fn load(options: &LoadOptions) -> Result<Self, FlareSyncError> {
+        let source = ConfigSource::load(options)?;
+        if source.strict(options)? {
+            source.check_unknown_keys()?;
+        }
+        Self::from_source(&source)
+    }
+
+    /// Loads every job named in `FLARESYNC_JOBS`, each from the config file profile of the
+    /// same name. Without `FLARESYNC_JOBS`, or when a profile was selected explicitly, this
+    /// is the single job [`Config::load`] returns.
+    pub fn load_jobs(options: &LoadOptions) -> Result<Vec<Job>, FlareSyncError> {
+        let names = match ConfigSource::load(options)?.get("FLARESYNC_JOBS") {
+            Some(names) if options.profile.is_none() && env::var(PROFILE_ENV).is_err() => names,
+            _ => {
+                return Ok(vec![Job {
+                    name: None,
+                    load_options: options.clone(),
+                    config: Self::load(options)?,
+                }])
+            }
+        };
+
+        let mut jobs: Vec<Job> = Vec::new();
+        for name in names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            if jobs.iter().any(|job| job.name.as_deref() == Some(name)) {
+                return Err(FlareSyncError::Config(format!(
+                    "FLARESYNC_JOBS lists job '{}' twice",
+                    name
+                )));
+            }
+            let load_options = LoadOptions {
+                profile: Some(name.to_string()),
+                ..options.clone()
+            };
+            let config = Self::load(&load_options)
+                .map_err(|e| FlareSyncError::Config(format!("Job '{}': {}", name, e)))?;
+            for job in &jobs {
+                let shared = [
+                    (
+                        "STATUS_FILE_PATH",
+                        job.config.status_file_path == config.status_file_path,
+                    ),
+                    (
+                        "HISTORY_FILE_PATH",
+                        job.config.history_file_path == config.history_file_path,
+                    ),
+                    (
+                        "STATE_FILE_PATH",
+                        job.config.state_file_path == config.state_file_path,
+                    ),
+                    (
+                        "DATABASE_PATH",
+                        job.config.database_path.is_some()
+                            && job.config.database_path == config.database_path,
+                    ),
+                    (
+                        "CONTROL_SOCKET",
+                        job.config.control_socket.is_some()
+                            && job.config.control_socket == config.control_socket,
+                    ),
+                    (
+                        "HEALTH_BIND_ADDRESS",
+                        job.config.health_bind_address.is_some()
+                            && job.config.health_bind_address == config.health_bind_address,
+                    ),
+                    (
+                        "ADMIN_BIND_ADDRESS",
+                        matches!((&job.config.admin, &config.admin),
+                            (Some(a), Some(b)) if a.bind_address == b.bind_address),
+                    ),
+                    (
+                        "DYNDNS_BIND_ADDRESS",
+                        matches!((&job.config.dyndns, &config.dyndns),
+                            (Some(a), Some(b)) if a.bind_address == b.bind_address),
+                    ),
+                ];
+                if let Some((key, _)) = shared.iter().find(|(_, shared)| *shared) {
+                    return Err(FlareSyncError::Config(format!(
+                        "Jobs '{}' and '{}' use the same {}; set a different one in each profile",
+                        job.name.as_deref().unwrap_or_default(),
+                        name,
+                        key
+                    )));
+                }
+                // The daemon holds a single PID file, lock, and Cloudflare request budget
+                // for all of its jobs.
+                let separate = [
+                    (
+                        "PID_FILE_PATH",
+                        job.config.pid_file_path != config.pid_file_path,
+                    ),
+                    (
+                        "LOCK_FILE_PATH",
+                        job.config.lock_file_path != config.lock_file_path,
+                    ),
+                    (
+                        "CLOUDFLARE_RATE_LIMIT",
+                        job.config.cloudflare_rate_limit != config.cloudflare_rate_limit,
+                    ),
+                ];
+                if let Some((key, _)) = separate.iter().find(|(_, separate)| *separate) {
+                    return Err(FlareSyncError::Config(format!(
+                        "Jobs '{}' and '{}' use a different {}; all jobs share one, so set it outside the profiles",
+                        job.name.as_deref().unwrap_or_default(),
+                        name,
+                        key
+                    )));
+                }
+            }
+            jobs.push(Job {
+                name: Some(name.to_string()),
+                load_options,
+                config,
+            });
+        }
+        if jobs.is_empty() {
+            return Err(FlareSyncError::Config(
+                "FLARESYNC_JOBS must name at least one profile".to_string(),
+            ));
+        }
+        Ok(jobs)
+    }
 
-        let api_token = env::var("CLOUDFLARE_API_TOKEN")
-            .map_err(|_| FlareSyncError::Config("CLOUDFLARE_API_TOKEN must be set".to_string()))?;
-        let zone_id = env::var("CLOUDFLARE_ZONE_ID")
-            .map_err(|_| FlareSyncError::Config("CLOUDFLARE_ZONE_ID must be set".to_string()))?;
-        let domain_names_str = env::var("DOMAIN_NAME")
-            .map_err(|_| FlareSyncError::Config("DOMAIN_NAME must be set".to_string()))?;
-        let update_interval_minutes: u64 = match env::var("UPDATE_INTERVAL") {
-            Ok(value) => value.parse().map_err(|_| {
+    fn from_source(source: &ConfigSource) -> Result<Self, FlareSyncError> {
+        let api_token = read_api_token(source)?;
+        let zone_id = source
+            .get("CLOUDFLARE_ZONE_ID")
+            .ok_or_else(|| FlareSyncError::Config("CLOUDFLARE_ZONE_ID must be set".to_string()))?;
+        let domain_names_str = source
+            .get("DOMAIN_NAME")
+            .ok_or_else(|| FlareSyncError::Config("DOMAIN_NAME must be set".to_string()))?;
+        let update_interval_minutes: u64 = match source.get("UPDATE_INTERVAL") {
+            Some(value) => value.parse().map_err(|_| {
                 FlareSyncError::Config("UPDATE_INTERVAL must be a number".to_string())
             })?,
-            Err(_) => DEFAULT_UPDATE_INTERVAL_MINUTES,
+            None => DEFAULT_UPDATE_INTERVAL_MINUTES,
         };
         if update_interval_minutes < 1 {
             return Err(FlareSyncError::Config(
@@ -40,27 +1608,331 @@ impl Config {
             .checked_mul(60)
             .ok_or_else(|| FlareSyncError::Config("UPDATE_INTERVAL is too large".to_string()))?;
 
-        let domain_names: Vec<String> = domain_names_str
+        let domain_names: Vec<&str> = domain_names_str
             .split([',', ';'])
             .map(|s| s.trim())
             .filter(|s| !s.is_empty())
-            .map(|s| s.to_string())
             .collect();
         if domain_names.is_empty() {
             return Err(FlareSyncError::Config(
                 "DOMAIN_NAME must include at least one non-empty domain".to_string(),
             ));
         }
-        let status_file_path = env::var("STATUS_FILE_PATH")
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| PathBuf::from(DEFAULT_STATUS_FILE_PATH));
 
-        Ok(Config {
-            api_token,
-            zone_id,
-            domain_names,
-            update_interval: Duration::from_secs(update_interval_seconds),
-            status_file_path,
+        let mut builder = Config::builder()
+            .api_token(api_token)
+            .zone(zone_id)
+            .domains(domain_names)
+            .update_interval(Duration::from_secs(update_interval_seconds));
+        if let Some(paused_domains) = source.get("PAUSED_DOMAINS") {
+            for domain_name in paused_domains
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+            {
+                builder = builder.paused_domain(domain_name);
+            }
+        }
+        if let Some(overlap_policy) = source.get("OVERLAP_POLICY") {
+            builder = builder.overlap_policy(overlap_policy.parse()?);
+        }
+        if let Some(failures) = parse_u64(source, "EXIT_AFTER_FAILURES")?.filter(|&n| n > 0) {
+            builder = builder.exit_after_failures(failures);
+        }
+        if let Some(domain_intervals) = source.get("DOMAIN_INTERVALS") {
+            for entry in domain_intervals
+                .split([',', ';'])
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+            {
+                let invalid = || {
+                    FlareSyncError::Config(format!(
+                        "DOMAIN_INTERVALS entry '{}' must look like domain=minutes",
+                        entry
+                    ))
+                };
+                let (domain_name, minutes) = entry.split_once('=').ok_or_else(invalid)?;
+                let minutes: u64 = minutes.trim().parse().map_err(|_| invalid())?;
+                let seconds = minutes.checked_mul(60).ok_or_else(invalid)?;
+                builder = builder.domain_interval(domain_name, Duration::from_secs(seconds));
+            }
+        }
+        if let Some(minutes) = source.get("ADAPTIVE_MIN_INTERVAL") {
+            let seconds = minutes
+                .parse::<u64>()
+                .ok()
+                .and_then(|minutes| minutes.checked_mul(60))
+                .ok_or_else(|| {
+                    FlareSyncError::Config(
+                        "ADAPTIVE_MIN_INTERVAL must be a number of minutes".to_string(),
+                    )
+                })?;
+            builder = builder.adaptive_min_interval(Duration::from_secs(seconds));
+        }
+        if let Some(requests_per_minute) = parse_u64(source, "CLOUDFLARE_RATE_LIMIT")? {
+            let requests_per_minute = u32::try_from(requests_per_minute).map_err(|_| {
+                FlareSyncError::Config("CLOUDFLARE_RATE_LIMIT is too large".to_string())
+            })?;
+            builder = builder.cloudflare_rate_limit(requests_per_minute);
+        }
+        builder = builder.startup(StartupPolicy {
+            delay: Duration::from_secs(parse_u64(source, "STARTUP_DELAY")?.unwrap_or(0)),
+            splay: Duration::from_secs(parse_u64(source, "STARTUP_SPLAY")?.unwrap_or(0)),
+            skip_initial_check: match source.get("SKIP_INITIAL_CHECK") {
+                Some(value) => parse_bool("SKIP_INITIAL_CHECK", &value)?,
+                None => false,
+            },
+        });
+        if let Some(prefetch) = source.get("PREFETCH_RECORDS") {
+            builder = builder.prefetch(prefetch.parse()?);
+        }
+        if let Some(status_file_path) = source.get("STATUS_FILE_PATH") {
+            builder = builder.status_file_path(status_file_path);
+        }
+        if let Some(history_file_path) = source.get("HISTORY_FILE_PATH") {
+            builder = builder.history_file_path(history_file_path);
+        }
+        if let Some(lock_file_path) = source.get("LOCK_FILE_PATH") {
+            builder = builder.lock_file_path(lock_file_path);
+        }
+        if let Some(pid_file_path) = source.get("PID_FILE_PATH") {
+            builder = builder.pid_file_path(pid_file_path);
+        }
+        if let Some(state_file_path) = source.get("STATE_FILE_PATH") {
+            builder = builder.state_file_path(state_file_path);
+        }
+        if let Some(database_path) = source.get("DATABASE_PATH") {
+            builder = builder.database_path(database_path);
+        }
+        if let Some(control_socket) = source.get("CONTROL_SOCKET") {
+            builder = builder.control_socket(control_socket);
+        }
+        if let Some(bind_address) = source.get("ADMIN_BIND_ADDRESS") {
+            let bind_address = bind_address.parse().map_err(|_| {
+                FlareSyncError::Config(
+                    "ADMIN_BIND_ADDRESS must be a socket address like 127.0.0.1:8081".to_string(),
+                )
+            })?;
+            let token = source.get("ADMIN_TOKEN").ok_or_else(|| {
+                FlareSyncError::Config(
+                    "ADMIN_TOKEN must be set when ADMIN_BIND_ADDRESS is set".to_string(),
+                )
+            })?;
+            builder = builder.admin(AdminConfig {
+                bind_address,
+                token,
+            });
+        }
+        if let Some(bind_address) = source.get("DYNDNS_BIND_ADDRESS") {
+            let bind_address = bind_address.parse().map_err(|_| {
+                FlareSyncError::Config(
+                    "DYNDNS_BIND_ADDRESS must be a socket address like 0.0.0.0:8245".to_string(),
+                )
+            })?;
+            let credential = |key: &str| {
+                source.get(key).ok_or_else(|| {
+                    FlareSyncError::Config(format!(
+                        "{} must be set when DYNDNS_BIND_ADDRESS is set",
+                        key
+                    ))
+                })
+            };
+            builder = builder.dyndns(DynDnsConfig {
+                bind_address,
+                username: credential("DYNDNS_USERNAME")?,
+                password: credential("DYNDNS_PASSWORD")?,
+            });
+        }
+        if let Some(health_bind_address) = source.get("HEALTH_BIND_ADDRESS") {
+            let health_bind_address = health_bind_address.parse().map_err(|_| {
+                FlareSyncError::Config(
+                    "HEALTH_BIND_ADDRESS must be a socket address like 0.0.0.0:8080".to_string(),
+                )
+            })?;
+            builder = builder.health_bind_address(health_bind_address);
+        }
+        if let Some(log_format) = source.get("LOG_FORMAT") {
+            builder = builder.log_format(log_format.parse()?);
+        }
+        if let Some(log_level) = source.get("LOG_LEVEL") {
+            builder = builder.log_level(log_level);
+        }
+        if let Some(log_file) = source.get("LOG_FILE") {
+            builder = builder.log_file(log_file);
+        }
+        builder = builder.log_rotation(rotation_policy_from_source(source)?);
+        if let Some(log_journald) = source.get("LOG_JOURNALD") {
+            builder = builder.log_journald(parse_bool("LOG_JOURNALD", &log_journald)?);
+        }
+        if let Some(log_eventlog) = source.get("LOG_EVENTLOG") {
+            builder = builder.log_eventlog(parse_bool("LOG_EVENTLOG", &log_eventlog)?);
+        }
+        if let Some(log_http_bodies) = source.get("LOG_HTTP_BODIES") {
+            builder = builder.log_http_bodies(parse_bool("LOG_HTTP_BODIES", &log_http_bodies)?);
+        }
+        if let Some(log_quiet) = source.get("LOG_QUIET") {
+            builder = builder.log_quiet(parse_bool("LOG_QUIET", &log_quiet)?);
+        }
+        if let Some(otlp_endpoint) = source.get("OTLP_ENDPOINT") {
+            builder = builder.otlp_endpoint(otlp_endpoint);
+        }
+        if let Some(syslog_address) = source.get("SYSLOG_ADDRESS") {
+            let facility = match source.get("SYSLOG_FACILITY") {
+                Some(facility) => syslog::parse_facility(&facility)?,
+                None => DEFAULT_SYSLOG_FACILITY,
+            };
+            builder = builder.syslog(syslog_address.parse()?, facility);
+        }
+        if let Some(address) = source.get("STATSD_ADDRESS") {
+            builder = builder.statsd(StatsdConfig {
+                address,
+                prefix: source.get("STATSD_PREFIX").unwrap_or_default(),
+                format: match source.get("STATSD_FORMAT") {
+                    Some(format) => format.parse()?,
+                    None => StatsdFormat::default(),
+                },
+            });
+        }
+        if let Some(heartbeat_url) = source.get("HEARTBEAT_URL") {
+            builder = builder.heartbeat_url(heartbeat_url);
+        }
+        if let Some(summary_interval) = source.get("SUMMARY_INTERVAL") {
+            builder = builder.summary_period(summary_interval.parse()?);
+        }
+        if let Some(sentry_dsn) = source.get("SENTRY_DSN") {
+            builder = builder.sentry_dsn(sentry_dsn);
+        }
+        if let Some(command) = source.get("ON_CHANGE_COMMAND") {
+            let mut hook = ChangeHook::new(command);
+            if let Some(timeout) = parse_u64(source, "ON_CHANGE_TIMEOUT")? {
+                hook.timeout = Duration::from_secs(timeout.max(1));
+            }
+            builder = builder.on_change(hook);
+        }
+        if let Some(backup_dir) = source.get("BACKUP_DIR") {
+            builder = builder.backup_dir(backup_dir);
+        }
+        if let Some(compress) = source.get("BACKUP_COMPRESS") {
+            builder = builder.backup_compress(parse_bool("BACKUP_COMPRESS", &compress)?);
+        }
+        if let Some(mode) = source.get("BACKUP_MODE") {
+            builder = builder.backup_mode(mode.parse()?);
+        }
+        if let Some(key) = read_backup_encryption_key(source)? {
+            builder = builder.backup_encryption_key(key);
+        }
+        if let Some(bucket) = source.get("S3_BUCKET") {
+            let required = |key: &str| {
+                source.get(key).ok_or_else(|| {
+                    FlareSyncError::Config(format!("{} must be set when S3_BUCKET is set", key))
+                })
+            };
+            let endpoint = required("S3_ENDPOINT")?;
+            if !endpoint.starts_with("https://") && !endpoint.starts_with("http://") {
+                return Err(FlareSyncError::Config(
+                    "S3_ENDPOINT must be an http:// or https:// URL".to_string(),
+                ));
+            }
+            builder = builder.offsite_backup(S3Config {
+                endpoint,
+                bucket,
+                region: source
+                    .get("S3_REGION")
+                    .unwrap_or_else(|| s3::DEFAULT_REGION.to_string()),
+                access_key_id: required("S3_ACCESS_KEY_ID")?,
+                secret_access_key: required("S3_SECRET_ACCESS_KEY")?,
+                prefix: source
+                    .get("S3_PREFIX")
+                    .unwrap_or_else(|| s3::DEFAULT_PREFIX.to_string()),
+            });
+        }
+        builder = builder.backup_retention(BackupRetention {
+            keep_last: parse_u64(source, "BACKUP_KEEP")?
+                .map(|keep| usize::try_from(keep).unwrap_or(usize::MAX)),
+            max_age: parse_u64(source, "BACKUP_MAX_AGE_DAYS")?
+                .map(|days| Duration::from_secs(days.saturating_mul(86400))),
+        });
+        for channel in notification_channels_from_source(source)? {
+            builder = builder.notification_channel(channel);
+        }
+        builder = builder.notification_templates(MessageTemplates {
+            updated: source.get("NOTIFY_TEMPLATE_UPDATED"),
+            failed: source.get("NOTIFY_TEMPLATE_FAILED"),
+            recovered: source.get("NOTIFY_TEMPLATE_RECOVERED"),
+        });
+        if let Some(attempts) = parse_u64(source, "NOTIFY_RETRY_ATTEMPTS")? {
+            builder =
+                builder.notification_retry_attempts(u32::try_from(attempts).unwrap_or(u32::MAX));
+        }
+        if let Some(mode) = source.get("NOTIFY_MODE") {
+            builder = builder.notification_mode(mode.parse()?);
+        }
+
+        builder.build()
+    }
+
+    /// Converts the environment into a config file. Secrets never go into it: each one
+    /// becomes a `_FILE` setting, keeping an existing one, and is otherwise written to
+    /// `token_file` for the API token or a `flaresync.<key>` file next to it.
+    pub fn migrate_env_to_toml(token_file: &Path) -> Result<ConfigMigration, FlareSyncError> {
+        let source = ConfigSource::load(&LoadOptions::default())?;
+        Self::from_source(&source)?;
+
+        let mut table = toml::Table::new();
+        let mut secret_files = Vec::new();
+        for &key in CONFIG_KEYS {
+            let Some(value) = source.get(key) else {
+                continue;
+            };
+            match key {
+                _ if SECRET_KEYS.contains(&key) => {
+                    let file_key = format!("{}_FILE", key);
+                    let path = match source.get(&file_key) {
+                        Some(path) => PathBuf::from(path),
+                        None => {
+                            let path = match key {
+                                "CLOUDFLARE_API_TOKEN" => token_file.to_path_buf(),
+                                _ => token_file.with_file_name(format!(
+                                    "flaresync.{}",
+                                    key.to_ascii_lowercase()
+                                )),
+                            };
+                            secret_files.push((path.clone(), value));
+                            path
+                        }
+                    };
+                    table.insert(
+                        file_key.to_ascii_lowercase(),
+                        toml::Value::String(path.display().to_string()),
+                    );
+                }
+                _ if key
+                    .strip_suffix("_FILE")
+                    .is_some_and(|secret| SECRET_KEYS.contains(&secret)) => {}
+                "FLARESYNC_STRICT" | "LOG_JOURNALD" | "LOG_EVENTLOG" | "LOG_HTTP_BODIES"
+                | "LOG_QUIET" | "SKIP_INITIAL_CHECK" => {
+                    table.insert(
+                        key.to_ascii_lowercase(),
+                        toml::Value::Boolean(parse_bool(key, &value)?),
+                    );
+                }
+                _ => {
+                    let value = match value.parse::<i64>() {
+                        Ok(number) => toml::Value::Integer(number),
+                        Err(_) => toml::Value::String(value),
+                    };
+                    table.insert(key.to_ascii_lowercase(), value);
+                }
+            }
+        }
+
+        let config_toml = toml::to_string(&table).map_err(|e| {
+            FlareSyncError::Config(format!("Failed to serialize config file: {}", e))
+        })?;
+
+        Ok(ConfigMigration {
+            config_toml,
+            secret_files,
         })
     }
 }
@@ -68,9 +1940,18 @@ impl Config {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::notify::EventKind;
     use std::env;
     use std::time::{SystemTime, UNIX_EPOCH};
 
+    fn file_options(path: &str, profile: Option<&str>) -> LoadOptions {
+        LoadOptions {
+            config_path: Some(PathBuf::from(path)),
+            profile: profile.map(str::to_string),
+            strict: false,
+        }
+    }
+
     fn run_test<T>(test: T)
     where
         T: FnOnce(),
@@ -79,10 +1960,148 @@ mod tests {
 
         let vars_to_clear = [
             "CLOUDFLARE_API_TOKEN",
+            "CLOUDFLARE_API_TOKEN_FILE",
+            "ADMIN_TOKEN_FILE",
+            "DYNDNS_PASSWORD_FILE",
+            "SMTP_PASSWORD_FILE",
             "CLOUDFLARE_ZONE_ID",
+            "CLOUDFLARE_RATE_LIMIT",
             "DOMAIN_NAME",
             "UPDATE_INTERVAL",
+            "DOMAIN_INTERVALS",
+            "PAUSED_DOMAINS",
+            "OVERLAP_POLICY",
+            "EXIT_AFTER_FAILURES",
+            "ADAPTIVE_MIN_INTERVAL",
+            "STARTUP_DELAY",
+            "STARTUP_SPLAY",
+            "SKIP_INITIAL_CHECK",
+            "PREFETCH_RECORDS",
             "STATUS_FILE_PATH",
+            "HISTORY_FILE_PATH",
+            "LOCK_FILE_PATH",
+            "PID_FILE_PATH",
+            "STATE_FILE_PATH",
+            "DATABASE_PATH",
+            "FLARESYNC_CONFIG",
+            "FLARESYNC_PROFILE",
+            "FLARESYNC_STRICT",
+            "FLARESYNC_JOBS",
+            "UPDATE_INTERVAL_MINS",
+            "HEALTH_BIND_ADDRESS",
+            "CONTROL_SOCKET",
+            "ADMIN_BIND_ADDRESS",
+            "ADMIN_TOKEN",
+            "DYNDNS_BIND_ADDRESS",
+            "DYNDNS_USERNAME",
+            "DYNDNS_PASSWORD",
+            "LOG_FORMAT",
+            "LOG_LEVEL",
+            "LOG_FILE",
+            "LOG_MAX_SIZE_MB",
+            "LOG_MAX_AGE_HOURS",
+            "LOG_RETENTION",
+            "LOG_JOURNALD",
+            "LOG_EVENTLOG",
+            "LOG_HTTP_BODIES",
+            "LOG_QUIET",
+            "OTLP_ENDPOINT",
+            "SYSLOG_ADDRESS",
+            "SYSLOG_FACILITY",
+            "STATSD_ADDRESS",
+            "STATSD_PREFIX",
+            "STATSD_FORMAT",
+            "HEARTBEAT_URL",
+            "SUMMARY_INTERVAL",
+            "SENTRY_DSN",
+            "ON_CHANGE_COMMAND",
+            "ON_CHANGE_TIMEOUT",
+            "BACKUP_DIR",
+            "BACKUP_COMPRESS",
+            "BACKUP_MODE",
+            "BACKUP_ENCRYPTION_KEY",
+            "BACKUP_ENCRYPTION_KEY_FILE",
+            "S3_ENDPOINT",
+            "S3_BUCKET",
+            "S3_REGION",
+            "S3_ACCESS_KEY_ID",
+            "S3_SECRET_ACCESS_KEY",
+            "S3_PREFIX",
+            "BACKUP_KEEP",
+            "BACKUP_MAX_AGE_DAYS",
+            "NOTIFY_TEMPLATE_UPDATED",
+            "NOTIFY_TEMPLATE_FAILED",
+            "NOTIFY_TEMPLATE_RECOVERED",
+            "NOTIFY_RETRY_ATTEMPTS",
+            "NOTIFY_MODE",
+            "NOTIFY_REMINDER_HOURS",
+            "WEBHOOK_URL",
+            "WEBHOOK_METHOD",
+            "WEBHOOK_HEADERS",
+            "WEBHOOK_BODY_TEMPLATE",
+            "WEBHOOK_EVENTS",
+            "WEBHOOK_FAILURE_THRESHOLD",
+            "DISCORD_WEBHOOK_URL",
+            "DISCORD_FAILURE_THRESHOLD",
+            "DISCORD_EVENTS",
+            "TELEGRAM_BOT_TOKEN",
+            "TELEGRAM_CHAT_ID",
+            "TELEGRAM_FAILURE_THRESHOLD",
+            "TELEGRAM_EVENTS",
+            "SLACK_WEBHOOK_URL",
+            "SLACK_EVENTS",
+            "SLACK_FAILURE_THRESHOLD",
+            "SMTP_HOST",
+            "SMTP_PORT",
+            "SMTP_TLS",
+            "SMTP_USERNAME",
+            "SMTP_PASSWORD",
+            "SMTP_FROM",
+            "SMTP_TO",
+            "SMTP_FAILURE_THRESHOLD",
+            "SMTP_EVENTS",
+            "NTFY_URL",
+            "NTFY_TOKEN",
+            "NTFY_PRIORITY",
+            "NTFY_TAGS",
+            "NTFY_EVENTS",
+            "NTFY_FAILURE_THRESHOLD",
+            "PUSHOVER_APP_TOKEN",
+            "PUSHOVER_USER_KEY",
+            "PUSHOVER_FAILURE_THRESHOLD",
+            "PUSHOVER_EVENTS",
+            "GOTIFY_URL",
+            "GOTIFY_APP_TOKEN",
+            "GOTIFY_PRIORITY",
+            "GOTIFY_FAILURE_THRESHOLD",
+            "GOTIFY_EVENTS",
+            "APPRISE_URL",
+            "APPRISE_URLS",
+            "APPRISE_TAG",
+            "APPRISE_EVENTS",
+            "APPRISE_FAILURE_THRESHOLD",
+            "MQTT_URL",
+            "MQTT_USERNAME",
+            "MQTT_PASSWORD",
+            "MQTT_TOPIC",
+            "MQTT_CLIENT_ID",
+            "MQTT_HA_DISCOVERY",
+            "MQTT_HA_DISCOVERY_PREFIX",
+            "MQTT_EVENTS",
+            "MQTT_FAILURE_THRESHOLD",
+            "MATRIX_HOMESERVER",
+            "MATRIX_ACCESS_TOKEN",
+            "MATRIX_ROOM_ID",
+            "MATRIX_FAILURE_THRESHOLD",
+            "MATRIX_EVENTS",
+            "TEAMS_WEBHOOK_URL",
+            "TEAMS_EVENTS",
+            "TEAMS_FAILURE_THRESHOLD",
+            "SIGNAL_API_URL",
+            "SIGNAL_NUMBER",
+            "SIGNAL_RECIPIENTS",
+            "SIGNAL_EVENTS",
+            "SIGNAL_FAILURE_THRESHOLD",
         ];
         let original_vars: Vec<_> = vars_to_clear
             .iter()
@@ -93,89 +2112,460 @@ mod tests {
             env::remove_var(var);
         }
 
-        let original_cwd = std::env::current_dir().unwrap();
-        let unique = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos();
-        let test_cwd = std::env::temp_dir().join(format!(
-            "flaresync_test_env_{}_{}",
-            std::process::id(),
-            unique
-        ));
-        std::fs::create_dir_all(&test_cwd).unwrap();
-        std::env::set_current_dir(&test_cwd).unwrap();
+        let original_cwd = std::env::current_dir().unwrap();
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let test_cwd = std::env::temp_dir().join(format!(
+            "flaresync_test_env_{}_{}",
+            std::process::id(),
+            unique
+        ));
+        std::fs::create_dir_all(&test_cwd).unwrap();
+        std::env::set_current_dir(&test_cwd).unwrap();
+
+        test();
+
+        std::env::set_current_dir(&original_cwd).unwrap();
+        std::fs::remove_dir_all(&test_cwd).ok();
+
+        for (var, val) in original_vars {
+            if let Some(v) = val {
+                env::set_var(var, v);
+            } else {
+                env::remove_var(var);
+            }
+        }
+    }
+
+    #[test]
+    fn test_config_from_env_missing_vars() {
+        run_test(|| {
+            let result = Config::from_env();
+            assert!(result.is_err());
+        });
+    }
+
+    #[test]
+    fn test_config_from_env_success() {
+        run_test(|| {
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com;another.com");
+            env::set_var("UPDATE_INTERVAL", "15");
+
+            let config = Config::from_env().unwrap();
+            assert_eq!(config.api_token, "test_token");
+            assert_eq!(config.zone_id, "test_zone_id");
+            assert_eq!(config.domain_names, vec!["example.com", "another.com"]);
+            assert_eq!(config.update_interval, Duration::from_secs(15 * 60));
+            assert_eq!(
+                config.status_file_path,
+                PathBuf::from(DEFAULT_STATUS_FILE_PATH)
+            );
+        });
+    }
+
+    #[test]
+    fn test_config_from_env_defaults_update_interval() {
+        run_test(|| {
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+
+            let config = Config::from_env().unwrap();
+            assert_eq!(
+                config.update_interval,
+                Duration::from_secs(DEFAULT_UPDATE_INTERVAL_MINUTES * 60)
+            );
+        });
+    }
+
+    #[test]
+    fn test_config_from_env_reads_exit_after_failures() {
+        run_test(|| {
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+            assert_eq!(Config::from_env().unwrap().exit_after_failures, None);
+
+            env::set_var("EXIT_AFTER_FAILURES", "5");
+            assert_eq!(Config::from_env().unwrap().exit_after_failures, Some(5));
+            env::set_var("EXIT_AFTER_FAILURES", "0");
+            assert_eq!(Config::from_env().unwrap().exit_after_failures, None);
+        });
+    }
+
+    #[test]
+    fn test_config_from_env_reads_cloudflare_rate_limit() {
+        run_test(|| {
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+            assert_eq!(
+                Config::from_env().unwrap().cloudflare_rate_limit,
+                rate_limit::DEFAULT_REQUESTS_PER_MINUTE
+            );
+
+            env::set_var("CLOUDFLARE_RATE_LIMIT", "600");
+            assert_eq!(Config::from_env().unwrap().cloudflare_rate_limit, 600);
+            env::set_var("CLOUDFLARE_RATE_LIMIT", "0");
+            assert!(matches!(Config::from_env(), Err(FlareSyncError::Config(_))));
+        });
+    }
+
+    #[test]
+    fn test_config_from_env_reads_logging_settings() {
+        run_test(|| {
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+            env::set_var("LOG_FORMAT", "json");
+
+            env::set_var("LOG_LEVEL", "debug");
+            env::set_var("LOG_FILE", "logs/flaresync.log");
+
+            let config = Config::from_env().unwrap();
+            assert_eq!(config.log_format, LogFormat::Json);
+            assert_eq!(config.log_level, "debug");
+            assert_eq!(config.log_file, Some(PathBuf::from("logs/flaresync.log")));
+            assert_eq!(
+                config.log_rotation,
+                RotationPolicy {
+                    max_size_bytes: Some(DEFAULT_LOG_MAX_SIZE_MB * 1024 * 1024),
+                    max_age: None,
+                    retention: DEFAULT_LOG_RETENTION,
+                }
+            );
+
+            env::set_var("LOG_MAX_SIZE_MB", "0");
+            env::set_var("LOG_MAX_AGE_HOURS", "24");
+            env::set_var("LOG_RETENTION", "7");
+            let config = Config::from_env().unwrap();
+            assert_eq!(
+                config.log_rotation,
+                RotationPolicy {
+                    max_size_bytes: None,
+                    max_age: Some(Duration::from_secs(24 * 3600)),
+                    retention: 7,
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn test_config_from_env_reads_otlp_endpoint() {
+        run_test(|| {
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+            assert_eq!(Config::from_env().unwrap().otlp_endpoint, None);
+
+            env::set_var("OTLP_ENDPOINT", "http://collector:4318");
+            assert_eq!(
+                Config::from_env().unwrap().otlp_endpoint.as_deref(),
+                Some("http://collector:4318")
+            );
+        });
+    }
+
+    #[test]
+    fn test_config_from_env_reads_sentry_dsn_as_a_secret() {
+        run_test(|| {
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+            assert_eq!(Config::from_env().unwrap().sentry_dsn, None);
+
+            let dsn = "https://public_key@o0.ingest.sentry.io/1";
+            env::set_var("SENTRY_DSN", dsn);
+            let config = Config::from_env().unwrap();
+            assert_eq!(config.sentry_dsn.as_deref(), Some(dsn));
+            assert!(config.secrets().iter().any(|secret| secret == dsn));
+        });
+    }
+
+    #[test]
+    fn test_config_from_env_reads_log_journald() {
+        run_test(|| {
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+            assert_eq!(Config::from_env().unwrap().log_journald, None);
+
+            env::set_var("LOG_JOURNALD", "false");
+            assert_eq!(Config::from_env().unwrap().log_journald, Some(false));
+
+            env::set_var("LOG_JOURNALD", "sometimes");
+            assert!(Config::from_env().is_err());
+        });
+    }
+
+    #[test]
+    fn test_config_from_env_reads_log_eventlog() {
+        run_test(|| {
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+            assert_eq!(Config::from_env().unwrap().log_eventlog, None);
+
+            env::set_var("LOG_EVENTLOG", "true");
+            assert_eq!(Config::from_env().unwrap().log_eventlog, Some(true));
+
+            env::set_var("LOG_EVENTLOG", "sometimes");
+            assert!(Config::from_env().is_err());
+        });
+    }
+
+    #[test]
+    fn test_config_from_env_reads_log_quiet() {
+        run_test(|| {
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+            assert!(!Config::from_env().unwrap().log_quiet);
+
+            env::set_var("LOG_QUIET", "true");
+            assert!(Config::from_env().unwrap().log_quiet);
+        });
+    }
+
+    #[test]
+    fn test_config_from_env_accepts_custom_status_file_path() {
+        run_test(|| {
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+            env::set_var("STATUS_FILE_PATH", "/tmp/flaresync-status.json");
+            env::set_var("HISTORY_FILE_PATH", "/tmp/flaresync-history.jsonl");
+            env::set_var("LOCK_FILE_PATH", "/tmp/flaresync.lock");
+            env::set_var("PID_FILE_PATH", "/run/flaresync.pid");
+            env::set_var("STATE_FILE_PATH", "/var/lib/flaresync/state.json");
+            env::set_var("BACKUP_DIR", "/var/backups/flaresync");
+            env::set_var("BACKUP_COMPRESS", "true");
+            env::set_var("CONTROL_SOCKET", "/run/flaresync/control.sock");
+
+            let config = Config::from_env().unwrap();
+            assert_eq!(
+                config.status_file_path,
+                PathBuf::from("/tmp/flaresync-status.json")
+            );
+            assert_eq!(
+                config.history_file_path,
+                PathBuf::from("/tmp/flaresync-history.jsonl")
+            );
+            assert_eq!(config.lock_file_path, PathBuf::from("/tmp/flaresync.lock"));
+            assert_eq!(config.pid_file_path, PathBuf::from("/run/flaresync.pid"));
+            assert_eq!(
+                config.state_file_path,
+                PathBuf::from("/var/lib/flaresync/state.json")
+            );
+            assert_eq!(config.backup_dir, PathBuf::from("/var/backups/flaresync"));
+            assert!(config.backup_compress);
+            assert_eq!(config.backup_mode, BackupMode::Files);
+            assert_eq!(
+                config.control_socket,
+                Some(PathBuf::from("/run/flaresync/control.sock"))
+            );
+
+            assert_eq!(config.database_path, None);
+            env::set_var("DATABASE_PATH", "/var/lib/flaresync/flaresync.db");
+            #[cfg(feature = "sqlite")]
+            assert_eq!(
+                Config::from_env().unwrap().database_path,
+                Some(PathBuf::from("/var/lib/flaresync/flaresync.db"))
+            );
+            #[cfg(not(feature = "sqlite"))]
+            assert!(matches!(Config::from_env(), Err(FlareSyncError::Config(_))));
+            env::remove_var("DATABASE_PATH");
+
+            env::set_var("BACKUP_MODE", "journal");
+            assert!(matches!(Config::from_env(), Err(FlareSyncError::Config(_))));
+            env::remove_var("BACKUP_COMPRESS");
+            assert_eq!(Config::from_env().unwrap().backup_mode, BackupMode::Journal);
+            env::set_var("BACKUP_MODE", "database");
+            assert!(matches!(Config::from_env(), Err(FlareSyncError::Config(_))));
+        });
+    }
+
+    #[test]
+    fn test_config_from_env_parses_health_bind_address() {
+        run_test(|| {
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+            env::set_var("HEALTH_BIND_ADDRESS", "0.0.0.0:8080");
+
+            let config = Config::from_env().unwrap();
+            assert_eq!(
+                config.health_bind_address,
+                Some("0.0.0.0:8080".parse().unwrap())
+            );
 
-        test();
+            env::set_var("HEALTH_BIND_ADDRESS", "not-an-address");
+            assert!(matches!(Config::from_env(), Err(FlareSyncError::Config(_))));
+        });
+    }
 
-        std::env::set_current_dir(&original_cwd).unwrap();
-        std::fs::remove_dir_all(&test_cwd).ok();
+    #[test]
+    fn test_config_from_env_parses_admin_api() {
+        run_test(|| {
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+            env::set_var("ADMIN_BIND_ADDRESS", "127.0.0.1:8081");
+            assert!(matches!(Config::from_env(), Err(FlareSyncError::Config(_))));
 
-        for (var, val) in original_vars {
-            if let Some(v) = val {
-                env::set_var(var, v);
-            } else {
-                env::remove_var(var);
-            }
-        }
+            env::set_var("ADMIN_TOKEN", "too-short");
+            assert!(matches!(Config::from_env(), Err(FlareSyncError::Config(_))));
+
+            env::set_var("ADMIN_TOKEN", "0123456789abcdef");
+            let config = Config::from_env().unwrap();
+            assert_eq!(
+                config.admin,
+                Some(AdminConfig {
+                    bind_address: "127.0.0.1:8081".parse().unwrap(),
+                    token: "0123456789abcdef".to_string(),
+                })
+            );
+            assert!(config.secrets().contains(&"0123456789abcdef".to_string()));
+        });
     }
 
     #[test]
-    fn test_config_from_env_missing_vars() {
+    fn test_config_from_env_parses_dyndns_server() {
         run_test(|| {
-            let result = Config::from_env();
-            assert!(result.is_err());
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+            env::set_var("DYNDNS_BIND_ADDRESS", "0.0.0.0:8245");
+            env::set_var("DYNDNS_USERNAME", "router");
+            assert!(matches!(Config::from_env(), Err(FlareSyncError::Config(_))));
+
+            env::set_var("DYNDNS_PASSWORD", "secret");
+            let config = Config::from_env().unwrap();
+            assert_eq!(
+                config.dyndns,
+                Some(DynDnsConfig {
+                    bind_address: "0.0.0.0:8245".parse().unwrap(),
+                    username: "router".to_string(),
+                    password: "secret".to_string(),
+                })
+            );
+            assert!(config.secrets().contains(&"secret".to_string()));
         });
     }
 
     #[test]
-    fn test_config_from_env_success() {
+    fn test_config_from_env_parses_offsite_backup_bucket() {
         run_test(|| {
             env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
             env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
-            env::set_var("DOMAIN_NAME", "example.com;another.com");
-            env::set_var("UPDATE_INTERVAL", "15");
+            env::set_var("DOMAIN_NAME", "example.com");
+            env::set_var("S3_BUCKET", "dns-backups");
+            env::set_var("S3_ENDPOINT", "https://account.r2.cloudflarestorage.com");
+            env::set_var("S3_ACCESS_KEY_ID", "key-id");
+            assert!(matches!(Config::from_env(), Err(FlareSyncError::Config(_))));
 
+            env::set_var("S3_SECRET_ACCESS_KEY", "secret-access-key");
+            env::set_var("S3_REGION", "auto");
             let config = Config::from_env().unwrap();
-            assert_eq!(config.api_token, "test_token");
-            assert_eq!(config.zone_id, "test_zone_id");
-            assert_eq!(config.domain_names, vec!["example.com", "another.com"]);
-            assert_eq!(config.update_interval, Duration::from_secs(15 * 60));
             assert_eq!(
-                config.status_file_path,
-                PathBuf::from(DEFAULT_STATUS_FILE_PATH)
+                config.offsite_backup,
+                Some(S3Config {
+                    endpoint: "https://account.r2.cloudflarestorage.com".to_string(),
+                    bucket: "dns-backups".to_string(),
+                    region: "auto".to_string(),
+                    access_key_id: "key-id".to_string(),
+                    secret_access_key: "secret-access-key".to_string(),
+                    prefix: "flaresync/".to_string(),
+                })
             );
+            assert!(config.secrets().contains(&"secret-access-key".to_string()));
+
+            env::set_var("S3_ENDPOINT", "account.r2.cloudflarestorage.com");
+            assert!(matches!(Config::from_env(), Err(FlareSyncError::Config(_))));
         });
     }
 
     #[test]
-    fn test_config_from_env_defaults_update_interval() {
+    fn test_config_from_env_reads_syslog_settings() {
         run_test(|| {
             env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
             env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
             env::set_var("DOMAIN_NAME", "example.com");
+            env::set_var("SYSLOG_ADDRESS", "udp://logs.lan:514");
+            env::set_var("SYSLOG_FACILITY", "local3");
 
             let config = Config::from_env().unwrap();
             assert_eq!(
-                config.update_interval,
-                Duration::from_secs(DEFAULT_UPDATE_INTERVAL_MINUTES * 60)
+                config.syslog_target,
+                Some(SyslogTarget::Udp("logs.lan:514".to_string()))
             );
+            assert_eq!(config.syslog_facility, 19);
+
+            env::set_var("SYSLOG_FACILITY", "kernel");
+            assert!(matches!(Config::from_env(), Err(FlareSyncError::Config(_))));
         });
     }
 
     #[test]
-    fn test_config_from_env_accepts_custom_status_file_path() {
+    fn test_config_from_env_reads_statsd_settings() {
         run_test(|| {
             env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
             env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
             env::set_var("DOMAIN_NAME", "example.com");
-            env::set_var("STATUS_FILE_PATH", "/tmp/flaresync-status.json");
+            env::set_var("STATSD_ADDRESS", "127.0.0.1:8125");
+            env::set_var("STATSD_FORMAT", "dogstatsd");
 
             let config = Config::from_env().unwrap();
             assert_eq!(
-                config.status_file_path,
-                PathBuf::from("/tmp/flaresync-status.json")
+                config.statsd,
+                Some(StatsdConfig {
+                    address: "127.0.0.1:8125".to_string(),
+                    prefix: String::new(),
+                    format: StatsdFormat::DogStatsd,
+                })
+            );
+        });
+    }
+
+    #[test]
+    fn test_config_from_env_reads_webhook_notifier() {
+        run_test(|| {
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+            env::set_var("WEBHOOK_URL", "https://hooks.example.com/flaresync");
+            env::set_var("WEBHOOK_METHOD", "put");
+            env::set_var(
+                "WEBHOOK_HEADERS",
+                "Authorization: Bearer abc, X-Source: flaresync",
             );
+            env::set_var("WEBHOOK_EVENTS", "failure,recovery");
+            env::set_var("WEBHOOK_FAILURE_THRESHOLD", "2");
+
+            let config = Config::from_env().unwrap();
+            match config.notification_channels.as_slice() {
+                [NotificationChannel {
+                    notifier: NotifierConfig::Webhook(webhook),
+                    filter,
+                }] => {
+                    assert_eq!(filter.events, vec![EventKind::Failed, EventKind::Recovered]);
+                    assert_eq!(filter.failure_threshold, Some(2));
+                    assert_eq!(webhook.method, reqwest::Method::PUT);
+                    assert_eq!(
+                        webhook.headers,
+                        vec![
+                            ("Authorization".to_string(), "Bearer abc".to_string()),
+                            ("X-Source".to_string(), "flaresync".to_string()),
+                        ]
+                    );
+                }
+                other => panic!("expected a webhook notifier, got {:?}", other),
+            }
+            assert!(config.secrets().contains(&"Bearer abc".to_string()));
         });
     }
 
@@ -192,6 +2582,100 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_config_from_env_reads_domain_intervals() {
+        run_test(|| {
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var(
+                "DOMAIN_NAME",
+                "critical.example.com,stable.example.com,example.com",
+            );
+            env::set_var("UPDATE_INTERVAL", "15");
+            env::set_var(
+                "DOMAIN_INTERVALS",
+                "Critical.example.com=1; stable.example.com = 60",
+            );
+
+            let config = Config::from_env().unwrap();
+            assert_eq!(
+                config.interval_for("critical.example.com"),
+                Duration::from_secs(60)
+            );
+            assert_eq!(
+                config.interval_for("stable.example.com"),
+                Duration::from_secs(3600)
+            );
+            assert_eq!(config.interval_for("example.com"), Duration::from_secs(900));
+            assert_eq!(config.cycle_interval(), Duration::from_secs(60));
+
+            env::set_var("PAUSED_DOMAINS", "Stable.Example.com");
+            let config = Config::from_env().unwrap();
+            assert_eq!(
+                config.paused_domains,
+                BTreeSet::from(["stable.example.com".to_string()])
+            );
+            assert_eq!(config.overlap_policy, OverlapPolicy::Queue);
+            env::set_var("OVERLAP_POLICY", "skip");
+            assert_eq!(
+                Config::from_env().unwrap().overlap_policy,
+                OverlapPolicy::Skip
+            );
+            env::remove_var("OVERLAP_POLICY");
+            env::set_var("PAUSED_DOMAINS", "other.example.com");
+            assert!(matches!(Config::from_env(), Err(FlareSyncError::Config(_))));
+            env::remove_var("PAUSED_DOMAINS");
+
+            env::set_var("DOMAIN_INTERVALS", "other.example.com=5");
+            assert!(matches!(Config::from_env(), Err(FlareSyncError::Config(_))));
+            env::set_var("DOMAIN_INTERVALS", "example.com=0");
+            assert!(matches!(Config::from_env(), Err(FlareSyncError::Config(_))));
+            env::remove_var("DOMAIN_INTERVALS");
+
+            env::set_var("ADAPTIVE_MIN_INTERVAL", "1");
+            let config = Config::from_env().unwrap();
+            assert_eq!(config.adaptive_min_interval, Some(Duration::from_secs(60)));
+            assert_eq!(config.max_interval(), Duration::from_secs(900));
+            env::set_var("ADAPTIVE_MIN_INTERVAL", "0");
+            assert!(matches!(Config::from_env(), Err(FlareSyncError::Config(_))));
+        });
+    }
+
+    #[test]
+    fn test_config_from_env_reads_startup_policy() {
+        run_test(|| {
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+            assert_eq!(
+                Config::from_env().unwrap().startup,
+                StartupPolicy::default()
+            );
+
+            env::set_var("STARTUP_DELAY", "30");
+            env::set_var("STARTUP_SPLAY", "600");
+            env::set_var("SKIP_INITIAL_CHECK", "true");
+            assert_eq!(
+                Config::from_env().unwrap().startup,
+                StartupPolicy {
+                    delay: Duration::from_secs(30),
+                    splay: Duration::from_secs(600),
+                    skip_initial_check: true,
+                }
+            );
+
+            env::set_var("SKIP_INITIAL_CHECK", "sometimes");
+            assert!(matches!(Config::from_env(), Err(FlareSyncError::Config(_))));
+            env::remove_var("SKIP_INITIAL_CHECK");
+
+            assert_eq!(Config::from_env().unwrap().prefetch, PrefetchPolicy::Off);
+            env::set_var("PREFETCH_RECORDS", "strict");
+            assert_eq!(Config::from_env().unwrap().prefetch, PrefetchPolicy::Strict);
+            env::set_var("PREFETCH_RECORDS", "maybe");
+            assert!(matches!(Config::from_env(), Err(FlareSyncError::Config(_))));
+        });
+    }
+
     #[test]
     fn test_config_from_env_rejects_zero_interval() {
         run_test(|| {
@@ -217,4 +2701,478 @@ mod tests {
             assert!(matches!(result, Err(FlareSyncError::Config(_))));
         });
     }
+
+    #[test]
+    fn test_config_builder_applies_defaults() {
+        let config = Config::builder()
+            .api_token("test_token")
+            .zone("test_zone_id")
+            .domain("example.com")
+            .domain("another.com")
+            .build()
+            .unwrap();
+
+        assert_eq!(config.api_token, "test_token");
+        assert_eq!(config.zone_id, "test_zone_id");
+        assert_eq!(config.domain_names, vec!["example.com", "another.com"]);
+        assert_eq!(
+            config.update_interval,
+            Duration::from_secs(DEFAULT_UPDATE_INTERVAL_MINUTES * 60)
+        );
+        assert_eq!(
+            config.status_file_path,
+            PathBuf::from(DEFAULT_STATUS_FILE_PATH)
+        );
+        assert_eq!(
+            config.state_file_path,
+            PathBuf::from("status/flaresync-status.state.json")
+        );
+    }
+
+    #[test]
+    fn test_config_builder_rejects_missing_fields() {
+        let result = Config::builder().api_token("test_token").build();
+        assert!(matches!(result, Err(FlareSyncError::Config(_))));
+
+        let result = Config::builder()
+            .api_token("test_token")
+            .zone("test_zone_id")
+            .domain("  ")
+            .build();
+        assert!(matches!(result, Err(FlareSyncError::Config(_))));
+    }
+
+    #[test]
+    fn test_config_builder_rejects_sub_minute_interval() {
+        let result = Config::builder()
+            .api_token("test_token")
+            .zone("test_zone_id")
+            .domain("example.com")
+            .update_interval(Duration::from_secs(30))
+            .build();
+        assert!(matches!(result, Err(FlareSyncError::Config(_))));
+    }
+
+    #[test]
+    fn test_config_builder_converts_unicode_domains_to_punycode() {
+        let config = Config::builder()
+            .api_token("test_token")
+            .zone("test_zone_id")
+            .domain("bücher.example")
+            .domain("*.Münich.example.com")
+            .domain("Example.COM")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            config.domain_names,
+            vec![
+                "xn--bcher-kva.example",
+                "*.xn--mnich-kva.example.com",
+                "example.com"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_builder_rejects_invalid_domain() {
+        let result = Config::builder()
+            .api_token("test_token")
+            .zone("test_zone_id")
+            .domain("bad..example.com")
+            .build();
+
+        match result {
+            Err(FlareSyncError::Config(message)) => {
+                assert!(message.contains("bad..example.com"));
+            }
+            other => panic!("expected config error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_config_load_reads_toml_file_with_env_override() {
+        run_test(|| {
+            std::fs::write(
+                "flaresync.toml",
+                "cloudflare_api_token = \"file_token\"\n\
+                 cloudflare_zone_id = \"file_zone\"\n\
+                 domain_name = [\"example.com\", \"another.com\"]\n\
+                 update_interval = 10\n",
+            )
+            .unwrap();
+            env::set_var("CLOUDFLARE_ZONE_ID", "env_zone");
+
+            let config = Config::load(&file_options("flaresync.toml", None)).unwrap();
+            assert_eq!(config.api_token, "file_token");
+            assert_eq!(config.zone_id, "env_zone");
+            assert_eq!(config.domain_names, vec!["example.com", "another.com"]);
+            assert_eq!(config.update_interval, Duration::from_secs(10 * 60));
+        });
+    }
+
+    #[test]
+    fn test_config_from_env_reads_token_file() {
+        run_test(|| {
+            std::fs::write("token.txt", "secret_token\n").unwrap();
+            env::set_var("CLOUDFLARE_API_TOKEN_FILE", "token.txt");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+
+            let config = Config::from_env().unwrap();
+            assert_eq!(config.api_token, "secret_token");
+        });
+    }
+
+    #[test]
+    fn test_config_from_env_reads_backup_encryption_key() {
+        run_test(|| {
+            let encoded = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=";
+            std::fs::write("backup.key", format!("{}\n", encoded)).unwrap();
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+            assert_eq!(Config::from_env().unwrap().backup_encryption_key, None);
+
+            env::set_var("BACKUP_ENCRYPTION_KEY_FILE", "backup.key");
+            let config = Config::from_env().unwrap();
+            assert_eq!(
+                config.backup_encryption_key,
+                Some(EncryptionKey::parse(encoded).unwrap())
+            );
+            assert!(config.secrets().contains(&encoded.to_string()));
+
+            env::set_var("BACKUP_ENCRYPTION_KEY", "too-short");
+            assert!(matches!(Config::from_env(), Err(FlareSyncError::Config(_))));
+            env::set_var("BACKUP_ENCRYPTION_KEY", encoded);
+            env::set_var("BACKUP_MODE", "journal");
+            let config = Config::from_env().unwrap();
+            assert_eq!(config.backup_mode, BackupMode::Journal);
+            assert!(config.backup_encryption_key.is_some());
+        });
+    }
+
+    #[test]
+    fn test_migrate_env_to_toml_keeps_secrets_out_of_config_file() {
+        run_test(|| {
+            let encoded = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=";
+            std::fs::write("dyndns.secret", "dyndns_secret\n").unwrap();
+            env::set_var("CLOUDFLARE_API_TOKEN", "cloudflare_secret");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+            env::set_var("ADMIN_BIND_ADDRESS", "127.0.0.1:8081");
+            env::set_var("ADMIN_TOKEN", "admin_secret_0123456789");
+            env::set_var("DYNDNS_BIND_ADDRESS", "127.0.0.1:8082");
+            env::set_var("DYNDNS_USERNAME", "router");
+            env::set_var("DYNDNS_PASSWORD_FILE", "dyndns.secret");
+            env::set_var("BACKUP_ENCRYPTION_KEY", encoded);
+            env::set_var("SMTP_HOST", "smtp.example.com");
+            env::set_var("SMTP_FROM", "flaresync@example.com");
+            env::set_var("SMTP_TO", "admin@example.com");
+            env::set_var("SMTP_PASSWORD", "smtp_secret");
+            env::set_var("TELEGRAM_BOT_TOKEN", "telegram_secret");
+            env::set_var("TELEGRAM_CHAT_ID", "42");
+
+            let migration = Config::migrate_env_to_toml(Path::new("secrets/api.token")).unwrap();
+            let secrets = Config::from_env().unwrap().secrets();
+            assert!(secrets.contains(&"dyndns_secret".to_string()));
+            for secret in &secrets {
+                assert!(
+                    !migration.config_toml.contains(secret.as_str()),
+                    "{} leaked into the config file",
+                    secret
+                );
+            }
+            let written: Vec<&str> = migration
+                .secret_files
+                .iter()
+                .map(|(_, secret)| secret.as_str())
+                .collect();
+            assert_eq!(
+                written,
+                vec![
+                    "cloudflare_secret",
+                    "admin_secret_0123456789",
+                    encoded,
+                    "telegram_secret",
+                    "smtp_secret"
+                ]
+            );
+            assert!(migration.secret_files.contains(&(
+                PathBuf::from("secrets/flaresync.smtp_password"),
+                "smtp_secret".to_string()
+            )));
+            assert!(migration
+                .config_toml
+                .contains("smtp_password_file = \"secrets/flaresync.smtp_password\""));
+            assert!(migration
+                .config_toml
+                .contains("dyndns_password_file = \"dyndns.secret\""));
+
+            std::fs::create_dir_all("secrets").unwrap();
+            std::fs::write("flaresync.toml", &migration.config_toml).unwrap();
+            for (path, secret) in &migration.secret_files {
+                std::fs::write(path, secret).unwrap();
+            }
+            for var in [
+                "CLOUDFLARE_API_TOKEN",
+                "ADMIN_TOKEN",
+                "DYNDNS_PASSWORD_FILE",
+                "BACKUP_ENCRYPTION_KEY",
+                "SMTP_PASSWORD",
+                "TELEGRAM_BOT_TOKEN",
+            ] {
+                env::remove_var(var);
+            }
+            let config = Config::load(&file_options("flaresync.toml", None)).unwrap();
+            let mut reloaded = config.secrets();
+            let mut expected = secrets.clone();
+            reloaded.sort();
+            expected.sort();
+            assert_eq!(reloaded, expected);
+        });
+    }
+
+    #[test]
+    fn test_migrate_env_to_toml_references_token_file() {
+        run_test(|| {
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com,another.com");
+            env::set_var("UPDATE_INTERVAL", "15");
+
+            let migration = Config::migrate_env_to_toml(Path::new("flaresync.token")).unwrap();
+            assert_eq!(
+                migration.secret_files,
+                vec![(PathBuf::from("flaresync.token"), "test_token".to_string())]
+            );
+            assert!(!migration.config_toml.contains("test_token"));
+
+            std::fs::write("flaresync.toml", &migration.config_toml).unwrap();
+            std::fs::write("flaresync.token", "test_token").unwrap();
+            for var in [
+                "CLOUDFLARE_API_TOKEN",
+                "CLOUDFLARE_ZONE_ID",
+                "DOMAIN_NAME",
+                "UPDATE_INTERVAL",
+            ] {
+                env::remove_var(var);
+            }
+
+            let config = Config::load(&file_options("flaresync.toml", None)).unwrap();
+            assert_eq!(config.api_token, "test_token");
+            assert_eq!(config.zone_id, "test_zone_id");
+            assert_eq!(config.domain_names, vec!["example.com", "another.com"]);
+            assert_eq!(config.update_interval, Duration::from_secs(15 * 60));
+        });
+    }
+
+    #[test]
+    fn test_config_load_selects_named_profile() {
+        run_test(|| {
+            std::fs::write(
+                "flaresync.toml",
+                "update_interval = 10\n\
+                 cloudflare_api_token = \"shared_token\"\n\
+                 [profiles.home]\n\
+                 cloudflare_zone_id = \"home_zone\"\n\
+                 domain_name = \"home.example.com\"\n\
+                 [profiles.office]\n\
+                 cloudflare_api_token = \"office_token\"\n\
+                 cloudflare_zone_id = \"office_zone\"\n\
+                 domain_name = \"office.example.com\"\n",
+            )
+            .unwrap();
+
+            let home = Config::load(&file_options("flaresync.toml", Some("home"))).unwrap();
+            assert_eq!(home.api_token, "shared_token");
+            assert_eq!(home.zone_id, "home_zone");
+            assert_eq!(home.domain_names, vec!["home.example.com"]);
+            assert_eq!(home.update_interval, Duration::from_secs(10 * 60));
+
+            env::set_var("FLARESYNC_PROFILE", "office");
+            let office = Config::load(&file_options("flaresync.toml", None)).unwrap();
+            assert_eq!(office.api_token, "office_token");
+            assert_eq!(office.zone_id, "office_zone");
+            assert_eq!(
+                file_options("flaresync.toml", None).describe(),
+                "flaresync.toml (profile office) + environment"
+            );
+            env::remove_var("FLARESYNC_PROFILE");
+            assert_eq!(LoadOptions::default().describe(), "environment");
+        });
+    }
+
+    #[test]
+    fn test_config_load_jobs_reads_each_profile() {
+        run_test(|| {
+            let jobs_file = "update_interval = 10\n\
+                 flaresync_jobs = [\"home\", \"office\"]\n\
+                 [profiles.home]\n\
+                 cloudflare_api_token = \"home_token\"\n\
+                 cloudflare_zone_id = \"home_zone\"\n\
+                 domain_name = \"home.example.com\"\n\
+                 status_file_path = \"status/home.json\"\n\
+                 history_file_path = \"status/home.jsonl\"\n\
+                 [profiles.office]\n\
+                 cloudflare_api_token = \"office_token\"\n\
+                 cloudflare_zone_id = \"office_zone\"\n\
+                 domain_name = \"office.example.com\"\n";
+            std::fs::write("flaresync.toml", jobs_file).unwrap();
+
+            let jobs = Config::load_jobs(&file_options("flaresync.toml", None)).unwrap();
+            let names: Vec<_> = jobs.iter().map(|job| job.name.as_deref()).collect();
+            assert_eq!(names, vec![Some("home"), Some("office")]);
+            assert_eq!(jobs[0].config.zone_id, "home_zone");
+            assert_eq!(jobs[1].config.api_token, "office_token");
+            assert_eq!(jobs[1].config.update_interval, Duration::from_secs(10 * 60));
+            assert_eq!(jobs[1].load_options.profile.as_deref(), Some("office"));
+
+            let single = Config::load_jobs(&file_options("flaresync.toml", Some("home"))).unwrap();
+            assert_eq!(single.len(), 1);
+            assert_eq!(single[0].name, None);
+            assert_eq!(single[0].config.zone_id, "home_zone");
+
+            std::fs::write(
+                "flaresync.toml",
+                jobs_file.replace("status_file_path = \"status/home.json\"\n", ""),
+            )
+            .unwrap();
+            match Config::load_jobs(&file_options("flaresync.toml", None)) {
+                Err(FlareSyncError::Config(message)) => {
+                    assert!(message.contains("STATUS_FILE_PATH"));
+                }
+                other => panic!("expected config error, got {:?}", other),
+            }
+
+            for (key, jobs_file) in [
+                (
+                    "HEALTH_BIND_ADDRESS",
+                    jobs_file.replace(
+                        "example.com\"\n",
+                        "example.com\"\nhealth_bind_address = \"127.0.0.1:8080\"\n",
+                    ),
+                ),
+                (
+                    "PID_FILE_PATH",
+                    jobs_file.replace(
+                        "office.example.com\"\n",
+                        "office.example.com\"\npid_file_path = \"status/office.pid\"\n",
+                    ),
+                ),
+            ] {
+                std::fs::write("flaresync.toml", jobs_file).unwrap();
+                match Config::load_jobs(&file_options("flaresync.toml", None)) {
+                    Err(FlareSyncError::Config(message)) => assert!(message.contains(key)),
+                    other => panic!("expected config error, got {:?}", other),
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn test_config_load_jobs_rejects_different_rate_limits() {
+        run_test(|| {
+            let jobs_file = "flaresync_jobs = [\"home\", \"office\"]\n\
+                 cloudflare_rate_limit = 100\n\
+                 [profiles.home]\n\
+                 cloudflare_api_token = \"home_token\"\n\
+                 cloudflare_zone_id = \"home_zone\"\n\
+                 domain_name = \"home.example.com\"\n\
+                 status_file_path = \"status/home.json\"\n\
+                 history_file_path = \"status/home.jsonl\"\n\
+                 [profiles.office]\n\
+                 cloudflare_api_token = \"office_token\"\n\
+                 cloudflare_zone_id = \"office_zone\"\n\
+                 domain_name = \"office.example.com\"\n";
+            std::fs::write("flaresync.toml", jobs_file).unwrap();
+            let jobs = Config::load_jobs(&file_options("flaresync.toml", None)).unwrap();
+            assert!(jobs
+                .iter()
+                .all(|job| job.config.cloudflare_rate_limit == 100));
+
+            std::fs::write(
+                "flaresync.toml",
+                jobs_file.replace(
+                    "office.example.com\"\n",
+                    "office.example.com\"\ncloudflare_rate_limit = 600\n",
+                ),
+            )
+            .unwrap();
+            match Config::load_jobs(&file_options("flaresync.toml", None)) {
+                Err(FlareSyncError::Config(message)) => {
+                    assert!(message.contains(
+                        "Jobs 'home' and 'office' use a different CLOUDFLARE_RATE_LIMIT"
+                    ));
+                }
+                other => panic!("expected config error, got {:?}", other),
+            }
+        });
+    }
+
+    #[test]
+    fn test_config_load_rejects_unknown_profile() {
+        run_test(|| {
+            std::fs::write(
+                "flaresync.toml",
+                "[profiles.home]\ndomain_name = \"home.example.com\"\n",
+            )
+            .unwrap();
+
+            match Config::load(&file_options("flaresync.toml", Some("office"))) {
+                Err(FlareSyncError::Config(message)) => {
+                    assert!(message.contains("office"));
+                    assert!(message.contains("home"));
+                }
+                other => panic!("expected config error, got {:?}", other),
+            }
+        });
+    }
+
+    #[test]
+    fn test_config_load_strict_rejects_misspelled_env_var() {
+        run_test(|| {
+            env::set_var("CLOUDFLARE_API_TOKEN", "test_token");
+            env::set_var("CLOUDFLARE_ZONE_ID", "test_zone_id");
+            env::set_var("DOMAIN_NAME", "example.com");
+            env::set_var("UPDATE_INTERVAL_MINS", "15");
+
+            assert!(Config::from_env().is_ok());
+
+            let options = LoadOptions {
+                strict: true,
+                ..LoadOptions::default()
+            };
+            match Config::load(&options) {
+                Err(FlareSyncError::Config(message)) => {
+                    assert!(message.contains("UPDATE_INTERVAL_MINS"));
+                    assert!(message.contains("did you mean 'UPDATE_INTERVAL'"));
+                }
+                other => panic!("expected config error, got {:?}", other),
+            }
+        });
+    }
+
+    #[test]
+    fn test_config_load_strict_rejects_unknown_file_key() {
+        run_test(|| {
+            std::fs::write(
+                "flaresync.toml",
+                "flaresync_strict = true\n\
+                 cloudflare_api_token = \"test_token\"\n\
+                 cloudflare_zone_id = \"test_zone_id\"\n\
+                 [profiles.home]\n\
+                 domian_name = \"example.com\"\n",
+            )
+            .unwrap();
+
+            match Config::load(&file_options("flaresync.toml", None)) {
+                Err(FlareSyncError::Config(message)) => {
+                    assert!(message.contains("profiles.home.domian_name"));
+                    assert!(message.contains("did you mean 'DOMAIN_NAME'"));
+                }
+                other => panic!("expected config error, got {:?}", other),
+            }
+        });
+    }
 }
@@ -0,0 +1,227 @@
+use chrono::Local;
+use std::fmt::Debug;
+use std::io::Write;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::logging::EVENTS_TARGET;
+
+const BIN_TARGET: &str = "flaresync";
+pub(crate) const RED: &str = "31";
+pub(crate) const GREEN: &str = "32";
+pub(crate) const YELLOW: &str = "33";
+pub(crate) const CYAN: &str = "36";
+pub(crate) const DIM: &str = "2";
+
+pub struct ConsoleLayer {
+    writer: BoxMakeWriter,
+    ansi: bool,
+}
+
+impl ConsoleLayer {
+    pub fn new(writer: BoxMakeWriter, ansi: bool) -> Self {
+        Self { writer, ansi }
+    }
+}
+
+struct ConsoleDomain(String);
+
+#[derive(Debug, Default)]
+struct ConsoleFields {
+    message: String,
+    outcome: Option<String>,
+    new_ip: Option<String>,
+    duration_ms: Option<u64>,
+    domain: Option<String>,
+}
+
+impl Visit for ConsoleFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field.name(), value.to_string());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "duration_ms" {
+            self.duration_ms = Some(value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.record(field.name(), format!("{:?}", value));
+    }
+}
+
+impl ConsoleFields {
+    fn record(&mut self, name: &str, value: String) {
+        match name {
+            "message" => self.message = value,
+            "outcome" => self.outcome = Some(value),
+            "new_ip" => self.new_ip = Some(value),
+            "domain" => self.domain = Some(value),
+            _ => {}
+        }
+    }
+}
+
+impl<S> Layer<S> for ConsoleLayer
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != "domain" {
+            return;
+        }
+        let mut fields = ConsoleFields::default();
+        attrs.record(&mut fields);
+        if let (Some(domain), Some(span)) = (fields.domain, ctx.span(id)) {
+            span.extensions_mut().insert(ConsoleDomain(domain));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = ConsoleFields::default();
+        event.record(&mut fields);
+        let domain = ctx.event_scope(event).and_then(|scope| {
+            scope.from_root().find_map(|span| {
+                span.extensions()
+                    .get::<ConsoleDomain>()
+                    .map(|domain| domain.0.clone())
+            })
+        });
+
+        let metadata = event.metadata();
+        let Some(line) = render_line(
+            *metadata.level(),
+            metadata.target(),
+            &fields,
+            domain.as_deref(),
+            self.ansi,
+        ) else {
+            return;
+        };
+        let timestamp = paint(&Local::now().format("%H:%M:%S").to_string(), DIM, self.ansi);
+        let _ = writeln!(
+            self.writer.make_writer_for(metadata),
+            "{} {}",
+            timestamp,
+            line
+        );
+    }
+}
+
+fn render_line(
+    level: Level,
+    target: &str,
+    fields: &ConsoleFields,
+    domain: Option<&str>,
+    ansi: bool,
+) -> Option<String> {
+    if let Some(outcome) = &fields.outcome {
+        let domain = domain.unwrap_or("-");
+        let new_ip = fields.new_ip.as_deref().unwrap_or("-");
+        let (symbol, color, detail) = match outcome.as_str() {
+            "updated" => ("↻", CYAN, format!("updated to {}", new_ip)),
+            "unchanged" => ("✓", GREEN, format!("unchanged ({})", new_ip)),
+            "missing" => ("?", YELLOW, "no matching DNS record".to_string()),
+            _ => ("✗", RED, fields.message.clone()),
+        };
+        let duration = fields
+            .duration_ms
+            .map(|duration_ms| format!(" {}", paint(&format!("{}ms", duration_ms), DIM, ansi)))
+            .unwrap_or_default();
+        return Some(format!(
+            "{} {} {}{}",
+            paint(symbol, color, ansi),
+            domain,
+            detail,
+            duration
+        ));
+    }
+
+    match level {
+        Level::ERROR => Some(format!("{} {}", paint("✗", RED, ansi), fields.message)),
+        Level::WARN => Some(format!("{} {}", paint("!", YELLOW, ansi), fields.message)),
+        Level::INFO if target != BIN_TARGET && target != EVENTS_TARGET => None,
+        _ => Some(format!("  {}", fields.message)),
+    }
+}
+
+pub(crate) fn paint(text: &str, color: &str, ansi: bool) -> String {
+    if ansi {
+        format!("\x1b[{}m{}\x1b[0m", color, text)
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(message: &str, outcome: Option<&str>) -> ConsoleFields {
+        ConsoleFields {
+            message: message.to_string(),
+            outcome: outcome.map(str::to_string),
+            new_ip: Some("203.0.113.7".to_string()),
+            duration_ms: Some(42),
+            domain: None,
+        }
+    }
+
+    #[test]
+    fn test_render_line_summarizes_domain_outcomes() {
+        let updated = fields("IP address updated", Some("updated"));
+        assert_eq!(
+            render_line(
+                Level::INFO,
+                BIN_TARGET,
+                &updated,
+                Some("example.com"),
+                false
+            ),
+            Some("↻ example.com updated to 203.0.113.7 42ms".to_string())
+        );
+
+        let unchanged = fields("No update needed", Some("unchanged"));
+        assert_eq!(
+            render_line(
+                Level::INFO,
+                BIN_TARGET,
+                &unchanged,
+                Some("example.com"),
+                true
+            ),
+            Some(
+                "\x1b[32m✓\x1b[0m example.com unchanged (203.0.113.7) \x1b[2m42ms\x1b[0m"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_line_hides_verbose_library_info() {
+        let verbose = fields("Checking DNS for domain", None);
+        assert_eq!(
+            render_line(Level::INFO, "flaresync::cloudflare", &verbose, None, false),
+            None
+        );
+
+        let event = fields("FlareSync started", None);
+        assert_eq!(
+            render_line(Level::INFO, EVENTS_TARGET, &event, None, false),
+            Some("  FlareSync started".to_string())
+        );
+
+        let warning = fields("Retrying", None);
+        assert_eq!(
+            render_line(Level::WARN, "flaresync::cloudflare", &warning, None, false),
+            Some("! Retrying".to_string())
+        );
+    }
+}
@@ -0,0 +1,411 @@
+use crate::config::{Config, LoadOptions};
+use crate::engine::FlareSync;
+use crate::errors::FlareSyncError;
+use crate::status::RuntimeStatus;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A request to a running daemon, sent as one line over the control socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ControlCommand {
+    /// Print the daemon's runtime status as JSON
+    Status,
+    /// Check every domain now
+    SyncNow,
+    /// Stop syncing, or skip one domain, until resumed
+    Pause,
+    /// Resume syncing, or one paused domain
+    Resume,
+    /// Re-read the configuration file and environment
+    ReloadConfig,
+}
+
+impl ControlCommand {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ControlCommand::Status => "status",
+            ControlCommand::SyncNow => "sync-now",
+            ControlCommand::Pause => "pause",
+            ControlCommand::Resume => "resume",
+            ControlCommand::ReloadConfig => "reload-config",
+        }
+    }
+}
+
+impl fmt::Display for ControlCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ControlCommand {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "status" => Ok(ControlCommand::Status),
+            "sync-now" => Ok(ControlCommand::SyncNow),
+            "pause" => Ok(ControlCommand::Pause),
+            "resume" => Ok(ControlCommand::Resume),
+            "reload-config" => Ok(ControlCommand::ReloadConfig),
+            other => Err(format!(
+                "Unknown command '{}'; expected status, sync-now, pause, resume, or reload-config",
+                other
+            )),
+        }
+    }
+}
+
+/// A command and, for `pause` and `resume`, the single domain it applies to. On the socket
+/// it is one line such as `pause example.com`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    pub domain: Option<String>,
+}
+
+impl From<ControlCommand> for ControlRequest {
+    fn from(command: ControlCommand) -> Self {
+        Self {
+            command,
+            domain: None,
+        }
+    }
+}
+
+impl fmt::Display for ControlRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.domain {
+            Some(domain) => write!(f, "{} {}", self.command, domain),
+            None => write!(f, "{}", self.command),
+        }
+    }
+}
+
+impl FromStr for ControlRequest {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut words = value.split_whitespace();
+        let command = words.next().unwrap_or_default().parse()?;
+        let domain = words.next().map(str::to_string);
+        if words.next().is_some() {
+            return Err(format!("Too many arguments in '{}'", value.trim()));
+        }
+        Ok(Self { command, domain })
+    }
+}
+
+/// The single JSON line the daemon answers each command with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ControlResponse {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<RuntimeStatus>,
+}
+
+impl ControlResponse {
+    fn ok(message: impl Into<String>) -> Self {
+        Self {
+            ok: true,
+            message: Some(message.into()),
+            status: None,
+        }
+    }
+
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            ..Self::ok(message)
+        }
+    }
+}
+
+/// Applies `request` to `engine`. `reload-config` loads the configuration again with the
+/// daemon's original `load_options`.
+pub fn execute(
+    engine: &FlareSync,
+    load_options: &LoadOptions,
+    request: &ControlRequest,
+) -> ControlResponse {
+    let command = request.command;
+    match (command, request.domain.as_deref()) {
+        (ControlCommand::Pause, Some(domain)) => match engine.pause_domain(domain) {
+            Ok(true) => ControlResponse::ok(format!("Syncing paused for {}", domain)),
+            Ok(false) => ControlResponse::ok(format!("{} is already paused", domain)),
+            Err(e) => ControlResponse::error(e.to_string()),
+        },
+        (ControlCommand::Resume, Some(domain)) => match engine.resume_domain(domain) {
+            Ok(true) => ControlResponse::ok(format!("Syncing resumed for {}", domain)),
+            Ok(false) => ControlResponse::ok(format!("{} is not paused", domain)),
+            Err(e) => ControlResponse::error(e.to_string()),
+        },
+        (_, Some(_)) => ControlResponse::error(format!("{} does not take a domain", command)),
+        (_, None) => execute_command(engine, load_options, command),
+    }
+}
+
+fn execute_command(
+    engine: &FlareSync,
+    load_options: &LoadOptions,
+    command: ControlCommand,
+) -> ControlResponse {
+    match command {
+        ControlCommand::Status => ControlResponse {
+            ok: true,
+            message: None,
+            status: Some(engine.status().borrow().clone()),
+        },
+        ControlCommand::SyncNow if engine.is_paused() => {
+            ControlResponse::error("Syncing is paused; resume it first")
+        }
+        ControlCommand::SyncNow => {
+            engine.check_now();
+            ControlResponse::ok("Checking every domain now")
+        }
+        ControlCommand::Pause if engine.is_paused() => {
+            ControlResponse::ok("Syncing is already paused")
+        }
+        ControlCommand::Pause => {
+            engine.pause();
+            ControlResponse::ok("Syncing paused")
+        }
+        ControlCommand::Resume if !engine.is_paused() => {
+            ControlResponse::ok("Syncing is not paused")
+        }
+        ControlCommand::Resume => {
+            engine.resume();
+            ControlResponse::ok("Syncing resumed")
+        }
+        ControlCommand::ReloadConfig => match Config::load(load_options) {
+            Ok(config) => {
+                engine.reload(config);
+                ControlResponse::ok("Configuration reloaded")
+            }
+            Err(e) => ControlResponse::error(format!("Failed to reload configuration: {}", e)),
+        },
+    }
+}
+
+#[cfg(unix)]
+pub use self::unix::{bind, remove, request, serve};
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+    use crate::logging::EVENTS_TARGET;
+    use std::fs;
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+    use std::path::Path;
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{UnixListener, UnixStream};
+    use tracing::{debug, info};
+
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Listens on `path`, replacing a socket left behind by an earlier run. Only the owner
+    /// may connect.
+    pub fn bind(path: &Path) -> Result<UnixListener, FlareSyncError> {
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path)?,
+            Ok(_) => {
+                return Err(FlareSyncError::Config(format!(
+                    "CONTROL_SOCKET {} exists and is not a socket",
+                    path.display()
+                )))
+            }
+            Err(_) => {}
+        }
+        let Some(file_name) = path.file_name() else {
+            return Err(FlareSyncError::Config(format!(
+                "CONTROL_SOCKET {} is not a file path",
+                path.display()
+            )));
+        };
+        let parent = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        fs::create_dir_all(parent)?;
+
+        // Bind inside a directory only the owner can enter, so nobody can connect before
+        // the socket's mode is restricted, then move the socket into place.
+        let staging = parent.join(format!(
+            ".{}.{}",
+            file_name.to_string_lossy(),
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&staging);
+        fs::DirBuilder::new().mode(0o700).create(&staging)?;
+        let staged = staging.join("socket");
+        let result = (|| -> Result<UnixListener, FlareSyncError> {
+            let listener = UnixListener::bind(&staged)?;
+            fs::set_permissions(&staged, fs::Permissions::from_mode(0o600))?;
+            fs::rename(&staged, path)?;
+            Ok(listener)
+        })();
+        let _ = fs::remove_dir_all(&staging);
+        result
+    }
+
+    /// Deletes the socket file when the daemon stops.
+    pub fn remove(path: &Path) {
+        let _ = fs::remove_file(path);
+    }
+
+    /// Answers one command per connection until the task is dropped.
+    pub async fn serve(
+        listener: UnixListener,
+        engine: FlareSync,
+        load_options: LoadOptions,
+    ) -> Result<(), FlareSyncError> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let engine = engine.clone();
+            let load_options = load_options.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle(stream, &engine, &load_options).await {
+                    debug!("Control connection failed: {}", e);
+                }
+            });
+        }
+    }
+
+    async fn handle(
+        stream: UnixStream,
+        engine: &FlareSync,
+        load_options: &LoadOptions,
+    ) -> Result<(), FlareSyncError> {
+        let (reader, mut writer) = stream.into_split();
+        let mut line = String::new();
+        tokio::time::timeout(REQUEST_TIMEOUT, BufReader::new(reader).read_line(&mut line))
+            .await
+            .map_err(|_| FlareSyncError::Timeout("No command received".to_string()))??;
+
+        let response = match line.parse::<ControlRequest>() {
+            Ok(request) => {
+                info!(target: EVENTS_TARGET, "Control command received: {}", request);
+                execute(engine, load_options, &request)
+            }
+            Err(message) => ControlResponse::error(message),
+        };
+        let mut body = serde_json::to_vec(&response)?;
+        body.push(b'\n');
+        writer.write_all(&body).await?;
+        Ok(())
+    }
+
+    /// Sends `request` to the daemon listening on `path` and returns its answer.
+    pub async fn request(
+        path: &Path,
+        request: &ControlRequest,
+    ) -> Result<ControlResponse, FlareSyncError> {
+        let stream = UnixStream::connect(path).await.map_err(|e| {
+            FlareSyncError::Io(std::io::Error::new(
+                e.kind(),
+                format!(
+                    "Cannot connect to {} (is the daemon running?): {}",
+                    path.display(),
+                    e
+                ),
+            ))
+        })?;
+        let (reader, mut writer) = stream.into_split();
+        writer
+            .write_all(format!("{}\n", request).as_bytes())
+            .await?;
+        let mut line = String::new();
+        tokio::time::timeout(REQUEST_TIMEOUT, BufReader::new(reader).read_line(&mut line))
+            .await
+            .map_err(|_| FlareSyncError::Timeout("The daemon did not answer".to_string()))??;
+        Ok(serde_json::from_str(&line)?)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[tokio::test]
+    async fn test_control_socket_drives_engine() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let test_dir = std::env::temp_dir().join(format!(
+            "flaresync_control_test_{}_{}",
+            std::process::id(),
+            unique
+        ));
+        let config = Config::builder()
+            .api_token("test_token")
+            .zone("test_zone_id")
+            .domains(["example.com"])
+            .status_file_path(test_dir.join("status.json"))
+            .history_file_path(test_dir.join("history.jsonl"))
+            .build()
+            .unwrap();
+        let engine = FlareSync::new(config).unwrap();
+        let path = test_dir.join("control.sock");
+        let listener = bind(&path).unwrap();
+        let server = tokio::spawn(serve(listener, engine.clone(), LoadOptions::default()));
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        let entries: Vec<_> = std::fs::read_dir(&test_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(entries, ["control.sock"]);
+
+        let send = |request_line: &str| {
+            let request_line = request_line.parse::<ControlRequest>().unwrap();
+            let path = path.clone();
+            async move { request(&path, &request_line).await.unwrap() }
+        };
+        let response = send("status").await;
+        assert!(response.ok);
+        assert!(!response.status.unwrap().paused);
+
+        let response = send("pause").await;
+        assert_eq!(response, ControlResponse::ok("Syncing paused"));
+        assert!(engine.is_paused());
+        let response = send("sync-now").await;
+        assert!(!response.ok);
+        send("resume").await;
+        assert!(!engine.is_paused());
+
+        let response = send("pause Example.com").await;
+        assert_eq!(
+            response,
+            ControlResponse::ok("Syncing paused for Example.com")
+        );
+        assert!(!engine.is_paused());
+        assert!(engine.paused_domains().contains("example.com"));
+        let status = send("status").await.status.unwrap();
+        assert!(status.domains["example.com"].paused);
+        assert!(!send("pause other.org").await.ok);
+        assert!(!send("sync-now example.com").await.ok);
+        send("resume example.com").await;
+        assert!(engine.paused_domains().is_empty());
+
+        assert_eq!(
+            "Sync-Now".parse::<ControlCommand>(),
+            Ok(ControlCommand::SyncNow)
+        );
+        assert!("restart".parse::<ControlCommand>().is_err());
+        assert!("pause a.com b.com".parse::<ControlRequest>().is_err());
+
+        server.abort();
+        remove(&path);
+        assert!(!path.exists());
+        let _ = std::fs::remove_dir_all(test_dir);
+    }
+}
@@ -0,0 +1,40 @@
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::BuildHasher;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+tokio::task_local! {
+    static CYCLE_ID: String;
+}
+
+pub fn new_cycle_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    format!("{:016x}", RandomState::new().hash_one(nanos))
+}
+
+pub async fn with_cycle_id<F: Future>(cycle_id: String, future: F) -> F::Output {
+    CYCLE_ID.scope(cycle_id, future).await
+}
+
+pub fn current_cycle_id() -> Option<String> {
+    CYCLE_ID.try_with(|cycle_id| cycle_id.clone()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cycle_id_is_visible_only_inside_scope() {
+        let cycle_id = new_cycle_id();
+        assert_eq!(cycle_id.len(), 16);
+        assert_ne!(cycle_id, new_cycle_id());
+
+        assert_eq!(current_cycle_id(), None);
+        let seen = with_cycle_id(cycle_id.clone(), async { current_cycle_id() }).await;
+        assert_eq!(seen, Some(cycle_id));
+    }
+}
@@ -0,0 +1,165 @@
+use crate::console::{paint, CYAN, DIM, GREEN, RED, YELLOW};
+use crate::history::HistoryEntry;
+use crate::status::RuntimeStatus;
+use chrono::{DateTime, FixedOffset, Local};
+use std::time::Duration;
+
+pub const CLEAR_SCREEN: &str = "\x1b[H\x1b[2J";
+
+fn parse_time(timestamp: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(timestamp).ok()
+}
+
+fn clock(timestamp: Option<&str>) -> String {
+    match timestamp.and_then(parse_time) {
+        Some(time) => time.with_timezone(&Local).format("%H:%M:%S").to_string(),
+        None => "never".to_string(),
+    }
+}
+
+fn next_check(status: &RuntimeStatus, update_interval: Duration, now: DateTime<Local>) -> String {
+    if status.shutting_down {
+        return "stopped".to_string();
+    }
+    let Some(last_cycle) = status.last_cycle_at.as_deref().and_then(parse_time) else {
+        return "waiting for the first cycle".to_string();
+    };
+    let Ok(interval) = chrono::Duration::from_std(update_interval) else {
+        return "unknown".to_string();
+    };
+    let remaining = (last_cycle + interval - now.fixed_offset()).num_seconds();
+    if remaining <= 0 {
+        "due now".to_string()
+    } else {
+        format!("in {}m {:02}s", remaining / 60, remaining % 60)
+    }
+}
+
+fn domain_symbol(status: &str) -> (&'static str, &'static str) {
+    match status {
+        "updated" => ("↻", CYAN),
+        "unchanged" => ("✓", GREEN),
+        "missing" => ("?", YELLOW),
+        "error" => ("✗", RED),
+        "paused" => ("‖", YELLOW),
+        _ => ("·", DIM),
+    }
+}
+
+/// Renders one dashboard frame from the status file, the configured domains, and the
+/// newest `event_lines` history entries.
+pub fn render(
+    status: &RuntimeStatus,
+    domains: &[String],
+    history: &[HistoryEntry],
+    update_interval: Duration,
+    event_lines: usize,
+    now: DateTime<Local>,
+    ansi: bool,
+) -> String {
+    let mut lines = vec![
+        format!(
+            "FlareSync  {}",
+            paint(&now.format("%Y-%m-%d %H:%M:%S").to_string(), DIM, ansi)
+        ),
+        String::new(),
+        format!(
+            "Public IP:   {} (checked {})",
+            status.last_public_ip.as_deref().unwrap_or("unknown"),
+            clock(status.last_ip_check_at.as_deref())
+        ),
+        format!("Last cycle:  {}", clock(status.last_cycle_at.as_deref())),
+        format!("Next check:  {}", next_check(status, update_interval, now)),
+    ];
+    if let Some(error) = &status.last_error {
+        lines.push(format!("Last error:  {}", paint(error, RED, ansi)));
+    }
+
+    lines.push(String::new());
+    lines.push(format!(
+        "  {:<32} {:<10} {:<10} {:<10} FAILURES",
+        "DOMAIN", "STATUS", "CHECKED", "UPDATED"
+    ));
+    for domain_name in domains {
+        let domain = status.domains.get(domain_name).cloned().unwrap_or_default();
+        let (symbol, color) = domain_symbol(domain.display_status());
+        lines.push(format!(
+            "{} {:<32} {:<10} {:<10} {:<10} {}",
+            paint(symbol, color, ansi),
+            domain_name,
+            domain.display_status(),
+            clock(domain.last_checked_at.as_deref()),
+            clock(domain.last_updated_at.as_deref()),
+            domain.consecutive_failures
+        ));
+    }
+
+    lines.push(String::new());
+    lines.push("Events".to_string());
+    let start = history.len().saturating_sub(event_lines);
+    if history.is_empty() {
+        lines.push(paint("  no events yet", DIM, ansi));
+    }
+    for entry in history[start..].iter().rev() {
+        let detail = match entry.result.as_str() {
+            "failed" => paint(
+                &format!(
+                    "failed: {}",
+                    entry.error.as_deref().unwrap_or("unknown error")
+                ),
+                RED,
+                ansi,
+            ),
+            result => format!(
+                "{} {} → {}",
+                result,
+                entry.old_ip.as_deref().unwrap_or("unknown"),
+                entry.new_ip
+            ),
+        };
+        lines.push(format!(
+            "  {}  {} {}",
+            paint(&clock(Some(&entry.timestamp)), DIM, ansi),
+            entry.domain,
+            detail
+        ));
+    }
+
+    let mut frame = lines.join("\n");
+    frame.push('\n');
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_shows_countdown_domains_and_newest_events() {
+        let now = Local::now();
+        let mut status = RuntimeStatus::new();
+        status.last_public_ip = Some("203.0.113.7".to_string());
+        status.last_cycle_at = Some((now - chrono::Duration::seconds(60)).to_rfc3339());
+        status.mark_domain_result("a.example.com", "updated", true);
+        let history = vec![
+            HistoryEntry::updated("a.example.com", "198.51.100.1", "203.0.113.6"),
+            HistoryEntry::updated("a.example.com", "203.0.113.6", "203.0.113.7"),
+        ];
+
+        let frame = render(
+            &status,
+            &["a.example.com".to_string(), "b.example.com".to_string()],
+            &history,
+            Duration::from_secs(300),
+            1,
+            now,
+            false,
+        );
+        assert!(frame.contains("Public IP:   203.0.113.7"));
+        assert!(frame.contains("Next check:  in 4m 00s"));
+        assert!(frame.contains("↻ a.example.com"));
+        assert!(frame.contains("· b.example.com                    pending"));
+        assert!(frame.contains("a.example.com updated 203.0.113.6 → 203.0.113.7"));
+        assert!(!frame.contains("198.51.100.1"));
+    }
+}
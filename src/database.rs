@@ -0,0 +1,422 @@
+use crate::backup::{self, BackupCheck, BackupRetention, Snapshot, SnapshotKind};
+use crate::cloudflare::DnsRecord;
+use crate::encryption::{self, EncryptionKey};
+use crate::errors::FlareSyncError;
+use crate::history::HistoryEntry;
+use crate::journal::{self, JournalEntry};
+use crate::state::{DomainState, SavedState};
+use chrono::NaiveDateTime;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value TEXT
+);
+CREATE TABLE IF NOT EXISTS records (
+    domain TEXT PRIMARY KEY,
+    record TEXT,
+    last_updated_at TEXT
+);
+CREATE TABLE IF NOT EXISTS history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp TEXT NOT NULL,
+    domain TEXT NOT NULL,
+    old_ip TEXT,
+    new_ip TEXT NOT NULL,
+    result TEXT NOT NULL,
+    error TEXT
+);
+CREATE INDEX IF NOT EXISTS history_domain ON history (domain, id);
+CREATE TABLE IF NOT EXISTS backups (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    saved_at TEXT NOT NULL,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    content TEXT NOT NULL,
+    document TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS backups_name ON backups (name, kind, id);
+";
+
+/// A SQLite database holding the saved state, the update history, and record backups in
+/// place of their separate files.
+pub struct Database {
+    connection: Connection,
+    path: PathBuf,
+}
+
+impl Database {
+    /// Opens the database at `path`, creating it, readable only by the owner, and its
+    /// tables if needed.
+    pub fn open(path: &Path) -> Result<Self, FlareSyncError> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        let connection = Connection::open(path)?;
+        #[cfg(unix)]
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        // Jobs of one host may run cycles at the same time.
+        connection.busy_timeout(Duration::from_secs(5))?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self {
+            connection,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn load_state(&self) -> Result<SavedState, FlareSyncError> {
+        let setting = |key: &str| -> Result<Option<String>, FlareSyncError> {
+            Ok(self
+                .connection
+                .query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| {
+                    row.get(0)
+                })
+                .optional()?
+                .flatten())
+        };
+        let mut state = SavedState {
+            zone_id: setting("zone_id")?,
+            last_public_ip: setting("last_public_ip")?,
+            ..SavedState::default()
+        };
+        let mut statement = self
+            .connection
+            .prepare("SELECT domain, record, last_updated_at FROM records")?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (domain, record, last_updated_at) = row?;
+            let record = record
+                .map(|record| serde_json::from_str::<DnsRecord>(&record))
+                .transpose()?;
+            state.domains.insert(
+                domain,
+                DomainState {
+                    record,
+                    last_updated_at,
+                },
+            );
+        }
+        Ok(state)
+    }
+
+    pub fn save_state(&mut self, state: &SavedState) -> Result<(), FlareSyncError> {
+        let transaction = self.connection.transaction()?;
+        for (key, value) in [
+            ("zone_id", &state.zone_id),
+            ("last_public_ip", &state.last_public_ip),
+        ] {
+            transaction.execute(
+                "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+                params![key, value],
+            )?;
+        }
+        transaction.execute("DELETE FROM records", [])?;
+        for (domain, saved) in &state.domains {
+            let record = saved
+                .record
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?;
+            transaction.execute(
+                "INSERT INTO records (domain, record, last_updated_at) VALUES (?1, ?2, ?3)",
+                params![domain, record, saved.last_updated_at],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    pub fn append_history(&self, entry: &HistoryEntry) -> Result<(), FlareSyncError> {
+        self.connection.execute(
+            "INSERT INTO history (timestamp, domain, old_ip, new_ip, result, error)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                entry.timestamp,
+                entry.domain,
+                entry.old_ip,
+                entry.new_ip,
+                entry.result,
+                entry.error
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Every history entry, oldest first.
+    pub fn history(&self) -> Result<Vec<HistoryEntry>, FlareSyncError> {
+        let mut statement = self.connection.prepare(
+            "SELECT timestamp, domain, old_ip, new_ip, result, error FROM history ORDER BY id",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok(HistoryEntry {
+                timestamp: row.get(0)?,
+                domain: row.get(1)?,
+                old_ip: row.get(2)?,
+                new_ip: row.get(3)?,
+                result: row.get(4)?,
+                error: row.get(5)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Stores `entry`. With `key`, the document is sealed by [`encryption::seal_json`] and
+    /// the record content is left out of its column.
+    pub fn insert_backup(
+        &self,
+        entry: &JournalEntry,
+        key: Option<&EncryptionKey>,
+    ) -> Result<(), FlareSyncError> {
+        let record = &entry.document.record;
+        let (content, document) = match key {
+            Some(key) => ("", encryption::seal_json(key, &entry.document)?.to_string()),
+            None => (
+                record.content.as_str(),
+                serde_json::to_string(&entry.document)?,
+            ),
+        };
+        self.connection.execute(
+            "INSERT INTO backups (saved_at, name, kind, content, document)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                entry.saved_at,
+                record.name,
+                entry.document.kind.as_str(),
+                content,
+                document
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The newest pre-update backup of the record named `domain` that satisfies `matches`.
+    pub fn newest_before(
+        &self,
+        domain: &str,
+        key: Option<&EncryptionKey>,
+        matches: impl Fn(&DnsRecord) -> bool,
+    ) -> Result<Option<Snapshot>, FlareSyncError> {
+        let mut statement = self.connection.prepare(
+            "SELECT id, saved_at, document FROM backups
+             WHERE name = ?1 AND kind = ?2 ORDER BY id DESC",
+        )?;
+        let mut rows = statement.query(params![domain, SnapshotKind::Before.as_str()])?;
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let saved_at: String = row.get(1)?;
+            let document: String = row.get(2)?;
+            let entry = JournalEntry::from_stored(&saved_at, &document, key)?;
+            if matches(&entry.document.record) {
+                return Ok(Some(Snapshot {
+                    source: format!(
+                        "{} backup {} (saved {})",
+                        self.path.display(),
+                        id,
+                        entry.saved_at
+                    ),
+                    saved_at: entry.created_at(),
+                    zone: entry.document.zone,
+                    record: entry.document.record,
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Checks every stored backup against its checksum.
+    pub fn verify_backups(
+        &self,
+        key: Option<&EncryptionKey>,
+    ) -> Result<Vec<BackupCheck>, FlareSyncError> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT id, saved_at, name, document FROM backups ORDER BY id")?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?;
+        let mut checks = Vec::new();
+        for row in rows {
+            let (id, saved_at, name, document) = row?;
+            checks.push(BackupCheck::new(
+                format!("{} backup {} of {}", self.path.display(), id, name),
+                JournalEntry::from_stored(&saved_at, &document, key).map(|entry| entry.document),
+            ));
+        }
+        Ok(checks)
+    }
+
+    /// Deletes the backups `retention` expires, grouped by record name and kind like
+    /// backup files, and returns a description of each.
+    pub fn prune_backups(
+        &self,
+        retention: BackupRetention,
+        now: NaiveDateTime,
+        dry_run: bool,
+    ) -> Result<Vec<String>, FlareSyncError> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT id, saved_at, name, kind FROM backups ORDER BY id")?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?;
+        let backups = rows.collect::<Result<Vec<_>, _>>()?;
+        let expired = backup::expired_by(
+            backups,
+            |(_, _, name, kind)| (name.clone(), kind.clone()),
+            |(_, saved_at, _, _)| journal::local_time(saved_at),
+            retention,
+            now,
+        );
+
+        let mut removed = Vec::with_capacity(expired.len());
+        for (id, saved_at, name, _) in expired {
+            if !dry_run {
+                self.connection
+                    .execute("DELETE FROM backups WHERE id = ?1", [id])?;
+            }
+            removed.push(format!(
+                "{} backup {} of {} (saved {})",
+                self.path.display(),
+                id,
+                name,
+                saved_at
+            ));
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup::Integrity;
+
+    fn record(content: &str) -> DnsRecord {
+        DnsRecord {
+            id: "1".to_string(),
+            name: "home.example.com".to_string(),
+            content: content.to_string(),
+            record_type: "A".to_string(),
+            proxied: false,
+            ttl: 1,
+            modified_on: None,
+        }
+    }
+
+    #[test]
+    fn test_database_stores_state_history_and_backups() {
+        let dir = &std::env::temp_dir().join(format!("flaresync_database_{}", std::process::id()));
+        let _ = fs::remove_dir_all(dir);
+        let path = dir.join("flaresync.db");
+        let mut database = Database::open(&path).unwrap();
+        assert_eq!(database.load_state().unwrap(), SavedState::default());
+
+        let mut state = SavedState::default();
+        state.mark_ip(&"203.0.113.2".parse().unwrap());
+        state.mark_record("zone", "home.example.com", &record("203.0.113.2"), true);
+        database.save_state(&state).unwrap();
+        state.forget_record("home.example.com");
+        database.save_state(&state).unwrap();
+        assert_eq!(Database::open(&path).unwrap().load_state().unwrap(), state);
+
+        let entry = HistoryEntry::updated("home.example.com", "203.0.113.1", "203.0.113.2");
+        database.append_history(&entry).unwrap();
+        database
+            .append_history(&HistoryEntry::rolled_back(
+                "home.example.com",
+                "203.0.113.2",
+                "203.0.113.1",
+            ))
+            .unwrap();
+        let history = database.history().unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0], entry);
+
+        for (saved_at, content, kind) in [
+            (
+                "2024-01-01T12:00:00+00:00",
+                "203.0.113.1",
+                SnapshotKind::Before,
+            ),
+            (
+                "2024-02-01T12:00:00+00:00",
+                "203.0.113.2",
+                SnapshotKind::Before,
+            ),
+            (
+                "2024-02-01T12:00:01+00:00",
+                "203.0.113.3",
+                SnapshotKind::After,
+            ),
+        ] {
+            let entry =
+                JournalEntry::saved_at(saved_at.to_string(), &record(content), kind, None, None);
+            database.insert_backup(&entry, None).unwrap();
+        }
+        let checks = database.verify_backups(None).unwrap();
+        assert_eq!(checks.len(), 3);
+        assert!(checks
+            .iter()
+            .all(|check| check.integrity == Integrity::Verified));
+        let latest = database
+            .newest_before("home.example.com", None, |_| true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(latest.record.content, "203.0.113.2");
+        assert!(latest.source.contains("backup 2"));
+        let older = database
+            .newest_before("home.example.com", None, |record| {
+                record.content == "203.0.113.1"
+            })
+            .unwrap()
+            .unwrap();
+        assert_eq!(older.record.content, "203.0.113.1");
+        assert!(database
+            .newest_before("other.example.com", None, |_| true)
+            .unwrap()
+            .is_none());
+
+        let retention = BackupRetention {
+            keep_last: Some(1),
+            max_age: None,
+        };
+        let now = chrono::Local::now().naive_local();
+        assert_eq!(
+            database.prune_backups(retention, now, true).unwrap().len(),
+            1
+        );
+        let removed = database.prune_backups(retention, now, false).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert!(removed[0].contains("backup 1 of home.example.com"));
+        assert!(database
+            .newest_before("home.example.com", None, |record| record.content
+                == "203.0.113.1")
+            .unwrap()
+            .is_none());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
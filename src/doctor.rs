@@ -0,0 +1,186 @@
+use crate::cloudflare::verify_access;
+use crate::config::Config;
+use crate::errors::FlareSyncError;
+use crate::ip_provider;
+use reqwest::Client as ReqwestClient;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheckStatus::Pass => write!(f, "PASS"),
+            CheckStatus::Warn => write!(f, "WARN"),
+            CheckStatus::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DoctorCheck {
+    pub status: CheckStatus,
+    pub name: String,
+    pub detail: String,
+}
+
+impl DoctorCheck {
+    fn new(status: CheckStatus, name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            status,
+            name: name.into(),
+            detail: detail.into(),
+        }
+    }
+}
+
+async fn connectivity(client: &ReqwestClient) -> DoctorCheck {
+    let name = "Outbound HTTPS to api.cloudflare.com";
+    match client
+        .get("https://api.cloudflare.com/client/v4/")
+        .send()
+        .await
+    {
+        Ok(resp) => DoctorCheck::new(
+            CheckStatus::Pass,
+            name,
+            format!("reachable (HTTP {})", resp.status().as_u16()),
+        ),
+        Err(e) => DoctorCheck::new(CheckStatus::Fail, name, e.to_string()),
+    }
+}
+
+fn ip_agreement(results: &[(&str, Result<Ipv4Addr, FlareSyncError>)]) -> DoctorCheck {
+    let name = "Public IP quorum";
+    let ips: Vec<Ipv4Addr> = results
+        .iter()
+        .filter_map(|(_, result)| result.as_ref().ok().copied())
+        .collect();
+    let distinct: BTreeSet<Ipv4Addr> = ips.iter().copied().collect();
+    let quorum = distinct
+        .iter()
+        .find(|ip| ips.iter().filter(|other| other == ip).count() >= 2);
+    match quorum {
+        Some(ip) if ips.len() == results.len() && distinct.len() == 1 => DoctorCheck::new(
+            CheckStatus::Pass,
+            name,
+            format!("all sources agree on {}", ip),
+        ),
+        Some(ip) => DoctorCheck::new(
+            CheckStatus::Warn,
+            name,
+            format!("{} by quorum, but not every source agreed", ip),
+        ),
+        None => DoctorCheck::new(
+            CheckStatus::Fail,
+            name,
+            "fewer than 2 of 3 sources agree; updates will be skipped",
+        ),
+    }
+}
+
+fn directory_writable(name: &str, dir: &Path) -> DoctorCheck {
+    let probe = dir.join(".flaresync-doctor");
+    let result = fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&probe, b"ok"))
+        .and_then(|_| fs::remove_file(&probe));
+    match result {
+        Ok(()) => DoctorCheck::new(
+            CheckStatus::Pass,
+            name,
+            format!("{} is writable", dir.display()),
+        ),
+        Err(e) => DoctorCheck::new(
+            CheckStatus::Fail,
+            name,
+            format!("{} is not writable: {}", dir.display(), e),
+        ),
+    }
+}
+
+pub async fn run(client: &ReqwestClient, config: &Config) -> Vec<DoctorCheck> {
+    let mut checks = vec![connectivity(client).await];
+
+    let sources = ip_provider::check_sources(client).await;
+    for (source, result) in &sources {
+        checks.push(match result {
+            Ok(ip) => DoctorCheck::new(
+                CheckStatus::Pass,
+                format!("IP source {}", source),
+                ip.to_string(),
+            ),
+            Err(e) => DoctorCheck::new(
+                CheckStatus::Warn,
+                format!("IP source {}", source),
+                e.to_string(),
+            ),
+        });
+    }
+    checks.push(ip_agreement(&sources));
+
+    match verify_access(
+        client,
+        &config.api_token,
+        &config.zone_id,
+        &config.domain_names,
+    )
+    .await
+    {
+        Ok(access) => checks.extend(access.into_iter().map(|check| match check.problem {
+            None => DoctorCheck::new(CheckStatus::Pass, check.name, "ok"),
+            Some(problem) => DoctorCheck::new(CheckStatus::Fail, check.name, problem),
+        })),
+        Err(e) => checks.push(DoctorCheck::new(
+            CheckStatus::Fail,
+            "Cloudflare API access",
+            e.to_string(),
+        )),
+    }
+
+    checks.push(directory_writable("Backup directory", &config.backup_dir));
+    if let Some(status_dir) = config.status_file_path.parent() {
+        checks.push(directory_writable("Status directory", status_dir));
+    }
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_agreement_grades_quorum() {
+        let ip = |s: &str| Ok(s.parse::<Ipv4Addr>().unwrap());
+        let down = || Err(FlareSyncError::IpProvider("down".to_string()));
+
+        let all = [
+            ("a", ip("203.0.113.7")),
+            ("b", ip("203.0.113.7")),
+            ("c", ip("203.0.113.7")),
+        ];
+        assert_eq!(ip_agreement(&all).status, CheckStatus::Pass);
+
+        let two = [
+            ("a", ip("203.0.113.7")),
+            ("b", down()),
+            ("c", ip("203.0.113.7")),
+        ];
+        assert_eq!(ip_agreement(&two).status, CheckStatus::Warn);
+
+        let split = [
+            ("a", ip("203.0.113.7")),
+            ("b", down()),
+            ("c", ip("198.51.100.1")),
+        ];
+        assert_eq!(ip_agreement(&split).status, CheckStatus::Fail);
+    }
+}
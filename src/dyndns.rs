@@ -0,0 +1,328 @@
+use crate::admin::constant_time_eq;
+use crate::config::normalize_domain_name;
+use crate::engine::{DomainResult, FlareSync};
+use crate::errors::FlareSyncError;
+use crate::logging::EVENTS_TARGET;
+use axum::extract::{ConnectInfo, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+
+/// The most hostnames one request may update, matching the DynDNS2 protocol.
+const MAX_HOSTNAMES: usize = 20;
+
+/// Where the DynDNS2 server listens and the credentials routers must send.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DynDnsConfig {
+    pub bind_address: SocketAddr,
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Clone)]
+struct DynDnsState {
+    engine: FlareSync,
+    credentials: Arc<DynDnsConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateQuery {
+    hostname: Option<String>,
+    myip: Option<String>,
+}
+
+/// Serves `/nic/update` on `listener` until the task is dropped.
+pub async fn serve(
+    listener: TcpListener,
+    engine: FlareSync,
+    config: DynDnsConfig,
+) -> Result<(), FlareSyncError> {
+    let state = DynDnsState {
+        engine,
+        credentials: Arc::new(config),
+    };
+    let app = Router::new()
+        .route("/nic/update", get(update))
+        .with_state(state);
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn update(
+    State(state): State<DynDnsState>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(query): Query<UpdateQuery>,
+) -> Response {
+    if !authorized(&headers, &state.credentials) {
+        warn!(
+            "Rejected DynDNS update from {} with bad credentials",
+            remote
+        );
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Basic realm=\"FlareSync\"")],
+            "badauth",
+        )
+            .into_response();
+    }
+
+    let hostnames: Vec<&str> = query
+        .hostname
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|hostname| !hostname.is_empty())
+        .collect();
+    if hostnames.is_empty() {
+        return plain("notfqdn");
+    }
+    if hostnames.len() > MAX_HOSTNAMES {
+        return plain("numhost");
+    }
+    let Some(ip) = requested_ip(query.myip.as_deref(), remote.ip()) else {
+        warn!(
+            "DynDNS update from {} carried no IPv4 address to publish",
+            remote
+        );
+        return plain("911");
+    };
+    if !is_public(&ip) {
+        warn!(
+            "DynDNS update from {} asked for {}, which is not a public address",
+            remote, ip
+        );
+        return plain("911");
+    }
+
+    let config = state.engine.config();
+    let hostnames: Vec<Option<String>> = hostnames
+        .into_iter()
+        .map(|hostname| {
+            normalize_domain_name(hostname)
+                .ok()
+                .filter(|name| config.domain_names.contains(name))
+        })
+        .collect();
+    let known: Vec<String> = hostnames.iter().flatten().cloned().collect();
+    let results = if known.is_empty() {
+        Vec::new()
+    } else {
+        info!(
+            target: EVENTS_TARGET,
+            "DynDNS update from {}: {} -> {}",
+            remote,
+            known.join(", "),
+            ip
+        );
+        match state.engine.push_ip(known, ip).await {
+            Ok(results) => results,
+            Err(e) => {
+                warn!("DynDNS update from {} failed: {}", remote, e);
+                return plain(
+                    &hostnames
+                        .iter()
+                        .map(|name| if name.is_some() { "911" } else { "nohost" })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                );
+            }
+        }
+    };
+
+    let lines: Vec<String> = hostnames
+        .iter()
+        .map(|name| {
+            let result = name.as_ref().and_then(|name| {
+                results
+                    .iter()
+                    .find(|(domain, _)| domain == name)
+                    .map(|(_, result)| result)
+            });
+            match result {
+                Some(DomainResult::Updated) => format!("good {}", ip),
+                Some(DomainResult::Unchanged) => format!("nochg {}", ip),
+                Some(DomainResult::Paused) => "911".to_string(),
+                Some(DomainResult::Failed(_)) => "dnserr".to_string(),
+                Some(DomainResult::Missing) | None => "nohost".to_string(),
+            }
+        })
+        .collect();
+    plain(&lines.join("\n"))
+}
+
+fn plain(body: &str) -> Response {
+    (
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        body.to_string(),
+    )
+        .into_response()
+}
+
+fn authorized(headers: &HeaderMap, credentials: &DynDnsConfig) -> bool {
+    let decoded = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|encoded| STANDARD.decode(encoded.trim()).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok());
+    let Some((username, password)) = decoded.as_deref().and_then(|value| value.split_once(':'))
+    else {
+        return false;
+    };
+    // Compare both halves so a wrong username takes as long as a wrong password.
+    let username_ok = constant_time_eq(username.as_bytes(), credentials.username.as_bytes());
+    let password_ok = constant_time_eq(password.as_bytes(), credentials.password.as_bytes());
+    username_ok & password_ok
+}
+
+/// The first IPv4 address in `myip`, falling back to the address the request came from.
+fn requested_ip(myip: Option<&str>, remote: IpAddr) -> Option<Ipv4Addr> {
+    if let Some(myip) = myip {
+        return myip
+            .split(',')
+            .find_map(|value| value.trim().parse::<Ipv4Addr>().ok());
+    }
+    match remote {
+        IpAddr::V4(ip) => Some(ip),
+        IpAddr::V6(ip) => ip.to_ipv4_mapped(),
+    }
+}
+
+/// Whether `ip` can be reached from the internet, so it makes sense in public DNS.
+/// Private, loopback, link-local, CGNAT (100.64.0.0/10), unspecified, and broadcast
+/// addresses are not.
+fn is_public(ip: &Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    let cgnat = first == 100 && (second & 0xc0) == 64;
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || cgnat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[tokio::test]
+    async fn test_dyndns_server_checks_credentials_and_hostnames() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let test_dir = std::env::temp_dir().join(format!(
+            "flaresync_dyndns_test_{}_{}",
+            std::process::id(),
+            unique
+        ));
+        let config = Config::builder()
+            .api_token("test_token")
+            .zone("test_zone_id")
+            .domains(["example.com", "home.example.com"])
+            .status_file_path(test_dir.join("status.json"))
+            .history_file_path(test_dir.join("history.jsonl"))
+            .build()
+            .unwrap();
+        let engine = FlareSync::new(config).unwrap();
+        engine.pause();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}/nic/update", listener.local_addr().unwrap());
+        tokio::spawn(serve(
+            listener,
+            engine,
+            DynDnsConfig {
+                bind_address: "127.0.0.1:0".parse().unwrap(),
+                username: "router".to_string(),
+                password: "secret".to_string(),
+            },
+        ));
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("{}?hostname=example.com", base))
+            .basic_auth("router", Some("wrong"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        assert_eq!(response.text().await.unwrap(), "badauth");
+
+        let get = |query: &str| {
+            client
+                .get(format!("{}?{}", base, query))
+                .basic_auth("router", Some("secret"))
+                .send()
+        };
+        let body = get("hostname=").await.unwrap().text().await.unwrap();
+        assert_eq!(body, "notfqdn");
+        let body = get("hostname=other.org&myip=203.0.113.7")
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "nohost");
+        let body = get("hostname=Home.Example.com,other.org&myip=2001:db8::1,203.0.113.7")
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "911\nnohost");
+        // An unknown hostname would get `nohost`, so `911` shows the address was refused
+        // before the hostnames were looked at. Without `myip` the peer is 127.0.0.1.
+        let body = get("hostname=other.org")
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "911");
+        for private in [
+            "192.168.1.10",
+            "10.0.0.2",
+            "100.64.0.1",
+            "169.254.1.1",
+            "0.0.0.0",
+        ] {
+            let body = get(&format!("hostname=other.org&myip={}", private))
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+            assert_eq!(body, "911");
+        }
+        assert!(is_public(&Ipv4Addr::new(203, 0, 113, 7)));
+        assert!(is_public(&Ipv4Addr::new(100, 128, 0, 1)));
+
+        assert_eq!(
+            requested_ip(None, "::ffff:198.51.100.4".parse().unwrap()),
+            Some(Ipv4Addr::new(198, 51, 100, 4))
+        );
+        assert_eq!(
+            requested_ip(Some("2001:db8::1"), "::1".parse().unwrap()),
+            None
+        );
+
+        let _ = std::fs::remove_dir_all(test_dir);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::errors::FlareSyncError;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// Marks an encrypted backup and is authenticated along with it, so the format version
+/// cannot be swapped without failing decryption.
+const MAGIC: &[u8] = b"FLARESYNC-ENC1\n";
+pub const KEY_LEN: usize = 32;
+const SEALED_FIELD: &str = "encrypted";
+
+/// A ChaCha20-Poly1305 key for backups, given as 32 base64-encoded bytes.
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey([u8; KEY_LEN]);
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("EncryptionKey([REDACTED])")
+    }
+}
+
+impl EncryptionKey {
+    pub fn parse(encoded: &str) -> Result<Self, FlareSyncError> {
+        STANDARD
+            .decode(encoded.trim())
+            .ok()
+            .and_then(|bytes| <[u8; KEY_LEN]>::try_from(bytes).ok())
+            .map(Self)
+            .ok_or_else(|| {
+                FlareSyncError::Config(format!(
+                    "Backup encryption key must be {} base64-encoded bytes, e.g. from `openssl rand -base64 32`",
+                    KEY_LEN
+                ))
+            })
+    }
+
+    /// The key as configured, for redaction.
+    pub fn encoded(&self) -> String {
+        STANDARD.encode(self.0)
+    }
+
+    /// The HMAC-SHA256 of `data` under this key, which authenticates backup checksums.
+    pub fn mac(&self, data: &[u8]) -> Vec<u8> {
+        crate::hashing::hmac_sha256(&self.0, data)
+    }
+
+    fn aead_key(&self) -> LessSafeKey {
+        LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &self.0).expect("32-byte key"))
+    }
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// `data` sealed under `key` with a random nonce: the header, the nonce, then the
+/// ciphertext and its tag.
+pub fn encrypt(key: &EncryptionKey, data: &[u8]) -> Result<Vec<u8>, FlareSyncError> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new().fill(&mut nonce).map_err(|_| {
+        FlareSyncError::Config("Failed to generate a nonce for backup encryption".to_string())
+    })?;
+    let mut sealed = data.to_vec();
+    key.aead_key()
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(MAGIC),
+            &mut sealed,
+        )
+        .map_err(|_| FlareSyncError::Config("Failed to encrypt backup".to_string()))?;
+
+    let mut out = Vec::with_capacity(MAGIC.len() + NONCE_LEN + sealed.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&sealed);
+    Ok(out)
+}
+
+pub fn decrypt(key: &EncryptionKey, data: &[u8]) -> Result<Vec<u8>, FlareSyncError> {
+    let body = data
+        .strip_prefix(MAGIC)
+        .filter(|body| body.len() >= NONCE_LEN + CHACHA20_POLY1305.tag_len())
+        .ok_or_else(|| FlareSyncError::Config("Not an encrypted backup".to_string()))?;
+    let (nonce, sealed) = body.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce)
+        .map_err(|_| FlareSyncError::Config("Not an encrypted backup".to_string()))?;
+    let mut sealed = sealed.to_vec();
+    let plain = key
+        .aead_key()
+        .open_in_place(nonce, Aad::from(MAGIC), &mut sealed)
+        .map_err(|_| {
+            FlareSyncError::Config(
+                "Backup could not be decrypted; the key is wrong or the file was modified"
+                    .to_string(),
+            )
+        })?;
+    Ok(plain.to_vec())
+}
+
+/// `value` sealed under `key` for backups kept as JSON rather than files, such as journal
+/// lines and database rows: `{"encrypted": "<base64 of the sealed JSON>"}`.
+pub fn seal_json(key: &EncryptionKey, value: &impl Serialize) -> Result<Value, FlareSyncError> {
+    let sealed = encrypt(key, &serde_json::to_vec(value)?)?;
+    let mut object = Map::new();
+    object.insert(SEALED_FIELD.to_string(), STANDARD.encode(sealed).into());
+    Ok(Value::Object(object))
+}
+
+/// Opens JSON sealed by [`seal_json`]. Any other value is returned as it is.
+pub fn open_json(value: Value, key: Option<&EncryptionKey>) -> Result<Value, FlareSyncError> {
+    let Some(sealed) = value.get(SEALED_FIELD).and_then(Value::as_str) else {
+        return Ok(value);
+    };
+    let key = key.ok_or_else(|| {
+        FlareSyncError::Config(
+            "it is encrypted; set BACKUP_ENCRYPTION_KEY or BACKUP_ENCRYPTION_KEY_FILE".to_string(),
+        )
+    })?;
+    let sealed = STANDARD
+        .decode(sealed)
+        .map_err(|_| FlareSyncError::Config("Not an encrypted backup".to_string()))?;
+    Ok(serde_json::from_slice(&decrypt(key, &sealed)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_reject_wrong_key_or_tampering() {
+        let key = EncryptionKey::parse(&STANDARD.encode([7u8; KEY_LEN])).unwrap();
+        assert_eq!(format!("{:?}", key), "EncryptionKey([REDACTED])");
+        assert!(EncryptionKey::parse("c2hvcnQ=").is_err());
+        assert!(EncryptionKey::parse("not base64!").is_err());
+
+        let data = br#"{"name":"internal.example.com","content":"10.0.0.1"}"#;
+        let sealed = encrypt(&key, data).unwrap();
+        assert!(is_encrypted(&sealed));
+        assert!(!sealed.windows(8).any(|window| window == b"internal"));
+        assert_ne!(sealed, encrypt(&key, data).unwrap());
+        assert_eq!(decrypt(&key, &sealed).unwrap(), data);
+
+        let other = EncryptionKey::parse(&STANDARD.encode([8u8; KEY_LEN])).unwrap();
+        assert!(decrypt(&other, &sealed).is_err());
+        let mut tampered = sealed.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(decrypt(&key, &tampered).is_err());
+        assert!(decrypt(&key, data).is_err());
+    }
+}
@@ -0,0 +1,1418 @@
+use crate::backup::BackupStore;
+use crate::cloudflare::{check_and_update_ip, sync_dns_record, DnsUpdateStatus};
+use crate::config::{normalize_domain_name, Config};
+use crate::correlation;
+use crate::errors::FlareSyncError;
+use crate::exit_code;
+use crate::health;
+use crate::heartbeat::{self, HeartbeatSignal};
+use crate::history::{self, HistoryEntry};
+use crate::ip_provider::get_current_ip;
+use crate::logging::EVENTS_TARGET;
+use crate::metrics;
+use crate::notify::{NotificationEvent, Notifications};
+use crate::prefetch::{self, PrefetchPolicy};
+use crate::rate_limit;
+use crate::redaction;
+use crate::scheduler::{self, AdaptiveInterval, OverlapPolicy, Scheduler, StartupPolicy};
+use crate::sd_notify;
+use crate::state::{self, SavedState};
+use crate::status::RuntimeStatus;
+use crate::summary::SummaryReport;
+use crate::supervisor;
+use chrono::Local;
+use reqwest::Client as ReqwestClient;
+use std::collections::BTreeSet;
+use std::net::Ipv4Addr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, mpsc, oneshot, watch, Notify};
+use tokio::task::JoinSet;
+use tokio::time;
+use tracing::{debug, error, info, info_span, warn, Instrument};
+
+/// Longer than a domain check with every Cloudflare retry exhausted, so only a hung update
+/// fails its domain. The systemd watchdog keeps being pinged while an update runs, so this
+/// does not have to fit within `WatchdogSec`.
+const DOMAIN_UPDATE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// How many events a slow subscriber may fall behind before it misses some.
+const EVENT_CAPACITY: usize = 64;
+
+/// How many pushed IPs may wait for the loop before [`FlareSync::push_ip`] blocks.
+const PUSH_CAPACITY: usize = 16;
+
+/// How long [`FlareSync::push_ip`] waits for the loop to apply a pushed IP.
+const PUSH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often an idle loop compares the wall clock with its timers to notice a resume.
+const CLOCK_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The sync engine behind the `flaresync` binary. Clones share the same engine, so one
+/// clone can call [`FlareSync::shutdown`] or [`FlareSync::check_now`] while another runs.
+///
+/// The engine does not install a tracing subscriber or listen for OS signals; embedders
+/// set those up themselves.
+#[derive(Clone)]
+pub struct FlareSync {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    config: RwLock<Arc<Config>>,
+    reloaded_config: Mutex<Option<Config>>,
+    client: ReqwestClient,
+    status_tx: watch::Sender<RuntimeStatus>,
+    shutdown_tx: watch::Sender<bool>,
+    paused_tx: watch::Sender<bool>,
+    paused_domains: Mutex<BTreeSet<String>>,
+    push_tx: mpsc::Sender<IpPush>,
+    push_rx: tokio::sync::Mutex<mpsc::Receiver<IpPush>>,
+    wake: Notify,
+    events: broadcast::Sender<EngineEvent>,
+}
+
+/// How a single sync cycle ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CycleOutcome {
+    /// Every due domain was checked. `exit_code` is the [`exit_code::for_cycle`] result.
+    Complete {
+        exit_code: u8,
+    },
+    IpCheckFailed,
+    Shutdown,
+}
+
+/// What the engine reports to [`FlareSync::subscribe`] receivers while it runs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineEvent {
+    IpDetected {
+        ip: Ipv4Addr,
+    },
+    IpCheckFailed {
+        error: String,
+    },
+    RecordUpdated {
+        domain: String,
+        old_ip: String,
+        new_ip: Ipv4Addr,
+        proxied: bool,
+    },
+    UpdateFailed {
+        domain: String,
+        error: String,
+        consecutive_failures: u32,
+    },
+    /// Sent after every cycle that was not interrupted by shutdown.
+    CycleCompleted {
+        checked: usize,
+        updated: usize,
+        failed: usize,
+        duration: Duration,
+        outcome: CycleOutcome,
+    },
+}
+
+/// How one domain fared in a cycle started by [`FlareSync::push_ip`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DomainResult {
+    Updated,
+    Unchanged,
+    Missing,
+    Paused,
+    Failed(String),
+}
+
+/// The domains a cycle checks, whether it rewrites records that already match, the IP to
+/// publish when it was pushed instead of detected, and records fetched at startup.
+struct CycleTargets<'a> {
+    domains: &'a [String],
+    force: bool,
+    ip: Option<Ipv4Addr>,
+    records: prefetch::Records,
+}
+
+struct CycleReport {
+    outcome: CycleOutcome,
+    results: Vec<(String, DomainResult)>,
+}
+
+struct IpPush {
+    domains: Vec<String>,
+    ip: Ipv4Addr,
+    reply: oneshot::Sender<Vec<(String, DomainResult)>>,
+}
+
+enum IdleOutcome {
+    Elapsed,
+    Woken,
+    Pushed(IpPush),
+    Shutdown,
+}
+
+enum IpCheckOutcome {
+    Complete(Result<Ipv4Addr, FlareSyncError>),
+    Shutdown,
+}
+
+enum DomainUpdateOutcome {
+    Complete(Result<DnsUpdateStatus, FlareSyncError>),
+    Shutdown,
+}
+
+/// The scheduled cycles that failed in a row, compared with `EXIT_AFTER_FAILURES`. Cycles
+/// for an IP pushed by a DynDNS2 client neither add to nor reset the streak, so the
+/// daemon only gives up when its own checks keep failing.
+#[derive(Debug, Default)]
+struct FailedCycles(u64);
+
+impl FailedCycles {
+    fn record(&mut self, pushed: bool, failed: bool) -> u64 {
+        if !pushed {
+            self.0 = if failed { self.0 + 1 } else { 0 };
+        }
+        self.0
+    }
+}
+
+impl FlareSync {
+    pub fn new(config: Config) -> Result<Self, FlareSyncError> {
+        let client = ReqwestClient::builder()
+            .timeout(Duration::from_secs(30))
+            .build()?;
+        let (push_tx, push_rx) = mpsc::channel(PUSH_CAPACITY);
+        rate_limit::configure(config.cloudflare_rate_limit);
+        redaction::global().add(config.secrets());
+        Ok(Self {
+            inner: Arc::new(Inner {
+                config: RwLock::new(Arc::new(config)),
+                reloaded_config: Mutex::new(None),
+                client,
+                status_tx: watch::channel(RuntimeStatus::new()).0,
+                shutdown_tx: watch::channel(false).0,
+                paused_tx: watch::channel(false).0,
+                paused_domains: Mutex::new(BTreeSet::new()),
+                push_tx,
+                push_rx: tokio::sync::Mutex::new(push_rx),
+                wake: Notify::new(),
+                events: broadcast::channel(EVENT_CAPACITY).0,
+            }),
+        })
+    }
+
+    /// The configuration in effect, which changes when a [`FlareSync::reload`] is applied.
+    pub fn config(&self) -> Arc<Config> {
+        self.inner.config.read().unwrap().clone()
+    }
+
+    /// Replaces the configuration at the start of the next cycle of [`FlareSync::run`],
+    /// which then checks every domain. The health endpoint keeps its bind address.
+    pub fn reload(&self, config: Config) {
+        redaction::global().add(config.secrets());
+        *self.inner.reloaded_config.lock().unwrap() = Some(config);
+        self.inner.wake.notify_one();
+    }
+
+    /// Suspends the cycles of [`FlareSync::run`] until [`FlareSync::resume`]. A cycle in
+    /// progress finishes first.
+    pub fn pause(&self) {
+        self.inner.paused_tx.send_replace(true);
+        self.inner.wake.notify_one();
+    }
+
+    /// Ends a pause; domains whose interval passed meanwhile are checked right away.
+    pub fn resume(&self) {
+        self.inner.paused_tx.send_replace(false);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.inner.paused_tx.borrow()
+    }
+
+    /// The domains cycles skip: those in `PAUSED_DOMAINS` and those paused with
+    /// [`FlareSync::pause_domain`].
+    pub fn paused_domains(&self) -> BTreeSet<String> {
+        let mut paused = self.config().paused_domains.clone();
+        paused.extend(self.inner.paused_domains.lock().unwrap().iter().cloned());
+        paused
+    }
+
+    /// Skips `domain` in later cycles until [`FlareSync::resume_domain`], leaving its
+    /// record alone. Returns `false` if it was already paused.
+    pub fn pause_domain(&self, domain: &str) -> Result<bool, FlareSyncError> {
+        let domain = self.configured_domain(domain)?;
+        if self.config().paused_domains.contains(&domain) {
+            return Ok(false);
+        }
+        let paused = self.inner.paused_domains.lock().unwrap().insert(domain);
+        self.publish_paused_domains();
+        Ok(paused)
+    }
+
+    /// Syncs `domain` again from its next scheduled check. Returns `false` if it was not
+    /// paused. Domains paused through `PAUSED_DOMAINS` can only be resumed by a reload.
+    pub fn resume_domain(&self, domain: &str) -> Result<bool, FlareSyncError> {
+        let domain = self.configured_domain(domain)?;
+        if self.config().paused_domains.contains(&domain) {
+            return Err(FlareSyncError::Config(format!(
+                "{} is paused by PAUSED_DOMAINS; remove it there and reload the configuration",
+                domain
+            )));
+        }
+        let resumed = self.inner.paused_domains.lock().unwrap().remove(&domain);
+        self.publish_paused_domains();
+        Ok(resumed)
+    }
+
+    fn configured_domain(&self, domain: &str) -> Result<String, FlareSyncError> {
+        let domain = normalize_domain_name(domain.trim())?;
+        if self.config().domain_names.contains(&domain) {
+            Ok(domain)
+        } else {
+            Err(FlareSyncError::Config(format!(
+                "{} is not a configured domain",
+                domain
+            )))
+        }
+    }
+
+    fn publish_paused_domains(&self) {
+        let paused = self.paused_domains();
+        self.inner
+            .status_tx
+            .send_modify(|status| status.mark_paused_domains(&paused));
+    }
+
+    /// Publishes `ip`, reported from outside (for example by a router), to `domains` in
+    /// the next cycle of [`FlareSync::run`] instead of detecting the public IP. Fails when
+    /// syncing is paused or the loop does not pick the request up within a minute.
+    pub async fn push_ip(
+        &self,
+        domains: Vec<String>,
+        ip: Ipv4Addr,
+    ) -> Result<Vec<(String, DomainResult)>, FlareSyncError> {
+        if self.is_paused() {
+            return Err(FlareSyncError::Io(std::io::Error::other(
+                "Syncing is paused",
+            )));
+        }
+        let (reply, result) = oneshot::channel();
+        let push = IpPush { domains, ip, reply };
+        let exchange = async {
+            self.inner.push_tx.send(push).await.ok()?;
+            result.await.ok()
+        };
+        match time::timeout(PUSH_TIMEOUT, exchange).await {
+            Ok(Some(results)) => Ok(results),
+            Ok(None) => Err(FlareSyncError::Io(std::io::Error::other(
+                "The sync loop stopped before applying the pushed IP",
+            ))),
+            Err(_) => Err(FlareSyncError::Timeout(format!(
+                "The sync loop did not apply the pushed IP within {}s",
+                PUSH_TIMEOUT.as_secs()
+            ))),
+        }
+    }
+
+    /// The latest runtime status, updated whenever the status file is written.
+    pub fn status(&self) -> watch::Receiver<RuntimeStatus> {
+        self.inner.status_tx.subscribe()
+    }
+
+    /// Stops [`FlareSync::run`] or [`FlareSync::run_once`] at the next wait or request, after
+    /// writing a final status. Later calls to either return immediately.
+    pub fn shutdown(&self) {
+        self.inner.shutdown_tx.send_replace(true);
+    }
+
+    /// Ends the current wait of [`FlareSync::run`] and checks every domain. A request made
+    /// during a cycle starts another cycle right after it.
+    pub fn check_now(&self) {
+        self.inner.wake.notify_one();
+    }
+
+    /// Receives every [`EngineEvent`] from now on. A receiver that falls more than 64
+    /// events behind gets `RecvError::Lagged` and skips the oldest ones.
+    pub fn subscribe(&self) -> broadcast::Receiver<EngineEvent> {
+        self.inner.events.subscribe()
+    }
+
+    fn emit(&self, event: EngineEvent) {
+        // Sending only fails when nobody is subscribed.
+        let _ = self.inner.events.send(event);
+    }
+
+    async fn shutdown_requested(&self) {
+        let mut shutdown = self.inner.shutdown_tx.subscribe();
+        let _ = shutdown.wait_for(|requested| *requested).await;
+    }
+
+    /// Runs the sync loop until [`FlareSync::shutdown`] is called. Serves the health
+    /// endpoint while running when `HEALTH_BIND_ADDRESS` is configured.
+    pub async fn run(&self) -> Result<(), FlareSyncError> {
+        let mut config = self.config();
+        let mut pushes = self.inner.push_rx.lock().await;
+        let mut pending_push = None;
+        info!(target: EVENTS_TARGET, "FlareSync started");
+        let mut status = RuntimeStatus::new();
+        let mut state = self.load_state();
+        state.restore(&mut status, &config.domain_names);
+
+        let mut background = JoinSet::new();
+        if let Some(health_bind_address) = config.health_bind_address {
+            let listener = TcpListener::bind(health_bind_address).await?;
+            info!(
+                target: EVENTS_TARGET,
+                "Health endpoint listening on {}",
+                health_bind_address
+            );
+            let status_rx = self.status();
+            let update_interval = config.cycle_interval();
+            background.spawn(async move {
+                if let Err(e) = health::serve(listener, status_rx, update_interval).await {
+                    error!("Health endpoint stopped: {}", e);
+                }
+            });
+        }
+
+        let mut prefetched = prefetch::Records::new();
+        if config.prefetch != PrefetchPolicy::Off {
+            let paused = self.paused_domains();
+            let domains: Vec<String> = config
+                .domain_names
+                .iter()
+                .filter(|domain_name| !paused.contains(*domain_name))
+                .cloned()
+                .collect();
+            info!("Fetching {} records before the first check", domains.len());
+            let results = tokio::select! {
+                results = prefetch::fetch(&self.inner.client, &config, &domains) => results,
+                _ = self.shutdown_requested() => {
+                    self.finish_shutdown(&mut status);
+                    return Ok(());
+                }
+            };
+            match prefetch::check(config.prefetch, results) {
+                Ok(records) => prefetched = records,
+                Err(e) => {
+                    error!(
+                        target: EVENTS_TARGET,
+                        "Startup record check failed: {}. Exiting.", e
+                    );
+                    status.mark_shutting_down();
+                    self.write_status(&status);
+                    return Err(e);
+                }
+            }
+        }
+
+        let saved = state.records(&config.zone_id, &config.domain_names);
+        if !saved.is_empty() {
+            info!("Using {} saved records for the first check", saved.len());
+        }
+        for (domain_name, record) in saved {
+            prefetched.entry(domain_name).or_insert(record);
+        }
+
+        self.write_status(&status);
+        sd_notify::ready(&format!(
+            "Syncing {} domains every {:?}",
+            config.domain_names.len(),
+            config.update_interval
+        ));
+
+        let mut notifications = daemon_notifications(&config);
+        notifications.publish_state(&status).await;
+        let mut summary = config
+            .summary_period
+            .map(|period| SummaryReport::new(period, Local::now()));
+        let mut scheduler = Scheduler::from_config(&config, Instant::now());
+        if config.startup != StartupPolicy::default() {
+            let now = Instant::now();
+            scheduler.delay_start(&config.startup, config.startup.random_splay(), now);
+            let wait = scheduler.time_until_next(now);
+            info!(
+                target: EVENTS_TARGET,
+                "Waiting for {:?} before the first check",
+                wait
+            );
+            if let Ok(wait) = chrono::Duration::from_std(wait) {
+                status.mark_first_check_at(Local::now() + wait);
+                self.write_status(&status);
+            }
+            // Records fetched or saved before a long wait may be stale by the first check.
+            prefetched.clear();
+            match self.idle_until_next_cycle(wait, &mut pushes).await {
+                IdleOutcome::Elapsed => {}
+                IdleOutcome::Woken => scheduler.wake_all(Instant::now()),
+                IdleOutcome::Pushed(push) => pending_push = Some(push),
+                IdleOutcome::Shutdown => {
+                    self.finish_shutdown(&mut status);
+                    return Ok(());
+                }
+            }
+        }
+        let mut adaptive = AdaptiveInterval::from_config(&config);
+        let mut cycle: u64 = 0;
+        let mut failed_cycles = FailedCycles::default();
+        loop {
+            let reloaded_config = self.inner.reloaded_config.lock().unwrap().take();
+            if let Some(reloaded_config) = reloaded_config {
+                config = Arc::new(reloaded_config);
+                *self.inner.config.write().unwrap() = config.clone();
+                notifications.reconfigure(daemon_notifications(&config));
+                summary = config
+                    .summary_period
+                    .map(|period| SummaryReport::new(period, Local::now()));
+                scheduler = Scheduler::from_config(&config, Instant::now());
+                adaptive = AdaptiveInterval::from_config(&config);
+                rate_limit::configure(config.cloudflare_rate_limit);
+                info!(
+                    target: EVENTS_TARGET,
+                    "Configuration reloaded. Syncing {} domains.",
+                    config.domain_names.len()
+                );
+            }
+            status.mark_paused_domains(&self.paused_domains());
+            if self.is_paused() {
+                if !status.paused {
+                    info!(target: EVENTS_TARGET, "Syncing paused");
+                    status.mark_paused(true);
+                    self.write_status(&status);
+                }
+                match self.idle_while_paused().await {
+                    IdleOutcome::Shutdown => break,
+                    _ => continue,
+                }
+            }
+            if status.paused {
+                info!(target: EVENTS_TARGET, "Syncing resumed");
+                status.mark_paused(false);
+                self.write_status(&status);
+            }
+
+            cycle += 1;
+            let (due, pushed_ip, reply) = match pending_push.take() {
+                Some(push) => (push.domains, Some(push.ip), Some(push.reply)),
+                None => (scheduler.due(Instant::now()), None, None),
+            };
+            let previous_ip = status.last_public_ip.clone();
+            let cycle_started = Instant::now();
+            let cycle_id = correlation::new_cycle_id();
+            let cycle_span = info_span!("cycle", cycle, cycle_id = %cycle_id);
+            let report = correlation::with_cycle_id(
+                cycle_id,
+                self.run_cycle(
+                    CycleTargets {
+                        domains: &due,
+                        force: false,
+                        ip: pushed_ip,
+                        records: std::mem::take(&mut prefetched),
+                    },
+                    &mut notifications,
+                    &mut status,
+                    &mut state,
+                    summary.as_mut(),
+                ),
+            )
+            .instrument(cycle_span)
+            .await;
+            if let Some(reply) = reply {
+                let _ = reply.send(report.results);
+            }
+            if !matches!(report.outcome, CycleOutcome::Shutdown) {
+                self.handle_overlap(&config, &mut scheduler, &mut status, cycle_started);
+                let failures = failed_cycles
+                    .record(pushed_ip.is_some(), status.stats.consecutive_failures > 0);
+                if config
+                    .exit_after_failures
+                    .is_some_and(|threshold| failures >= threshold)
+                {
+                    let exit_code = match report.outcome {
+                        CycleOutcome::Complete { exit_code } => exit_code,
+                        _ => exit_code::NETWORK_ERROR,
+                    };
+                    error!(
+                        target: EVENTS_TARGET,
+                        "{} cycles in a row failed. Exiting with code {}.", failures, exit_code
+                    );
+                    sd_notify::stopping();
+                    status.mark_shutting_down();
+                    self.write_status(&status);
+                    return Err(FlareSyncError::FailureThreshold {
+                        failures,
+                        exit_code,
+                    });
+                }
+            }
+
+            let wait = match report.outcome {
+                CycleOutcome::Shutdown => break,
+                CycleOutcome::IpCheckFailed => {
+                    scheduler.postpone(&due, Duration::from_secs(60), Instant::now());
+                    scheduler.time_until_next(Instant::now())
+                }
+                CycleOutcome::Complete { .. } => {
+                    let now = Instant::now();
+                    if let Some(adaptive) = &mut adaptive {
+                        let ip_changed =
+                            previous_ip.is_some() && previous_ip != status.last_public_ip;
+                        let cap = adaptive.record_cycle(ip_changed);
+                        if ip_changed {
+                            info!(
+                                target: EVENTS_TARGET,
+                                "Public IP changed. Checking every {:?} until it is stable.",
+                                cap.unwrap_or(config.max_interval())
+                            );
+                        }
+                        scheduler.set_cap(cap, now);
+                    }
+                    scheduler.mark_run(&due, now);
+                    let wait = scheduler.time_until_next(now);
+                    info!("Waiting for {:?} before next check", wait);
+                    wait
+                }
+            };
+
+            if let Some(summary) = &mut summary {
+                summary.record_cycle();
+                let now = Local::now();
+                if summary.is_due(now) {
+                    info!(target: EVENTS_TARGET, "{}", summary.render(&status));
+                    summary.reset(now);
+                }
+            }
+
+            sd_notify::status(&format!(
+                "Public IP {}; last cycle {}",
+                status.last_public_ip.as_deref().unwrap_or("unknown"),
+                if status.stats.consecutive_failures == 0 {
+                    "succeeded"
+                } else {
+                    "failed"
+                }
+            ));
+            match self.idle_until_next_cycle(wait, &mut pushes).await {
+                IdleOutcome::Elapsed => {}
+                IdleOutcome::Woken => scheduler.wake_all(Instant::now()),
+                IdleOutcome::Pushed(push) => pending_push = Some(push),
+                IdleOutcome::Shutdown => break,
+            }
+        }
+
+        self.finish_shutdown(&mut status);
+        Ok(())
+    }
+
+    fn finish_shutdown(&self, status: &mut RuntimeStatus) {
+        info!(target: EVENTS_TARGET, "Shutdown signal received. Exiting.");
+        sd_notify::stopping();
+        status.mark_shutting_down();
+        self.write_status(status);
+    }
+
+    /// Runs a single cycle over every domain, continuing from the status file. With `force`,
+    /// records that already match the public IP are rewritten too. Failed notification
+    /// deliveries are not retried.
+    pub async fn run_once(&self, force: bool) -> CycleOutcome {
+        let config = self.config();
+        let mut status = RuntimeStatus::read_from_path(&config.status_file_path)
+            .unwrap_or_else(|_| RuntimeStatus::new());
+        let mut state = self.load_state();
+        let mut notifications = Notifications::new(
+            config.notification_channels.clone(),
+            config.notification_templates.clone(),
+            0,
+            config.notification_mode,
+        );
+
+        correlation::with_cycle_id(
+            correlation::new_cycle_id(),
+            self.run_cycle(
+                CycleTargets {
+                    domains: &config.domain_names,
+                    force,
+                    ip: None,
+                    records: prefetch::Records::new(),
+                },
+                &mut notifications,
+                &mut status,
+                &mut state,
+                None,
+            ),
+        )
+        .await
+        .outcome
+    }
+
+    async fn run_cycle(
+        &self,
+        mut targets: CycleTargets<'_>,
+        notifications: &mut Notifications,
+        status: &mut RuntimeStatus,
+        state: &mut SavedState,
+        mut summary: Option<&mut SummaryReport>,
+    ) -> CycleReport {
+        let config = self.config();
+        let client = &self.inner.client;
+        sd_notify::watchdog();
+        self.send_heartbeat(HeartbeatSignal::Start).await;
+        notifications.retry_pending(client).await;
+        let cycle_started = Instant::now();
+        let mut results = Vec::new();
+        let ip_check_outcome = match targets.ip {
+            Some(ip) => {
+                info!(
+                    target: EVENTS_TARGET,
+                    "Received IP {} for {}",
+                    ip,
+                    targets.domains.join(", ")
+                );
+                IpCheckOutcome::Complete(Ok(ip))
+            }
+            None => {
+                let ip_check_started = Instant::now();
+                let outcome = self.wait_for_ip_or_shutdown().await;
+                if let IpCheckOutcome::Complete(result) = &outcome {
+                    metrics::record_ip_detection(ip_check_started.elapsed(), result.is_ok());
+                }
+                outcome
+            }
+        };
+        let current_ip = match ip_check_outcome {
+            IpCheckOutcome::Shutdown => return CycleReport::shutdown(results),
+            IpCheckOutcome::Complete(Ok(ip)) => ip,
+            IpCheckOutcome::Complete(Err(e)) => {
+                error!("Failed to get current IP: {}. Retrying in 1 minute.", e);
+                status.mark_ip_check_error(&e);
+                if let Some(summary) = summary.as_deref_mut() {
+                    summary.record_ip_check_failure();
+                }
+                status.mark_cycle_complete(false, cycle_started.elapsed());
+                self.write_status(status);
+                self.emit(EngineEvent::IpCheckFailed {
+                    error: e.to_string(),
+                });
+                self.emit(EngineEvent::CycleCompleted {
+                    checked: 0,
+                    updated: 0,
+                    failed: 0,
+                    duration: cycle_started.elapsed(),
+                    outcome: CycleOutcome::IpCheckFailed,
+                });
+                self.send_heartbeat(HeartbeatSignal::Fail).await;
+                return CycleReport {
+                    outcome: CycleOutcome::IpCheckFailed,
+                    results,
+                };
+            }
+        };
+        info!(ip = %current_ip, "Current public IP: {}", current_ip);
+        status.mark_ip_check_success(&current_ip);
+        self.emit(EngineEvent::IpDetected { ip: current_ip });
+        self.write_status(status);
+        if state.mark_ip(&current_ip) {
+            self.write_state(state);
+        }
+
+        let mut updated = 0;
+        let mut failures = Vec::new();
+        let paused = self.paused_domains();
+        for domain_name in targets.domains {
+            if paused.contains(domain_name) {
+                debug!("Skipping {} while it is paused", domain_name);
+                results.push((domain_name.clone(), DomainResult::Paused));
+                continue;
+            }
+            let domain_span = info_span!("domain", domain = %domain_name, zone = %config.zone_id);
+            let domain_started = Instant::now();
+            let update = {
+                let client = client.clone();
+                let api_token = config.api_token.clone();
+                let zone_id = config.zone_id.clone();
+                let domain_name = domain_name.clone();
+                let force = targets.force;
+                let prefetched = targets.records.remove(&domain_name);
+                let backups = BackupStore::from_config(&config);
+                async move {
+                    let backups = backups.resolve_zone_name(&client, &api_token).await;
+                    match prefetched {
+                        Some(Some(record)) => {
+                            debug!("Using the record known at startup for {}", domain_name);
+                            sync_dns_record(
+                                &client,
+                                &api_token,
+                                &zone_id,
+                                record,
+                                &current_ip,
+                                force,
+                                &backups,
+                            )
+                            .await
+                        }
+                        Some(None) => {
+                            warn!("No matching DNS record found for {}.", domain_name);
+                            Ok(DnsUpdateStatus::Missing)
+                        }
+                        None => {
+                            check_and_update_ip(
+                                &client,
+                                &api_token,
+                                &zone_id,
+                                &domain_name,
+                                &current_ip,
+                                force,
+                                &backups,
+                            )
+                            .await
+                        }
+                    }
+                }
+            };
+            let task_name = format!("Update for {}", domain_name);
+            let update_outcome = tokio::select! {
+                result = sd_notify::while_pinging_watchdog(supervisor::supervise(
+                    &task_name,
+                    DOMAIN_UPDATE_TIMEOUT,
+                    update,
+                )).instrument(domain_span.clone()) => DomainUpdateOutcome::Complete(result),
+                _ = self.shutdown_requested() => DomainUpdateOutcome::Shutdown,
+            };
+            let domain_duration = domain_started.elapsed();
+            let duration_ms = domain_duration.as_millis() as u64;
+            let entered = domain_span.enter();
+            let previous_failures = status
+                .domains
+                .get(domain_name)
+                .map_or(0, |domain| domain.consecutive_failures);
+
+            let mut notifications_to_send = Vec::new();
+            let mut changed_from = None;
+            match update_outcome {
+                DomainUpdateOutcome::Complete(Ok(update_status)) => {
+                    match update_status {
+                        DnsUpdateStatus::Updated {
+                            previous_content,
+                            proxied,
+                            record,
+                        } => {
+                            if state.mark_record(&config.zone_id, domain_name, &record, true) {
+                                self.write_state(state);
+                            }
+                            record_history(
+                                &config,
+                                &HistoryEntry::updated(
+                                    domain_name,
+                                    &previous_content,
+                                    &current_ip.to_string(),
+                                ),
+                            );
+                            prune_backups(&config);
+                            info!(
+                                target: EVENTS_TARGET,
+                                outcome = "updated",
+                                new_ip = %current_ip,
+                                duration_ms,
+                                "IP address updated successfully for {}",
+                                domain_name
+                            );
+                            status.mark_domain_result(domain_name, "updated", true);
+                            if let Some(summary) = summary.as_deref_mut() {
+                                summary.record_ip_change();
+                            }
+                            metrics::record_domain_result(domain_name, "updated", domain_duration);
+                            notifications_to_send.push(NotificationEvent::updated(
+                                domain_name,
+                                &previous_content,
+                                &current_ip.to_string(),
+                                proxied,
+                            ));
+                            self.emit(EngineEvent::RecordUpdated {
+                                domain: domain_name.clone(),
+                                old_ip: previous_content.clone(),
+                                new_ip: current_ip,
+                                proxied,
+                            });
+                            changed_from = Some(previous_content);
+                            updated += 1;
+                            results.push((domain_name.clone(), DomainResult::Updated));
+                        }
+                        DnsUpdateStatus::Unchanged { record } => {
+                            if state.mark_record(&config.zone_id, domain_name, &record, false) {
+                                self.write_state(state);
+                            }
+                            info!(
+                                outcome = "unchanged",
+                                new_ip = %current_ip,
+                                duration_ms,
+                                "No update needed for {}",
+                                domain_name
+                            );
+                            status.mark_domain_result(domain_name, "unchanged", false);
+                            results.push((domain_name.clone(), DomainResult::Unchanged));
+                            metrics::record_domain_result(
+                                domain_name,
+                                "unchanged",
+                                domain_duration,
+                            );
+                        }
+                        DnsUpdateStatus::Missing => {
+                            if state.forget_record(domain_name) {
+                                self.write_state(state);
+                            }
+                            info!(
+                                outcome = "missing",
+                                duration_ms, "No matching DNS record found for {}", domain_name
+                            );
+                            status.mark_domain_result(domain_name, "missing", false);
+                            results.push((domain_name.clone(), DomainResult::Missing));
+                            metrics::record_domain_result(domain_name, "missing", domain_duration);
+                        }
+                    }
+                    if previous_failures > 0 {
+                        info!(
+                            target: EVENTS_TARGET,
+                            "{} recovered after {} failed attempts", domain_name, previous_failures
+                        );
+                        notifications_to_send.push(NotificationEvent::recovered(
+                            domain_name,
+                            &current_ip.to_string(),
+                            previous_failures,
+                        ));
+                    }
+                    self.write_status(status);
+                }
+                DomainUpdateOutcome::Complete(Err(e)) => {
+                    error!(
+                        outcome = "error",
+                        duration_ms, "Failed to check or update IP for {}: {}", domain_name, e
+                    );
+                    record_history(
+                        &config,
+                        &HistoryEntry::failed(domain_name, &current_ip.to_string(), &e),
+                    );
+                    status.mark_domain_error(domain_name, &e);
+                    // The saved record may be what failed, for example after it was deleted.
+                    if state.forget_record(domain_name) {
+                        self.write_state(state);
+                    }
+                    if let Some(summary) = summary.as_deref_mut() {
+                        summary.record_update_failure();
+                    }
+                    metrics::record_domain_result(domain_name, "error", domain_duration);
+                    failures.push(exit_code::for_error(&e));
+                    results.push((domain_name.clone(), DomainResult::Failed(e.to_string())));
+                    self.write_status(status);
+                    let consecutive_failures = status.domains[domain_name].consecutive_failures;
+                    self.emit(EngineEvent::UpdateFailed {
+                        domain: domain_name.clone(),
+                        error: e.to_string(),
+                        consecutive_failures,
+                    });
+                    notifications_to_send.push(NotificationEvent::failed(
+                        domain_name,
+                        &current_ip.to_string(),
+                        &e,
+                        consecutive_failures,
+                    ));
+                }
+                DomainUpdateOutcome::Shutdown => return CycleReport::shutdown(results),
+            }
+            drop(entered);
+            sd_notify::watchdog();
+
+            if let (Some(hook), Some(old_ip)) = (&config.on_change, &changed_from) {
+                if let Err(e) = sd_notify::while_pinging_watchdog(hook.run(
+                    domain_name,
+                    old_ip,
+                    &current_ip.to_string(),
+                ))
+                .instrument(domain_span.clone())
+                .await
+                {
+                    warn!(parent: &domain_span, "on_change hook failed for {}: {}", domain_name, e);
+                }
+            }
+
+            for notification in &notifications_to_send {
+                sd_notify::while_pinging_watchdog(notifications.dispatch(client, notification))
+                    .instrument(domain_span.clone())
+                    .await;
+            }
+        }
+        notifications.flush_digest(client).await;
+
+        let cycle_ok = failures.is_empty();
+        status.mark_cycle_complete(cycle_ok, cycle_started.elapsed());
+        self.write_status(status);
+        notifications.publish_state(status).await;
+        let signal = if cycle_ok {
+            HeartbeatSignal::Success
+        } else {
+            HeartbeatSignal::Fail
+        };
+        self.send_heartbeat(signal).await;
+        let outcome = CycleOutcome::Complete {
+            exit_code: exit_code::for_cycle(updated, &failures),
+        };
+        self.emit(EngineEvent::CycleCompleted {
+            checked: targets.domains.len(),
+            updated,
+            failed: failures.len(),
+            duration: cycle_started.elapsed(),
+            outcome,
+        });
+        CycleReport { outcome, results }
+    }
+
+    async fn send_heartbeat(&self, signal: HeartbeatSignal) {
+        if let Some(heartbeat_url) = &self.config().heartbeat_url {
+            heartbeat::ping(&self.inner.client, heartbeat_url, signal).await;
+        }
+    }
+
+    async fn wait_for_ip_or_shutdown(&self) -> IpCheckOutcome {
+        tokio::select! {
+            result = get_current_ip(&self.inner.client) => IpCheckOutcome::Complete(result),
+            _ = self.shutdown_requested() => IpCheckOutcome::Shutdown,
+        }
+    }
+
+    /// Waits for the next cycle, a [`FlareSync::check_now`] request, or shutdown, pinging
+    /// the systemd watchdog in between when it is enabled.
+    async fn idle_until_next_cycle(
+        &self,
+        duration: Duration,
+        pushes: &mut mpsc::Receiver<IpPush>,
+    ) -> IdleOutcome {
+        let ping_interval = sd_notify::watchdog_interval()
+            .unwrap_or(CLOCK_CHECK_INTERVAL)
+            .min(CLOCK_CHECK_INTERVAL);
+        let deadline = Instant::now() + duration;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return IdleOutcome::Elapsed;
+            }
+            let nap = remaining.min(ping_interval);
+            let nap_started = SystemTime::now();
+            tokio::select! {
+                _ = time::sleep(nap) => {
+                    sd_notify::watchdog();
+                    if let Some(gap) = scheduler::clock_jump(nap, nap_started, SystemTime::now()) {
+                        warn!(
+                            target: EVENTS_TARGET,
+                            "Clock jumped {:?} ahead while waiting (system suspend or VM pause?). Checking now.",
+                            gap
+                        );
+                        return IdleOutcome::Woken;
+                    }
+                }
+                _ = self.inner.wake.notified() => return IdleOutcome::Woken,
+                Some(push) = pushes.recv() => return IdleOutcome::Pushed(push),
+                _ = self.shutdown_requested() => return IdleOutcome::Shutdown,
+            }
+        }
+    }
+
+    /// Applies `OVERLAP_POLICY` to the checks that fell due while the cycle started at
+    /// `cycle_started` was running, and counts them so chronic slowness shows up.
+    fn handle_overlap(
+        &self,
+        config: &Config,
+        scheduler: &mut Scheduler,
+        status: &mut RuntimeStatus,
+        cycle_started: Instant,
+    ) {
+        let now = Instant::now();
+        let overlapped = scheduler.came_due(cycle_started, now);
+        if overlapped.is_empty() {
+            return;
+        }
+        let ran_for = now.duration_since(cycle_started);
+        match config.overlap_policy {
+            OverlapPolicy::Queue => warn!(
+                target: EVENTS_TARGET,
+                "{} fell due while the last cycle ran for {:?}. Checking now.",
+                overlapped.join(", "),
+                ran_for
+            ),
+            OverlapPolicy::Skip => {
+                scheduler.skip_missed(&overlapped, now);
+                warn!(
+                    target: EVENTS_TARGET,
+                    "Skipped checks for {} that fell due while the last cycle ran for {:?}",
+                    overlapped.join(", "),
+                    ran_for
+                );
+            }
+        }
+        metrics::record_overlapping_checks(overlapped.len(), config.overlap_policy.as_str());
+        status.mark_overlapping_checks(overlapped.len());
+        self.write_status(status);
+    }
+
+    /// Waits for [`FlareSync::resume`] (`Elapsed`), a wake-up such as a reload (`Woken`),
+    /// or shutdown.
+    async fn idle_while_paused(&self) -> IdleOutcome {
+        let mut paused = self.inner.paused_tx.subscribe();
+        let ping_interval = sd_notify::watchdog_interval().unwrap_or(CLOCK_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = paused.wait_for(|paused| !*paused) => return IdleOutcome::Elapsed,
+                _ = time::sleep(ping_interval) => sd_notify::watchdog(),
+                _ = self.inner.wake.notified() => return IdleOutcome::Woken,
+                _ = self.shutdown_requested() => return IdleOutcome::Shutdown,
+            }
+        }
+    }
+
+    /// The state saved by an earlier run, or an empty one if there is none or it cannot be
+    /// read.
+    fn load_state(&self) -> SavedState {
+        let config = self.config();
+        state::load(&config).unwrap_or_else(|e| {
+            warn!(
+                "Ignoring saved state in {}: {}",
+                state::location(&config).display(),
+                e
+            );
+            SavedState::default()
+        })
+    }
+
+    fn write_state(&self, state: &SavedState) {
+        let config = self.config();
+        if let Err(e) = state::save(&config, state) {
+            warn!(
+                "Failed to save state to {}: {}",
+                state::location(&config).display(),
+                e
+            );
+        }
+    }
+
+    fn write_status(&self, status: &RuntimeStatus) {
+        let config = self.config();
+        self.inner.status_tx.send_replace(status.clone());
+        if let Err(e) = status.write_to_path(&config.status_file_path) {
+            warn!(
+                "Failed to write status file {}: {}",
+                config.status_file_path.display(),
+                e
+            );
+        }
+    }
+}
+
+impl CycleReport {
+    fn shutdown(results: Vec<(String, DomainResult)>) -> Self {
+        Self {
+            outcome: CycleOutcome::Shutdown,
+            results,
+        }
+    }
+}
+
+fn daemon_notifications(config: &Config) -> Notifications {
+    Notifications::new(
+        config.notification_channels.clone(),
+        config.notification_templates.clone(),
+        config.notification_retry_attempts,
+        config.notification_mode,
+    )
+}
+
+/// Appends to the history, logging a warning instead of failing.
+pub fn record_history(config: &Config, entry: &HistoryEntry) {
+    if let Err(e) = history::record(config, entry) {
+        warn!(
+            "Failed to append to history in {}: {}",
+            history::location(config).display(),
+            e
+        );
+    }
+}
+
+/// Applies the configured backup retention, logging a warning instead of failing.
+pub fn prune_backups(config: &Config) {
+    match BackupStore::from_config(config).prune(config.backup_retention, false) {
+        Ok(removed) if !removed.is_empty() => {
+            info!("Pruned {} old DNS record backups", removed.len())
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Failed to prune DNS record backups: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[tokio::test]
+    async fn test_run_stops_after_shutdown_and_writes_final_status() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let test_dir = std::env::temp_dir().join(format!(
+            "flaresync_engine_test_{}_{}",
+            std::process::id(),
+            unique
+        ));
+        let status_path = test_dir.join("status.json");
+        let config = Config::builder()
+            .api_token("test_token")
+            .zone("test_zone_id")
+            .domains(["example.com"])
+            .status_file_path(&status_path)
+            .history_file_path(test_dir.join("history.jsonl"))
+            .state_file_path(test_dir.join("state.json"))
+            .build()
+            .unwrap();
+
+        let engine = FlareSync::new(config).unwrap();
+        let status = engine.status();
+        let mut events = engine.subscribe();
+        engine.clone().shutdown();
+        time::timeout(Duration::from_secs(5), engine.run())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(status.borrow().shutting_down);
+        assert!(matches!(
+            events.try_recv(),
+            Err(broadcast::error::TryRecvError::Empty)
+        ));
+        assert!(
+            RuntimeStatus::read_from_path(&status_path)
+                .unwrap()
+                .shutting_down
+        );
+        assert_eq!(engine.run_once(false).await, CycleOutcome::Shutdown);
+        let _ = std::fs::remove_dir_all(test_dir);
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_receive_the_events_of_a_cycle() {
+        let test_dir = std::env::temp_dir().join(format!(
+            "flaresync_engine_events_test_{}",
+            std::process::id()
+        ));
+        let config = Config::builder()
+            .api_token("test_token")
+            .zone("test_zone_id")
+            .domains(["example.com"])
+            .status_file_path(test_dir.join("status.json"))
+            .history_file_path(test_dir.join("history.jsonl"))
+            .state_file_path(test_dir.join("state.json"))
+            .build()
+            .unwrap();
+        let engine = FlareSync::new(config).unwrap();
+        let config = engine.config();
+        engine.pause_domain("example.com").unwrap();
+        let mut events = engine.subscribe();
+        let mut notifications = Notifications::new(
+            config.notification_channels.clone(),
+            config.notification_templates.clone(),
+            0,
+            config.notification_mode,
+        );
+        let ip = Ipv4Addr::new(203, 0, 113, 9);
+
+        let report = engine
+            .run_cycle(
+                CycleTargets {
+                    domains: &config.domain_names,
+                    force: false,
+                    ip: Some(ip),
+                    records: prefetch::Records::new(),
+                },
+                &mut notifications,
+                &mut RuntimeStatus::new(),
+                &mut SavedState::default(),
+                None,
+            )
+            .await;
+
+        assert_eq!(
+            report.results,
+            [("example.com".to_string(), DomainResult::Paused)]
+        );
+        assert_eq!(events.try_recv().unwrap(), EngineEvent::IpDetected { ip });
+        match events.try_recv().unwrap() {
+            EngineEvent::CycleCompleted {
+                checked,
+                updated,
+                failed,
+                outcome,
+                ..
+            } => {
+                assert_eq!((checked, updated, failed), (1, 0, 0));
+                assert_eq!(outcome, report.outcome);
+            }
+            event => panic!("expected CycleCompleted, got {:?}", event),
+        }
+        assert!(matches!(
+            events.try_recv(),
+            Err(broadcast::error::TryRecvError::Empty)
+        ));
+        let _ = std::fs::remove_dir_all(test_dir);
+    }
+
+    #[tokio::test]
+    async fn test_cycle_stamps_last_cycle_at_in_the_status_file() {
+        let test_dir = std::env::temp_dir().join(format!(
+            "flaresync_engine_status_test_{}",
+            std::process::id()
+        ));
+        let status_path = test_dir.join("status.json");
+        let engine = FlareSync::new(
+            Config::builder()
+                .api_token("test_token")
+                .zone("test_zone_id")
+                .domains(["example.com"])
+                .status_file_path(&status_path)
+                .history_file_path(test_dir.join("history.jsonl"))
+                .state_file_path(test_dir.join("state.json"))
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+        engine.pause_domain("example.com").unwrap();
+        let config = engine.config();
+        let mut status = RuntimeStatus::new();
+
+        engine
+            .run_cycle(
+                CycleTargets {
+                    domains: &config.domain_names,
+                    force: false,
+                    ip: Some(Ipv4Addr::new(203, 0, 113, 9)),
+                    records: prefetch::Records::new(),
+                },
+                &mut Notifications::new(
+                    config.notification_channels.clone(),
+                    config.notification_templates.clone(),
+                    0,
+                    config.notification_mode,
+                ),
+                &mut status,
+                &mut SavedState::default(),
+                None,
+            )
+            .await;
+
+        let written = RuntimeStatus::read_from_path(&status_path).unwrap();
+        assert_eq!(written, status);
+        assert!(written.last_cycle_at.is_some());
+        assert_eq!(written.last_successful_cycle_at, written.last_cycle_at);
+        assert_eq!(written.stats.total_cycles, 1);
+        let _ = std::fs::remove_dir_all(test_dir);
+    }
+
+    #[test]
+    fn test_failed_cycles_ignore_pushed_ips() {
+        let mut failed_cycles = FailedCycles::default();
+        assert_eq!(failed_cycles.record(false, true), 1);
+        assert_eq!(failed_cycles.record(true, true), 1);
+        assert_eq!(failed_cycles.record(true, true), 1);
+        assert_eq!(failed_cycles.record(false, true), 2);
+        assert_eq!(failed_cycles.record(true, false), 2);
+        assert_eq!(failed_cycles.record(false, false), 0);
+    }
+
+    #[tokio::test]
+    async fn test_check_now_ends_the_wait() {
+        let config = Config::builder()
+            .api_token("test_token")
+            .zone("test_zone_id")
+            .domains(["example.com"])
+            .build()
+            .unwrap();
+        let engine = FlareSync::new(config).unwrap();
+        let mut pushes = engine.inner.push_rx.lock().await;
+
+        engine.check_now();
+        let outcome = time::timeout(
+            Duration::from_secs(5),
+            engine.idle_until_next_cycle(Duration::from_secs(3600), &mut pushes),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(outcome, IdleOutcome::Woken));
+
+        let outcome = engine
+            .idle_until_next_cycle(Duration::from_millis(10), &mut pushes)
+            .await;
+        assert!(matches!(outcome, IdleOutcome::Elapsed));
+    }
+
+    #[test]
+    fn test_pause_and_resume_individual_domains() {
+        let config = Config::builder()
+            .api_token("test_token")
+            .zone("test_zone_id")
+            .domains(["example.com", "www.example.com", "stable.example.com"])
+            .paused_domain("stable.example.com")
+            .build()
+            .unwrap();
+        let engine = FlareSync::new(config).unwrap();
+        let status = engine.status();
+        assert_eq!(
+            engine.paused_domains(),
+            ["stable.example.com".to_string()].into()
+        );
+
+        assert!(engine.pause_domain(" WWW.example.com ").unwrap());
+        assert!(!engine.pause_domain("www.example.com").unwrap());
+        assert!(!engine.pause_domain("stable.example.com").unwrap());
+        assert!(!engine.is_paused());
+        assert!(status.borrow().domains["www.example.com"].paused);
+        assert!(status.borrow().domains["stable.example.com"].paused);
+        assert!(engine
+            .pause_domain("other.example.com")
+            .unwrap_err()
+            .to_string()
+            .contains("other.example.com is not a configured domain"));
+
+        assert!(engine
+            .resume_domain("stable.example.com")
+            .unwrap_err()
+            .to_string()
+            .contains("stable.example.com is paused by PAUSED_DOMAINS"));
+        assert!(engine.resume_domain("www.example.com").unwrap());
+        assert!(!engine.resume_domain("www.example.com").unwrap());
+        assert!(!engine.resume_domain("example.com").unwrap());
+        assert!(!status.borrow().domains["www.example.com"].paused);
+        assert_eq!(
+            engine.paused_domains(),
+            ["stable.example.com".to_string()].into()
+        );
+    }
+
+    #[test]
+    fn test_every_job_and_reload_adds_secrets_to_the_redactor() {
+        let config = |token: &str| {
+            Config::builder()
+                .api_token(token)
+                .zone("test_zone_id")
+                .domains(["example.com"])
+                .build()
+                .unwrap()
+        };
+        let logging_redactor = redaction::global().clone();
+
+        let home = FlareSync::new(config("home_job_token")).unwrap();
+        FlareSync::new(config("office_job_token")).unwrap();
+        home.reload(config("rotated_home_token"));
+
+        assert_eq!(
+            logging_redactor.redact("home_job_token office_job_token rotated_home_token"),
+            "[REDACTED] [REDACTED] [REDACTED]"
+        );
+    }
+}
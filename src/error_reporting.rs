@@ -0,0 +1,113 @@
+use crate::errors::FlareSyncError;
+use crate::redaction::Redactor;
+use sentry::protocol::Event;
+use sentry::types::Dsn;
+use sentry::ClientInitGuard;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::Subscriber;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+pub struct ErrorReporting {
+    guard: ClientInitGuard,
+}
+
+impl ErrorReporting {
+    pub fn init(dsn: &str, redactor: Redactor) -> Result<Self, FlareSyncError> {
+        let dsn: Dsn = dsn
+            .parse()
+            .map_err(|e| FlareSyncError::Config(format!("Invalid SENTRY_DSN: {}", e)))?;
+        let guard = sentry::init(sentry::ClientOptions {
+            dsn: Some(dsn),
+            release: sentry::release_name!(),
+            attach_stacktrace: true,
+            before_send: Some(Arc::new({
+                let redactor = redactor.clone();
+                move |event| Some(redact_event(&redactor, event))
+            })),
+            before_breadcrumb: Some(Arc::new(move |mut breadcrumb| {
+                redact_field(&redactor, &mut breadcrumb.message);
+                Some(breadcrumb)
+            })),
+            ..Default::default()
+        });
+        Ok(Self { guard })
+    }
+
+    pub fn layer<S>(&self) -> impl Layer<S>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        sentry::integrations::tracing::layer()
+    }
+
+    pub fn shutdown(&self) {
+        self.guard.flush(Some(Duration::from_secs(2)));
+    }
+}
+
+fn redact_event(redactor: &Redactor, mut event: Event<'static>) -> Event<'static> {
+    redact_field(redactor, &mut event.message);
+    for exception in &mut event.exception.values {
+        redact_field(redactor, &mut exception.value);
+    }
+    for breadcrumb in &mut event.breadcrumbs.values {
+        redact_field(redactor, &mut breadcrumb.message);
+    }
+    event
+}
+
+fn redact_field(redactor: &Redactor, field: &mut Option<String>) {
+    if let Some(value) = field {
+        *value = redactor.redact(value).into_owned();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sentry::protocol::{Breadcrumb, Exception};
+
+    #[test]
+    fn test_events_are_redacted_before_sending() {
+        let redactor = Redactor::new(["secret-token"]);
+        let event = Event {
+            message: Some("Request with secret-token failed".to_string()),
+            exception: vec![Exception {
+                ty: "FlareSyncError".to_string(),
+                value: Some("Bearer secret-token rejected".to_string()),
+                ..Default::default()
+            }]
+            .into(),
+            breadcrumbs: vec![Breadcrumb {
+                message: Some("token secret-token".to_string()),
+                ..Default::default()
+            }]
+            .into(),
+            ..Default::default()
+        };
+
+        let event = redact_event(&redactor, event);
+        assert_eq!(
+            event.message.as_deref(),
+            Some("Request with [REDACTED] failed")
+        );
+        assert_eq!(
+            event.exception.values[0].value.as_deref(),
+            Some("Bearer [REDACTED] rejected")
+        );
+        assert_eq!(
+            event.breadcrumbs.values[0].message.as_deref(),
+            Some("token [REDACTED]")
+        );
+    }
+
+    #[test]
+    fn test_init_rejects_invalid_dsn() {
+        let err = ErrorReporting::init("not a dsn", Redactor::new(Vec::<String>::new()))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Invalid SENTRY_DSN"));
+    }
+}
@@ -7,9 +7,18 @@ pub enum FlareSyncError {
     Network(reqwest::Error),
     Timeout(String),
     Json(serde_json::Error),
+    Database(String),
     IpProvider(String),
     CloudflareTransient(String),
     Cloudflare(String),
+    Notification(String),
+    AlreadyRunning(String),
+    /// The daemon gave up after `failures` failed cycles in a row. `exit_code` is the code
+    /// the last of them would have exited with.
+    FailureThreshold {
+        failures: u64,
+        exit_code: u8,
+    },
 }
 
 impl fmt::Display for FlareSyncError {
@@ -20,11 +29,17 @@ impl fmt::Display for FlareSyncError {
             FlareSyncError::Network(e) => write!(f, "Network error: {}", e),
             FlareSyncError::Timeout(s) => write!(f, "Timeout error: {}", s),
             FlareSyncError::Json(e) => write!(f, "JSON error: {}", e),
+            FlareSyncError::Database(s) => write!(f, "Database error: {}", s),
             FlareSyncError::IpProvider(s) => write!(f, "IP provider error: {}", s),
             FlareSyncError::CloudflareTransient(s) => {
                 write!(f, "Cloudflare transient error: {}", s)
             }
             FlareSyncError::Cloudflare(s) => write!(f, "Cloudflare API error: {}", s),
+            FlareSyncError::Notification(s) => write!(f, "Notification error: {}", s),
+            FlareSyncError::AlreadyRunning(s) => write!(f, "Already running: {}", s),
+            FlareSyncError::FailureThreshold { failures, .. } => {
+                write!(f, "Stopped after {} consecutive failed cycles", failures)
+            }
         }
     }
 }
@@ -48,3 +63,10 @@ impl From<serde_json::Error> for FlareSyncError {
         FlareSyncError::Json(err)
     }
 }
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for FlareSyncError {
+    fn from(err: rusqlite::Error) -> FlareSyncError {
+        FlareSyncError::Database(err.to_string())
+    }
+}
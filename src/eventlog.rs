@@ -0,0 +1,150 @@
+use crate::errors::FlareSyncError;
+use std::io::{self, Write};
+use std::sync::Arc;
+use tracing::{Level, Metadata};
+use tracing_subscriber::fmt::MakeWriter;
+use windows_sys::Win32::Foundation::HANDLE;
+use windows_sys::Win32::System::EventLog::{
+    DeregisterEventSource, RegisterEventSourceW, ReportEventW, EVENTLOG_ERROR_TYPE,
+    EVENTLOG_INFORMATION_TYPE, EVENTLOG_WARNING_TYPE, REPORT_EVENT_TYPE,
+};
+
+pub const EVENT_SOURCE: &str = "FlareSync";
+
+fn wide(value: &str) -> Vec<u16> {
+    value.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+struct EventSource(HANDLE);
+
+// The handle returned by RegisterEventSourceW may be used from any thread.
+unsafe impl Send for EventSource {}
+unsafe impl Sync for EventSource {}
+
+impl Drop for EventSource {
+    fn drop(&mut self) {
+        unsafe {
+            DeregisterEventSource(self.0);
+        }
+    }
+}
+
+/// Writes each log line as one entry in the Windows Application event log.
+#[derive(Clone)]
+pub struct EventLogMakeWriter {
+    source: Arc<EventSource>,
+}
+
+impl EventLogMakeWriter {
+    pub fn register() -> Result<Self, FlareSyncError> {
+        let name = wide(EVENT_SOURCE);
+        let handle = unsafe { RegisterEventSourceW(std::ptr::null(), name.as_ptr()) };
+        if handle.is_null() {
+            return Err(FlareSyncError::Config(format!(
+                "Failed to register event log source {}: {}",
+                EVENT_SOURCE,
+                io::Error::last_os_error()
+            )));
+        }
+        Ok(Self {
+            source: Arc::new(EventSource(handle)),
+        })
+    }
+
+    fn writer_for_level(&self, level: Level) -> EventLogWriter {
+        EventLogWriter {
+            owner: self.clone(),
+            event_type: event_type(level),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for EventLogMakeWriter {
+    type Writer = EventLogWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.writer_for_level(Level::INFO)
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        self.writer_for_level(*meta.level())
+    }
+}
+
+pub struct EventLogWriter {
+    owner: EventLogMakeWriter,
+    event_type: REPORT_EVENT_TYPE,
+    buffer: Vec<u8>,
+}
+
+impl Write for EventLogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for EventLogWriter {
+    fn drop(&mut self) {
+        let text = String::from_utf8_lossy(&self.buffer);
+        let text = text.trim_end();
+        if text.is_empty() {
+            return;
+        }
+
+        let message = wide(text);
+        let strings = [message.as_ptr()];
+        let reported = unsafe {
+            ReportEventW(
+                self.owner.source.0,
+                self.event_type,
+                0,
+                0,
+                std::ptr::null_mut(),
+                1,
+                0,
+                strings.as_ptr(),
+                std::ptr::null(),
+            )
+        };
+        if reported == 0 {
+            eprintln!(
+                "Failed to write log line to the event log: {}",
+                io::Error::last_os_error()
+            );
+        }
+    }
+}
+
+fn event_type(level: Level) -> REPORT_EVENT_TYPE {
+    match level {
+        Level::ERROR => EVENTLOG_ERROR_TYPE,
+        Level::WARN => EVENTLOG_WARNING_TYPE,
+        Level::INFO | Level::DEBUG | Level::TRACE => EVENTLOG_INFORMATION_TYPE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wide_strings_are_nul_terminated() {
+        assert_eq!(wide("Flare"), [70, 108, 97, 114, 101, 0]);
+        assert_eq!(wide(""), [0]);
+    }
+
+    #[test]
+    fn test_levels_map_to_event_types() {
+        assert_eq!(event_type(Level::ERROR), EVENTLOG_ERROR_TYPE);
+        assert_eq!(event_type(Level::WARN), EVENTLOG_WARNING_TYPE);
+        for level in [Level::INFO, Level::DEBUG, Level::TRACE] {
+            assert_eq!(event_type(level), EVENTLOG_INFORMATION_TYPE);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::errors::FlareSyncError;
+
+/// The cycle succeeded and no record needed a change.
+pub const SUCCESS: u8 = 0;
+/// An unexpected error, such as an unwritable file or an unparseable response.
+pub const FAILURE: u8 = 1;
+pub const CONFIG_ERROR: u8 = 2;
+/// Cloudflare rejected the API token or it lacks a permission.
+pub const AUTH_ERROR: u8 = 3;
+/// The public IP or Cloudflare could not be reached.
+pub const NETWORK_ERROR: u8 = 4;
+/// At least one domain failed for mixed or non-network reasons.
+pub const PARTIAL_FAILURE: u8 = 5;
+/// Another instance holds the lock file, or another daemon the PID file, so this run did
+/// nothing.
+pub const LOCKED: u8 = 6;
+/// The cycle succeeded and at least one record was updated.
+pub const UPDATED: u8 = 10;
+pub const INTERRUPTED: u8 = 130;
+
+pub fn for_error(err: &FlareSyncError) -> u8 {
+    match err {
+        FlareSyncError::Config(_) => CONFIG_ERROR,
+        FlareSyncError::Network(e) => match e.status().map(|status| status.as_u16()) {
+            Some(401 | 403) => AUTH_ERROR,
+            Some(status) if status == 429 || status >= 500 => NETWORK_ERROR,
+            Some(_) => FAILURE,
+            None => NETWORK_ERROR,
+        },
+        FlareSyncError::Timeout(_)
+        | FlareSyncError::IpProvider(_)
+        | FlareSyncError::CloudflareTransient(_) => NETWORK_ERROR,
+        FlareSyncError::Cloudflare(message) => {
+            let message = message.to_ascii_lowercase();
+            if message.contains("authentication") || message.contains("unauthorized") {
+                AUTH_ERROR
+            } else {
+                FAILURE
+            }
+        }
+        FlareSyncError::Io(_)
+        | FlareSyncError::Json(_)
+        | FlareSyncError::Database(_)
+        | FlareSyncError::Notification(_) => FAILURE,
+        FlareSyncError::AlreadyRunning(_) => LOCKED,
+        FlareSyncError::FailureThreshold { exit_code, .. } => *exit_code,
+    }
+}
+
+/// Combines the per-domain results of a completed cycle. `failures` holds the
+/// [`for_error`] code of each failed domain.
+pub fn for_cycle(updated: usize, failures: &[u8]) -> u8 {
+    match failures {
+        [] if updated > 0 => UPDATED,
+        [] => SUCCESS,
+        [first, rest @ ..]
+            if matches!(*first, AUTH_ERROR | NETWORK_ERROR)
+                && rest.iter().all(|code| code == first) =>
+        {
+            *first
+        }
+        _ => PARTIAL_FAILURE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cycle_exit_codes() {
+        assert_eq!(for_cycle(0, &[]), SUCCESS);
+        assert_eq!(for_cycle(2, &[]), UPDATED);
+        assert_eq!(for_cycle(1, &[AUTH_ERROR, AUTH_ERROR]), AUTH_ERROR);
+        assert_eq!(for_cycle(0, &[NETWORK_ERROR]), NETWORK_ERROR);
+        assert_eq!(for_cycle(0, &[NETWORK_ERROR, AUTH_ERROR]), PARTIAL_FAILURE);
+        assert_eq!(for_cycle(1, &[FAILURE]), PARTIAL_FAILURE);
+        assert_eq!(
+            for_error(&FlareSyncError::Config("missing".to_string())),
+            CONFIG_ERROR
+        );
+        assert_eq!(
+            for_error(&FlareSyncError::Cloudflare(
+                "API error fetching example.com: [Object {\"code\": Number(10000), \"message\": String(\"Authentication error\")}]".to_string()
+            )),
+            AUTH_ERROR
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use crate::errors::FlareSyncError;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{self, Read, Write};
+
+const MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Backups are small JSON documents, so anything that inflates past this is refused
+/// instead of being read into memory.
+const MAX_DECOMPRESSED_LEN: u64 = 16 * 1024 * 1024;
+
+pub fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC)
+}
+
+/// A single-member gzip file holding `data`.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(data)
+        .and_then(|()| encoder.finish())
+        .expect("writing to a Vec cannot fail")
+}
+
+fn invalid(reason: &dyn std::fmt::Display) -> FlareSyncError {
+    FlareSyncError::Io(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Invalid gzip data: {}", reason),
+    ))
+}
+
+/// The contents of the first member of the gzip file `data`, checked against its CRC.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, FlareSyncError> {
+    if !is_gzip(data) {
+        return Err(invalid(&"missing header"));
+    }
+    let mut inflated = Vec::new();
+    GzDecoder::new(data)
+        .take(MAX_DECOMPRESSED_LEN + 1)
+        .read_to_end(&mut inflated)
+        .map_err(|e| invalid(&e))?;
+    if inflated.len() as u64 > MAX_DECOMPRESSED_LEN {
+        return Err(invalid(&format!(
+            "larger than {} bytes when decompressed",
+            MAX_DECOMPRESSED_LEN
+        )));
+    }
+    Ok(inflated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_reject_corruption() {
+        let data = br#"{"name":"example.com","content":"203.0.113.1"}"#.repeat(20);
+        let compressed = compress(&data);
+        assert!(is_gzip(&compressed));
+        assert!(compressed.len() < data.len());
+        assert_eq!(decompress(&compressed).unwrap(), data);
+
+        let mut corrupted = compressed.clone();
+        let crc_offset = corrupted.len() - 8;
+        corrupted[crc_offset] ^= 0xff;
+        assert!(decompress(&corrupted).is_err());
+        assert!(decompress(b"{}").is_err());
+
+        // A member written by gzip(1), with the original file name in the header.
+        let mut named = vec![0x1f, 0x8b, 8, 0x08, 0, 0, 0, 0, 0, 3];
+        named.extend_from_slice(b"backup.json\0");
+        named.extend_from_slice(&compressed[10..]);
+        assert_eq!(decompress(&named).unwrap(), data);
+    }
+
+    #[test]
+    fn test_decompress_refuses_oversized_output() {
+        let bomb = compress(&vec![0u8; MAX_DECOMPRESSED_LEN as usize + 1]);
+        assert!(bomb.len() < 64 * 1024);
+        assert!(decompress(&bomb)
+            .unwrap_err()
+            .to_string()
+            .contains("larger than"));
+    }
+}
@@ -0,0 +1,279 @@
+use crate::errors::FlareSyncError;
+use crate::status::RuntimeStatus;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::{DateTime, FixedOffset, Local};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+
+#[derive(Clone)]
+struct HealthState {
+    status: watch::Receiver<RuntimeStatus>,
+    update_interval: Duration,
+}
+
+pub async fn serve(
+    listener: TcpListener,
+    status: watch::Receiver<RuntimeStatus>,
+    update_interval: Duration,
+) -> Result<(), FlareSyncError> {
+    let app = Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/stats", get(stats))
+        .with_state(HealthState {
+            status,
+            update_interval,
+        });
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn healthz(State(state): State<HealthState>) -> Json<Value> {
+    let status = state.status.borrow();
+    Json(json!({
+        "status": "ok",
+        "started_at": status.started_at,
+    }))
+}
+
+async fn stats(State(state): State<HealthState>) -> Json<Value> {
+    let status = state.status.borrow();
+    Json(json!(status.stats_at(Local::now())))
+}
+
+async fn readyz(State(state): State<HealthState>) -> (StatusCode, Json<Value>) {
+    let status = state.status.borrow().clone();
+    match check_readiness(&status, state.update_interval, Local::now()) {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(json!({
+                "status": "ready",
+                "last_successful_cycle_at": status.last_successful_cycle_at,
+            })),
+        ),
+        Err(reason) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({
+                "status": "not_ready",
+                "reason": reason,
+                "last_successful_cycle_at": status.last_successful_cycle_at,
+                "last_error": status.last_error,
+            })),
+        ),
+    }
+}
+
+pub fn check_readiness(
+    status: &RuntimeStatus,
+    update_interval: Duration,
+    now: DateTime<Local>,
+) -> Result<(), String> {
+    if status.shutting_down {
+        return Err("shutting down".to_string());
+    }
+    if status.paused {
+        return Ok(());
+    }
+
+    let last_success =
+        parse_timestamp("last_successful_cycle_at", &status.last_successful_cycle_at)?;
+    // A first check delayed past startup is only late once it has had its own interval.
+    let first_check = parse_timestamp("first_check_at", &status.first_check_at)?;
+    let Some(since) = last_success.max(first_check) else {
+        return Err("no successful cycle yet".to_string());
+    };
+
+    let max_age = chrono::Duration::from_std(update_interval * 2)
+        .map_err(|e| format!("invalid update interval: {}", e))?;
+    if now.signed_duration_since(since) > max_age {
+        if last_success == Some(since) {
+            return Err(format!(
+                "last successful cycle is older than {:?}",
+                update_interval * 2
+            ));
+        }
+        return Err(format!(
+            "no successful cycle within {:?} of the first check",
+            update_interval * 2
+        ));
+    }
+
+    Ok(())
+}
+
+fn parse_timestamp(
+    field: &str,
+    value: &Option<String>,
+) -> Result<Option<DateTime<FixedOffset>>, String> {
+    value
+        .as_deref()
+        .map(DateTime::parse_from_rfc3339)
+        .transpose()
+        .map_err(|e| format!("invalid {}: {}", field, e))
+}
+
+/// Asks a running instance's `/readyz` (or any URL answering like it) whether it is
+/// ready, returning the reported reason when it is not.
+pub async fn probe(client: &reqwest::Client, url: &str) -> Result<(), String> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("{} is unreachable: {}", url, e))?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body: Value = response.json().await.unwrap_or_default();
+    match body["reason"].as_str() {
+        Some(reason) => Err(format!("{} returned {}: {}", url, status, reason)),
+        None => Err(format!("{} returned {}", url, status)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_readiness_requires_recent_successful_cycle() {
+        let interval = Duration::from_secs(300);
+        let mut status = RuntimeStatus::new();
+        let now = Local::now();
+
+        assert!(check_readiness(&status, interval, now).is_err());
+
+        status.last_successful_cycle_at = Some((now - chrono::Duration::minutes(9)).to_rfc3339());
+        assert!(check_readiness(&status, interval, now).is_ok());
+
+        status.last_successful_cycle_at = Some((now - chrono::Duration::minutes(11)).to_rfc3339());
+        assert!(check_readiness(&status, interval, now).is_err());
+    }
+
+    #[test]
+    fn test_check_readiness_waits_for_a_delayed_first_check() {
+        let interval = Duration::from_secs(300);
+        let mut status = RuntimeStatus::new();
+        let now = Local::now();
+
+        status.mark_first_check_at(now + chrono::Duration::minutes(30));
+        assert!(check_readiness(&status, interval, now).is_ok());
+
+        status.mark_first_check_at(now - chrono::Duration::minutes(9));
+        assert!(check_readiness(&status, interval, now).is_ok());
+
+        status.mark_first_check_at(now - chrono::Duration::minutes(11));
+        assert_eq!(
+            check_readiness(&status, interval, now),
+            Err("no successful cycle within 600s of the first check".to_string())
+        );
+
+        status.last_successful_cycle_at = Some((now - chrono::Duration::minutes(5)).to_rfc3339());
+        assert!(check_readiness(&status, interval, now).is_ok());
+
+        status.mark_first_check_at(now - chrono::Duration::minutes(40));
+        status.last_successful_cycle_at = Some((now - chrono::Duration::minutes(20)).to_rfc3339());
+        assert_eq!(
+            check_readiness(&status, interval, now),
+            Err("last successful cycle is older than 600s".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probe_reports_readyz() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/readyz", listener.local_addr().unwrap());
+        let (status_tx, status_rx) = watch::channel(RuntimeStatus::new());
+        tokio::spawn(serve(listener, status_rx, Duration::from_secs(300)));
+        let client = reqwest::Client::new();
+
+        assert_eq!(
+            probe(&client, &url).await,
+            Err(format!(
+                "{} returned 503 Service Unavailable: no successful cycle yet",
+                url
+            ))
+        );
+
+        status_tx.send_modify(|status| status.mark_cycle_complete(true, Duration::ZERO));
+        assert_eq!(probe(&client, &url).await, Ok(()));
+
+        assert!(probe(&client, "http://127.0.0.1:9/readyz")
+            .await
+            .unwrap_err()
+            .contains("is unreachable"));
+    }
+
+    #[test]
+    fn test_check_readiness_fails_while_shutting_down() {
+        let mut status = RuntimeStatus::new();
+        status.mark_cycle_complete(true, Duration::from_secs(1));
+        status.mark_shutting_down();
+
+        assert_eq!(
+            check_readiness(&status, Duration::from_secs(300), Local::now()),
+            Err("shutting down".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_health_endpoints_report_status_codes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (status_tx, status_rx) = watch::channel(RuntimeStatus::new());
+        tokio::spawn(serve(listener, status_rx, Duration::from_secs(300)));
+
+        let client = reqwest::Client::new();
+        let healthz = client
+            .get(format!("http://{}/healthz", address))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(healthz.status(), reqwest::StatusCode::OK);
+
+        let readyz = client
+            .get(format!("http://{}/readyz", address))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(readyz.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            probe(&client, &format!("http://{}/readyz", address)).await,
+            Err(format!(
+                "http://{}/readyz returned 503 Service Unavailable: no successful cycle yet",
+                address
+            ))
+        );
+
+        let mut status = RuntimeStatus::new();
+        status.mark_cycle_complete(true, Duration::from_secs(1));
+        status_tx.send_replace(status);
+
+        let readyz = client
+            .get(format!("http://{}/readyz", address))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(readyz.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            probe(&client, &format!("http://{}/readyz", address)).await,
+            Ok(())
+        );
+
+        let stats: Value = client
+            .get(format!("http://{}/stats", address))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(stats["total_cycles"], 1);
+        assert_eq!(stats["average_cycle_duration_ms"], 1000);
+    }
+}
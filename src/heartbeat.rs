@@ -0,0 +1,56 @@
+use reqwest::Client as ReqwestClient;
+use std::time::Duration;
+use tracing::warn;
+
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeartbeatSignal {
+    Start,
+    Success,
+    Fail,
+}
+
+pub fn heartbeat_url(base_url: &str, signal: HeartbeatSignal) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    match signal {
+        HeartbeatSignal::Start => format!("{}/start", base_url),
+        HeartbeatSignal::Success => base_url.to_string(),
+        HeartbeatSignal::Fail => format!("{}/fail", base_url),
+    }
+}
+
+pub async fn ping(client: &ReqwestClient, base_url: &str, signal: HeartbeatSignal) {
+    let url = heartbeat_url(base_url, signal);
+    let result = client
+        .get(&url)
+        .timeout(HEARTBEAT_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    if let Err(e) = result {
+        warn!("Failed to send {:?} heartbeat ping: {}", signal, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heartbeat_url_appends_signal_suffix() {
+        let base_url = "https://hc-ping.com/abc-123/";
+        assert_eq!(
+            heartbeat_url(base_url, HeartbeatSignal::Start),
+            "https://hc-ping.com/abc-123/start"
+        );
+        assert_eq!(
+            heartbeat_url(base_url, HeartbeatSignal::Success),
+            "https://hc-ping.com/abc-123"
+        );
+        assert_eq!(
+            heartbeat_url(base_url, HeartbeatSignal::Fail),
+            "https://hc-ping.com/abc-123/fail"
+        );
+    }
+}
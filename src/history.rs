@@ -0,0 +1,204 @@
+use crate::config::Config;
+#[cfg(feature = "sqlite")]
+use crate::database::Database;
+use crate::errors::FlareSyncError;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub timestamp: String,
+    pub domain: String,
+    pub old_ip: Option<String>,
+    pub new_ip: String,
+    pub result: String,
+    pub error: Option<String>,
+}
+
+impl HistoryEntry {
+    pub fn updated(domain: &str, old_ip: &str, new_ip: &str) -> Self {
+        Self {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            domain: domain.to_string(),
+            old_ip: Some(old_ip.to_string()),
+            new_ip: new_ip.to_string(),
+            result: "updated".to_string(),
+            error: None,
+        }
+    }
+
+    pub fn rolled_back(domain: &str, old_ip: &str, new_ip: &str) -> Self {
+        Self {
+            result: "rolled_back".to_string(),
+            ..Self::updated(domain, old_ip, new_ip)
+        }
+    }
+
+    pub fn failed(domain: &str, new_ip: &str, error: &FlareSyncError) -> Self {
+        Self {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            domain: domain.to_string(),
+            old_ip: None,
+            new_ip: new_ip.to_string(),
+            result: "failed".to_string(),
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+pub fn append(path: &Path, entry: &HistoryEntry) -> Result<(), FlareSyncError> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(&line)?;
+    Ok(())
+}
+
+pub fn read(path: &Path) -> Result<Vec<HistoryEntry>, FlareSyncError> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        entries.push(serde_json::from_str(&line)?);
+    }
+    Ok(entries)
+}
+
+/// Where the history of `config` is kept: its database if `DATABASE_PATH` is set, or else
+/// its history file.
+pub fn location(config: &Config) -> &Path {
+    config
+        .database_path
+        .as_deref()
+        .unwrap_or(&config.history_file_path)
+}
+
+/// Appends `entry` to the history of `config`, in its database or history file.
+pub fn record(config: &Config, entry: &HistoryEntry) -> Result<(), FlareSyncError> {
+    #[cfg(feature = "sqlite")]
+    if let Some(database) = &config.database_path {
+        return Database::open(database)?.append_history(entry);
+    }
+    append(&config.history_file_path, entry)
+}
+
+/// The history of `config`, oldest first, from its database or history file.
+pub fn load(config: &Config) -> Result<Vec<HistoryEntry>, FlareSyncError> {
+    #[cfg(feature = "sqlite")]
+    if let Some(database) = &config.database_path {
+        return Database::open(database)?.history();
+    }
+    read(&config.history_file_path)
+}
+
+/// The newest update of `domain` that has not been rolled back yet.
+pub fn last_update<'a>(entries: &'a [HistoryEntry], domain: &str) -> Option<&'a HistoryEntry> {
+    entries
+        .iter()
+        .rev()
+        .find(|entry| {
+            entry.domain == domain && matches!(entry.result.as_str(), "updated" | "rolled_back")
+        })
+        .filter(|entry| entry.result == "updated")
+}
+
+pub fn query<'a>(
+    entries: &'a [HistoryEntry],
+    domain: Option<&str>,
+    limit: usize,
+) -> Vec<&'a HistoryEntry> {
+    entries
+        .iter()
+        .rev()
+        .filter(|entry| domain.map(|d| entry.domain == d).unwrap_or(true))
+        .take(limit)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn test_history_appends_and_queries_newest_first() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let test_dir = std::env::temp_dir().join(format!(
+            "flaresync_history_test_{}_{}",
+            std::process::id(),
+            unique
+        ));
+        let path = test_dir.join("history.jsonl");
+
+        assert!(read(&path).unwrap().is_empty());
+
+        append(
+            &path,
+            &HistoryEntry::updated("example.com", "203.0.113.1", "203.0.113.2"),
+        )
+        .unwrap();
+        append(
+            &path,
+            &HistoryEntry::updated("other.com", "203.0.113.1", "203.0.113.2"),
+        )
+        .unwrap();
+        append(
+            &path,
+            &HistoryEntry::updated("example.com", "203.0.113.2", "203.0.113.3"),
+        )
+        .unwrap();
+
+        let entries = read(&path).unwrap();
+        assert_eq!(entries.len(), 3);
+
+        let latest = query(&entries, Some("example.com"), 1);
+        assert_eq!(latest.len(), 1);
+        assert_eq!(latest[0].old_ip.as_deref(), Some("203.0.113.2"));
+        assert_eq!(latest[0].new_ip, "203.0.113.3");
+
+        assert_eq!(query(&entries, None, 10).len(), 3);
+
+        fs::remove_dir_all(test_dir).ok();
+    }
+
+    #[test]
+    fn test_last_update_ignores_rolled_back_updates() {
+        let mut entries = vec![
+            HistoryEntry::updated("example.com", "203.0.113.1", "203.0.113.2"),
+            HistoryEntry::failed(
+                "example.com",
+                "203.0.113.3",
+                &FlareSyncError::Timeout("slow".to_string()),
+            ),
+        ];
+        let last = last_update(&entries, "example.com").unwrap();
+        assert_eq!(last.old_ip.as_deref(), Some("203.0.113.1"));
+        assert!(last_update(&entries, "other.com").is_none());
+
+        entries.push(HistoryEntry::rolled_back(
+            "example.com",
+            "203.0.113.2",
+            "203.0.113.1",
+        ));
+        assert!(last_update(&entries, "example.com").is_none());
+    }
+}
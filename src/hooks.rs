@@ -0,0 +1,110 @@
+use crate::errors::FlareSyncError;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::{debug, info};
+
+pub const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeHook {
+    pub command: String,
+    pub timeout: Duration,
+}
+
+impl ChangeHook {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            timeout: DEFAULT_HOOK_TIMEOUT,
+        }
+    }
+
+    fn shell(&self) -> Command {
+        #[cfg(windows)]
+        {
+            let mut command = Command::new("cmd");
+            command.arg("/C").arg(&self.command);
+            command
+        }
+        #[cfg(not(windows))]
+        {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(&self.command);
+            command
+        }
+    }
+
+    pub async fn run(
+        &self,
+        domain: &str,
+        old_ip: &str,
+        new_ip: &str,
+    ) -> Result<(), FlareSyncError> {
+        let mut command = self.shell();
+        command
+            .env("DOMAIN", domain)
+            .env("OLD_IP", old_ip)
+            .env("NEW_IP", new_ip)
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+
+        let output = tokio::time::timeout(self.timeout, command.output())
+            .await
+            .map_err(|_| {
+                FlareSyncError::Timeout(format!(
+                    "on_change hook did not finish within {}s",
+                    self.timeout.as_secs()
+                ))
+            })??;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !stdout.trim().is_empty() {
+            debug!("on_change hook stdout: {}", stdout.trim());
+        }
+        if !output.status.success() {
+            return Err(FlareSyncError::Io(std::io::Error::other(format!(
+                "on_change hook exited with {}: {}",
+                output.status,
+                stderr.trim()
+            ))));
+        }
+        if !stderr.trim().is_empty() {
+            debug!("on_change hook stderr: {}", stderr.trim());
+        }
+        info!("on_change hook completed for {}", domain);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_change_hook_receives_ip_environment() {
+        let hook = ChangeHook::new(
+            r#"test "$DOMAIN $OLD_IP $NEW_IP" = "example.com 198.51.100.1 203.0.113.7""#,
+        );
+        hook.run("example.com", "198.51.100.1", "203.0.113.7")
+            .await
+            .unwrap();
+
+        let failing = ChangeHook::new("echo broken >&2; exit 3");
+        let error = failing
+            .run("example.com", "198.51.100.1", "203.0.113.7")
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("broken"));
+
+        let slow = ChangeHook {
+            command: "sleep 5".to_string(),
+            timeout: Duration::from_millis(50),
+        };
+        assert!(matches!(
+            slow.run("example.com", "198.51.100.1", "203.0.113.7").await,
+            Err(FlareSyncError::Timeout(_))
+        ));
+    }
+}
@@ -1,12 +1,12 @@
 use crate::errors::FlareSyncError;
-use log::error;
 use reqwest::Client as ReqwestClient;
 use std::collections::HashMap;
 use std::net::Ipv4Addr;
 use std::time::Duration;
 use tokio::time;
+use tracing::error;
 
-const IP_SOURCES: [&str; 3] = [
+pub const IP_SOURCES: [&str; 3] = [
     "https://api.ipify.org",
     "https://checkip.amazonaws.com",
     "https://ipv4.icanhazip.com",
@@ -68,16 +68,26 @@ async fn fetch_ipv4_from_source(
     }
 }
 
-pub async fn get_current_ip(client: &ReqwestClient) -> Result<Ipv4Addr, FlareSyncError> {
+pub async fn check_sources(
+    client: &ReqwestClient,
+) -> Vec<(&'static str, Result<Ipv4Addr, FlareSyncError>)> {
     let (r1, r2, r3) = tokio::join!(
         fetch_ipv4_from_source(client, IP_SOURCES[0]),
         fetch_ipv4_from_source(client, IP_SOURCES[1]),
         fetch_ipv4_from_source(client, IP_SOURCES[2]),
     );
+    IP_SOURCES.into_iter().zip([r1, r2, r3]).collect()
+}
 
+pub fn quorum_ip(
+    sources: &[(&'static str, Result<Ipv4Addr, FlareSyncError>)],
+) -> Result<Ipv4Addr, FlareSyncError> {
     let mut counts: HashMap<Ipv4Addr, usize> = HashMap::new();
-    for ip in [r1, r2, r3].into_iter().flatten() {
-        *counts.entry(ip).or_insert(0) += 1;
+    for ip in sources
+        .iter()
+        .filter_map(|(_, result)| result.as_ref().ok())
+    {
+        *counts.entry(*ip).or_insert(0) += 1;
     }
 
     if let Some((ip, count)) = counts.into_iter().max_by_key(|(_, count)| *count) {
@@ -90,3 +100,31 @@ pub async fn get_current_ip(client: &ReqwestClient) -> Result<Ipv4Addr, FlareSyn
         "Failed to determine public IP by quorum (need 2 of 3 sources to agree)".to_string(),
     ))
 }
+
+pub async fn get_current_ip(client: &ReqwestClient) -> Result<Ipv4Addr, FlareSyncError> {
+    quorum_ip(&check_sources(client).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quorum_ip_needs_two_agreeing_sources() {
+        let ip = Ipv4Addr::new(203, 0, 113, 7);
+        let failed = || Err(FlareSyncError::IpProvider("down".to_string()));
+        let sources = [
+            (IP_SOURCES[0], Ok(ip)),
+            (IP_SOURCES[1], failed()),
+            (IP_SOURCES[2], Ok(ip)),
+        ];
+        assert_eq!(quorum_ip(&sources).unwrap(), ip);
+
+        let sources = [
+            (IP_SOURCES[0], Ok(ip)),
+            (IP_SOURCES[1], Ok(Ipv4Addr::new(198, 51, 100, 1))),
+            (IP_SOURCES[2], failed()),
+        ];
+        assert!(quorum_ip(&sources).is_err());
+    }
+}
@@ -0,0 +1,375 @@
+use crate::atomic_file;
+use crate::backup::{self, BackupCheck, BackupDocument, BackupRetention, SnapshotKind, ZoneInfo};
+use crate::cloudflare::{sanitize_filename_component, DnsRecord};
+use crate::encryption::{self, EncryptionKey};
+use crate::errors::FlareSyncError;
+use crate::logging::EVENTS_TARGET;
+use chrono::{DateTime, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+pub const JOURNAL_SUFFIX: &str = "_journal.ndjson";
+
+/// One line of a backup journal: a backup document and when it was saved. The document's
+/// checksum also covers `saved_at`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub saved_at: String,
+    #[serde(flatten)]
+    pub document: BackupDocument,
+}
+
+impl JournalEntry {
+    pub fn new(
+        record: &DnsRecord,
+        kind: SnapshotKind,
+        zone: Option<&ZoneInfo>,
+        key: Option<&EncryptionKey>,
+    ) -> Self {
+        Self::saved_at(Local::now().to_rfc3339(), record, kind, zone, key)
+    }
+
+    /// An entry saved at `saved_at`, an RFC 3339 timestamp.
+    pub fn saved_at(
+        saved_at: String,
+        record: &DnsRecord,
+        kind: SnapshotKind,
+        zone: Option<&ZoneInfo>,
+        key: Option<&EncryptionKey>,
+    ) -> Self {
+        let mut entry = Self {
+            saved_at,
+            document: BackupDocument::new(record, kind, zone, key),
+        };
+        let value = serde_json::to_value(&entry).expect("journal entries serialize");
+        entry.document.checksum = Some(backup::checksum(&value, key));
+        entry
+    }
+
+    /// Parses one journal line, upgrading older document versions like a backup file.
+    pub fn parse(line: &str, key: Option<&EncryptionKey>) -> Result<Self, FlareSyncError> {
+        Self::from_value(serde_json::from_str(line)?, key)
+    }
+
+    /// Parses a document stored apart from when it was saved, as in the database.
+    pub fn from_stored(
+        saved_at: &str,
+        document: &str,
+        key: Option<&EncryptionKey>,
+    ) -> Result<Self, FlareSyncError> {
+        let mut value = encryption::open_json(serde_json::from_str(document)?, key)?;
+        if let Some(object) = value.as_object_mut() {
+            object.insert("saved_at".to_string(), saved_at.into());
+        }
+        Self::from_value(value, key)
+    }
+
+    fn from_value(value: Value, key: Option<&EncryptionKey>) -> Result<Self, FlareSyncError> {
+        let value = encryption::open_json(value, key)?;
+        let saved_at = value
+            .get("saved_at")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| FlareSyncError::Config("Journal entry has no saved_at".to_string()))?;
+        Ok(Self {
+            saved_at,
+            document: BackupDocument::from_value(value, key)?,
+        })
+    }
+
+    pub fn created_at(&self) -> Option<NaiveDateTime> {
+        local_time(&self.saved_at)
+    }
+}
+
+/// An RFC 3339 `saved_at` timestamp in local time, like backup file names.
+pub(crate) fn local_time(saved_at: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(saved_at)
+        .ok()
+        .map(|saved_at| saved_at.with_timezone(&Local).naive_local())
+}
+
+/// The journal of the record named `record_name` in `dir`.
+pub fn path(dir: &Path, record_name: &str) -> PathBuf {
+    dir.join(format!(
+        "{}{}",
+        sanitize_filename_component(record_name),
+        JOURNAL_SUFFIX
+    ))
+}
+
+pub fn is_journal(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.len() > JOURNAL_SUFFIX.len() && name.ends_with(JOURNAL_SUFFIX))
+}
+
+/// The journals in `dir`, sorted by path.
+pub fn list(dir: &Path) -> Result<Vec<PathBuf>, FlareSyncError> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut journals = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if is_journal(&path) {
+            journals.push(path);
+        }
+    }
+    journals.sort();
+    Ok(journals)
+}
+
+/// Appends `entry` as one synced line, so a crash never leaves more than a partial last
+/// line, which the next append starts after. A new journal is readable only by the owner.
+/// With `key`, the line holds the entry sealed by [`encryption::seal_json`].
+pub fn append(
+    path: &Path,
+    entry: &JournalEntry,
+    key: Option<&EncryptionKey>,
+) -> Result<(), FlareSyncError> {
+    let mut open_options = OpenOptions::new();
+    open_options.create(true).read(true).append(true);
+    #[cfg(unix)]
+    open_options.mode(0o600);
+    let mut file = open_options.open(path)?;
+
+    let mut line = Vec::new();
+    if file.seek(SeekFrom::End(0))? > 0 {
+        let mut last = [0u8];
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            line.push(b'\n');
+        }
+    }
+    match key {
+        Some(key) => serde_json::to_writer(&mut line, &encryption::seal_json(key, entry)?)?,
+        None => serde_json::to_writer(&mut line, entry)?,
+    }
+    line.push(b'\n');
+    file.write_all(&line)?;
+    file.sync_data()?;
+    Ok(())
+}
+
+fn read_lines(path: &Path) -> Result<Vec<String>, FlareSyncError> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(contents.lines().map(str::to_string).collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The entries of the journal at `path`, oldest first. A missing journal is empty, and
+/// lines that cannot be parsed, such as one cut short by a crash, are skipped.
+pub fn read(path: &Path, key: Option<&EncryptionKey>) -> Result<Vec<JournalEntry>, FlareSyncError> {
+    let mut entries = Vec::new();
+    for (number, line) in read_lines(path)?.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match JournalEntry::parse(line, key) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => warn!(
+                target: EVENTS_TARGET,
+                "Skipping line {} of backup journal {}: {}",
+                number + 1,
+                path.display(),
+                e
+            ),
+        }
+    }
+    Ok(entries)
+}
+
+/// Checks each line of the journal at `path` against its checksum. Unlike [`read`], a
+/// line that cannot be parsed is reported as a failure.
+pub fn verify(
+    path: &Path,
+    key: Option<&EncryptionKey>,
+) -> Result<Vec<BackupCheck>, FlareSyncError> {
+    Ok(read_lines(path)?
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            BackupCheck::new(
+                format!("{} line {}", path.display(), number + 1),
+                JournalEntry::parse(line, key).map(|entry| entry.document),
+            )
+        })
+        .collect())
+}
+
+/// Removes the entries `retention` expires from the journal at `path`, keeping the newest
+/// entry of each kind, and returns a description of each. Lines that cannot be parsed are
+/// kept as they are.
+pub fn prune(
+    path: &Path,
+    key: Option<&EncryptionKey>,
+    retention: BackupRetention,
+    now: NaiveDateTime,
+    dry_run: bool,
+) -> Result<Vec<String>, FlareSyncError> {
+    let lines = read_lines(path)?;
+    let entries: Vec<(usize, JournalEntry)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(index, line)| Some((index, JournalEntry::parse(line, key).ok()?)))
+        .collect();
+    let expired = backup::expired_by(
+        entries,
+        |(_, entry)| entry.document.kind,
+        |(_, entry)| entry.created_at(),
+        retention,
+        now,
+    );
+    if expired.is_empty() || dry_run {
+        return Ok(describe(path, &expired));
+    }
+
+    let mut kept = String::new();
+    for (index, line) in lines.iter().enumerate() {
+        if !expired.iter().any(|(expired, _)| *expired == index) {
+            kept.push_str(line);
+            kept.push('\n');
+        }
+    }
+    atomic_file::write_private(path, kept.as_bytes())?;
+    Ok(describe(path, &expired))
+}
+
+fn describe(path: &Path, expired: &[(usize, JournalEntry)]) -> Vec<String> {
+    expired
+        .iter()
+        .map(|(_, entry)| format!("{} (saved {})", path.display(), entry.saved_at))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup::{BackupStore, Integrity};
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+
+    fn record(content: &str) -> DnsRecord {
+        DnsRecord {
+            id: "1".to_string(),
+            name: "home.example.com".to_string(),
+            content: content.to_string(),
+            record_type: "A".to_string(),
+            proxied: false,
+            ttl: 1,
+            modified_on: None,
+        }
+    }
+
+    fn entry(saved_at: &str, content: &str, kind: SnapshotKind) -> JournalEntry {
+        JournalEntry::saved_at(saved_at.to_string(), &record(content), kind, None, None)
+    }
+
+    #[test]
+    fn test_journal_appends_reads_and_prunes_entries() {
+        let dir =
+            &std::env::temp_dir().join(format!("flaresync_backup_journal_{}", std::process::id()));
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+        let path = path(dir, "home.example.com");
+        assert!(is_journal(&path));
+        assert!(read(&path, None).unwrap().is_empty());
+
+        for entry in [
+            entry(
+                "2024-01-01T12:00:00+00:00",
+                "203.0.113.1",
+                SnapshotKind::Before,
+            ),
+            entry(
+                "2024-02-01T12:00:00+00:00",
+                "203.0.113.2",
+                SnapshotKind::Before,
+            ),
+            entry(
+                "2024-02-01T12:00:01+00:00",
+                "203.0.113.3",
+                SnapshotKind::After,
+            ),
+        ] {
+            append(&path, &entry, None).unwrap();
+        }
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"saved_at\":\"2024-03").unwrap();
+        append(
+            &path,
+            &entry(
+                "2024-03-01T12:00:00+00:00",
+                "203.0.113.4",
+                SnapshotKind::After,
+            ),
+            None,
+        )
+        .unwrap();
+        #[cfg(unix)]
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        let entries = read(&path, None).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[3].document.record.content, "203.0.113.4");
+        assert_eq!(entries[2].document.kind, SnapshotKind::After);
+        assert_eq!(list(dir).unwrap(), vec![path.clone()]);
+        assert_eq!(
+            backup::read(&path, None).unwrap().record.content,
+            "203.0.113.2"
+        );
+        let latest = BackupStore::new(dir)
+            .latest("home.example.com")
+            .unwrap()
+            .unwrap();
+        assert_eq!(latest.record.content, "203.0.113.2");
+        assert!(latest.source.contains("2024-02-01T12:00:00+00:00"));
+
+        let retention = BackupRetention {
+            keep_last: Some(1),
+            max_age: None,
+        };
+        let now = Local::now().naive_local();
+        assert_eq!(prune(&path, None, retention, now, true).unwrap().len(), 2);
+        assert_eq!(read(&path, None).unwrap().len(), 4);
+        let removed = prune(&path, None, retention, now, false).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(removed[0].contains("2024-01-01T12:00:00+00:00"));
+        let entries = read(&path, None).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].document.record.content, "203.0.113.2");
+        assert_eq!(entries[1].document.record.content, "203.0.113.4");
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .contains("\n{\"saved_at\":\"2024-03\n"));
+
+        let backdated = fs::read_to_string(&path)
+            .unwrap()
+            .replace("2024-02-01T12:00:00+00:00", "2023-02-01T12:00:00+00:00");
+        fs::write(&path, backdated).unwrap();
+        let integrity: Vec<_> = verify(&path, None)
+            .unwrap()
+            .into_iter()
+            .map(|check| check.integrity)
+            .collect();
+        assert!(matches!(integrity[0], Integrity::Failed(_)));
+        assert_eq!(integrity[2], Integrity::Verified);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::systemd::UnitOptions;
+
+pub const LABEL: &str = "io.github.battermanz.flaresync";
+pub const PLIST_NAME: &str = "io.github.battermanz.flaresync.plist";
+pub const DAEMON_DIR: &str = "/Library/LaunchDaemons";
+
+/// launchd starts jobs with a bare `PATH`; this also finds Homebrew tools used by
+/// `on_change` hooks.
+const PATH: &str = "/opt/homebrew/bin:/usr/local/bin:/usr/bin:/bin:/usr/sbin:/sbin";
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn string(key: &str, value: &str) -> String {
+    format!(
+        "    <key>{}</key>\n    <string>{}</string>\n",
+        key,
+        escape(value)
+    )
+}
+
+/// A launchd property list that runs the sync loop, kept alive after crashes and
+/// non-zero exits, or with `timer_minutes` runs `update` every that many minutes.
+pub fn plist(options: &UnitOptions) -> String {
+    let mut arguments = vec![options.executable.display().to_string()];
+    if let Some(config) = &options.config {
+        arguments.push("--config".to_string());
+        arguments.push(config.display().to_string());
+    }
+    if let Some(profile) = &options.profile {
+        arguments.push("--profile".to_string());
+        arguments.push(profile.clone());
+    }
+    let oneshot = options.timer_minutes.is_some();
+    arguments.push(if oneshot { "update" } else { "run" }.to_string());
+
+    let working_directory = options.working_directory.display().to_string();
+    let log_file = options
+        .working_directory
+        .join("flaresync.log")
+        .display()
+        .to_string();
+    let mut plist = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n\
+         <dict>\n",
+    );
+    plist.push_str(&string("Label", LABEL));
+    plist.push_str("    <key>ProgramArguments</key>\n    <array>\n");
+    for argument in &arguments {
+        plist.push_str(&format!("        <string>{}</string>\n", escape(argument)));
+    }
+    plist.push_str("    </array>\n");
+    plist.push_str(&string("WorkingDirectory", &working_directory));
+    plist.push_str(&format!(
+        "    <key>EnvironmentVariables</key>\n    <dict>\n        <key>PATH</key>\n        <string>{}</string>\n    </dict>\n",
+        PATH
+    ));
+    if let Some(user) = &options.user {
+        plist.push_str(&string("UserName", user));
+    }
+    plist.push_str("    <key>RunAtLoad</key>\n    <true/>\n");
+    match options.timer_minutes {
+        Some(minutes) => plist.push_str(&format!(
+            "    <key>StartInterval</key>\n    <integer>{}</integer>\n",
+            minutes * 60
+        )),
+        None => plist.push_str(
+            "    <key>KeepAlive</key>\n    <dict>\n        <key>SuccessfulExit</key>\n        <false/>\n    </dict>\n    \
+             <key>ThrottleInterval</key>\n    <integer>30</integer>\n    \
+             <key>ExitTimeOut</key>\n    <integer>30</integer>\n",
+        ),
+    }
+    plist.push_str("    <key>ProcessType</key>\n    <string>Background</string>\n");
+    plist.push_str(&string("StandardOutPath", &log_file));
+    plist.push_str(&string("StandardErrorPath", &log_file));
+    plist.push_str("</dict>\n</plist>\n");
+    plist
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_plist_keeps_loop_alive_or_runs_update_on_interval() {
+        let options = UnitOptions {
+            executable: PathBuf::from("/opt/homebrew/bin/flaresync"),
+            working_directory: PathBuf::from("/Users/me/Flare & Sync"),
+            config: Some(PathBuf::from("/Users/me/flaresync.toml")),
+            profile: Some("home".to_string()),
+            user: Some("me".to_string()),
+            timer_minutes: None,
+        };
+        let daemon = plist(&options);
+        assert!(daemon.contains(
+            "        <string>--profile</string>\n        <string>home</string>\n        <string>run</string>\n"
+        ));
+        assert!(daemon.contains("<string>/Users/me/Flare &amp; Sync</string>"));
+        assert!(daemon.contains("<key>SuccessfulExit</key>\n        <false/>"));
+        assert!(daemon.contains("<key>UserName</key>\n    <string>me</string>"));
+        assert!(!daemon.contains("StartInterval"));
+        assert!(daemon.ends_with("</dict>\n</plist>\n"));
+
+        let timer = plist(&UnitOptions {
+            timer_minutes: Some(10),
+            ..options
+        });
+        assert!(timer.contains("<string>update</string>"));
+        assert!(timer.contains("<key>StartInterval</key>\n    <integer>600</integer>"));
+        assert!(!timer.contains("KeepAlive"));
+    }
+}
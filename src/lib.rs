@@ -1,8 +1,61 @@
+pub mod admin;
+pub mod apply;
+pub mod atomic_file;
+pub mod backup;
+pub mod banner;
+pub mod cli;
 pub mod cloudflare;
 pub mod config;
+pub mod console;
+pub mod control;
+pub mod correlation;
+pub mod dashboard;
+#[cfg(feature = "sqlite")]
+pub mod database;
+pub mod doctor;
+pub mod dyndns;
+pub mod encryption;
+pub mod engine;
+#[cfg(feature = "sentry")]
+pub mod error_reporting;
 pub mod errors;
+#[cfg(windows)]
+pub mod eventlog;
+pub mod exit_code;
+pub mod gzip;
+pub mod hashing;
+pub mod health;
+pub mod heartbeat;
+pub mod history;
+pub mod hooks;
 pub mod ip_provider;
+pub mod journal;
+pub mod launchd;
+pub mod lock;
+pub mod log_rotation;
+pub mod logging;
+pub mod metrics;
+pub mod notify;
+pub mod prefetch;
+pub mod rate_limit;
+pub mod redaction;
+pub mod s3;
+pub mod scheduler;
+pub mod sd_notify;
+pub mod setup;
+pub mod state;
 pub mod status;
+pub mod summary;
+pub mod supervisor;
+pub mod syslog;
+pub mod systemd;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+pub mod wake_signal;
+#[cfg(windows)]
+pub mod win_service;
+
+pub use engine::FlareSync;
 
 #[cfg(test)]
 pub(crate) mod test_support {
@@ -0,0 +1,187 @@
+use crate::errors::FlareSyncError;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// An exclusive lock on `LOCK_FILE_PATH`, held for as long as the value lives. The
+/// operating system releases it when the process exits, so a crashed run never leaves a
+/// stale lock behind.
+#[derive(Debug)]
+pub struct InstanceLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl InstanceLock {
+    /// Takes the lock without waiting. Returns `Ok(None)` when another process holds it.
+    pub fn try_acquire(path: &Path) -> Result<Option<Self>, FlareSyncError> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => return Ok(None),
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        writeln!(file, "{}", std::process::id())?;
+        Ok(Some(Self {
+            file,
+            path: path.to_path_buf(),
+        }))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the locked file is still the one at `path`, rather than one that was removed
+    /// after it was opened.
+    #[cfg(unix)]
+    fn is_at_path(&self) -> Result<bool, FlareSyncError> {
+        use std::os::unix::fs::MetadataExt;
+
+        let current = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        let locked = self.file.metadata()?;
+        Ok(current.dev() == locked.dev() && current.ino() == locked.ino())
+    }
+
+    /// Without inode numbers to compare, the locked file is taken to be the one at `path`.
+    #[cfg(not(unix))]
+    fn is_at_path(&self) -> Result<bool, FlareSyncError> {
+        Ok(true)
+    }
+}
+
+/// The daemon's `PID_FILE_PATH`: an [`InstanceLock`] whose file is removed again when the
+/// daemon stops.
+#[derive(Debug)]
+pub struct PidFile {
+    lock: InstanceLock,
+}
+
+impl PidFile {
+    /// Takes the lock and writes the PID without waiting. Returns `Ok(None)` when another
+    /// daemon holds it.
+    pub fn try_acquire(path: &Path) -> Result<Option<Self>, FlareSyncError> {
+        loop {
+            let Some(lock) = InstanceLock::try_acquire(path)? else {
+                return Ok(None);
+            };
+            // A stopping daemon removes the file while it still holds the lock; a lock taken
+            // on the file just before that no longer guards the path, so take a new one.
+            if lock.is_at_path()? {
+                return Ok(Some(Self { lock }));
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.lock.path()
+    }
+}
+
+impl Drop for PidFile {
+    /// Removes the file before the lock is released, so no other daemon can lock it in
+    /// between.
+    fn drop(&mut self) {
+        let _ = fs::remove_file(self.lock.path());
+    }
+}
+
+/// The process ID recorded by the current holder of the lock at `path`, if it can be read.
+pub fn holder(path: &Path) -> Option<u32> {
+    let mut contents = String::new();
+    File::open(path).ok()?.read_to_string(&mut contents).ok()?;
+    contents.trim().parse().ok()
+}
+
+/// Describes who holds the lock at `path`, for log and error messages.
+pub fn describe_holder(path: &Path) -> String {
+    match holder(path) {
+        Some(pid) => format!("PID {} holds {}", pid, path.display()),
+        None => format!("another process holds {}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn test_lock_is_exclusive_until_released() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let test_dir = std::env::temp_dir().join(format!(
+            "flaresync_lock_test_{}_{}",
+            std::process::id(),
+            unique
+        ));
+        let path = test_dir.join("flaresync.lock");
+
+        let lock = InstanceLock::try_acquire(&path).unwrap().unwrap();
+        assert_eq!(lock.path(), path);
+        assert_eq!(holder(&path), Some(std::process::id()));
+        assert!(InstanceLock::try_acquire(&path).unwrap().is_none());
+        assert_eq!(
+            describe_holder(&path),
+            format!("PID {} holds {}", std::process::id(), path.display())
+        );
+
+        drop(lock);
+        assert!(InstanceLock::try_acquire(&path).unwrap().is_some());
+
+        let _ = fs::remove_dir_all(test_dir);
+    }
+
+    #[test]
+    fn test_pid_file_holds_pid_and_is_removed_on_drop() {
+        let test_dir =
+            std::env::temp_dir().join(format!("flaresync_pid_file_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&test_dir);
+        let path = test_dir.join("flaresync.pid");
+
+        let pid_file = PidFile::try_acquire(&path).unwrap().unwrap();
+        assert_eq!(pid_file.path(), path);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+        assert!(PidFile::try_acquire(&path).unwrap().is_none());
+
+        drop(pid_file);
+        assert!(!path.exists());
+
+        #[cfg(unix)]
+        {
+            let stale = InstanceLock::try_acquire(&path).unwrap().unwrap();
+            assert!(stale.is_at_path().unwrap());
+            fs::remove_file(&path).unwrap();
+            assert!(!stale.is_at_path().unwrap());
+            let pid_file = PidFile::try_acquire(&path).unwrap().unwrap();
+            assert_eq!(holder(&path), Some(std::process::id()));
+            drop(pid_file);
+            assert!(!path.exists());
+        }
+
+        let _ = fs::remove_dir_all(test_dir);
+    }
+}
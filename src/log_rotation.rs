@@ -0,0 +1,232 @@
+use crate::errors::FlareSyncError;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RotationPolicy {
+    pub max_size_bytes: Option<u64>,
+    pub max_age: Option<Duration>,
+    pub retention: usize,
+}
+
+struct ActiveFile {
+    file: File,
+    size: u64,
+    opened_at: SystemTime,
+}
+
+pub struct RotatingFile {
+    path: PathBuf,
+    policy: RotationPolicy,
+    active: Mutex<ActiveFile>,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, policy: RotationPolicy) -> Result<Self, FlareSyncError> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let active = open_active(path).map_err(|e| {
+            FlareSyncError::Config(format!("Failed to open log file {}: {}", path.display(), e))
+        })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            policy,
+            active: Mutex::new(active),
+        })
+    }
+
+    fn needs_rotation(&self, active: &ActiveFile, incoming: usize) -> bool {
+        let too_big = self
+            .policy
+            .max_size_bytes
+            .map(|max| active.size > 0 && active.size + incoming as u64 > max)
+            .unwrap_or(false);
+        let too_old = self
+            .policy
+            .max_age
+            .map(|max| {
+                SystemTime::now()
+                    .duration_since(active.opened_at)
+                    .map(|age| age >= max)
+                    .unwrap_or(false)
+            })
+            .unwrap_or(false);
+        too_big || too_old
+    }
+
+    fn rotate(&self, active: &mut ActiveFile) -> io::Result<()> {
+        active.file.flush()?;
+        if self.policy.retention == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let oldest = rotated_path(&self.path, self.policy.retention);
+            if oldest.exists() {
+                fs::remove_file(&oldest)?;
+            }
+            for index in (1..self.policy.retention).rev() {
+                let from = rotated_path(&self.path, index);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, index + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+
+        *active = ActiveFile {
+            file: OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?,
+            size: 0,
+            opened_at: SystemTime::now(),
+        };
+        Ok(())
+    }
+}
+
+impl Write for &RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut active = self
+            .active
+            .lock()
+            .map_err(|_| io::Error::other("log file lock poisoned"))?;
+        if self.needs_rotation(&active, buf.len()) {
+            self.rotate(&mut active)?;
+        }
+        let written = active.file.write(buf)?;
+        active.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut active = self
+            .active
+            .lock()
+            .map_err(|_| io::Error::other("log file lock poisoned"))?;
+        active.file.flush()
+    }
+}
+
+fn open_active(path: &Path) -> io::Result<ActiveFile> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let metadata = file.metadata()?;
+    let opened_at = metadata
+        .created()
+        .or_else(|_| metadata.modified())
+        .unwrap_or_else(|_| SystemTime::now());
+    Ok(ActiveFile {
+        file,
+        size: metadata.len(),
+        opened_at,
+    })
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "flaresync".to_string());
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}.{}.{}", stem, index, extension.to_string_lossy()),
+        None => format!("{}.{}", stem, index),
+    };
+    path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        std::env::temp_dir().join(format!(
+            "flaresync_{}_{}_{}",
+            name,
+            std::process::id(),
+            unique
+        ))
+    }
+
+    #[test]
+    fn test_rotated_path_keeps_extension() {
+        assert_eq!(
+            rotated_path(Path::new("logs/flaresync.log"), 2),
+            PathBuf::from("logs/flaresync.2.log")
+        );
+        assert_eq!(
+            rotated_path(Path::new("logs/flaresync"), 1),
+            PathBuf::from("logs/flaresync.1")
+        );
+    }
+
+    #[test]
+    fn test_rotating_file_rotates_by_size_and_prunes_old_files() {
+        let dir = test_dir("log_rotation");
+        let path = dir.join("flaresync.log");
+        let rotating = RotatingFile::open(
+            &path,
+            RotationPolicy {
+                max_size_bytes: Some(10),
+                max_age: None,
+                retention: 2,
+            },
+        )
+        .unwrap();
+
+        for line in [
+            "first-line\n",
+            "second-line\n",
+            "third-line\n",
+            "fourth-line\n",
+        ] {
+            (&rotating).write_all(line.as_bytes()).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth-line\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("flaresync.1.log")).unwrap(),
+            "third-line\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("flaresync.2.log")).unwrap(),
+            "second-line\n"
+        );
+        assert!(!dir.join("flaresync.3.log").exists());
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_rotating_file_rotates_by_age() {
+        let dir = test_dir("log_rotation_age");
+        let path = dir.join("flaresync.log");
+        let rotating = RotatingFile::open(
+            &path,
+            RotationPolicy {
+                max_size_bytes: None,
+                max_age: Some(Duration::ZERO),
+                retention: 1,
+            },
+        )
+        .unwrap();
+
+        (&rotating).write_all(b"old\n").unwrap();
+        (&rotating).write_all(b"new\n").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+        assert!(dir.join("flaresync.1.log").exists());
+
+        fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,434 @@
+use crate::config::Config;
+use crate::console::ConsoleLayer;
+use crate::errors::FlareSyncError;
+use crate::log_rotation::RotatingFile;
+use crate::redaction::{RedactingLayer, RedactingMakeWriter};
+use crate::syslog::SyslogMakeWriter;
+use std::io::IsTerminal;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::Subscriber;
+use tracing_subscriber::filter::FilterFn;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+pub const EVENTS_TARGET: &str = "flaresync::events";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    #[default]
+    Auto,
+    Text,
+    Json,
+    Console,
+}
+
+impl FromStr for LogFormat {
+    type Err = FlareSyncError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "auto" => Ok(LogFormat::Auto),
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            "console" => Ok(LogFormat::Console),
+            _ => Err(FlareSyncError::Config(
+                "LOG_FORMAT must be auto, text, json, or console".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LoggingGuard {
+    #[cfg(feature = "otel")]
+    telemetry: Option<crate::telemetry::Telemetry>,
+    #[cfg(feature = "sentry")]
+    error_reporting: Option<crate::error_reporting::ErrorReporting>,
+}
+
+impl Drop for LoggingGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(telemetry) = &self.telemetry {
+            telemetry.shutdown();
+        }
+        #[cfg(feature = "sentry")]
+        if let Some(error_reporting) = &self.error_reporting {
+            error_reporting.shutdown();
+        }
+    }
+}
+
+pub fn init(config: &Config) -> Result<LoggingGuard, FlareSyncError> {
+    let filter = env_filter(config)?;
+
+    let redactor = crate::redaction::global().clone();
+    redactor.add(config.secrets());
+    let journald_layer =
+        journald_layer(config)?.map(|layer| RedactingLayer::new(layer, redactor.clone()));
+    let eventlog_layer = eventlog_writer(config)?.map(|writer| {
+        tracing_subscriber::fmt::layer()
+            .without_time()
+            .with_target(false)
+            .with_ansi(false)
+            .with_writer(RedactingMakeWriter::new(writer, redactor.clone()))
+    });
+
+    let writer = match &config.log_file {
+        Some(path) => Some(BoxMakeWriter::new(RedactingMakeWriter::new(
+            Arc::new(RotatingFile::open(path, config.log_rotation.clone())?),
+            redactor.clone(),
+        ))),
+        None if journald_layer.is_some() || eventlog_layer.is_some() => None,
+        None => Some(BoxMakeWriter::new(RedactingMakeWriter::new(
+            std::io::stdout,
+            redactor.clone(),
+        ))),
+    };
+
+    let ansi = config.log_file.is_none() && std::io::stdout().is_terminal();
+    let log_format = match config.log_format {
+        LogFormat::Auto if ansi => LogFormat::Console,
+        log_format => log_format,
+    };
+    let fmt_layer = writer.map(|writer| fmt_layer(log_format, ansi, writer));
+
+    #[cfg(feature = "otel")]
+    let telemetry = config
+        .otlp_endpoint
+        .as_deref()
+        .map(crate::telemetry::Telemetry::init)
+        .transpose()?;
+    #[cfg(feature = "otel")]
+    let otel_layer = telemetry
+        .as_ref()
+        .map(|telemetry| RedactingLayer::new(telemetry.layer(), redactor.clone()));
+    #[cfg(not(feature = "otel"))]
+    let otel_layer: Option<tracing_subscriber::layer::Identity> = None;
+
+    #[cfg(feature = "sentry")]
+    let error_reporting = config
+        .sentry_dsn
+        .as_deref()
+        .map(|dsn| crate::error_reporting::ErrorReporting::init(dsn, redactor.clone()))
+        .transpose()?;
+    #[cfg(feature = "sentry")]
+    let sentry_layer = error_reporting
+        .as_ref()
+        .map(|error_reporting| error_reporting.layer());
+    #[cfg(not(feature = "sentry"))]
+    let sentry_layer: Option<tracing_subscriber::layer::Identity> = None;
+
+    let syslog_layer = match &config.syslog_target {
+        Some(target) => {
+            let writer = SyslogMakeWriter::connect(target.clone(), config.syslog_facility)?;
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .without_time()
+                    .with_level(false)
+                    .with_target(false)
+                    .with_ansi(false)
+                    .with_writer(RedactingMakeWriter::new(writer, redactor.clone())),
+            )
+        }
+        None => None,
+    };
+
+    let quiet_filter = config.log_quiet.then(quiet_filter);
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(quiet_filter)
+        .with(fmt_layer)
+        .with(journald_layer)
+        .with(eventlog_layer)
+        .with(syslog_layer)
+        .with(otel_layer)
+        .with(sentry_layer)
+        .try_init()
+        .map_err(|e| FlareSyncError::Config(format!("Failed to initialize logging: {}", e)))?;
+
+    #[cfg(not(feature = "otel"))]
+    if config.otlp_endpoint.is_some() {
+        tracing::warn!("OTLP_ENDPOINT is set but this build does not include the otel feature");
+    }
+    #[cfg(not(feature = "sentry"))]
+    if config.sentry_dsn.is_some() {
+        tracing::warn!("SENTRY_DSN is set but this build does not include the sentry feature");
+    }
+
+    Ok(LoggingGuard {
+        #[cfg(feature = "otel")]
+        telemetry,
+        #[cfg(feature = "sentry")]
+        error_reporting,
+    })
+}
+
+/// `LOG_LEVEL`, plus Cloudflare request and response bodies with `LOG_HTTP_BODIES`.
+fn env_filter(config: &Config) -> Result<EnvFilter, FlareSyncError> {
+    let mut filter = EnvFilter::try_new(&config.log_level).map_err(|e| {
+        FlareSyncError::Config(format!("Invalid LOG_LEVEL {}: {}", config.log_level, e))
+    })?;
+    if config.log_http_bodies {
+        let directive = format!("{}=debug", crate::cloudflare::HTTP_LOG_TARGET);
+        filter = filter.add_directive(directive.parse().expect("valid log directive"));
+    }
+    Ok(filter)
+}
+
+/// `LOG_QUIET`: keeps warnings, errors, and events on [`EVENTS_TARGET`], and every span
+/// so the kept events still carry their cycle and domain.
+fn quiet_filter() -> FilterFn {
+    tracing_subscriber::filter::filter_fn(|metadata| {
+        metadata.is_span()
+            || *metadata.level() <= tracing::Level::WARN
+            || metadata.target() == EVENTS_TARGET
+    })
+}
+
+/// Writes each event to `writer` as a line in `log_format`. `Auto` writes plain text, so
+/// callers resolve it against the terminal first.
+fn fmt_layer<S>(
+    log_format: LogFormat,
+    ansi: bool,
+    writer: BoxMakeWriter,
+) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    match log_format {
+        LogFormat::Auto | LogFormat::Text => tracing_subscriber::fmt::layer()
+            .with_target(false)
+            .with_ansi(ansi)
+            .with_writer(writer)
+            .boxed(),
+        LogFormat::Console => ConsoleLayer::new(writer, ansi).boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_writer(writer)
+            .boxed(),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn journald_layer(config: &Config) -> Result<Option<tracing_journald::Layer>, FlareSyncError> {
+    let explicit = config.log_journald.is_some();
+    let enabled = config
+        .log_journald
+        .unwrap_or(config.log_file.is_none() && std::env::var_os("JOURNAL_STREAM").is_some());
+    if !enabled {
+        return Ok(None);
+    }
+
+    match tracing_journald::layer() {
+        Ok(layer) => Ok(Some(
+            layer
+                .with_syslog_identifier("flaresync".to_string())
+                .with_field_prefix(Some("FLARESYNC".to_string())),
+        )),
+        Err(e) if explicit => Err(FlareSyncError::Config(format!(
+            "Failed to connect to journald: {}",
+            e
+        ))),
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn journald_layer(
+    config: &Config,
+) -> Result<Option<tracing_subscriber::layer::Identity>, FlareSyncError> {
+    if config.log_journald == Some(true) {
+        return Err(FlareSyncError::Config(
+            "LOG_JOURNALD is only supported on Linux".to_string(),
+        ));
+    }
+    Ok(None)
+}
+
+#[cfg(windows)]
+fn eventlog_writer(config: &Config) -> Result<Option<BoxMakeWriter>, FlareSyncError> {
+    let enabled = config
+        .log_eventlog
+        .unwrap_or(config.log_file.is_none() && crate::win_service::is_running_as_service());
+    if !enabled {
+        return Ok(None);
+    }
+    Ok(Some(BoxMakeWriter::new(
+        crate::eventlog::EventLogMakeWriter::register()?,
+    )))
+}
+
+#[cfg(not(windows))]
+fn eventlog_writer(config: &Config) -> Result<Option<BoxMakeWriter>, FlareSyncError> {
+    if config.log_eventlog == Some(true) {
+        return Err(FlareSyncError::Config(
+            "LOG_EVENTLOG is only supported on Windows".to_string(),
+        ));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{self, Write};
+    use std::sync::Mutex;
+
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Captured {
+        fn make_writer(&self) -> BoxMakeWriter {
+            let sink = self.clone();
+            BoxMakeWriter::new(move || sink.clone())
+        }
+
+        fn lines(&self) -> Vec<String> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(str::to_string)
+                .collect()
+        }
+    }
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn config() -> crate::config::ConfigBuilder {
+        Config::builder()
+            .api_token("test_token")
+            .zone("test_zone_id")
+            .domains(["example.com"])
+    }
+
+    #[test]
+    fn test_json_lines_carry_cycle_and_domain_spans() {
+        let captured = Captured::default();
+        let subscriber = tracing_subscriber::registry().with(fmt_layer(
+            LogFormat::Json,
+            false,
+            captured.make_writer(),
+        ));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _cycle = tracing::info_span!("cycle", cycle = 3, cycle_id = "c0ffee").entered();
+            let _domain = tracing::info_span!("domain", domain = "example.com").entered();
+            tracing::info!("Record is up to date");
+        });
+
+        let lines = captured.lines();
+        assert_eq!(lines.len(), 1);
+        let line: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["fields"]["message"], "Record is up to date");
+        assert_eq!(line["span"]["domain"], "example.com");
+        assert_eq!(line["spans"][0]["name"], "cycle");
+        assert_eq!(line["spans"][0]["cycle"], 3);
+        assert_eq!(line["spans"][0]["cycle_id"], "c0ffee");
+    }
+
+    #[test]
+    fn test_journald_is_only_used_when_enabled_or_detected() {
+        let disabled = config().log_journald(false).build().unwrap();
+        assert!(journald_layer(&disabled).unwrap().is_none());
+
+        let log_file = config().log_file("logs/flaresync.log").build().unwrap();
+        assert!(journald_layer(&log_file).unwrap().is_none());
+
+        #[cfg(not(target_os = "linux"))]
+        assert!(
+            journald_layer(&config().log_journald(true).build().unwrap())
+                .unwrap_err()
+                .to_string()
+                .contains("only supported on Linux")
+        );
+    }
+
+    #[test]
+    fn test_eventlog_is_only_used_when_enabled_on_windows() {
+        let disabled = config().log_eventlog(false).build().unwrap();
+        assert!(eventlog_writer(&disabled).unwrap().is_none());
+
+        #[cfg(not(windows))]
+        {
+            assert!(eventlog_writer(&config().build().unwrap())
+                .unwrap()
+                .is_none());
+            assert!(
+                eventlog_writer(&config().log_eventlog(true).build().unwrap())
+                    .unwrap_err()
+                    .to_string()
+                    .contains("LOG_EVENTLOG is only supported on Windows")
+            );
+        }
+    }
+
+    #[test]
+    fn test_quiet_filter_keeps_events_warnings_and_spans() {
+        let captured = Captured::default();
+        let subscriber = tracing_subscriber::registry()
+            .with(quiet_filter())
+            .with(fmt_layer(LogFormat::Text, false, captured.make_writer()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _domain = tracing::info_span!("domain", domain = "example.com").entered();
+            tracing::info!("Record is up to date");
+            tracing::info!(target: EVENTS_TARGET, "Updated example.com");
+            tracing::warn!("Retrying");
+        });
+
+        let lines = captured.lines();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("domain{domain=\"example.com\"}: Updated example.com"));
+        assert!(lines[1].ends_with("domain{domain=\"example.com\"}: Retrying"));
+    }
+
+    #[test]
+    fn test_env_filter_rejects_invalid_log_level() {
+        assert!(env_filter(&config().log_level("debug").build().unwrap()).is_ok());
+        let err = env_filter(&config().log_level("flaresync=loud").build().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("Invalid LOG_LEVEL flaresync=loud"));
+    }
+
+    #[test]
+    fn test_env_filter_enables_http_bodies_on_request() {
+        let filter = env_filter(&config().build().unwrap()).unwrap();
+        assert!(!filter
+            .to_string()
+            .contains(crate::cloudflare::HTTP_LOG_TARGET));
+
+        let filter = env_filter(&config().log_http_bodies(true).build().unwrap()).unwrap();
+        assert!(filter
+            .to_string()
+            .contains(&format!("{}=debug", crate::cloudflare::HTTP_LOG_TARGET)));
+    }
+
+    #[test]
+    fn test_log_format_from_str() {
+        assert_eq!("json".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert_eq!(" Text ".parse::<LogFormat>().unwrap(), LogFormat::Text);
+        assert_eq!("console".parse::<LogFormat>().unwrap(), LogFormat::Console);
+        assert_eq!(config().build().unwrap().log_format, LogFormat::Auto);
+        assert!(matches!(
+            "xml".parse::<LogFormat>(),
+            Err(FlareSyncError::Config(_))
+        ));
+    }
+}
@@ -1,132 +1,499 @@
-use flaresync::cloudflare::{check_and_update_ip, DnsUpdateStatus};
-use flaresync::config::Config;
+use chrono::Local;
+use clap::{CommandFactory, Parser};
+use flaresync::admin;
+use flaresync::apply;
+use flaresync::backup::{BackupStore, Integrity, Snapshot};
+use flaresync::banner;
+#[cfg(windows)]
+use flaresync::cli::ServiceAction;
+use flaresync::cli::{Cli, Command};
+use flaresync::cloudflare::{
+    apply_record_change, export_zone_file, get_dns_record, list_dns_records, list_zone_a_records,
+    list_zones, pending_change, restore_dns_record, snapshot_dns_records, verify_access, DnsRecord,
+};
+use flaresync::config::{Config, Job, LoadOptions};
+#[cfg(unix)]
+use flaresync::control;
+use flaresync::dashboard;
+use flaresync::doctor::{self, CheckStatus};
+use flaresync::dyndns;
+use flaresync::engine::{prune_backups, record_history, CycleOutcome, FlareSync};
 use flaresync::errors::FlareSyncError;
-use flaresync::ip_provider::get_current_ip;
+use flaresync::exit_code;
+use flaresync::health;
+use flaresync::history::{self, HistoryEntry};
+use flaresync::ip_provider::{check_sources, get_current_ip, quorum_ip};
+use flaresync::launchd;
+use flaresync::lock::{self, InstanceLock, PidFile};
+use flaresync::logging::{self, LoggingGuard, EVENTS_TARGET};
+use flaresync::metrics;
+use flaresync::notify::{NotificationEvent, Notifications};
+use flaresync::rate_limit;
+use flaresync::setup;
 use flaresync::status::RuntimeStatus;
-use log::{error, info, warn};
+use flaresync::systemd::{self, UnitOptions};
+use flaresync::wake_signal::WakeSignal;
+#[cfg(windows)]
+use flaresync::win_service;
 use reqwest::Client as ReqwestClient;
-use std::net::Ipv4Addr;
+use std::fs::OpenOptions;
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::Path;
 use std::time::Duration;
 use tokio::time;
+use tracing::{info, warn, Instrument};
+
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_secs(5);
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let log_config_path =
-        std::env::var("LOG_CONFIG_PATH").unwrap_or_else(|_| "log4rs.yaml".to_string());
-    log4rs::init_file(&log_config_path, Default::default())?;
+    let cli = Cli::parse();
+    let load_options = LoadOptions {
+        config_path: cli.config.clone(),
+        profile: cli.profile.clone(),
+        strict: cli.strict,
+    };
 
-    let config = Config::from_env()?;
-
-    let client = ReqwestClient::builder()
-        .timeout(Duration::from_secs(30))
-        .build()?;
-
-    info!("FlareSync started");
-    let mut status = RuntimeStatus::new();
-    write_status(&status, &config);
-
-    loop {
-        let current_ip = match wait_for_ip_or_shutdown(&client).await {
-            IpCheckOutcome::Shutdown => {
-                info!("Shutdown signal received. Exiting.");
-                status.mark_shutting_down();
-                write_status(&status, &config);
-                break;
-            }
-            IpCheckOutcome::Complete(Ok(ip)) => ip,
-            IpCheckOutcome::Complete(Err(e)) => {
-                error!("Failed to get current IP: {}. Retrying in 1 minute.", e);
-                status.mark_ip_check_error(&e);
-                write_status(&status, &config);
-                if sleep_or_shutdown(Duration::from_secs(60)).await {
-                    info!("Shutdown signal received. Exiting.");
-                    status.mark_shutting_down();
-                    write_status(&status, &config);
-                    break;
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => {
+            if let Err(e) = run(Config::load_jobs(&load_options)?).await {
+                eprintln!("Error: {}", e);
+                let code = e
+                    .downcast_ref::<FlareSyncError>()
+                    .map_or(exit_code::FAILURE, exit_code::for_error);
+                std::process::exit(code.into());
+            }
+            Ok(())
+        }
+        Command::Update { force } => {
+            let code = match load_config(&load_options) {
+                Ok(config) => update_once(config, force).await,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    exit_code::for_error(&e)
                 }
-                continue;
+            };
+            std::process::exit(code.into())
+        }
+        Command::Init {
+            output,
+            token_file,
+            force,
+        } => Ok(init_config(&output, &token_file, force).await?),
+        Command::MigrateConfig {
+            output,
+            token_file,
+            force,
+        } => Ok(migrate_config(&output, &token_file, force)?),
+        Command::History { domain, limit } => {
+            let config = load_config(&load_options)?;
+            Ok(print_history(&config, domain.as_deref(), limit)?)
+        }
+        Command::Status { json } => {
+            let config = load_config(&load_options)?;
+            Ok(print_status(&config, json)?)
+        }
+        Command::Watch { events } => {
+            let config = load_config(&load_options)?;
+            watch_dashboard(&config, events).await;
+            Ok(())
+        }
+        Command::Stats => {
+            let config = load_config(&load_options)?;
+            Ok(print_stats(&config)?)
+        }
+        Command::Backup => {
+            let config = load_config(&load_options)?;
+            Ok(backup_records(&config).await?)
+        }
+        Command::Restore {
+            file,
+            latest,
+            domain,
+            dry_run,
+            allow_other_zone,
+        } => {
+            let config = load_config(&load_options)?;
+            Ok(restore_backups(
+                &config,
+                file.as_deref(),
+                latest,
+                domain.as_deref(),
+                dry_run,
+                allow_other_zone,
+            )
+            .await?)
+        }
+        Command::Rollback {
+            domain,
+            dry_run,
+            force,
+        } => {
+            let config = load_config(&load_options)?;
+            Ok(rollback_updates(&config, domain.as_deref(), dry_run, force).await?)
+        }
+        Command::PruneBackups { dry_run } => {
+            let config = load_config(&load_options)?;
+            Ok(print_pruned_backups(&config, dry_run)?)
+        }
+        Command::VerifyBackups => {
+            let config = load_config(&load_options)?;
+            Ok(print_backup_verification(&config)?)
+        }
+        Command::TestNotify { channel } => {
+            let config = load_config(&load_options)?;
+            Ok(test_notifications(&config, channel.as_deref()).await?)
+        }
+        Command::Verify { json } => {
+            let config = load_config(&load_options)?;
+            Ok(print_verification(&config, json).await?)
+        }
+        Command::Completions { shell } => {
+            clap_complete::generate(
+                shell,
+                &mut Cli::command(),
+                "flaresync",
+                &mut std::io::stdout(),
+            );
+            Ok(())
+        }
+        Command::Doctor => {
+            let config = load_config(&load_options)?;
+            Ok(print_doctor(&config).await?)
+        }
+        Command::Export { output, force } => {
+            let config = load_config(&load_options)?;
+            Ok(export_zone(&config, output.as_deref(), force).await?)
+        }
+        Command::Import { file, dry_run, yes } => {
+            let config = load_config(&load_options)?;
+            Ok(import_records(&config, &file, dry_run, yes).await?)
+        }
+        Command::Install {
+            timer,
+            launchd,
+            interval,
+            unit_dir,
+            user,
+            dry_run,
+            force,
+        } => {
+            let options = UnitOptions {
+                executable: std::env::current_exe()?,
+                working_directory: std::env::current_dir()?,
+                config: cli.config.as_deref().map(std::path::absolute).transpose()?,
+                profile: cli.profile.clone(),
+                user,
+                timer_minutes: timer.then_some(interval),
+            };
+            if launchd {
+                let unit_dir = unit_dir.unwrap_or_else(|| launchd::DAEMON_DIR.into());
+                Ok(install_plist(&options, &unit_dir, dry_run, force)?)
+            } else {
+                let unit_dir = unit_dir.unwrap_or_else(|| "/etc/systemd/system".into());
+                Ok(install_units(&options, &unit_dir, dry_run, force)?)
             }
-        };
-        info!("Current public IP: {}", current_ip);
-        status.mark_ip_check_success(&current_ip);
-        write_status(&status, &config);
-
-        let mut shutting_down = false;
-        for domain_name in &config.domain_names {
-            let update_outcome = tokio::select! {
-                result = check_and_update_ip(
-                    &client,
-                    &config.api_token,
-                    &config.zone_id,
-                    domain_name,
-                    &current_ip,
-                ) => DomainUpdateOutcome::Complete(result),
-                _ = shutdown_signal() => DomainUpdateOutcome::Shutdown,
+        }
+        #[cfg(windows)]
+        Command::Service { action } => windows_service(
+            action,
+            cli.config.as_deref(),
+            cli.profile.as_deref(),
+            cli.strict,
+            load_options,
+        ),
+        Command::Healthcheck { url } => {
+            let code = match healthcheck(&load_options, url.as_deref()).await {
+                Ok(()) => {
+                    println!("healthy");
+                    0
+                }
+                Err(reason) => {
+                    eprintln!("unhealthy: {}", reason);
+                    1
+                }
             };
+            std::process::exit(code)
+        }
+        #[cfg(unix)]
+        Command::Control { command, domain } => {
+            let config = load_config(&load_options)?;
+            Ok(send_control_command(&config, &control::ControlRequest { command, domain }).await?)
+        }
+        Command::Ip { verbose, json } => Ok(print_ip(verbose, json).await?),
+        Command::Diff { json } => {
+            let config = load_config(&load_options)?;
+            Ok(print_diff(&config, json).await?)
+        }
+        Command::List { json } => {
+            let config = load_config(&load_options)?;
+            Ok(print_records(&config, json).await?)
+        }
+    }
+}
 
-            match update_outcome {
-                DomainUpdateOutcome::Complete(Ok(update_status)) => {
-                    match update_status {
-                        DnsUpdateStatus::Updated => {
-                            info!("IP address updated successfully for {}", domain_name);
-                            status.mark_domain_result(domain_name, "updated", true);
-                        }
-                        DnsUpdateStatus::Unchanged => {
-                            info!("No update needed for {}", domain_name);
-                            status.mark_domain_result(domain_name, "unchanged", false);
-                        }
-                        DnsUpdateStatus::Missing => {
-                            info!("No matching DNS record found for {}", domain_name);
-                            status.mark_domain_result(domain_name, "missing", false);
-                        }
-                    }
-                    write_status(&status, &config);
-                }
-                DomainUpdateOutcome::Complete(Err(e)) => {
-                    error!("Failed to check or update IP for {}: {}", domain_name, e);
-                    status.mark_domain_error(domain_name, &e);
-                    write_status(&status, &config);
+/// Loads the configuration and applies its process-wide Cloudflare rate limit.
+fn load_config(load_options: &LoadOptions) -> Result<Config, FlareSyncError> {
+    let config = Config::load(load_options)?;
+    rate_limit::configure(config.cloudflare_rate_limit);
+    Ok(config)
+}
+
+fn init_runtime(config: Config) -> Result<(LoggingGuard, FlareSync), FlareSyncError> {
+    let logging_guard = logging::init(&config)?;
+    if let Some(statsd) = &config.statsd {
+        metrics::init_statsd(&statsd.address, &statsd.prefix, statsd.format)?;
+    }
+    Ok((logging_guard, FlareSync::new(config)?))
+}
+
+async fn update_once(config: Config, force: bool) -> u8 {
+    let (_logging_guard, engine) = match init_runtime(config) {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return exit_code::for_error(&e);
+        }
+    };
+    let lock_file_path = &engine.config().lock_file_path;
+    let _lock = match InstanceLock::try_acquire(lock_file_path) {
+        Ok(Some(lock)) => lock,
+        Ok(None) => {
+            warn!(
+                target: EVENTS_TARGET,
+                "Another FlareSync instance is running ({}). Skipping this update.",
+                lock::describe_holder(lock_file_path)
+            );
+            return exit_code::LOCKED;
+        }
+        Err(e) => {
+            eprintln!("Error: Failed to lock {}: {}", lock_file_path.display(), e);
+            return exit_code::for_error(&e);
+        }
+    };
+    let handle = engine.clone();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        handle.shutdown();
+    });
+
+    match engine.run_once(force).await {
+        CycleOutcome::Complete { exit_code } => exit_code,
+        CycleOutcome::IpCheckFailed => exit_code::NETWORK_ERROR,
+        CycleOutcome::Shutdown => {
+            eprintln!("Interrupted before the update finished");
+            exit_code::INTERRUPTED
+        }
+    }
+}
+
+async fn run(jobs: Vec<Job>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut jobs = jobs.into_iter();
+    let Some(first) = jobs.next() else {
+        return Err(FlareSyncError::Config("No sync jobs configured".to_string()).into());
+    };
+    let (_logging_guard, engine) = init_runtime(first.config)?;
+    let mut engines = vec![(first.name, first.load_options, engine)];
+    for job in jobs {
+        engines.push((job.name, job.load_options, FlareSync::new(job.config)?));
+    }
+    for (name, load_options, engine) in &engines {
+        let _span = job_span(name.as_deref()).entered();
+        banner::log(&engine.config(), &load_options.describe());
+    }
+    let pid_file_path = &engines[0].2.config().pid_file_path;
+    let Some(_pid_file) = PidFile::try_acquire(pid_file_path)? else {
+        return Err(FlareSyncError::AlreadyRunning(format!(
+            "another daemon uses this state directory ({})",
+            lock::describe_holder(pid_file_path)
+        ))
+        .into());
+    };
+    let Some(_lock) = wait_for_lock(&engines[0].2.config().lock_file_path).await? else {
+        info!(target: EVENTS_TARGET, "Shutdown signal received. Exiting.");
+        return Ok(());
+    };
+    for (name, load_options, engine) in &engines {
+        serve_endpoints(engine, load_options.clone())
+            .instrument(job_span(name.as_deref()))
+            .await?;
+    }
+    let mut wake_signal = WakeSignal::install()?;
+    let handles: Vec<FlareSync> = engines
+        .iter()
+        .map(|(_, _, engine)| engine.clone())
+        .collect();
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                signal = wake_signal.recv() => {
+                    info!(target: EVENTS_TARGET, "{} received. Checking now.", signal);
+                    handles.iter().for_each(FlareSync::check_now);
                 }
-                DomainUpdateOutcome::Shutdown => {
-                    info!("Shutdown signal received. Exiting.");
-                    status.mark_shutting_down();
-                    write_status(&status, &config);
-                    shutting_down = true;
+                _ = shutdown_signal() => {
+                    handles.iter().for_each(FlareSync::shutdown);
                     break;
                 }
             }
         }
+    });
 
-        if shutting_down {
-            break;
+    let mut tasks = tokio::task::JoinSet::new();
+    for (name, _, engine) in &engines {
+        let engine = engine.clone();
+        tasks.spawn(async move { engine.run().await }.instrument(job_span(name.as_deref())));
+    }
+    let mut result = Ok(());
+    while let Some(joined) = tasks.join_next().await {
+        let outcome = joined
+            .map_err(|e| FlareSyncError::Io(std::io::Error::other(format!("Job panicked: {}", e))))
+            .and_then(|outcome| outcome);
+        if let Err(e) = outcome {
+            if result.is_ok() && engines.len() > 1 {
+                tracing::error!("A job stopped: {}. Stopping the other jobs.", e);
+                engines.iter().for_each(|(_, _, engine)| engine.shutdown());
+            }
+            result = result.and(Err(e));
         }
-
-        info!("Waiting for {:?} before next check", config.update_interval);
-        if sleep_or_shutdown(config.update_interval).await {
-            info!("Shutdown signal received. Exiting.");
-            status.mark_shutting_down();
-            write_status(&status, &config);
-            break;
+    }
+    #[cfg(unix)]
+    for (_, _, engine) in &engines {
+        if let Some(path) = &engine.config().control_socket {
+            control::remove(path);
         }
     }
+    Ok(result?)
+}
 
+/// The span that tags a job's log lines with its name when `FLARESYNC_JOBS` is in use.
+fn job_span(name: Option<&str>) -> tracing::Span {
+    match name {
+        Some(name) => tracing::info_span!("job", job = %name),
+        None => tracing::Span::none(),
+    }
+}
+
+/// Starts the control socket, admin API, and DynDNS2 server that `engine`'s configuration
+/// enables.
+async fn serve_endpoints(
+    engine: &FlareSync,
+    load_options: LoadOptions,
+) -> Result<(), FlareSyncError> {
+    let config = engine.config();
+    if let Some(path) = &config.control_socket {
+        serve_control_socket(path, engine, load_options.clone())?;
+    }
+    if let Some(admin) = &config.admin {
+        let listener = tokio::net::TcpListener::bind(admin.bind_address).await?;
+        info!(
+            target: EVENTS_TARGET,
+            "Admin API listening on {}",
+            admin.bind_address
+        );
+        let engine = engine.clone();
+        let token = admin.token.clone();
+        tokio::spawn(async move {
+            if let Err(e) = admin::serve(listener, engine, load_options, &token).await {
+                tracing::error!("Admin API stopped: {}", e);
+            }
+        });
+    }
+    if let Some(dyndns) = config.dyndns.clone() {
+        let listener = tokio::net::TcpListener::bind(dyndns.bind_address).await?;
+        info!(
+            target: EVENTS_TARGET,
+            "DynDNS2 server listening on {}",
+            dyndns.bind_address
+        );
+        let engine = engine.clone();
+        tokio::spawn(async move {
+            if let Err(e) = dyndns::serve(listener, engine, dyndns).await {
+                tracing::error!("DynDNS2 server stopped: {}", e);
+            }
+        });
+    }
     Ok(())
 }
 
-enum IpCheckOutcome {
-    Complete(Result<Ipv4Addr, FlareSyncError>),
-    Shutdown,
+#[cfg(unix)]
+fn serve_control_socket(
+    path: &Path,
+    engine: &FlareSync,
+    load_options: LoadOptions,
+) -> Result<(), FlareSyncError> {
+    let listener = control::bind(path)?;
+    info!(
+        target: EVENTS_TARGET,
+        "Control socket listening on {}",
+        path.display()
+    );
+    let engine = engine.clone();
+    tokio::spawn(async move {
+        if let Err(e) = control::serve(listener, engine, load_options).await {
+            tracing::error!("Control socket stopped: {}", e);
+        }
+    });
+    Ok(())
 }
 
-enum DomainUpdateOutcome {
-    Complete(Result<DnsUpdateStatus, FlareSyncError>),
-    Shutdown,
+#[cfg(not(unix))]
+fn serve_control_socket(
+    _path: &Path,
+    _engine: &FlareSync,
+    _load_options: LoadOptions,
+) -> Result<(), FlareSyncError> {
+    Err(FlareSyncError::Config(
+        "CONTROL_SOCKET is only supported on Unix".to_string(),
+    ))
 }
 
-async fn wait_for_ip_or_shutdown(client: &ReqwestClient) -> IpCheckOutcome {
-    tokio::select! {
-        result = get_current_ip(client) => IpCheckOutcome::Complete(result),
-        _ = shutdown_signal() => IpCheckOutcome::Shutdown,
+#[cfg(unix)]
+async fn send_control_command(
+    config: &Config,
+    request: &control::ControlRequest,
+) -> Result<(), FlareSyncError> {
+    let path = config
+        .control_socket
+        .as_deref()
+        .ok_or_else(|| FlareSyncError::Config("CONTROL_SOCKET is not set".to_string()))?;
+    let response = control::request(path, request).await?;
+    if let Some(status) = &response.status {
+        println!("{}", serde_json::to_string_pretty(status)?);
+    }
+    match response.message {
+        Some(message) if !response.ok => Err(FlareSyncError::Io(std::io::Error::other(message))),
+        Some(message) => {
+            println!("{}", message);
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Waits until no other instance holds the lock, so a daemon started while a one-shot
+/// update is still running takes over once it finishes. Returns `None` on shutdown.
+async fn wait_for_lock(path: &Path) -> Result<Option<InstanceLock>, FlareSyncError> {
+    let mut warned = false;
+    loop {
+        if let Some(lock) = InstanceLock::try_acquire(path)? {
+            if warned {
+                info!(target: EVENTS_TARGET, "Acquired {}. Starting.", path.display());
+            }
+            return Ok(Some(lock));
+        }
+        if !warned {
+            warn!(
+                target: EVENTS_TARGET,
+                "Another FlareSync instance is running ({}). Waiting for it to exit.",
+                lock::describe_holder(path)
+            );
+            warned = true;
+        }
+        if sleep_or_shutdown(LOCK_RETRY_INTERVAL).await {
+            return Ok(None);
+        }
     }
 }
 
@@ -137,14 +504,971 @@ async fn sleep_or_shutdown(duration: Duration) -> bool {
     }
 }
 
-fn write_status(status: &RuntimeStatus, config: &Config) {
-    if let Err(e) = status.write_to_path(&config.status_file_path) {
-        warn!(
-            "Failed to write status file {}: {}",
+fn migrate_config(output: &Path, token_file: &Path, force: bool) -> Result<(), FlareSyncError> {
+    let migration = Config::migrate_env_to_toml(token_file)?;
+
+    for (path, secret) in &migration.secret_files {
+        write_new_file(path, secret.as_bytes(), force, 0o600)?;
+        println!("Wrote secret to {}", path.display());
+    }
+    write_new_file(output, migration.config_toml.as_bytes(), force, 0o600)?;
+    println!("Wrote config file to {}", output.display());
+    Ok(())
+}
+
+fn prompt(question: &str) -> Result<String, FlareSyncError> {
+    print!("{}", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
+
+async fn init_config(output: &Path, token_file: &Path, force: bool) -> Result<(), FlareSyncError> {
+    for path in [output, token_file] {
+        if path.exists() && !force {
+            return Err(FlareSyncError::Config(format!(
+                "{} already exists; pass --force to overwrite it",
+                path.display()
+            )));
+        }
+    }
+
+    let api_token = prompt("Cloudflare API token: ")?;
+    if api_token.is_empty() {
+        return Err(FlareSyncError::Config("No API token given".to_string()));
+    }
+    let client = ReqwestClient::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+
+    let zones = list_zones(&client, &api_token).await?;
+    let zone = match zones.len() {
+        0 => {
+            return Err(FlareSyncError::Config(
+                "The token cannot see any zones; it needs Zone:Read and DNS:Edit".to_string(),
+            ))
+        }
+        1 => &zones[0],
+        _ => {
+            for (index, zone) in zones.iter().enumerate() {
+                println!("{:>3}. {}", index + 1, zone.name);
+            }
+            let selection = prompt(&format!("Zone [1-{}]: ", zones.len()))?;
+            match setup::parse_selection(&selection, zones.len())?.as_slice() {
+                [index] => &zones[*index],
+                _ => {
+                    return Err(FlareSyncError::Config(
+                        "Select exactly one zone".to_string(),
+                    ))
+                }
+            }
+        }
+    };
+    println!("Using zone {} ({})", zone.name, zone.id);
+
+    let records = list_zone_a_records(&client, &api_token, &zone.id).await?;
+    let domains: Vec<String> = if records.is_empty() {
+        let names =
+            prompt("No A records found; domains to create records for (comma-separated): ")?;
+        names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect()
+    } else {
+        for (index, record) in records.iter().enumerate() {
+            println!("{:>3}. {} -> {}", index + 1, record.name, record.content);
+        }
+        let selection = prompt("Domains to keep in sync (e.g. 1,3-4): ")?;
+        setup::parse_selection(&selection, records.len())?
+            .into_iter()
+            .map(|index| records[index].name.clone())
+            .collect()
+    };
+    if domains.is_empty() {
+        return Err(FlareSyncError::Config("No domains selected".to_string()));
+    }
+
+    let config_toml = setup::config_toml(token_file, &zone.id, &domains)?;
+    write_new_file(token_file, api_token.as_bytes(), force, 0o600)?;
+    println!("Wrote API token to {}", token_file.display());
+    write_new_file(output, config_toml.as_bytes(), force, 0o644)?;
+    println!("Wrote config file to {}", output.display());
+    println!(
+        "Check it with `flaresync --config {} doctor`",
+        output.display()
+    );
+    Ok(())
+}
+
+fn write_new_file(
+    path: &Path,
+    contents: &[u8],
+    overwrite: bool,
+    #[cfg_attr(not(unix), allow(unused_variables))] mode: u32,
+) -> Result<(), FlareSyncError> {
+    let mut open_options = OpenOptions::new();
+    open_options.write(true);
+    if overwrite {
+        open_options.create(true).truncate(true);
+    } else {
+        open_options.create_new(true);
+    }
+    #[cfg(unix)]
+    open_options.mode(mode);
+
+    let mut file = open_options.open(path).map_err(|e| {
+        FlareSyncError::Config(format!("Failed to write {}: {}", path.display(), e))
+    })?;
+    // An overwritten file keeps its old mode otherwise.
+    #[cfg(unix)]
+    file.set_permissions(std::fs::Permissions::from_mode(mode))?;
+    file.write_all(contents)?;
+    Ok(())
+}
+
+fn print_history(
+    config: &Config,
+    domain: Option<&str>,
+    limit: usize,
+) -> Result<(), FlareSyncError> {
+    let entries = history::load(config)?;
+    let matching = history::query(&entries, domain, limit);
+    if matching.is_empty() {
+        println!(
+            "No recorded updates in {}",
+            history::location(config).display()
+        );
+        return Ok(());
+    }
+
+    for entry in matching {
+        let change = match &entry.old_ip {
+            Some(old_ip) => format!("{} -> {}", old_ip, entry.new_ip),
+            None => format!("-> {}", entry.new_ip),
+        };
+        match &entry.error {
+            Some(error) => println!(
+                "{}  {}  {}  {}: {}",
+                entry.timestamp, entry.domain, change, entry.result, error
+            ),
+            None => println!(
+                "{}  {}  {}  {}",
+                entry.timestamp, entry.domain, change, entry.result
+            ),
+        }
+    }
+    Ok(())
+}
+
+fn read_status(config: &Config) -> Result<RuntimeStatus, FlareSyncError> {
+    RuntimeStatus::read_from_path(&config.status_file_path).map_err(|e| {
+        FlareSyncError::Config(format!(
+            "Failed to read status file {}: {}",
+            config.status_file_path.display(),
+            e
+        ))
+    })
+}
+
+fn print_status(config: &Config, json: bool) -> Result<(), FlareSyncError> {
+    let status = read_status(config)?.for_domains(&config.domain_names);
+    let entries = history::load(config).unwrap_or_default();
+    let errors: Vec<&HistoryEntry> = entries
+        .iter()
+        .rev()
+        .filter(|entry| entry.error.is_some())
+        .take(5)
+        .collect();
+
+    if json {
+        let value = status.report_json(&errors)?;
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+
+    print!("{}", status.render_report(&config.domain_names, &errors));
+    Ok(())
+}
+
+async fn watch_dashboard(config: &Config, events: usize) {
+    let ansi = std::io::IsTerminal::is_terminal(&std::io::stdout());
+    loop {
+        let frame = match RuntimeStatus::read_from_path(&config.status_file_path) {
+            Ok(status) => {
+                let history = history::load(config).unwrap_or_default();
+                dashboard::render(
+                    &status,
+                    &config.domain_names,
+                    &history,
+                    config.cycle_interval(),
+                    events,
+                    Local::now(),
+                    ansi,
+                )
+            }
+            Err(e) => format!(
+                "Waiting for status file {}: {}\n",
+                config.status_file_path.display(),
+                e
+            ),
+        };
+        print!("{}{}", dashboard::CLEAR_SCREEN, frame);
+        let _ = std::io::stdout().flush();
+        if sleep_or_shutdown(Duration::from_secs(1)).await {
+            break;
+        }
+    }
+}
+
+fn print_stats(config: &Config) -> Result<(), FlareSyncError> {
+    let status = read_status(config)?;
+    let stats = &status.stats;
+    println!("Started at:             {}", status.started_at);
+    println!("Last update:            {}", status.updated_at);
+    println!("Uptime:                 {}s", stats.uptime_seconds);
+    println!("Total cycles:           {}", stats.total_cycles);
+    println!("Successful cycles:      {}", stats.successful_cycles);
+    println!("Consecutive failures:   {}", stats.consecutive_failures);
+    println!("Total updates:          {}", stats.total_updates);
+    println!(
+        "Average cycle duration: {}ms",
+        stats.average_cycle_duration_ms
+    );
+    println!("Overlapping checks:     {}", stats.overlapping_checks);
+    Ok(())
+}
+
+async fn backup_records(config: &Config) -> Result<(), FlareSyncError> {
+    let client = ReqwestClient::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let backups = BackupStore::from_config(config)
+        .resolve_zone_name(&client, &config.api_token)
+        .await;
+    let mut total = 0;
+    for domain_name in &config.domain_names {
+        let records = snapshot_dns_records(
+            &client,
+            &config.api_token,
+            &config.zone_id,
+            domain_name,
+            &backups,
+        )
+        .await?;
+        if records.is_empty() {
+            println!("No A record found for {}", domain_name);
+        }
+        for record in &records {
+            println!(
+                "Backed up {} {} ({})",
+                record.record_type, record.name, record.content
+            );
+        }
+        total += records.len();
+    }
+    prune_backups(config);
+    println!(
+        "{} records backed up to {}",
+        total,
+        config.backup_dir.display()
+    );
+    Ok(())
+}
+
+async fn restore_backups(
+    config: &Config,
+    file: Option<&Path>,
+    latest: bool,
+    domain: Option<&str>,
+    dry_run: bool,
+    allow_other_zone: bool,
+) -> Result<(), FlareSyncError> {
+    let backups = BackupStore::from_config(config);
+    let mut snapshots = Vec::new();
+    if let Some(file) = file {
+        let document = backups.read(file)?;
+        snapshots.push(Snapshot {
+            source: file.display().to_string(),
+            saved_at: None,
+            zone: document.zone,
+            record: document.record,
+        });
+    }
+    if latest {
+        let domains: Vec<&str> = match domain {
+            Some(domain) => vec![domain],
+            None => config.domain_names.iter().map(String::as_str).collect(),
+        };
+        for domain in domains {
+            match backups.latest(domain)? {
+                Some(snapshot) => snapshots.push(snapshot),
+                None => println!("No backup found for {}", domain),
+            }
+        }
+    }
+    for snapshot in &snapshots {
+        snapshot.check_zone(&config.zone_id, allow_other_zone)?;
+    }
+
+    let client = ReqwestClient::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let backups = backups.resolve_zone_name(&client, &config.api_token).await;
+    for Snapshot {
+        source,
+        zone,
+        record,
+        ..
+    } in snapshots
+    {
+        // A backup that names its zone goes back there; check_zone only lets another zone
+        // than ZONE_ID through with --allow-other-zone.
+        let (zone_id, backups) = match zone {
+            Some(zone) => (zone.id.clone(), backups.clone().zone(Some(zone))),
+            None => (config.zone_id.clone(), backups.clone()),
+        };
+        if zone_id != config.zone_id {
+            println!(
+                "Restoring {} into zone {}, not the configured zone {}",
+                record.name,
+                backups
+                    .zone
+                    .as_ref()
+                    .and_then(|zone| zone.name.as_deref())
+                    .unwrap_or(&zone_id),
+                config.zone_id
+            );
+        }
+        let current = restore_dns_record(
+            &client,
+            &config.api_token,
+            &zone_id,
+            &record,
+            &backups,
+            dry_run,
+        )
+        .await?;
+        if !dry_run {
+            prune_backups(config);
+        }
+        println!(
+            "{}{} {} ({}): {} ttl={} proxied={} -> {} ttl={} proxied={}",
+            if dry_run { "[dry run] " } else { "" },
+            record.record_type,
+            record.name,
+            source,
+            current.content,
+            current.ttl,
+            current.proxied,
+            record.content,
+            record.ttl,
+            record.proxied
+        );
+    }
+    Ok(())
+}
+
+async fn rollback_updates(
+    config: &Config,
+    domain: Option<&str>,
+    dry_run: bool,
+    force: bool,
+) -> Result<(), FlareSyncError> {
+    let domains: Vec<&str> = match domain {
+        Some(domain) => vec![domain],
+        None => config.domain_names.iter().map(String::as_str).collect(),
+    };
+    let entries = history::load(config)?;
+    let client = ReqwestClient::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let backups = BackupStore::from_config(config)
+        .resolve_zone_name(&client, &config.api_token)
+        .await;
+
+    for domain in domains {
+        let Some(update) = history::last_update(&entries, domain) else {
+            println!("{}: no update to roll back", domain);
+            continue;
+        };
+        let Some(old_ip) = update.old_ip.as_deref() else {
+            println!(
+                "{}: the last update has no previous IP to roll back to",
+                domain
+            );
+            continue;
+        };
+        let Some(current) =
+            get_dns_record(&client, &config.api_token, &config.zone_id, domain).await?
+        else {
+            println!("{}: no A record found", domain);
+            continue;
+        };
+        if current.content != update.new_ip && !force {
+            println!(
+                "{}: record is {} but the update at {} set {}; skipping (use --force)",
+                domain, current.content, update.timestamp, update.new_ip
+            );
+            continue;
+        }
+
+        // Prefer the backup taken just before the update; otherwise only the content is reverted.
+        let target = match backups.latest_with_content(domain, old_ip)? {
+            Some(record) => record,
+            None => DnsRecord {
+                content: old_ip.to_string(),
+                ..current.clone()
+            },
+        };
+        restore_dns_record(
+            &client,
+            &config.api_token,
+            &config.zone_id,
+            &target,
+            &backups,
+            dry_run,
+        )
+        .await?;
+        if !dry_run {
+            record_history(
+                config,
+                &HistoryEntry::rolled_back(domain, &current.content, old_ip),
+            );
+            prune_backups(config);
+        }
+        println!(
+            "{}{}: {} -> {} (update at {})",
+            if dry_run { "[dry run] " } else { "" },
+            domain,
+            current.content,
+            target.content,
+            update.timestamp
+        );
+    }
+    Ok(())
+}
+
+fn print_pruned_backups(config: &Config, dry_run: bool) -> Result<(), FlareSyncError> {
+    if !config.backup_retention.is_enabled() {
+        println!("No backup retention configured; set BACKUP_KEEP or BACKUP_MAX_AGE_DAYS");
+        return Ok(());
+    }
+    let removed = BackupStore::from_config(config).prune(config.backup_retention, dry_run)?;
+    for backup in &removed {
+        println!(
+            "{} {}",
+            if dry_run { "Would delete" } else { "Deleted" },
+            backup
+        );
+    }
+    println!(
+        "{} backups {}",
+        removed.len(),
+        if dry_run { "would be pruned" } else { "pruned" }
+    );
+    Ok(())
+}
+
+fn print_backup_verification(config: &Config) -> Result<(), FlareSyncError> {
+    let checks = BackupStore::from_config(config).verify()?;
+    let mut failures = 0;
+    for check in &checks {
+        match &check.integrity {
+            Integrity::Verified => println!("OK    {}", check.source),
+            Integrity::Unchecked => println!("WARN  {}: no checksum", check.source),
+            Integrity::Failed(problem) => {
+                failures += 1;
+                println!("FAIL  {}: {}", check.source, problem);
+            }
+        }
+    }
+    println!("{} backups checked, {} failed", checks.len(), failures);
+    if failures > 0 {
+        return Err(FlareSyncError::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "{} of {} backups failed verification",
+                failures,
+                checks.len()
+            ),
+        )));
+    }
+    Ok(())
+}
+
+async fn test_notifications(config: &Config, channel: Option<&str>) -> Result<(), FlareSyncError> {
+    let channels: Vec<_> = config
+        .notification_channels
+        .iter()
+        .filter(|candidate| channel.is_none_or(|name| candidate.notifier.name() == name))
+        .cloned()
+        .collect();
+    if channels.is_empty() {
+        println!("No notification channels configured");
+        return Ok(());
+    }
+
+    let client = ReqwestClient::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let notifications = Notifications::new(
+        channels,
+        config.notification_templates.clone(),
+        0,
+        config.notification_mode,
+    );
+    let event =
+        NotificationEvent::updated(&config.domain_names[0], "192.0.2.1", "192.0.2.2", false);
+    let results = notifications.send_test(&client, &event).await;
+
+    let mut failures = 0;
+    for (name, result) in &results {
+        match result {
+            Ok(()) => println!("OK    {}", name),
+            Err(e) => {
+                failures += 1;
+                println!("FAIL  {}: {}", name, e);
+            }
+        }
+    }
+    if failures > 0 {
+        return Err(FlareSyncError::Notification(format!(
+            "{} of {} channels failed",
+            failures,
+            results.len()
+        )));
+    }
+    Ok(())
+}
+
+async fn print_verification(config: &Config, json: bool) -> Result<(), FlareSyncError> {
+    let client = ReqwestClient::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let checks = verify_access(
+        &client,
+        &config.api_token,
+        &config.zone_id,
+        &config.domain_names,
+    )
+    .await?;
+
+    let failures = checks
+        .iter()
+        .filter(|check| check.problem.is_some())
+        .count();
+    if json {
+        let value = serde_json::json!({ "ok": failures == 0, "checks": checks });
+        println!("{}", serde_json::to_string_pretty(&value)?);
+    } else {
+        for check in &checks {
+            match &check.problem {
+                None => println!("OK    {}", check.name),
+                Some(problem) => println!("FAIL  {}: {}", check.name, problem),
+            }
+        }
+    }
+    if failures > 0 {
+        return Err(FlareSyncError::Cloudflare(format!(
+            "{} of {} checks failed",
+            failures,
+            checks.len()
+        )));
+    }
+    Ok(())
+}
+
+async fn print_doctor(config: &Config) -> Result<(), FlareSyncError> {
+    let client = ReqwestClient::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    println!("FlareSync {}", env!("CARGO_PKG_VERSION"));
+    let checks = doctor::run(&client, config).await;
+    for check in &checks {
+        println!("{}  {}: {}", check.status, check.name, check.detail);
+    }
+
+    let failures = checks
+        .iter()
+        .filter(|check| check.status == CheckStatus::Fail)
+        .count();
+    if failures > 0 {
+        return Err(FlareSyncError::Config(format!(
+            "{} of {} checks failed",
+            failures,
+            checks.len()
+        )));
+    }
+    Ok(())
+}
+
+async fn export_zone(
+    config: &Config,
+    output: Option<&Path>,
+    force: bool,
+) -> Result<(), FlareSyncError> {
+    let client = ReqwestClient::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let zone_file = export_zone_file(&client, &config.api_token, &config.zone_id).await?;
+    match output {
+        Some(path) => {
+            write_new_file(path, zone_file.as_bytes(), force, 0o644)?;
+            println!("Wrote zone {} to {}", config.zone_id, path.display());
+        }
+        None => print!("{}", zone_file),
+    }
+    Ok(())
+}
+
+async fn import_records(
+    config: &Config,
+    file: &Path,
+    dry_run: bool,
+    yes: bool,
+) -> Result<(), FlareSyncError> {
+    let input = std::fs::read_to_string(file)
+        .map_err(|e| FlareSyncError::Config(format!("Failed to read {}: {}", file.display(), e)))?;
+    let is_json = file
+        .extension()
+        .is_some_and(|extension| extension == "json")
+        || input.trim_start().starts_with('[');
+    let records = if is_json {
+        apply::parse_json(&input)?
+    } else {
+        apply::parse_zone_file(&input)?
+    };
+
+    let owned = |name: &str| {
+        config
+            .domain_names
+            .iter()
+            .any(|domain| domain.eq_ignore_ascii_case(name))
+    };
+    let (desired, skipped): (Vec<_>, Vec<_>) =
+        records.into_iter().partition(|record| owned(&record.name));
+    if !skipped.is_empty() {
+        println!(
+            "Skipping {} records for names that are not configured domains",
+            skipped.len()
+        );
+    }
+
+    let client = ReqwestClient::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let mut existing = Vec::new();
+    for domain_name in &config.domain_names {
+        if desired
+            .iter()
+            .any(|record| record.name.eq_ignore_ascii_case(domain_name))
+        {
+            existing.extend(
+                list_dns_records(&client, &config.api_token, &config.zone_id, domain_name).await?,
+            );
+        }
+    }
+
+    let changes = apply::plan(&desired, &existing);
+    if changes.is_empty() {
+        println!("No changes; {} records already match", desired.len());
+        return Ok(());
+    }
+    for change in &changes {
+        println!("{}", change);
+    }
+    if dry_run {
+        return Ok(());
+    }
+    if !yes {
+        let answer = prompt(&format!("Apply {} changes? [y/N] ", changes.len()))?;
+        if !matches!(answer.to_ascii_lowercase().as_str(), "y" | "yes") {
+            println!("Aborted");
+            return Ok(());
+        }
+    }
+
+    let backups = BackupStore::from_config(config)
+        .resolve_zone_name(&client, &config.api_token)
+        .await;
+    for change in &changes {
+        apply_record_change(
+            &client,
+            &config.api_token,
+            &config.zone_id,
+            change,
+            &backups,
+        )
+        .await?;
+    }
+    prune_backups(config);
+    println!("Applied {} changes", changes.len());
+    Ok(())
+}
+
+fn install_units(
+    options: &UnitOptions,
+    unit_dir: &Path,
+    dry_run: bool,
+    force: bool,
+) -> Result<(), FlareSyncError> {
+    let mut units = vec![(systemd::SERVICE_NAME, systemd::service_unit(options))];
+    if let Some(minutes) = options.timer_minutes {
+        units.push((systemd::TIMER_NAME, systemd::timer_unit(minutes)));
+    }
+
+    if dry_run {
+        for (name, contents) in &units {
+            println!("# {}\n{}", unit_dir.join(name).display(), contents);
+        }
+        return Ok(());
+    }
+    for (name, contents) in &units {
+        let path = unit_dir.join(name);
+        write_new_file(&path, contents.as_bytes(), force, 0o644)?;
+        println!("Wrote {}", path.display());
+    }
+    let enable = match options.timer_minutes {
+        Some(_) => systemd::TIMER_NAME,
+        None => systemd::SERVICE_NAME,
+    };
+    println!(
+        "Enable it with: systemctl daemon-reload && systemctl enable --now {}",
+        enable
+    );
+    Ok(())
+}
+
+fn install_plist(
+    options: &UnitOptions,
+    plist_dir: &Path,
+    dry_run: bool,
+    force: bool,
+) -> Result<(), FlareSyncError> {
+    let path = plist_dir.join(launchd::PLIST_NAME);
+    let contents = launchd::plist(options);
+    if dry_run {
+        println!("# {}\n{}", path.display(), contents);
+        return Ok(());
+    }
+    write_new_file(&path, contents.as_bytes(), force, 0o644)?;
+    println!("Wrote {}", path.display());
+    if plist_dir == Path::new(launchd::DAEMON_DIR) {
+        println!(
+            "Load it with: sudo launchctl bootstrap system {}",
+            path.display()
+        );
+    } else {
+        println!(
+            "Load it with: launchctl bootstrap gui/$(id -u) {}",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn windows_service(
+    action: ServiceAction,
+    config: Option<&Path>,
+    profile: Option<&str>,
+    strict: bool,
+    load_options: LoadOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        ServiceAction::Install { start } => {
+            let mut arguments: Vec<std::ffi::OsString> = Vec::new();
+            if let Some(config) = config {
+                arguments.push("--config".into());
+                arguments.push(std::path::absolute(config)?.into_os_string());
+            }
+            if let Some(profile) = profile {
+                arguments.push("--profile".into());
+                arguments.push(profile.into());
+            }
+            if strict {
+                arguments.push("--strict".into());
+            }
+            for argument in ["service", "run", "--working-directory"] {
+                arguments.push(argument.into());
+            }
+            arguments.push(std::env::current_dir()?.into_os_string());
+
+            win_service::install(std::env::current_exe()?, arguments, start)?;
+            println!("Installed the {} service", win_service::SERVICE_NAME);
+            if !start {
+                println!("Start it with: sc.exe start {}", win_service::SERVICE_NAME);
+            }
+            Ok(())
+        }
+        ServiceAction::Uninstall => {
+            win_service::uninstall()?;
+            println!("Removed the {} service", win_service::SERVICE_NAME);
+            Ok(())
+        }
+        ServiceAction::Run { working_directory } => {
+            if let Some(working_directory) = working_directory {
+                std::env::set_current_dir(working_directory)?;
+            }
+            let runtime = tokio::runtime::Handle::current();
+            tokio::task::block_in_place(|| {
+                win_service::run(Box::new(move || {
+                    let jobs = Config::load_jobs(&load_options).map_err(|e| e.to_string())?;
+                    runtime.block_on(run(jobs)).map_err(|e| e.to_string())
+                }))
+            })?;
+            Ok(())
+        }
+    }
+}
+
+async fn healthcheck(load_options: &LoadOptions, url: Option<&str>) -> Result<(), String> {
+    if let Some(url) = url {
+        let client = ReqwestClient::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .map_err(|e| e.to_string())?;
+        return health::probe(&client, url).await;
+    }
+
+    let config = Config::load(load_options).map_err(|e| e.to_string())?;
+    let status = RuntimeStatus::read_from_path(&config.status_file_path).map_err(|e| {
+        format!(
+            "cannot read status file {}: {}",
             config.status_file_path.display(),
             e
+        )
+    })?;
+    health::check_readiness(&status, config.cycle_interval(), Local::now())
+}
+
+async fn print_ip(verbose: bool, json: bool) -> Result<(), FlareSyncError> {
+    let client = ReqwestClient::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let sources = check_sources(&client).await;
+    let ip = quorum_ip(&sources);
+
+    if json {
+        let sources: Vec<_> = sources
+            .iter()
+            .map(|(url, result)| match result {
+                Ok(ip) => serde_json::json!({ "url": url, "ip": ip.to_string() }),
+                Err(e) => serde_json::json!({ "url": url, "error": e.to_string() }),
+            })
+            .collect();
+        let value = serde_json::json!({
+            "ipv4": ip.as_ref().ok().map(ToString::to_string),
+            "sources": sources,
+        });
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return ip.map(|_| ());
+    }
+
+    if verbose {
+        for (url, result) in &sources {
+            match result {
+                Ok(ip) => eprintln!("{:<32} {}", url, ip),
+                Err(e) => eprintln!("{:<32} {}", url, e),
+            }
+        }
+    }
+    println!("{}", ip?);
+    Ok(())
+}
+
+async fn print_diff(config: &Config, json: bool) -> Result<(), FlareSyncError> {
+    let client = ReqwestClient::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let current_ip = get_current_ip(&client).await?;
+
+    let mut changes = Vec::new();
+    let mut missing = Vec::new();
+    for domain_name in &config.domain_names {
+        match get_dns_record(&client, &config.api_token, &config.zone_id, domain_name).await? {
+            Some(record) => changes.extend(pending_change(&record, &current_ip)),
+            None => missing.push(domain_name),
+        }
+    }
+
+    if json {
+        let value = serde_json::json!({
+            "public_ip": current_ip.to_string(),
+            "changes": changes,
+            "missing": missing,
+        });
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+
+    println!("Public IP: {}", current_ip);
+    for change in &changes {
+        println!("{}", change);
+    }
+    for domain_name in &missing {
+        println!("{}: no A record (skipped)", domain_name);
+    }
+    if changes.is_empty() {
+        println!("No changes");
+    } else {
+        println!(
+            "{} of {} records would change",
+            changes.len(),
+            config.domain_names.len()
         );
     }
+    Ok(())
+}
+
+async fn print_records(config: &Config, json: bool) -> Result<(), FlareSyncError> {
+    let client = ReqwestClient::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let mut domains: Vec<(&str, Vec<DnsRecord>)> = Vec::new();
+    for domain_name in &config.domain_names {
+        let records =
+            list_dns_records(&client, &config.api_token, &config.zone_id, domain_name).await?;
+        domains.push((domain_name, records));
+    }
+
+    if json {
+        let domains: Vec<_> = domains
+            .iter()
+            .map(|(domain, records)| serde_json::json!({ "domain": domain, "records": records }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&domains)?);
+        return Ok(());
+    }
+
+    println!(
+        "{:<32} {:<6} {:<40} {:<6} {:<8} MODIFIED",
+        "DOMAIN", "TYPE", "CONTENT", "TTL", "PROXIED"
+    );
+    for (domain, records) in &domains {
+        if records.is_empty() {
+            println!("{:<32} (no records)", domain);
+        }
+        for record in records {
+            let ttl = match record.ttl {
+                1 => "auto".to_string(),
+                ttl => ttl.to_string(),
+            };
+            println!(
+                "{:<32} {:<6} {:<40} {:<6} {:<8} {}",
+                record.name,
+                record.record_type,
+                record.content,
+                ttl,
+                if record.proxied { "yes" } else { "no" },
+                record.modified_on.as_deref().unwrap_or("-")
+            );
+        }
+    }
+    Ok(())
 }
 
 #[cfg(unix)]
@@ -158,7 +1482,20 @@ async fn shutdown_signal() {
     }
 }
 
-#[cfg(not(unix))]
+#[cfg(windows)]
+async fn shutdown_signal() {
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => {
+            // A service may have no console to receive Ctrl+C from.
+            if result.is_err() {
+                win_service::stop_requested().await;
+            }
+        }
+        _ = win_service::stop_requested() => {}
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
 async fn shutdown_signal() {
     let _ = tokio::signal::ctrl_c().await;
 }
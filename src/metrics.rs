@@ -0,0 +1,284 @@
+use crate::errors::FlareSyncError;
+use std::net::UdpSocket;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+static STATSD: OnceLock<StatsdSink> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatsdFormat {
+    #[default]
+    Statsd,
+    DogStatsd,
+}
+
+impl FromStr for StatsdFormat {
+    type Err = FlareSyncError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "statsd" => Ok(StatsdFormat::Statsd),
+            "dogstatsd" => Ok(StatsdFormat::DogStatsd),
+            _ => Err(FlareSyncError::Config(
+                "STATSD_FORMAT must be statsd or dogstatsd".to_string(),
+            )),
+        }
+    }
+}
+
+struct StatsdSink {
+    socket: UdpSocket,
+    prefix: String,
+    format: StatsdFormat,
+}
+
+impl StatsdSink {
+    fn line(&self, name: &str, value: &str, kind: &str, tags: &[(&str, &str)]) -> String {
+        let name = format!("{}{}", self.prefix, name);
+        match self.format {
+            StatsdFormat::DogStatsd if !tags.is_empty() => {
+                let tags: Vec<String> = tags
+                    .iter()
+                    .map(|(key, value)| format!("{}:{}", key, value))
+                    .collect();
+                format!("{}:{}|{}|#{}", name, value, kind, tags.join(","))
+            }
+            StatsdFormat::DogStatsd => format!("{}:{}|{}", name, value, kind),
+            StatsdFormat::Statsd => {
+                let mut name = name;
+                for (_, value) in tags {
+                    name.push('.');
+                    name.push_str(&sanitize_statsd_segment(value));
+                }
+                format!("{}:{}|{}", name, value, kind)
+            }
+        }
+    }
+
+    fn send(&self, name: &str, value: &str, kind: &str, tags: &[(&str, &str)]) {
+        let cycle_id = crate::correlation::current_cycle_id();
+        let mut tags = tags.to_vec();
+        if let (StatsdFormat::DogStatsd, Some(cycle_id)) = (self.format, &cycle_id) {
+            tags.push(("cycle_id", cycle_id));
+        }
+        let line = self.line(name, value, kind, &tags);
+        let _ = self.socket.send(line.as_bytes());
+    }
+}
+
+fn sanitize_statsd_segment(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+pub fn init_statsd(
+    address: &str,
+    prefix: &str,
+    format: StatsdFormat,
+) -> Result<(), FlareSyncError> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.connect(address).map_err(|e| {
+        FlareSyncError::Config(format!("Invalid STATSD_ADDRESS {}: {}", address, e))
+    })?;
+    socket.set_nonblocking(true)?;
+
+    let prefix = if prefix.is_empty() || prefix.ends_with('.') {
+        prefix.to_string()
+    } else {
+        format!("{}.", prefix)
+    };
+    let _ = STATSD.set(StatsdSink {
+        socket,
+        prefix,
+        format,
+    });
+    Ok(())
+}
+
+fn statsd() -> Option<&'static StatsdSink> {
+    STATSD.get()
+}
+
+fn outcome(success: bool) -> &'static str {
+    if success {
+        "success"
+    } else {
+        "failure"
+    }
+}
+
+pub fn record_ip_detection(duration: Duration, success: bool) {
+    #[cfg(feature = "otel")]
+    otel::instruments().ip_detection_duration.record(
+        duration.as_secs_f64(),
+        &otel::attributes(&[("outcome", outcome(success))]),
+    );
+    if let Some(sink) = statsd() {
+        sink.send(
+            "flaresync.ip_detection.duration",
+            &duration.as_millis().to_string(),
+            "ms",
+            &[("outcome", outcome(success))],
+        );
+    }
+}
+
+pub fn record_domain_result(domain: &str, result: &str, duration: Duration) {
+    let tags = [("domain", domain), ("result", result)];
+    #[cfg(feature = "otel")]
+    {
+        let attributes = otel::attributes(&tags);
+        let instruments = otel::instruments();
+        instruments.domain_checks.add(1, &attributes);
+        instruments
+            .domain_duration
+            .record(duration.as_secs_f64(), &attributes);
+    }
+    if let Some(sink) = statsd() {
+        sink.send("flaresync.domain.checks", "1", "c", &tags);
+        sink.send(
+            "flaresync.domain.duration",
+            &duration.as_millis().to_string(),
+            "ms",
+            &tags,
+        );
+    }
+}
+
+pub fn record_overlapping_checks(count: usize, policy: &str) {
+    #[cfg(feature = "otel")]
+    otel::instruments()
+        .overlapping_checks
+        .add(count as u64, &otel::attributes(&[("policy", policy)]));
+    if let Some(sink) = statsd() {
+        sink.send(
+            "flaresync.cycle.overlapping_checks",
+            &count.to_string(),
+            "c",
+            &[("policy", policy)],
+        );
+    }
+}
+
+pub fn record_cloudflare_retry(error_kind: &str) {
+    #[cfg(feature = "otel")]
+    otel::instruments()
+        .cloudflare_retries
+        .add(1, &otel::attributes(&[("error", error_kind)]));
+    if let Some(sink) = statsd() {
+        sink.send(
+            "flaresync.cloudflare.retries",
+            "1",
+            "c",
+            &[("error", error_kind)],
+        );
+    }
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use opentelemetry::metrics::{Counter, Histogram};
+    use opentelemetry::{global, KeyValue};
+    use std::sync::OnceLock;
+
+    pub(super) struct Instruments {
+        pub ip_detection_duration: Histogram<f64>,
+        pub domain_checks: Counter<u64>,
+        pub domain_duration: Histogram<f64>,
+        pub cloudflare_retries: Counter<u64>,
+        pub overlapping_checks: Counter<u64>,
+    }
+
+    static INSTRUMENTS: OnceLock<Instruments> = OnceLock::new();
+
+    pub(super) fn attributes(tags: &[(&'static str, &str)]) -> Vec<KeyValue> {
+        let mut attributes: Vec<KeyValue> = tags
+            .iter()
+            .map(|(key, value)| KeyValue::new(*key, value.to_string()))
+            .collect();
+        if let Some(cycle_id) = crate::correlation::current_cycle_id() {
+            attributes.push(KeyValue::new("cycle_id", cycle_id));
+        }
+        attributes
+    }
+
+    pub(super) fn instruments() -> &'static Instruments {
+        INSTRUMENTS.get_or_init(|| {
+            let meter = global::meter("flaresync");
+            Instruments {
+                ip_detection_duration: meter
+                    .f64_histogram("flaresync.ip_detection.duration")
+                    .with_unit("s")
+                    .with_description("Time taken to resolve the public IP by quorum")
+                    .build(),
+                domain_checks: meter
+                    .u64_counter("flaresync.domain.checks")
+                    .with_description("Domain checks by result")
+                    .build(),
+                domain_duration: meter
+                    .f64_histogram("flaresync.domain.duration")
+                    .with_unit("s")
+                    .with_description("Time taken to check and update a domain")
+                    .build(),
+                cloudflare_retries: meter
+                    .u64_counter("flaresync.cloudflare.retries")
+                    .with_description("Cloudflare API requests retried after a transient error")
+                    .build(),
+                overlapping_checks: meter
+                    .u64_counter("flaresync.cycle.overlapping_checks")
+                    .with_description("Checks that fell due while a cycle was still running")
+                    .build(),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sink(format: StatsdFormat) -> StatsdSink {
+        StatsdSink {
+            socket: UdpSocket::bind("127.0.0.1:0").unwrap(),
+            prefix: "home.".to_string(),
+            format,
+        }
+    }
+
+    #[test]
+    fn test_statsd_line_encodes_tags_in_metric_name() {
+        let line = sink(StatsdFormat::Statsd).line(
+            "flaresync.domain.checks",
+            "1",
+            "c",
+            &[("domain", "www.example.com"), ("result", "updated")],
+        );
+        assert_eq!(
+            line,
+            "home.flaresync.domain.checks.www_example_com.updated:1|c"
+        );
+    }
+
+    #[test]
+    fn test_dogstatsd_line_uses_tags() {
+        let line = sink(StatsdFormat::DogStatsd).line(
+            "flaresync.ip_detection.duration",
+            "250",
+            "ms",
+            &[("outcome", "success")],
+        );
+        assert_eq!(
+            line,
+            "home.flaresync.ip_detection.duration:250|ms|#outcome:success"
+        );
+    }
+}
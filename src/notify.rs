@@ -0,0 +1,869 @@
+mod apprise;
+mod discord;
+mod email;
+mod gotify;
+mod matrix;
+mod mqtt;
+mod ntfy;
+mod pushover;
+mod signal;
+mod slack;
+mod teams;
+mod telegram;
+mod webhook;
+
+pub use apprise::AppriseConfig;
+pub use discord::DiscordConfig;
+pub use email::{EmailConfig, SmtpTls};
+pub use gotify::GotifyConfig;
+pub use matrix::MatrixConfig;
+pub use mqtt::{MqttConfig, DEFAULT_HA_DISCOVERY_PREFIX};
+pub use ntfy::NtfyConfig;
+pub use pushover::PushoverConfig;
+pub use signal::SignalConfig;
+pub use slack::SlackConfig;
+pub use teams::TeamsConfig;
+pub use telegram::TelegramConfig;
+pub use webhook::WebhookConfig;
+
+use crate::correlation;
+use crate::errors::FlareSyncError;
+use crate::status::RuntimeStatus;
+use reqwest::Client as ReqwestClient;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+const NOTIFICATION_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+pub const DEFAULT_RETRY_ATTEMPTS: u32 = 5;
+const MAX_PENDING_DELIVERIES: usize = 100;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(30);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Updated,
+    Failed,
+    Recovered,
+    Digest,
+}
+
+impl EventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventKind::Updated => "updated",
+            EventKind::Failed => "failed",
+            EventKind::Recovered => "recovered",
+            EventKind::Digest => "digest",
+        }
+    }
+}
+
+impl FromStr for EventKind {
+    type Err = FlareSyncError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "updated" | "change" => Ok(EventKind::Updated),
+            "failed" | "failure" => Ok(EventKind::Failed),
+            "recovered" | "recovery" => Ok(EventKind::Recovered),
+            _ => Err(FlareSyncError::Config(format!(
+                "Unknown notification event {}; expected updated, failed, or recovered",
+                value.trim()
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NotificationMode {
+    #[default]
+    Event,
+    Digest,
+}
+
+impl FromStr for NotificationMode {
+    type Err = FlareSyncError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "event" => Ok(NotificationMode::Event),
+            "digest" => Ok(NotificationMode::Digest),
+            _ => Err(FlareSyncError::Config(
+                "NOTIFY_MODE must be event or digest".to_string(),
+            )),
+        }
+    }
+}
+
+pub fn parse_event_kinds(value: &str) -> Result<Vec<EventKind>, FlareSyncError> {
+    value
+        .split([',', ';'])
+        .map(str::trim)
+        .filter(|kind| !kind.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NotificationEvent {
+    pub kind: EventKind,
+    pub domain: String,
+    pub old_ip: Option<String>,
+    pub new_ip: String,
+    pub proxied: Option<bool>,
+    pub error: Option<String>,
+    pub failure_streak: u32,
+    pub timestamp: String,
+    pub cycle_id: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<NotificationEvent>,
+    #[serde(skip)]
+    pub message: Option<String>,
+}
+
+impl NotificationEvent {
+    pub fn updated(domain: &str, old_ip: &str, new_ip: &str, proxied: bool) -> Self {
+        Self {
+            old_ip: Some(old_ip.to_string()),
+            proxied: Some(proxied),
+            ..Self::new(EventKind::Updated, domain, new_ip)
+        }
+    }
+
+    pub fn failed(domain: &str, new_ip: &str, error: &FlareSyncError, failure_streak: u32) -> Self {
+        Self {
+            error: Some(error.to_string()),
+            failure_streak,
+            ..Self::new(EventKind::Failed, domain, new_ip)
+        }
+    }
+
+    pub fn recovered(domain: &str, new_ip: &str, failure_streak: u32) -> Self {
+        Self {
+            failure_streak,
+            ..Self::new(EventKind::Recovered, domain, new_ip)
+        }
+    }
+
+    pub fn digest(events: Vec<NotificationEvent>) -> Self {
+        let domains: Vec<&str> = events.iter().map(|event| event.domain.as_str()).collect();
+        let new_ip = events
+            .last()
+            .map(|event| event.new_ip.clone())
+            .unwrap_or_default();
+        Self {
+            events: events.clone(),
+            ..Self::new(EventKind::Digest, &domains.join(", "), &new_ip)
+        }
+    }
+
+    fn new(kind: EventKind, domain: &str, new_ip: &str) -> Self {
+        Self {
+            kind,
+            domain: domain.to_string(),
+            old_ip: None,
+            new_ip: new_ip.to_string(),
+            proxied: None,
+            error: None,
+            failure_streak: 0,
+            timestamp: chrono::Local::now().to_rfc3339(),
+            cycle_id: correlation::current_cycle_id(),
+            events: Vec::new(),
+            message: None,
+        }
+    }
+
+    pub fn placeholder(&self, name: &str) -> Option<Cow<'_, str>> {
+        let value = match name {
+            "kind" => self.kind.as_str(),
+            "domain" => &self.domain,
+            "old_ip" => self.old_ip.as_deref().unwrap_or(""),
+            "new_ip" => &self.new_ip,
+            "error" => self.error.as_deref().unwrap_or(""),
+            "failure_streak" => return Some(Cow::Owned(self.failure_streak.to_string())),
+            "timestamp" => &self.timestamp,
+            "cycle_id" => self.cycle_id.as_deref().unwrap_or(""),
+            _ => return None,
+        };
+        Some(Cow::Borrowed(value))
+    }
+
+    pub fn message_or(&self, default: String) -> String {
+        self.message.clone().unwrap_or(default)
+    }
+
+    pub fn has_failures(&self) -> bool {
+        self.kind == EventKind::Failed || self.events.iter().any(NotificationEvent::has_failures)
+    }
+
+    pub fn digest_text(&self) -> String {
+        let lines: Vec<String> = self
+            .events
+            .iter()
+            .map(|event| {
+                let line = match event.kind {
+                    EventKind::Updated => format!(
+                        "{}: {} → {}",
+                        event.domain,
+                        event.old_ip.as_deref().unwrap_or("unknown"),
+                        event.new_ip
+                    ),
+                    EventKind::Failed => format!(
+                        "{}: failed {} times in a row ({})",
+                        event.domain,
+                        event.failure_streak,
+                        event.error.as_deref().unwrap_or("unknown error")
+                    ),
+                    EventKind::Recovered => format!(
+                        "{}: recovered after {} failed attempts",
+                        event.domain, event.failure_streak
+                    ),
+                    EventKind::Digest => event.digest_text(),
+                };
+                format!("• {}", event.message_or(line))
+            })
+            .collect();
+        lines.join("\n")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventFilter {
+    pub events: Vec<EventKind>,
+    pub failure_threshold: Option<u32>,
+    pub reminder_interval: Option<Duration>,
+}
+
+impl EventFilter {
+    pub fn new(failure_threshold: Option<u32>) -> Self {
+        Self {
+            events: vec![EventKind::Updated, EventKind::Failed],
+            failure_threshold,
+            reminder_interval: None,
+        }
+    }
+
+    pub fn matches(&self, event: &NotificationEvent) -> bool {
+        if !self.events.contains(&event.kind) {
+            return false;
+        }
+        match (event.kind, self.failure_threshold) {
+            (EventKind::Failed, Some(threshold)) => event.failure_streak == threshold,
+            _ => true,
+        }
+    }
+}
+
+pub fn render_template(
+    template: &str,
+    event: &NotificationEvent,
+    escape: impl Fn(&str) -> String,
+) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            rendered.push_str(&rest[start..]);
+            return rendered;
+        };
+        let name = after[..end].trim();
+        match event.placeholder(name) {
+            Some(value) => rendered.push_str(&escape(&value)),
+            None => rendered.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageTemplates {
+    pub updated: Option<String>,
+    pub failed: Option<String>,
+    pub recovered: Option<String>,
+}
+
+impl MessageTemplates {
+    pub fn render(&self, event: &NotificationEvent) -> Option<String> {
+        let template = match event.kind {
+            EventKind::Updated => self.updated.as_deref(),
+            EventKind::Failed => self.failed.as_deref(),
+            EventKind::Recovered => self.recovered.as_deref(),
+            EventKind::Digest => None,
+        }?;
+        Some(render_template(template, event, str::to_string))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotifierConfig {
+    Webhook(WebhookConfig),
+    Discord(DiscordConfig),
+    Telegram(TelegramConfig),
+    Slack(SlackConfig),
+    Email(EmailConfig),
+    Ntfy(NtfyConfig),
+    Pushover(PushoverConfig),
+    Gotify(GotifyConfig),
+    Apprise(AppriseConfig),
+    Mqtt(MqttConfig),
+    Matrix(MatrixConfig),
+    Teams(TeamsConfig),
+    Signal(SignalConfig),
+}
+
+impl NotifierConfig {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NotifierConfig::Webhook(_) => "webhook",
+            NotifierConfig::Discord(_) => "discord",
+            NotifierConfig::Telegram(_) => "telegram",
+            NotifierConfig::Slack(_) => "slack",
+            NotifierConfig::Email(_) => "email",
+            NotifierConfig::Ntfy(_) => "ntfy",
+            NotifierConfig::Pushover(_) => "pushover",
+            NotifierConfig::Gotify(_) => "gotify",
+            NotifierConfig::Apprise(_) => "apprise",
+            NotifierConfig::Mqtt(_) => "mqtt",
+            NotifierConfig::Matrix(_) => "matrix",
+            NotifierConfig::Teams(_) => "teams",
+            NotifierConfig::Signal(_) => "signal",
+        }
+    }
+
+    pub fn secrets(&self) -> Vec<String> {
+        match self {
+            NotifierConfig::Webhook(config) => config.secrets(),
+            NotifierConfig::Discord(config) => vec![config.webhook_url.clone()],
+            NotifierConfig::Telegram(config) => vec![config.bot_token.clone()],
+            NotifierConfig::Slack(config) => vec![config.webhook_url.clone()],
+            NotifierConfig::Email(config) => config.password.clone().into_iter().collect(),
+            NotifierConfig::Ntfy(config) => config.access_token.clone().into_iter().collect(),
+            NotifierConfig::Pushover(config) => {
+                vec![config.app_token.clone(), config.user_key.clone()]
+            }
+            NotifierConfig::Gotify(config) => vec![config.app_token.clone()],
+            NotifierConfig::Apprise(config) => config.urls.clone().into_iter().collect(),
+            NotifierConfig::Mqtt(config) => config.password.clone().into_iter().collect(),
+            NotifierConfig::Matrix(config) => vec![config.access_token.clone()],
+            NotifierConfig::Teams(config) => vec![config.webhook_url.clone()],
+            NotifierConfig::Signal(_) => Vec::new(),
+        }
+    }
+
+    async fn send(
+        &self,
+        client: &ReqwestClient,
+        event: &NotificationEvent,
+    ) -> Result<(), FlareSyncError> {
+        match self {
+            NotifierConfig::Webhook(config) => webhook::send(client, config, event).await,
+            NotifierConfig::Discord(config) => discord::send(client, config, event).await,
+            NotifierConfig::Telegram(config) => telegram::send(client, config, event).await,
+            NotifierConfig::Slack(config) => slack::send(client, config, event).await,
+            NotifierConfig::Email(config) => email::send(config, event).await,
+            NotifierConfig::Ntfy(config) => ntfy::send(client, config, event).await,
+            NotifierConfig::Pushover(config) => pushover::send(client, config, event).await,
+            NotifierConfig::Gotify(config) => gotify::send(client, config, event).await,
+            NotifierConfig::Apprise(config) => apprise::send(client, config, event).await,
+            NotifierConfig::Mqtt(config) => mqtt::send(config, event).await,
+            NotifierConfig::Matrix(config) => matrix::send(client, config, event).await,
+            NotifierConfig::Teams(config) => teams::send(client, config, event).await,
+            NotifierConfig::Signal(config) => signal::send(client, config, event).await,
+        }
+    }
+
+    async fn publish_state(&self, status: &RuntimeStatus) -> Result<(), FlareSyncError> {
+        match self {
+            NotifierConfig::Mqtt(config) => mqtt::publish_state(config, status).await,
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotificationChannel {
+    pub notifier: NotifierConfig,
+    pub filter: EventFilter,
+}
+
+#[derive(Debug, Clone)]
+struct PendingDelivery {
+    channel: usize,
+    event: NotificationEvent,
+    attempts: u32,
+    next_attempt: Instant,
+}
+
+fn retry_delay(attempts: u32) -> Duration {
+    RETRY_BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempts.saturating_sub(1)))
+        .min(RETRY_MAX_DELAY)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Notifications {
+    channels: Vec<NotificationChannel>,
+    templates: MessageTemplates,
+    retry_attempts: u32,
+    mode: NotificationMode,
+    pending: VecDeque<PendingDelivery>,
+    digest: Vec<Vec<NotificationEvent>>,
+    failure_alerts: HashMap<(usize, String), Instant>,
+}
+
+impl Notifications {
+    pub fn new(
+        channels: Vec<NotificationChannel>,
+        templates: MessageTemplates,
+        retry_attempts: u32,
+        mode: NotificationMode,
+    ) -> Self {
+        let digest = vec![Vec::new(); channels.len()];
+        Self {
+            channels,
+            templates,
+            retry_attempts,
+            mode,
+            pending: VecDeque::new(),
+            digest,
+            failure_alerts: HashMap::new(),
+        }
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Switches to `next`, built from a reloaded configuration, keeping the queued
+    /// retries, digest events, and failure alert times of every channel whose notifier
+    /// is still configured. State of removed channels is dropped.
+    pub fn reconfigure(&mut self, mut next: Notifications) {
+        let moved: Vec<Option<usize>> = self
+            .channels
+            .iter()
+            .map(|channel| {
+                next.channels
+                    .iter()
+                    .position(|next_channel| next_channel.notifier == channel.notifier)
+            })
+            .collect();
+        next.pending = std::mem::take(&mut self.pending)
+            .into_iter()
+            .filter_map(|delivery| {
+                moved[delivery.channel].map(|channel| PendingDelivery {
+                    channel,
+                    ..delivery
+                })
+            })
+            .collect();
+        next.failure_alerts = std::mem::take(&mut self.failure_alerts)
+            .into_iter()
+            .filter_map(|((channel, domain), sent)| {
+                moved[channel].map(|channel| ((channel, domain), sent))
+            })
+            .collect();
+        if next.mode == NotificationMode::Digest {
+            for (events, channel) in std::mem::take(&mut self.digest).into_iter().zip(&moved) {
+                if let Some(channel) = channel {
+                    next.digest[*channel].extend(events);
+                }
+            }
+        }
+        *self = next;
+    }
+
+    pub async fn dispatch(&mut self, client: &ReqwestClient, event: &NotificationEvent) {
+        let event = NotificationEvent {
+            message: self.templates.render(event),
+            ..event.clone()
+        };
+        if event.kind != EventKind::Failed {
+            self.failure_alerts
+                .retain(|(_, domain), _| *domain != event.domain);
+        }
+        let now = Instant::now();
+        for index in 0..self.channels.len() {
+            if !self.should_send(index, &event, now) {
+                continue;
+            }
+            match self.mode {
+                NotificationMode::Event => self.deliver(client, index, event.clone(), 0).await,
+                NotificationMode::Digest => self.digest[index].push(event.clone()),
+            }
+        }
+    }
+
+    fn should_send(&mut self, channel: usize, event: &NotificationEvent, now: Instant) -> bool {
+        let filter = &self.channels[channel].filter;
+        let Some(reminder_interval) = filter.reminder_interval else {
+            return filter.matches(event);
+        };
+        if event.kind != EventKind::Failed {
+            return filter.matches(event);
+        }
+        let threshold = filter.failure_threshold.unwrap_or(1);
+        if !filter.events.contains(&EventKind::Failed) || event.failure_streak < threshold {
+            return false;
+        }
+        let key = (channel, event.domain.clone());
+        match self.failure_alerts.get(&key) {
+            Some(sent)
+                if event.failure_streak > threshold
+                    && now.duration_since(*sent) < reminder_interval =>
+            {
+                false
+            }
+            _ => {
+                self.failure_alerts.insert(key, now);
+                true
+            }
+        }
+    }
+
+    /// Sends `event` once through every channel, ignoring event filters, digest mode,
+    /// and retries, and returns each channel's result.
+    pub async fn send_test(
+        &self,
+        client: &ReqwestClient,
+        event: &NotificationEvent,
+    ) -> Vec<(&'static str, Result<(), FlareSyncError>)> {
+        let event = NotificationEvent {
+            message: self.templates.render(event),
+            ..event.clone()
+        };
+        let mut results = Vec::new();
+        for channel in &self.channels {
+            let result = channel.notifier.send(client, &event).await;
+            results.push((channel.notifier.name(), result));
+        }
+        results
+    }
+
+    /// Publishes the current state of every domain on channels that keep one, such as MQTT
+    /// with Home Assistant discovery. Failures are only logged, since the next cycle
+    /// publishes again.
+    pub async fn publish_state(&self, status: &RuntimeStatus) {
+        for channel in &self.channels {
+            if let Err(e) = channel.notifier.publish_state(status).await {
+                warn!("Failed to publish {} state: {}", channel.notifier.name(), e);
+            }
+        }
+    }
+
+    pub async fn flush_digest(&mut self, client: &ReqwestClient) {
+        for index in 0..self.channels.len() {
+            let mut events = std::mem::take(&mut self.digest[index]);
+            let event = match events.len() {
+                0 => continue,
+                1 => events.remove(0),
+                _ => NotificationEvent::digest(events),
+            };
+            self.deliver(client, index, event, 0).await;
+        }
+    }
+
+    pub async fn retry_pending(&mut self, client: &ReqwestClient) {
+        let now = Instant::now();
+        let (due, waiting): (VecDeque<_>, VecDeque<_>) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|delivery| delivery.next_attempt <= now);
+        self.pending = waiting;
+        for delivery in due {
+            self.deliver(client, delivery.channel, delivery.event, delivery.attempts)
+                .await;
+        }
+    }
+
+    async fn deliver(
+        &mut self,
+        client: &ReqwestClient,
+        channel: usize,
+        event: NotificationEvent,
+        attempts: u32,
+    ) {
+        let name = self.channels[channel].notifier.name();
+        let error = match self.channels[channel].notifier.send(client, &event).await {
+            Ok(()) => {
+                if attempts > 0 {
+                    info!(
+                        "Delivered {} notification for {} after {} retries",
+                        name, event.domain, attempts
+                    );
+                }
+                return;
+            }
+            Err(e) => e,
+        };
+
+        let attempts = attempts + 1;
+        if attempts > self.retry_attempts {
+            warn!(
+                "Failed to send {} notification for {}: {}; giving up",
+                name, event.domain, error
+            );
+            return;
+        }
+        let delay = retry_delay(attempts);
+        warn!(
+            "Failed to send {} notification for {}: {}; retrying in {}s",
+            name,
+            event.domain,
+            error,
+            delay.as_secs()
+        );
+        if self.pending.len() >= MAX_PENDING_DELIVERIES {
+            if let Some(dropped) = self.pending.pop_front() {
+                warn!(
+                    "Notification retry queue is full; dropping {} notification for {}",
+                    self.channels[dropped.channel].notifier.name(),
+                    dropped.event.domain
+                );
+            }
+        }
+        self.pending.push_back(PendingDelivery {
+            channel,
+            event,
+            attempts,
+            next_attempt: Instant::now() + delay,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template_substitutes_known_placeholders() {
+        let event = NotificationEvent::updated("example.com", "198.51.100.1", "203.0.113.7", false);
+        let rendered = render_template(
+            "{{domain}}: {{ old_ip }} -> {{new_ip}} ({{unknown}}) {{kind",
+            &event,
+            str::to_string,
+        );
+        assert_eq!(
+            rendered,
+            "example.com: 198.51.100.1 -> 203.0.113.7 ({{unknown}}) {{kind"
+        );
+    }
+
+    #[test]
+    fn test_event_filter_by_kind_and_failure_threshold() {
+        let error = FlareSyncError::Cloudflare("permission denied".to_string());
+        let failed =
+            |streak| NotificationEvent::failed("example.com", "203.0.113.7", &error, streak);
+        let recovered = NotificationEvent::recovered("example.com", "203.0.113.7", 4);
+
+        let every_failure = EventFilter::new(None);
+        assert!(every_failure.matches(&failed(1)));
+        assert!(every_failure.matches(&failed(2)));
+        assert!(!every_failure.matches(&recovered));
+
+        let filter = EventFilter {
+            events: parse_event_kinds("failure, recovery").unwrap(),
+            failure_threshold: Some(3),
+            reminder_interval: None,
+        };
+        assert!(!filter.matches(&failed(2)));
+        assert!(filter.matches(&failed(3)));
+        assert!(!filter.matches(&failed(4)));
+        assert!(filter.matches(&recovered));
+        assert!(!filter.matches(&NotificationEvent::updated(
+            "example.com",
+            "198.51.100.1",
+            "203.0.113.7",
+            false
+        )));
+    }
+
+    #[test]
+    fn test_failure_reminders_suppress_duplicates() {
+        let error = FlareSyncError::Cloudflare("permission denied".to_string());
+        let failed =
+            |streak| NotificationEvent::failed("example.com", "203.0.113.7", &error, streak);
+        let mut filter = EventFilter::new(None);
+        filter.reminder_interval = Some(Duration::from_secs(6 * 3600));
+        let mut notifications = Notifications::new(
+            vec![NotificationChannel {
+                notifier: NotifierConfig::Webhook(WebhookConfig::new("http://127.0.0.1/hook")),
+                filter,
+            }],
+            Default::default(),
+            0,
+            NotificationMode::Event,
+        );
+
+        let start = Instant::now();
+        assert!(notifications.should_send(0, &failed(1), start));
+        assert!(!notifications.should_send(0, &failed(2), start + Duration::from_secs(300)));
+        assert!(notifications.should_send(0, &failed(73), start + Duration::from_secs(6 * 3600)));
+        assert!(!notifications.should_send(
+            0,
+            &failed(74),
+            start + Duration::from_secs(6 * 3600 + 300)
+        ));
+        assert!(notifications.should_send(
+            0,
+            &failed(1),
+            start + Duration::from_secs(6 * 3600 + 600)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reconfigure_keeps_the_state_of_unchanged_channels() {
+        let unreachable = || {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}/hook", listener.local_addr().unwrap())
+        };
+        let mut filter = EventFilter::new(None);
+        filter.reminder_interval = Some(Duration::from_secs(6 * 3600));
+        let channel = |url: String| NotificationChannel {
+            notifier: NotifierConfig::Webhook(WebhookConfig::new(url)),
+            filter: filter.clone(),
+        };
+        let (removed, kept, added) = (unreachable(), unreachable(), unreachable());
+        let error = FlareSyncError::Cloudflare("permission denied".to_string());
+        let failed =
+            |streak| NotificationEvent::failed("example.com", "203.0.113.7", &error, streak);
+        let client = ReqwestClient::new();
+
+        let mut notifications = Notifications::new(
+            vec![channel(removed.clone()), channel(kept.clone())],
+            Default::default(),
+            2,
+            NotificationMode::Event,
+        );
+        notifications.dispatch(&client, &failed(1)).await;
+        assert_eq!(notifications.pending(), 2);
+        assert_eq!(notifications.failure_alerts.len(), 2);
+
+        notifications.reconfigure(Notifications::new(
+            vec![channel(kept), channel(added)],
+            Default::default(),
+            2,
+            NotificationMode::Event,
+        ));
+        assert_eq!(notifications.pending(), 1);
+        assert_eq!(notifications.pending[0].channel, 0);
+        let later = Instant::now() + Duration::from_secs(300);
+        assert!(!notifications.should_send(0, &failed(2), later));
+        assert!(notifications.should_send(1, &failed(2), later));
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off_exponentially() {
+        assert_eq!(retry_delay(1), Duration::from_secs(30));
+        assert_eq!(retry_delay(3), Duration::from_secs(120));
+        assert_eq!(retry_delay(20), RETRY_MAX_DELAY);
+    }
+
+    #[tokio::test]
+    async fn test_failed_delivery_is_queued_for_retry() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        drop(listener);
+        let channel = NotificationChannel {
+            notifier: NotifierConfig::Webhook(WebhookConfig::new(url)),
+            filter: EventFilter::new(None),
+        };
+        let event = NotificationEvent::updated("example.com", "198.51.100.1", "203.0.113.7", false);
+        let client = ReqwestClient::new();
+
+        let mut notifications = Notifications::new(
+            vec![channel.clone()],
+            Default::default(),
+            2,
+            NotificationMode::Event,
+        );
+        notifications.dispatch(&client, &event).await;
+        assert_eq!(notifications.pending(), 1);
+        notifications.retry_pending(&client).await;
+        assert_eq!(notifications.pending(), 1);
+
+        let mut without_retries = Notifications::new(
+            vec![channel],
+            Default::default(),
+            0,
+            NotificationMode::Event,
+        );
+        without_retries.dispatch(&client, &event).await;
+        assert_eq!(without_retries.pending(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_send_test_ignores_filters_and_reports_each_channel() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        drop(listener);
+        let channel = NotificationChannel {
+            notifier: NotifierConfig::Webhook(WebhookConfig::new(url)),
+            filter: EventFilter {
+                events: Vec::new(),
+                ..EventFilter::new(None)
+            },
+        };
+        let event = NotificationEvent::updated("example.com", "198.51.100.1", "203.0.113.7", false);
+        let notifications = Notifications::new(
+            vec![channel],
+            Default::default(),
+            2,
+            NotificationMode::Digest,
+        );
+
+        let results = notifications.send_test(&ReqwestClient::new(), &event).await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, "webhook");
+        assert!(results[0].1.is_err());
+        assert_eq!(notifications.pending(), 0);
+    }
+
+    #[test]
+    fn test_digest_lists_each_event() {
+        let error = FlareSyncError::Cloudflare("permission denied".to_string());
+        let mut templated = NotificationEvent::recovered("c.example.com", "203.0.113.7", 2);
+        templated.message = Some("c is back".to_string());
+        let digest = NotificationEvent::digest(vec![
+            NotificationEvent::updated("a.example.com", "198.51.100.1", "203.0.113.7", false),
+            NotificationEvent::failed("b.example.com", "203.0.113.7", &error, 1),
+            templated,
+        ]);
+
+        assert_eq!(digest.kind, EventKind::Digest);
+        assert_eq!(digest.domain, "a.example.com, b.example.com, c.example.com");
+        assert!(digest.has_failures());
+        assert_eq!(
+            digest.digest_text(),
+            "• a.example.com: 198.51.100.1 → 203.0.113.7\n\
+             • b.example.com: failed 1 times in a row (Cloudflare API error: permission denied)\n\
+             • c is back"
+        );
+    }
+
+    #[test]
+    fn test_message_templates_select_by_event_kind() {
+        let templates = MessageTemplates {
+            failed: Some("{{domain}} down ({{failure_streak}}x): {{error}}".to_string()),
+            ..MessageTemplates::default()
+        };
+        let updated =
+            NotificationEvent::updated("example.com", "198.51.100.1", "203.0.113.7", false);
+        assert_eq!(templates.render(&updated), None);
+
+        let error = FlareSyncError::Cloudflare("permission denied".to_string());
+        let failed = NotificationEvent::failed("example.com", "203.0.113.7", &error, 3);
+        assert_eq!(
+            templates.render(&failed).unwrap(),
+            "example.com down (3x): Cloudflare API error: permission denied"
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use super::{EventKind, NotificationEvent, NOTIFICATION_TIMEOUT};
+use crate::errors::FlareSyncError;
+use reqwest::Client as ReqwestClient;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppriseConfig {
+    pub endpoint: String,
+    pub urls: Option<String>,
+    pub tag: Option<String>,
+}
+
+impl AppriseConfig {
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            urls: None,
+            tag: None,
+        }
+    }
+}
+
+pub(super) fn payload(config: &AppriseConfig, event: &NotificationEvent) -> Value {
+    let (title, body, notify_type) = match event.kind {
+        EventKind::Updated => (
+            format!("{} updated", event.domain),
+            format!(
+                "{} → {}",
+                event.old_ip.as_deref().unwrap_or("unknown"),
+                event.new_ip
+            ),
+            "success",
+        ),
+        EventKind::Failed => (
+            format!("{} update failed", event.domain),
+            format!(
+                "{} (failure {} in a row)",
+                event.error.as_deref().unwrap_or("unknown error"),
+                event.failure_streak
+            ),
+            "failure",
+        ),
+        EventKind::Recovered => (
+            format!("{} recovered", event.domain),
+            format!(
+                "Recovered after {} failed attempts (public IP {})",
+                event.failure_streak, event.new_ip
+            ),
+            "success",
+        ),
+        EventKind::Digest => (
+            format!("FlareSync: {} DNS events", event.events.len()),
+            event.digest_text(),
+            if event.has_failures() {
+                "failure"
+            } else {
+                "success"
+            },
+        ),
+    };
+    let body = event.message_or(body);
+    let mut payload = json!({
+        "title": title,
+        "body": body,
+        "type": notify_type,
+    });
+    if let Some(urls) = &config.urls {
+        payload["urls"] = json!(urls);
+    }
+    if let Some(tag) = &config.tag {
+        payload["tag"] = json!(tag);
+    }
+    payload
+}
+
+pub(super) async fn send(
+    client: &ReqwestClient,
+    config: &AppriseConfig,
+    event: &NotificationEvent,
+) -> Result<(), FlareSyncError> {
+    client
+        .post(&config.endpoint)
+        .timeout(NOTIFICATION_TIMEOUT)
+        .json(&payload(config, event))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apprise_payload_includes_optional_urls_and_tag() {
+        let mut config = AppriseConfig::new("http://apprise:8000/notify/");
+        let error = FlareSyncError::Cloudflare("permission denied".to_string());
+        let event = NotificationEvent::failed("example.com", "203.0.113.7", &error, 2);
+
+        let payload = payload(&config, &event);
+        assert_eq!(payload["type"], "failure");
+        assert!(payload.get("urls").is_none());
+
+        config.urls = Some("tgram://token/chat".to_string());
+        config.tag = Some("dns".to_string());
+        let payload = super::payload(&config, &event);
+        assert_eq!(payload["urls"], "tgram://token/chat");
+        assert_eq!(payload["tag"], "dns");
+    }
+}
@@ -0,0 +1,121 @@
+use super::{EventKind, NotificationEvent, NOTIFICATION_TIMEOUT};
+use crate::errors::FlareSyncError;
+use reqwest::Client as ReqwestClient;
+use serde_json::{json, Value};
+
+const GREEN: u32 = 0x2ecc71;
+const RED: u32 = 0xe74c3c;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscordConfig {
+    pub webhook_url: String,
+}
+
+impl DiscordConfig {
+    pub fn new(webhook_url: impl Into<String>) -> Self {
+        Self {
+            webhook_url: webhook_url.into(),
+        }
+    }
+}
+
+fn field(name: &str, value: impl Into<String>, inline: bool) -> Value {
+    json!({ "name": name, "value": value.into(), "inline": inline })
+}
+
+pub(super) fn payload(event: &NotificationEvent) -> Value {
+    let mut embed = match event.kind {
+        EventKind::Updated => {
+            let proxied = match event.proxied {
+                Some(true) => "Yes",
+                Some(false) => "No",
+                None => "Unknown",
+            };
+            json!({
+                "title": format!("DNS record updated: {}", event.domain),
+                "color": GREEN,
+                "fields": [
+                    field("Domain", event.domain.clone(), true),
+                    field(
+                        "IP",
+                        format!(
+                            "{} → {}",
+                            event.old_ip.as_deref().unwrap_or("unknown"),
+                            event.new_ip
+                        ),
+                        true,
+                    ),
+                    field("Proxied", proxied, true),
+                ],
+                "timestamp": event.timestamp,
+            })
+        }
+        EventKind::Failed => json!({
+                "title": format!("DNS updates failing: {}", event.domain),
+                "description": event.error.clone().unwrap_or_default(),
+                "color": RED,
+                "fields": [
+                    field("Domain", event.domain.clone(), true),
+                    field("Consecutive failures", event.failure_streak.to_string(), true),
+                    field("Public IP", event.new_ip.clone(), true),
+                ],
+                "timestamp": event.timestamp,
+        }),
+        EventKind::Recovered => json!({
+            "title": format!("DNS updates recovered: {}", event.domain),
+            "color": GREEN,
+            "fields": [
+                field("Domain", event.domain.clone(), true),
+                field("Failures before recovery", event.failure_streak.to_string(), true),
+                field("Public IP", event.new_ip.clone(), true),
+            ],
+            "timestamp": event.timestamp,
+        }),
+        EventKind::Digest => json!({
+            "title": format!("DNS events: {} domains", event.events.len()),
+            "description": event.digest_text(),
+            "color": if event.has_failures() { RED } else { GREEN },
+            "timestamp": event.timestamp,
+        }),
+    };
+    if let Some(message) = &event.message {
+        embed["description"] = json!(message);
+    }
+    json!({ "username": "FlareSync", "embeds": [embed] })
+}
+
+pub(super) async fn send(
+    client: &ReqwestClient,
+    config: &DiscordConfig,
+    event: &NotificationEvent,
+) -> Result<(), FlareSyncError> {
+    client
+        .post(&config.webhook_url)
+        .timeout(NOTIFICATION_TIMEOUT)
+        .json(&payload(event))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discord_payload_formats_updates_and_failures() {
+        let updated =
+            NotificationEvent::updated("example.com", "198.51.100.1", "203.0.113.7", true);
+        let embed = &payload(&updated)["embeds"][0];
+        assert_eq!(embed["color"], GREEN);
+        assert_eq!(embed["fields"][1]["value"], "198.51.100.1 → 203.0.113.7");
+        assert_eq!(embed["fields"][2]["value"], "Yes");
+
+        let error = FlareSyncError::Cloudflare("permission denied".to_string());
+        let repeated = NotificationEvent::failed("example.com", "203.0.113.7", &error, 3);
+        let embed = &payload(&repeated)["embeds"][0];
+        assert_eq!(embed["color"], RED);
+        assert_eq!(embed["fields"][1]["value"], "3");
+    }
+}
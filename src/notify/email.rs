@@ -0,0 +1,186 @@
+use super::{EventKind, NotificationEvent, NOTIFICATION_TIMEOUT};
+use crate::errors::FlareSyncError;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SmtpTls {
+    #[default]
+    StartTls,
+    Tls,
+    None,
+}
+
+impl FromStr for SmtpTls {
+    type Err = FlareSyncError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "starttls" => Ok(SmtpTls::StartTls),
+            "tls" => Ok(SmtpTls::Tls),
+            "none" => Ok(SmtpTls::None),
+            _ => Err(FlareSyncError::Config(
+                "SMTP_TLS must be starttls, tls, or none".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+impl EmailConfig {
+    pub fn new(host: impl Into<String>, from: impl Into<String>, to: Vec<String>) -> Self {
+        Self {
+            host: host.into(),
+            port: None,
+            tls: SmtpTls::default(),
+            username: None,
+            password: None,
+            from: from.into(),
+            to,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), FlareSyncError> {
+        if self.to.is_empty() {
+            return Err(FlareSyncError::Config(
+                "SMTP_TO must include at least one address".to_string(),
+            ));
+        }
+        for address in std::iter::once(&self.from).chain(&self.to) {
+            parse_mailbox(address)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_mailbox(address: &str) -> Result<Mailbox, FlareSyncError> {
+    address
+        .parse()
+        .map_err(|e| FlareSyncError::Config(format!("Invalid email address {}: {}", address, e)))
+}
+
+pub(super) fn subject_and_body(event: &NotificationEvent) -> (String, String) {
+    match event.kind {
+        EventKind::Updated => (
+            format!("[FlareSync] {} updated to {}", event.domain, event.new_ip),
+            format!(
+                "The DNS record for {} was updated.\n\nOld IP: {}\nNew IP: {}\nTime: {}\n",
+                event.domain,
+                event.old_ip.as_deref().unwrap_or("unknown"),
+                event.new_ip,
+                event.timestamp
+            ),
+        ),
+        EventKind::Failed => (
+            format!(
+                "[FlareSync] {} failing ({} consecutive failures)",
+                event.domain, event.failure_streak
+            ),
+            format!(
+                "Updating the DNS record for {} has failed {} times in a row.\n\nLast error: {}\nPublic IP: {}\nTime: {}\n",
+                event.domain,
+                event.failure_streak,
+                event.error.as_deref().unwrap_or("unknown"),
+                event.new_ip,
+                event.timestamp
+            ),
+        ),
+        EventKind::Recovered => (
+            format!("[FlareSync] {} recovered", event.domain),
+            format!(
+                "Updating the DNS record for {} succeeded again after {} failed attempts.\n\nPublic IP: {}\nTime: {}\n",
+                event.domain, event.failure_streak, event.new_ip, event.timestamp
+            ),
+        ),
+        EventKind::Digest => (
+            format!("[FlareSync] {} DNS events", event.events.len()),
+            format!("{}\n\nTime: {}\n", event.digest_text(), event.timestamp),
+        ),
+    }
+}
+
+pub(super) async fn send(
+    config: &EmailConfig,
+    event: &NotificationEvent,
+) -> Result<(), FlareSyncError> {
+    let (subject, body) = subject_and_body(event);
+    let body = event.message_or(body);
+
+    let mut message = Message::builder()
+        .from(parse_mailbox(&config.from)?)
+        .subject(subject);
+    for address in &config.to {
+        message = message.to(parse_mailbox(address)?);
+    }
+    let message = message
+        .body(body)
+        .map_err(|e| FlareSyncError::Notification(format!("Failed to build email: {}", e)))?;
+
+    let smtp_error = |e: lettre::transport::smtp::Error| {
+        FlareSyncError::Notification(format!("SMTP error: {}", e))
+    };
+    let mut transport = match config.tls {
+        SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+            .map_err(smtp_error)?,
+        SmtpTls::Tls => {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host).map_err(smtp_error)?
+        }
+        SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+    };
+    if let Some(port) = config.port {
+        transport = transport.port(port);
+    }
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+    transport
+        .timeout(Some(NOTIFICATION_TIMEOUT))
+        .build()
+        .send(message)
+        .await
+        .map_err(smtp_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_email_subject_and_address_validation() {
+        let config = EmailConfig::new(
+            "smtp.example.com",
+            "FlareSync <flaresync@example.com>",
+            vec!["admin@example.com".to_string()],
+        );
+        config.validate().unwrap();
+
+        let updated =
+            NotificationEvent::updated("example.com", "198.51.100.1", "203.0.113.7", false);
+        let (subject, body) = subject_and_body(&updated);
+        assert_eq!(subject, "[FlareSync] example.com updated to 203.0.113.7");
+        assert!(body.contains("Old IP: 198.51.100.1"));
+
+        let error = FlareSyncError::Cloudflare("permission denied".to_string());
+        let third = NotificationEvent::failed("example.com", "203.0.113.7", &error, 3);
+        assert_eq!(
+            subject_and_body(&third).0,
+            "[FlareSync] example.com failing (3 consecutive failures)"
+        );
+
+        let invalid = EmailConfig::new("smtp.example.com", "not an address", vec![]);
+        assert!(matches!(invalid.validate(), Err(FlareSyncError::Config(_))));
+    }
+}
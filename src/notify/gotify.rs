@@ -0,0 +1,98 @@
+use super::{EventKind, NotificationEvent, NOTIFICATION_TIMEOUT};
+use crate::errors::FlareSyncError;
+use reqwest::Client as ReqwestClient;
+use serde_json::{json, Value};
+
+pub const DEFAULT_PRIORITY: u8 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GotifyConfig {
+    pub server_url: String,
+    pub app_token: String,
+    pub priority: u8,
+}
+
+impl GotifyConfig {
+    pub fn new(server_url: impl Into<String>, app_token: impl Into<String>) -> Self {
+        Self {
+            server_url: server_url.into(),
+            app_token: app_token.into(),
+            priority: DEFAULT_PRIORITY,
+        }
+    }
+
+    pub fn message_url(&self) -> String {
+        format!("{}/message", self.server_url.trim_end_matches('/'))
+    }
+}
+
+pub(super) fn payload(config: &GotifyConfig, event: &NotificationEvent) -> Value {
+    let (title, message) = match event.kind {
+        EventKind::Updated => (
+            format!("{} updated", event.domain),
+            format!(
+                "{} → {}",
+                event.old_ip.as_deref().unwrap_or("unknown"),
+                event.new_ip
+            ),
+        ),
+        EventKind::Failed => (
+            format!("{} failing", event.domain),
+            format!(
+                "Failed {} times in a row: {}",
+                event.failure_streak,
+                event.error.as_deref().unwrap_or("unknown error")
+            ),
+        ),
+        EventKind::Recovered => (
+            format!("{} recovered", event.domain),
+            format!(
+                "Recovered after {} failed attempts. Public IP: {}",
+                event.failure_streak, event.new_ip
+            ),
+        ),
+        EventKind::Digest => (
+            format!("FlareSync: {} DNS events", event.events.len()),
+            event.digest_text(),
+        ),
+    };
+    let message = event.message_or(message);
+    json!({
+        "title": title,
+        "message": message,
+        "priority": config.priority,
+    })
+}
+
+pub(super) async fn send(
+    client: &ReqwestClient,
+    config: &GotifyConfig,
+    event: &NotificationEvent,
+) -> Result<(), FlareSyncError> {
+    client
+        .post(config.message_url())
+        .timeout(NOTIFICATION_TIMEOUT)
+        .header("X-Gotify-Key", &config.app_token)
+        .json(&payload(config, event))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gotify_message_url_and_payload() {
+        let config = GotifyConfig::new("https://gotify.example.com/", "app-token");
+        assert_eq!(config.message_url(), "https://gotify.example.com/message");
+
+        let updated =
+            NotificationEvent::updated("example.com", "198.51.100.1", "203.0.113.7", false);
+        let payload = payload(&config, &updated);
+        assert_eq!(payload["title"], "example.com updated");
+        assert_eq!(payload["priority"], 5);
+    }
+}
@@ -0,0 +1,126 @@
+use super::{EventKind, NotificationEvent, NOTIFICATION_TIMEOUT};
+use crate::errors::FlareSyncError;
+use reqwest::{Client as ReqwestClient, Url};
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatrixConfig {
+    pub homeserver: String,
+    pub access_token: String,
+    pub room_id: String,
+}
+
+impl MatrixConfig {
+    pub fn new(
+        homeserver: impl Into<String>,
+        access_token: impl Into<String>,
+        room_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            homeserver: homeserver.into(),
+            access_token: access_token.into(),
+            room_id: room_id.into(),
+        }
+    }
+
+    pub fn send_url(&self, txn_id: &str) -> Result<Url, FlareSyncError> {
+        let mut url = Url::parse(&self.homeserver).map_err(|e| {
+            FlareSyncError::Config(format!(
+                "Invalid MATRIX_HOMESERVER {}: {}",
+                self.homeserver, e
+            ))
+        })?;
+        url.path_segments_mut()
+            .map_err(|_| {
+                FlareSyncError::Config(format!("Invalid MATRIX_HOMESERVER {}", self.homeserver))
+            })?
+            .pop_if_empty()
+            .extend([
+                "_matrix",
+                "client",
+                "v3",
+                "rooms",
+                &self.room_id,
+                "send",
+                "m.room.message",
+                txn_id,
+            ]);
+        Ok(url)
+    }
+}
+
+pub(super) fn message(event: &NotificationEvent) -> String {
+    match event.kind {
+        EventKind::Updated => format!(
+            "✅ {} updated: {} → {}",
+            event.domain,
+            event.old_ip.as_deref().unwrap_or("unknown"),
+            event.new_ip
+        ),
+        EventKind::Failed => format!(
+            "❌ {} failed {} times in a row: {}",
+            event.domain,
+            event.failure_streak,
+            event.error.as_deref().unwrap_or("unknown error")
+        ),
+        EventKind::Recovered => format!(
+            "✅ {} recovered after {} failed attempts (public IP {})",
+            event.domain, event.failure_streak, event.new_ip
+        ),
+        EventKind::Digest => format!(
+            "📋 {} DNS events\n{}",
+            event.events.len(),
+            event.digest_text()
+        ),
+    }
+}
+
+pub(super) async fn send(
+    client: &ReqwestClient,
+    config: &MatrixConfig,
+    event: &NotificationEvent,
+) -> Result<(), FlareSyncError> {
+    let body = event.message_or(message(event));
+    let txn_id = format!(
+        "flaresync-{}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+    );
+    client
+        .put(config.send_url(&txn_id)?)
+        .timeout(NOTIFICATION_TIMEOUT)
+        .bearer_auth(&config.access_token)
+        .json(&json!({ "msgtype": "m.text", "body": body }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matrix_send_url_encodes_room_id() {
+        let config = MatrixConfig::new(
+            "https://matrix.example.org/",
+            "syt_token",
+            "!abcdef:example.org",
+        );
+        assert_eq!(
+            config.send_url("txn1").unwrap().as_str(),
+            "https://matrix.example.org/_matrix/client/v3/rooms/!abcdef:example.org/send/m.room.message/txn1"
+        );
+
+        let error = FlareSyncError::Cloudflare("permission denied".to_string());
+        let streak = NotificationEvent::failed("example.com", "203.0.113.7", &error, 3);
+        assert_eq!(
+            message(&streak),
+            "❌ example.com failed 3 times in a row: Cloudflare API error: permission denied"
+        );
+    }
+}
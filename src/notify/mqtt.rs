@@ -0,0 +1,386 @@
+use super::{EventKind, NotificationEvent, NOTIFICATION_TIMEOUT};
+use crate::errors::FlareSyncError;
+use crate::status::RuntimeStatus;
+use reqwest::Url;
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS, TlsConfiguration, Transport};
+use rustls::{ClientConfig, RootCertStore};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const DEFAULT_TOPIC: &str = "flaresync";
+pub const DEFAULT_CLIENT_ID: &str = "flaresync";
+pub const DEFAULT_HA_DISCOVERY_PREFIX: &str = "homeassistant";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub tls: bool,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub topic: String,
+    pub client_id: String,
+    pub discovery_prefix: Option<String>,
+}
+
+impl MqttConfig {
+    pub fn from_url(url: &str) -> Result<Self, FlareSyncError> {
+        let invalid =
+            |reason: &str| FlareSyncError::Config(format!("Invalid MQTT_URL {}: {}", url, reason));
+        let parsed = Url::parse(url).map_err(|e| invalid(&e.to_string()))?;
+        let (tls, default_port) = match parsed.scheme() {
+            "mqtt" | "tcp" => (false, 1883),
+            "mqtts" | "ssl" => (true, 8883),
+            _ => return Err(invalid("scheme must be mqtt or mqtts")),
+        };
+        let host = parsed
+            .host_str()
+            .filter(|host| !host.is_empty())
+            .ok_or_else(|| invalid("missing host"))?;
+        Ok(Self {
+            host: host.to_string(),
+            port: parsed.port().unwrap_or(default_port),
+            tls,
+            username: None,
+            password: None,
+            topic: DEFAULT_TOPIC.to_string(),
+            client_id: DEFAULT_CLIENT_ID.to_string(),
+            discovery_prefix: None,
+        })
+    }
+}
+
+pub(super) fn messages(
+    config: &MqttConfig,
+    event: &NotificationEvent,
+) -> Result<Vec<(String, bool, Vec<u8>)>, FlareSyncError> {
+    let topic = config.topic.trim_end_matches('/');
+    let mut messages = vec![(
+        format!("{}/events", topic),
+        false,
+        serde_json::to_vec(event)?,
+    )];
+    let domain_events = std::iter::once(event)
+        .chain(&event.events)
+        .filter(|event| event.kind != EventKind::Digest);
+    for domain_event in domain_events {
+        if domain_event.kind == EventKind::Updated {
+            messages.push((
+                format!("{}/{}/ip", topic, domain_event.domain),
+                true,
+                domain_event.new_ip.clone().into_bytes(),
+            ));
+        }
+        if let Some(prefix) = &config.discovery_prefix {
+            messages.extend(discovery_messages(
+                topic,
+                prefix.trim_end_matches('/'),
+                &domain_event.domain,
+            )?);
+            let state = serde_json::json!({
+                "status": domain_event.kind.as_str(),
+                "ip": domain_event.new_ip,
+                "error": domain_event.error,
+                "failure_streak": domain_event.failure_streak,
+                "timestamp": domain_event.timestamp,
+            });
+            messages.push((
+                format!("{}/{}/status", topic, domain_event.domain),
+                true,
+                serde_json::to_vec(&state)?,
+            ));
+        }
+    }
+    Ok(messages)
+}
+
+/// The Home Assistant discovery configs and retained state of every domain in `status`,
+/// published at startup and after every cycle so the sensors exist and stay current even
+/// when nothing changes. Empty without a discovery prefix.
+pub(super) fn state_messages(
+    config: &MqttConfig,
+    status: &RuntimeStatus,
+) -> Result<Vec<(String, bool, Vec<u8>)>, FlareSyncError> {
+    let Some(prefix) = &config.discovery_prefix else {
+        return Ok(Vec::new());
+    };
+    let topic = config.topic.trim_end_matches('/');
+    let mut messages = Vec::new();
+    for (domain, domain_status) in &status.domains {
+        messages.extend(discovery_messages(
+            topic,
+            prefix.trim_end_matches('/'),
+            domain,
+        )?);
+        let ip = status
+            .last_public_ip
+            .as_deref()
+            .filter(|_| domain_status.last_checked_at.is_some())
+            .filter(|_| domain_status.last_error.is_none());
+        if let Some(ip) = ip {
+            messages.push((
+                format!("{}/{}/ip", topic, domain),
+                true,
+                ip.as_bytes().to_vec(),
+            ));
+        }
+        let state = serde_json::json!({
+            "status": domain_status.display_status(),
+            "ip": ip,
+            "error": domain_status.last_error,
+            "failure_streak": domain_status.consecutive_failures,
+            "timestamp": domain_status.last_checked_at,
+        });
+        messages.push((
+            format!("{}/{}/status", topic, domain),
+            true,
+            serde_json::to_vec(&state)?,
+        ));
+    }
+    Ok(messages)
+}
+
+fn discovery_messages(
+    topic: &str,
+    prefix: &str,
+    domain: &str,
+) -> Result<Vec<(String, bool, Vec<u8>)>, FlareSyncError> {
+    let object_id: String = domain
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let node_id = format!("flaresync_{}", object_id);
+    let device = serde_json::json!({
+        "identifiers": [node_id],
+        "name": format!("FlareSync {}", domain),
+        "manufacturer": "FlareSync",
+        "sw_version": env!("CARGO_PKG_VERSION"),
+    });
+    let status_topic = format!("{}/{}/status", topic, domain);
+    let sensors = [
+        (
+            "ip",
+            serde_json::json!({
+                "name": "IP address",
+                "state_topic": format!("{}/{}/ip", topic, domain),
+                "icon": "mdi:ip-network",
+            }),
+        ),
+        (
+            "status",
+            serde_json::json!({
+                "name": "Last update status",
+                "state_topic": status_topic,
+                "value_template": "{{ value_json.status }}",
+                "json_attributes_topic": status_topic,
+                "icon": "mdi:cloud-sync",
+            }),
+        ),
+        (
+            "last_update",
+            serde_json::json!({
+                "name": "Last update",
+                "state_topic": status_topic,
+                "value_template": "{{ value_json.timestamp }}",
+                "device_class": "timestamp",
+            }),
+        ),
+    ];
+
+    let mut messages = Vec::with_capacity(sensors.len());
+    for (object, mut payload) in sensors {
+        payload["unique_id"] = format!("{}_{}", node_id, object).into();
+        payload["device"] = device.clone();
+        messages.push((
+            format!("{}/sensor/{}/{}/config", prefix, node_id, object),
+            true,
+            serde_json::to_vec(&payload)?,
+        ));
+    }
+    Ok(messages)
+}
+
+fn tls_config() -> Result<Arc<ClientConfig>, FlareSyncError> {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| FlareSyncError::Notification(format!("MQTT TLS error: {}", e)))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+pub(super) async fn send(
+    config: &MqttConfig,
+    event: &NotificationEvent,
+) -> Result<(), FlareSyncError> {
+    publish(config, messages(config, event)?).await
+}
+
+pub(super) async fn publish_state(
+    config: &MqttConfig,
+    status: &RuntimeStatus,
+) -> Result<(), FlareSyncError> {
+    let messages = state_messages(config, status)?;
+    if messages.is_empty() {
+        return Ok(());
+    }
+    publish(config, messages).await
+}
+
+async fn publish(
+    config: &MqttConfig,
+    messages: Vec<(String, bool, Vec<u8>)>,
+) -> Result<(), FlareSyncError> {
+    let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    if let Some(username) = &config.username {
+        options.set_credentials(username, config.password.clone().unwrap_or_default());
+    }
+    if config.tls {
+        options.set_transport(Transport::tls_with_config(TlsConfiguration::Rustls(
+            tls_config()?,
+        )));
+    }
+
+    let mqtt_error =
+        |e: &dyn std::fmt::Display| FlareSyncError::Notification(format!("MQTT error: {}", e));
+    let (client, mut eventloop) = AsyncClient::new(options, messages.len() + 1);
+    for (topic, retain, payload) in &messages {
+        client
+            .publish(topic, QoS::AtLeastOnce, *retain, payload.clone())
+            .await
+            .map_err(|e| mqtt_error(&e))?;
+    }
+
+    let mut pending = messages.len();
+    tokio::time::timeout(NOTIFICATION_TIMEOUT, async {
+        while pending > 0 {
+            if let Event::Incoming(Packet::PubAck(_)) =
+                eventloop.poll().await.map_err(|e| mqtt_error(&e))?
+            {
+                pending -= 1;
+            }
+        }
+        Ok::<_, FlareSyncError>(())
+    })
+    .await
+    .map_err(|_| {
+        FlareSyncError::Timeout(format!(
+            "MQTT broker {}:{} did not acknowledge the publish",
+            config.host, config.port
+        ))
+    })??;
+
+    if client.disconnect().await.is_ok() {
+        let _ = tokio::time::timeout(Duration::from_secs(1), eventloop.poll()).await;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mqtt_url_and_topics() {
+        let config = MqttConfig::from_url("mqtts://broker.example.com").unwrap();
+        assert_eq!((config.port, config.tls), (8883, true));
+        let config = MqttConfig::from_url("mqtt://192.168.1.10:1884").unwrap();
+        assert_eq!(
+            (config.host.as_str(), config.port, config.tls),
+            ("192.168.1.10", 1884, false)
+        );
+        assert!(matches!(
+            MqttConfig::from_url("http://broker.example.com"),
+            Err(FlareSyncError::Config(_))
+        ));
+
+        let event = NotificationEvent::updated("example.com", "198.51.100.1", "203.0.113.7", false);
+        let messages = messages(&config, &event).unwrap();
+        assert_eq!(messages[0].0, "flaresync/events");
+        assert_eq!(
+            messages[1],
+            (
+                "flaresync/example.com/ip".to_string(),
+                true,
+                b"203.0.113.7".to_vec()
+            )
+        );
+    }
+
+    #[test]
+    fn test_home_assistant_discovery() {
+        let mut config = MqttConfig::from_url("mqtt://broker.example.com").unwrap();
+        config.discovery_prefix = Some(DEFAULT_HA_DISCOVERY_PREFIX.to_string());
+        let event =
+            NotificationEvent::updated("home.example.com", "198.51.100.1", "203.0.113.7", false);
+        let messages = messages(&config, &event).unwrap();
+        let topics: Vec<&str> = messages
+            .iter()
+            .map(|(topic, _, _)| topic.as_str())
+            .collect();
+        assert_eq!(
+            topics,
+            [
+                "flaresync/events",
+                "flaresync/home.example.com/ip",
+                "homeassistant/sensor/flaresync_home_example_com/ip/config",
+                "homeassistant/sensor/flaresync_home_example_com/status/config",
+                "homeassistant/sensor/flaresync_home_example_com/last_update/config",
+                "flaresync/home.example.com/status",
+            ]
+        );
+
+        let ip_sensor: serde_json::Value = serde_json::from_slice(&messages[2].2).unwrap();
+        assert_eq!(ip_sensor["state_topic"], "flaresync/home.example.com/ip");
+        assert_eq!(ip_sensor["unique_id"], "flaresync_home_example_com_ip");
+        let state: serde_json::Value = serde_json::from_slice(&messages[5].2).unwrap();
+        assert_eq!(state["status"], "updated");
+        assert!(messages[2..].iter().all(|(_, retain, _)| *retain));
+    }
+
+    #[test]
+    fn test_state_is_published_for_a_cycle_without_changes() {
+        let mut config = MqttConfig::from_url("mqtt://broker.example.com").unwrap();
+        let mut status = RuntimeStatus::new();
+        status.mark_ip_check_success(&"203.0.113.7".parse().unwrap());
+        status.mark_domain_result("home.example.com", "unchanged", false);
+        assert!(state_messages(&config, &status).unwrap().is_empty());
+
+        config.discovery_prefix = Some(DEFAULT_HA_DISCOVERY_PREFIX.to_string());
+        let messages = state_messages(&config, &status).unwrap();
+        let topics: Vec<&str> = messages
+            .iter()
+            .map(|(topic, _, _)| topic.as_str())
+            .collect();
+        assert_eq!(
+            topics,
+            [
+                "homeassistant/sensor/flaresync_home_example_com/ip/config",
+                "homeassistant/sensor/flaresync_home_example_com/status/config",
+                "homeassistant/sensor/flaresync_home_example_com/last_update/config",
+                "flaresync/home.example.com/ip",
+                "flaresync/home.example.com/status",
+            ]
+        );
+        assert!(messages.iter().all(|(_, retain, _)| *retain));
+        assert_eq!(messages[3].2, b"203.0.113.7");
+        let state: serde_json::Value = serde_json::from_slice(&messages[4].2).unwrap();
+        assert_eq!(state["status"], "unchanged");
+        assert_eq!(state["ip"], "203.0.113.7");
+        assert!(state["timestamp"].is_string());
+
+        status.mark_domain_error(
+            "home.example.com",
+            &FlareSyncError::Config("bad token".to_string()),
+        );
+        let messages = state_messages(&config, &status).unwrap();
+        assert_eq!(messages.len(), 4);
+        let state: serde_json::Value = serde_json::from_slice(&messages[3].2).unwrap();
+        assert_eq!(state["status"], "error");
+        assert!(state["ip"].is_null());
+    }
+}
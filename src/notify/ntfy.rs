@@ -0,0 +1,132 @@
+use super::{EventKind, NotificationEvent, NOTIFICATION_TIMEOUT};
+use crate::errors::FlareSyncError;
+use reqwest::Client as ReqwestClient;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NtfyConfig {
+    pub topic_url: String,
+    pub access_token: Option<String>,
+    pub priority: Option<u8>,
+    pub tags: Vec<String>,
+}
+
+impl NtfyConfig {
+    pub fn new(topic_url: impl Into<String>) -> Self {
+        Self {
+            topic_url: topic_url.into(),
+            access_token: None,
+            priority: None,
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn parse_priority(value: &str) -> Result<u8, FlareSyncError> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "1" | "min" => Ok(1),
+            "2" | "low" => Ok(2),
+            "3" | "default" => Ok(3),
+            "4" | "high" => Ok(4),
+            "5" | "max" | "urgent" => Ok(5),
+            _ => Err(FlareSyncError::Config(
+                "NTFY_PRIORITY must be 1-5 or min, low, default, high, max".to_string(),
+            )),
+        }
+    }
+
+    pub fn parse_tags(value: &str) -> Vec<String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+pub(super) fn message(event: &NotificationEvent) -> (String, String, &'static str) {
+    match event.kind {
+        EventKind::Updated => (
+            format!("{} updated", event.domain),
+            format!(
+                "{} → {}",
+                event.old_ip.as_deref().unwrap_or("unknown"),
+                event.new_ip
+            ),
+            "white_check_mark",
+        ),
+        EventKind::Failed => (
+            format!("{} update failed", event.domain),
+            format!(
+                "{} (failure {} in a row)",
+                event.error.as_deref().unwrap_or("unknown error"),
+                event.failure_streak
+            ),
+            "x",
+        ),
+        EventKind::Recovered => (
+            format!("{} recovered", event.domain),
+            format!(
+                "Recovered after {} failed attempts (public IP {})",
+                event.failure_streak, event.new_ip
+            ),
+            "green_circle",
+        ),
+        EventKind::Digest => (
+            format!("FlareSync: {} DNS events", event.events.len()),
+            event.digest_text(),
+            "clipboard",
+        ),
+    }
+}
+
+pub(super) async fn send(
+    client: &ReqwestClient,
+    config: &NtfyConfig,
+    event: &NotificationEvent,
+) -> Result<(), FlareSyncError> {
+    let (title, body, kind_tag) = message(event);
+    let body = event.message_or(body);
+    let tags: Vec<&str> = std::iter::once(kind_tag)
+        .chain(config.tags.iter().map(String::as_str))
+        .collect();
+
+    let mut request = client
+        .post(&config.topic_url)
+        .timeout(NOTIFICATION_TIMEOUT)
+        .header("Title", title)
+        .header("Tags", tags.join(","))
+        .body(body);
+    if let Some(priority) = config.priority {
+        request = request.header("Priority", priority.to_string());
+    }
+    if let Some(token) = &config.access_token {
+        request = request.bearer_auth(token);
+    }
+    request.send().await?.error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ntfy_priority_and_message() {
+        assert_eq!(NtfyConfig::parse_priority("high").unwrap(), 4);
+        assert_eq!(NtfyConfig::parse_priority(" 5 ").unwrap(), 5);
+        assert!(matches!(
+            NtfyConfig::parse_priority("6"),
+            Err(FlareSyncError::Config(_))
+        ));
+        assert_eq!(
+            NtfyConfig::parse_tags("house, ,dns"),
+            vec!["house".to_string(), "dns".to_string()]
+        );
+
+        let event = NotificationEvent::updated("example.com", "198.51.100.1", "203.0.113.7", false);
+        let (title, body, tag) = message(&event);
+        assert_eq!(title, "example.com updated");
+        assert_eq!(body, "198.51.100.1 → 203.0.113.7");
+        assert_eq!(tag, "white_check_mark");
+    }
+}
@@ -0,0 +1,99 @@
+use super::{EventKind, NotificationEvent, NOTIFICATION_TIMEOUT};
+use crate::errors::FlareSyncError;
+use reqwest::Client as ReqwestClient;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PushoverConfig {
+    pub app_token: String,
+    pub user_key: String,
+}
+
+impl PushoverConfig {
+    pub fn new(app_token: impl Into<String>, user_key: impl Into<String>) -> Self {
+        Self {
+            app_token: app_token.into(),
+            user_key: user_key.into(),
+        }
+    }
+}
+
+pub(super) fn payload(config: &PushoverConfig, event: &NotificationEvent) -> Value {
+    let (title, message, priority) = match event.kind {
+        EventKind::Updated => (
+            format!("{} updated", event.domain),
+            format!(
+                "{} → {}",
+                event.old_ip.as_deref().unwrap_or("unknown"),
+                event.new_ip
+            ),
+            0,
+        ),
+        EventKind::Failed => (
+            format!("{} failing", event.domain),
+            format!(
+                "Failed {} times in a row: {}",
+                event.failure_streak,
+                event.error.as_deref().unwrap_or("unknown error")
+            ),
+            1,
+        ),
+        EventKind::Recovered => (
+            format!("{} recovered", event.domain),
+            format!(
+                "Recovered after {} failed attempts. Public IP: {}",
+                event.failure_streak, event.new_ip
+            ),
+            0,
+        ),
+        EventKind::Digest => (
+            format!("FlareSync: {} DNS events", event.events.len()),
+            event.digest_text(),
+            i32::from(event.has_failures()),
+        ),
+    };
+    let message = event.message_or(message);
+    json!({
+        "token": config.app_token,
+        "user": config.user_key,
+        "title": title,
+        "message": message,
+        "priority": priority,
+    })
+}
+
+pub(super) async fn send(
+    client: &ReqwestClient,
+    config: &PushoverConfig,
+    event: &NotificationEvent,
+) -> Result<(), FlareSyncError> {
+    client
+        .post("https://api.pushover.net/1/messages.json")
+        .timeout(NOTIFICATION_TIMEOUT)
+        .json(&payload(config, event))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pushover_payload_priorities() {
+        let config = PushoverConfig::new("app-token", "user-key");
+
+        let updated =
+            NotificationEvent::updated("example.com", "198.51.100.1", "203.0.113.7", false);
+        let payload = payload(&config, &updated);
+        assert_eq!(payload["user"], "user-key");
+        assert_eq!(payload["message"], "198.51.100.1 → 203.0.113.7");
+        assert_eq!(payload["priority"], 0);
+
+        let error = FlareSyncError::Cloudflare("permission denied".to_string());
+        let third = NotificationEvent::failed("example.com", "203.0.113.7", &error, 3);
+        assert_eq!(super::payload(&config, &third)["priority"], 1);
+    }
+}
@@ -0,0 +1,102 @@
+use super::{EventKind, NotificationEvent, NOTIFICATION_TIMEOUT};
+use crate::errors::FlareSyncError;
+use reqwest::Client as ReqwestClient;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignalConfig {
+    pub api_url: String,
+    pub number: String,
+    pub recipients: Vec<String>,
+}
+
+impl SignalConfig {
+    pub fn new(
+        api_url: impl Into<String>,
+        number: impl Into<String>,
+        recipients: Vec<String>,
+    ) -> Self {
+        Self {
+            api_url: api_url.into(),
+            number: number.into(),
+            recipients,
+        }
+    }
+
+    pub fn send_url(&self) -> String {
+        format!("{}/v2/send", self.api_url.trim_end_matches('/'))
+    }
+}
+
+pub(super) fn message(event: &NotificationEvent) -> String {
+    match event.kind {
+        EventKind::Updated => format!(
+            "✅ FlareSync: {} updated\n{} → {}",
+            event.domain,
+            event.old_ip.as_deref().unwrap_or("unknown"),
+            event.new_ip
+        ),
+        EventKind::Failed => format!(
+            "❌ FlareSync: {} failed {} times in a row\n{}",
+            event.domain,
+            event.failure_streak,
+            event.error.as_deref().unwrap_or("unknown error")
+        ),
+        EventKind::Recovered => format!(
+            "✅ FlareSync: {} recovered after {} failed attempts\nPublic IP: {}",
+            event.domain, event.failure_streak, event.new_ip
+        ),
+        EventKind::Digest => format!(
+            "📋 FlareSync: {} DNS events\n{}",
+            event.events.len(),
+            event.digest_text()
+        ),
+    }
+}
+
+pub(super) fn payload(config: &SignalConfig, event: &NotificationEvent) -> Value {
+    json!({
+        "message": event.message_or(message(event)),
+        "number": config.number,
+        "recipients": config.recipients,
+    })
+}
+
+pub(super) async fn send(
+    client: &ReqwestClient,
+    config: &SignalConfig,
+    event: &NotificationEvent,
+) -> Result<(), FlareSyncError> {
+    client
+        .post(config.send_url())
+        .timeout(NOTIFICATION_TIMEOUT)
+        .json(&payload(config, event))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signal_send_url_and_payload() {
+        let config = SignalConfig::new(
+            "http://signal-api:8080/",
+            "+31600000000",
+            vec!["+31611111111".to_string(), "group.abc=".to_string()],
+        );
+        assert_eq!(config.send_url(), "http://signal-api:8080/v2/send");
+
+        let event = NotificationEvent::updated("example.com", "198.51.100.1", "203.0.113.7", false);
+        let payload = payload(&config, &event);
+        assert_eq!(
+            payload["message"],
+            "✅ FlareSync: example.com updated\n198.51.100.1 → 203.0.113.7"
+        );
+        assert_eq!(payload["number"], "+31600000000");
+        assert_eq!(payload["recipients"][1], "group.abc=");
+    }
+}
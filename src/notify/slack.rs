@@ -0,0 +1,106 @@
+use super::{EventKind, NotificationEvent, NOTIFICATION_TIMEOUT};
+use crate::errors::FlareSyncError;
+use reqwest::Client as ReqwestClient;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlackConfig {
+    pub webhook_url: String,
+}
+
+impl SlackConfig {
+    pub fn new(webhook_url: impl Into<String>) -> Self {
+        Self {
+            webhook_url: webhook_url.into(),
+        }
+    }
+}
+
+pub(super) fn payload(event: &NotificationEvent) -> Value {
+    let (summary, details) = match event.kind {
+        EventKind::Updated => (
+            format!(
+                ":white_check_mark: DNS record updated for *{}*",
+                event.domain
+            ),
+            vec![
+                format!("*Old IP*\n{}", event.old_ip.as_deref().unwrap_or("unknown")),
+                format!("*New IP*\n{}", event.new_ip),
+            ],
+        ),
+        EventKind::Failed => (
+            format!(":x: DNS update failed for *{}*", event.domain),
+            vec![
+                format!("*Error*\n{}", event.error.as_deref().unwrap_or("unknown")),
+                format!("*Consecutive failures*\n{}", event.failure_streak),
+            ],
+        ),
+        EventKind::Recovered => (
+            format!(
+                ":large_green_circle: DNS updates recovered for *{}*",
+                event.domain
+            ),
+            vec![
+                format!("*Public IP*\n{}", event.new_ip),
+                format!("*Failures before recovery*\n{}", event.failure_streak),
+            ],
+        ),
+        EventKind::Digest => (
+            format!(":clipboard: {} DNS events", event.events.len()),
+            vec![event.digest_text()],
+        ),
+    };
+    let summary = event.message_or(summary);
+    let fields: Vec<Value> = details
+        .into_iter()
+        .map(|text| json!({ "type": "mrkdwn", "text": text }))
+        .collect();
+
+    json!({
+        "text": summary,
+        "blocks": [
+            { "type": "section", "text": { "type": "mrkdwn", "text": summary } },
+            { "type": "section", "fields": fields },
+            {
+                "type": "context",
+                "elements": [{ "type": "mrkdwn", "text": format!("FlareSync · {}", event.timestamp) }]
+            }
+        ]
+    })
+}
+
+pub(super) async fn send(
+    client: &ReqwestClient,
+    config: &SlackConfig,
+    event: &NotificationEvent,
+) -> Result<(), FlareSyncError> {
+    client
+        .post(&config.webhook_url)
+        .timeout(NOTIFICATION_TIMEOUT)
+        .json(&payload(event))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slack_payload_uses_block_kit_sections() {
+        let event = NotificationEvent::updated("example.com", "198.51.100.1", "203.0.113.7", false);
+        let payload = payload(&event);
+
+        assert_eq!(
+            payload["blocks"][0]["text"]["text"],
+            ":white_check_mark: DNS record updated for *example.com*"
+        );
+        assert_eq!(
+            payload["blocks"][1]["fields"][1]["text"],
+            "*New IP*\n203.0.113.7"
+        );
+        assert_eq!(payload["text"], payload["blocks"][0]["text"]["text"]);
+    }
+}
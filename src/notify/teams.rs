@@ -0,0 +1,134 @@
+use super::{EventKind, NotificationEvent, NOTIFICATION_TIMEOUT};
+use crate::errors::FlareSyncError;
+use reqwest::Client as ReqwestClient;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TeamsConfig {
+    pub webhook_url: String,
+}
+
+impl TeamsConfig {
+    pub fn new(webhook_url: impl Into<String>) -> Self {
+        Self {
+            webhook_url: webhook_url.into(),
+        }
+    }
+}
+
+pub(super) fn payload(event: &NotificationEvent) -> Value {
+    let (title, color, facts) = match event.kind {
+        EventKind::Updated => (
+            format!("DNS record updated for {}", event.domain),
+            "Good",
+            vec![
+                (
+                    "Old IP",
+                    event.old_ip.as_deref().unwrap_or("unknown").to_string(),
+                ),
+                ("New IP", event.new_ip.clone()),
+            ],
+        ),
+        EventKind::Failed => (
+            format!("DNS update failed for {}", event.domain),
+            "Attention",
+            vec![
+                (
+                    "Error",
+                    event.error.as_deref().unwrap_or("unknown").to_string(),
+                ),
+                ("Consecutive failures", event.failure_streak.to_string()),
+            ],
+        ),
+        EventKind::Recovered => (
+            format!("DNS updates recovered for {}", event.domain),
+            "Good",
+            vec![
+                ("Public IP", event.new_ip.clone()),
+                ("Failures before recovery", event.failure_streak.to_string()),
+            ],
+        ),
+        EventKind::Digest => (
+            format!("{} DNS events", event.events.len()),
+            if event.has_failures() {
+                "Attention"
+            } else {
+                "Good"
+            },
+            Vec::new(),
+        ),
+    };
+    let facts: Vec<Value> = facts
+        .into_iter()
+        .map(|(title, value)| json!({ "title": title, "value": value }))
+        .collect();
+
+    let mut body = vec![json!({
+        "type": "TextBlock",
+        "text": event.message_or(title),
+        "weight": "Bolder",
+        "size": "Medium",
+        "color": color,
+        "wrap": true,
+    })];
+    if event.kind == EventKind::Digest {
+        body.push(json!({ "type": "TextBlock", "text": event.digest_text(), "wrap": true }));
+    } else {
+        body.push(json!({ "type": "FactSet", "facts": facts }));
+    }
+    body.push(json!({
+        "type": "TextBlock",
+        "text": format!("FlareSync · {}", event.timestamp),
+        "isSubtle": true,
+        "size": "Small",
+        "wrap": true,
+    }));
+
+    json!({
+        "type": "message",
+        "attachments": [{
+            "contentType": "application/vnd.microsoft.card.adaptive",
+            "content": {
+                "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                "type": "AdaptiveCard",
+                "version": "1.4",
+                "body": body,
+            }
+        }]
+    })
+}
+
+pub(super) async fn send(
+    client: &ReqwestClient,
+    config: &TeamsConfig,
+    event: &NotificationEvent,
+) -> Result<(), FlareSyncError> {
+    client
+        .post(&config.webhook_url)
+        .timeout(NOTIFICATION_TIMEOUT)
+        .json(&payload(event))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_teams_payload_is_adaptive_card() {
+        let event = NotificationEvent::updated("example.com", "198.51.100.1", "203.0.113.7", false);
+        let payload = payload(&event);
+
+        let card = &payload["attachments"][0];
+        assert_eq!(
+            card["contentType"],
+            "application/vnd.microsoft.card.adaptive"
+        );
+        let body = &card["content"]["body"];
+        assert_eq!(body[0]["text"], "DNS record updated for example.com");
+        assert_eq!(body[1]["facts"][1]["value"], "203.0.113.7");
+    }
+}
@@ -0,0 +1,90 @@
+use super::{EventKind, NotificationEvent, NOTIFICATION_TIMEOUT};
+use crate::errors::FlareSyncError;
+use reqwest::Client as ReqwestClient;
+use serde_json::json;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TelegramConfig {
+    pub bot_token: String,
+    pub chat_id: String,
+}
+
+impl TelegramConfig {
+    pub fn new(bot_token: impl Into<String>, chat_id: impl Into<String>) -> Self {
+        Self {
+            bot_token: bot_token.into(),
+            chat_id: chat_id.into(),
+        }
+    }
+}
+
+pub(super) fn message(event: &NotificationEvent) -> String {
+    match event.kind {
+        EventKind::Updated => format!(
+            "✅ {} updated\n{} → {}",
+            event.domain,
+            event.old_ip.as_deref().unwrap_or("unknown"),
+            event.new_ip
+        ),
+        EventKind::Failed => format!(
+            "❌ {} failed {} times in a row\n{}",
+            event.domain,
+            event.failure_streak,
+            event.error.as_deref().unwrap_or("unknown error")
+        ),
+        EventKind::Recovered => format!(
+            "✅ {} recovered after {} failed attempts\nPublic IP: {}",
+            event.domain, event.failure_streak, event.new_ip
+        ),
+        EventKind::Digest => format!(
+            "📋 {} DNS events\n{}",
+            event.events.len(),
+            event.digest_text()
+        ),
+    }
+}
+
+pub(super) async fn send(
+    client: &ReqwestClient,
+    config: &TelegramConfig,
+    event: &NotificationEvent,
+) -> Result<(), FlareSyncError> {
+    let text = event.message_or(message(event));
+    client
+        .post(format!(
+            "https://api.telegram.org/bot{}/sendMessage",
+            config.bot_token
+        ))
+        .timeout(NOTIFICATION_TIMEOUT)
+        .json(&json!({
+            "chat_id": config.chat_id,
+            "text": text,
+            "disable_web_page_preview": true,
+        }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_telegram_message_for_updates_and_error_streaks() {
+        let updated =
+            NotificationEvent::updated("example.com", "198.51.100.1", "203.0.113.7", false);
+        assert_eq!(
+            message(&updated),
+            "✅ example.com updated\n198.51.100.1 → 203.0.113.7"
+        );
+
+        let error = FlareSyncError::Cloudflare("permission denied".to_string());
+        let streak = NotificationEvent::failed("example.com", "203.0.113.7", &error, 3);
+        assert_eq!(
+            message(&streak),
+            "❌ example.com failed 3 times in a row\nCloudflare API error: permission denied"
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use super::{render_template, NotificationEvent, NOTIFICATION_TIMEOUT};
+use crate::errors::FlareSyncError;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client as ReqwestClient, Method};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookConfig {
+    pub url: String,
+    pub method: Method,
+    pub headers: Vec<(String, String)>,
+    pub body_template: Option<String>,
+}
+
+impl WebhookConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            method: Method::POST,
+            headers: Vec::new(),
+            body_template: None,
+        }
+    }
+
+    pub fn parse_method(value: &str) -> Result<Method, FlareSyncError> {
+        Method::from_bytes(value.trim().to_ascii_uppercase().as_bytes())
+            .map_err(|_| FlareSyncError::Config(format!("Invalid WEBHOOK_METHOD {}", value)))
+    }
+
+    pub fn parse_headers(value: &str) -> Result<Vec<(String, String)>, FlareSyncError> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|header| !header.is_empty())
+            .map(|header| {
+                let (name, value) = header.split_once(':').ok_or_else(|| {
+                    FlareSyncError::Config(format!(
+                        "WEBHOOK_HEADERS entry {} must look like Name: value",
+                        header
+                    ))
+                })?;
+                let name = name.trim();
+                reqwest::header::HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+                    FlareSyncError::Config(format!("Invalid WEBHOOK_HEADERS name {}", name))
+                })?;
+                Ok((name.to_string(), value.trim().to_string()))
+            })
+            .collect()
+    }
+
+    pub(super) fn secrets(&self) -> Vec<String> {
+        let mut secrets = vec![self.url.clone()];
+        secrets.extend(self.headers.iter().map(|(_, value)| value.clone()));
+        secrets
+    }
+}
+
+fn escape_json(value: &str) -> String {
+    let quoted = serde_json::Value::String(value.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+pub(super) fn body(
+    config: &WebhookConfig,
+    event: &NotificationEvent,
+) -> Result<String, FlareSyncError> {
+    match &config.body_template {
+        Some(template) => Ok(render_template(template, event, escape_json)),
+        None => Ok(serde_json::to_string(event)?),
+    }
+}
+
+pub(super) async fn send(
+    client: &ReqwestClient,
+    config: &WebhookConfig,
+    event: &NotificationEvent,
+) -> Result<(), FlareSyncError> {
+    let mut request = client
+        .request(config.method.clone(), &config.url)
+        .timeout(NOTIFICATION_TIMEOUT)
+        .header(CONTENT_TYPE, "application/json")
+        .body(body(config, event)?);
+    for (name, value) in &config.headers {
+        request = request.header(name, value);
+    }
+    request.send().await?.error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderMap;
+    use axum::routing::put;
+    use axum::Router;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_webhook_body_template_escapes_json_values() {
+        let mut config = WebhookConfig::new("http://localhost/hook");
+        config.body_template = Some(r#"{"text":"{{domain}} failed: {{error}}"}"#.to_string());
+        let error = FlareSyncError::Cloudflare(r#"bad "record""#.to_string());
+        let event = NotificationEvent::failed("example.com", "203.0.113.7", &error, 1);
+
+        let body: serde_json::Value =
+            serde_json::from_str(&body(&config, &event).unwrap()).unwrap();
+        assert_eq!(
+            body["text"],
+            r#"example.com failed: Cloudflare API error: bad "record""#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_webhook_send_uses_configured_method_and_headers() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let app = Router::new().route(
+            "/hook",
+            put(move |headers: HeaderMap, body: String| {
+                let tx = tx.clone();
+                async move {
+                    let token = headers["x-token"].to_str().unwrap().to_string();
+                    tx.send((token, body)).unwrap();
+                }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut config = WebhookConfig::new(format!("http://{}/hook", address));
+        config.method = WebhookConfig::parse_method("put").unwrap();
+        config.headers = WebhookConfig::parse_headers("X-Token: abc123").unwrap();
+        let event = NotificationEvent::updated("example.com", "198.51.100.1", "203.0.113.7", false);
+
+        send(&reqwest::Client::new(), &config, &event)
+            .await
+            .unwrap();
+
+        let (token, body) = rx.recv().await.unwrap();
+        assert_eq!(token, "abc123");
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["kind"], "updated");
+        assert_eq!(body["old_ip"], "198.51.100.1");
+    }
+}
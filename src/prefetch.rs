@@ -0,0 +1,174 @@
+use crate::cloudflare::{get_dns_record, DnsRecord};
+use crate::config::Config;
+use crate::errors::FlareSyncError;
+use crate::exit_code;
+use crate::logging::EVENTS_TARGET;
+use reqwest::Client as ReqwestClient;
+use std::collections::HashMap;
+use std::str::FromStr;
+use tracing::{error, info, warn};
+
+/// Records fetched before the first cycle, by domain. `None` means the domain has no A
+/// record.
+pub type Records = HashMap<String, Option<DnsRecord>>;
+
+/// Whether the daemon fetches every record before its first cycle, and what a missing
+/// record or a permission error then means.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PrefetchPolicy {
+    /// Fetch nothing up front.
+    #[default]
+    Off,
+    /// Fetch every record and log problems, then start anyway.
+    Warn,
+    /// Refuse to start when a record is missing or the token cannot read one.
+    Strict,
+}
+
+impl PrefetchPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PrefetchPolicy::Off => "off",
+            PrefetchPolicy::Warn => "warn",
+            PrefetchPolicy::Strict => "strict",
+        }
+    }
+}
+
+impl FromStr for PrefetchPolicy {
+    type Err = FlareSyncError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "off" => Ok(PrefetchPolicy::Off),
+            "warn" => Ok(PrefetchPolicy::Warn),
+            "strict" => Ok(PrefetchPolicy::Strict),
+            _ => Err(FlareSyncError::Config(
+                "PREFETCH_RECORDS must be off, warn, or strict".to_string(),
+            )),
+        }
+    }
+}
+
+/// Looks up the A record of each domain in `domains`, one after another.
+pub async fn fetch(
+    client: &ReqwestClient,
+    config: &Config,
+    domains: &[String],
+) -> Vec<(String, Result<Option<DnsRecord>, FlareSyncError>)> {
+    let mut results = Vec::with_capacity(domains.len());
+    for domain_name in domains {
+        let record = get_dns_record(client, &config.api_token, &config.zone_id, domain_name).await;
+        results.push((domain_name.clone(), record));
+    }
+    results
+}
+
+/// Logs every missing record and failed lookup and keeps the records that were found.
+/// Under [`PrefetchPolicy::Strict`], returns the first missing record or permission error
+/// instead. Other lookup errors are left for the first cycle to retry.
+pub fn check(
+    policy: PrefetchPolicy,
+    results: Vec<(String, Result<Option<DnsRecord>, FlareSyncError>)>,
+) -> Result<Records, FlareSyncError> {
+    let total = results.len();
+    let mut records = Records::new();
+    let mut problem = None;
+    for (domain_name, result) in results {
+        match result {
+            Ok(Some(record)) => {
+                records.insert(domain_name, Some(record));
+            }
+            Ok(None) => {
+                warn!(
+                    target: EVENTS_TARGET,
+                    "No A record exists for {}. FlareSync only updates existing records.", domain_name
+                );
+                problem.get_or_insert_with(|| {
+                    FlareSyncError::Config(format!("No A record exists for {}", domain_name))
+                });
+                records.insert(domain_name, None);
+            }
+            Err(e) if exit_code::for_error(&e) == exit_code::AUTH_ERROR => {
+                error!(
+                    target: EVENTS_TARGET,
+                    "The API token cannot read the record for {}: {}", domain_name, e
+                );
+                problem.get_or_insert(e);
+            }
+            Err(e) => warn!(
+                target: EVENTS_TARGET,
+                "Failed to fetch the record for {} at startup: {}", domain_name, e
+            ),
+        }
+    }
+    info!(
+        "Fetched {} of {} records before the first check",
+        records.values().flatten().count(),
+        total
+    );
+    match problem {
+        Some(problem) if policy == PrefetchPolicy::Strict => Err(problem),
+        _ => Ok(records),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(name: &str) -> DnsRecord {
+        DnsRecord {
+            id: "1".to_string(),
+            name: name.to_string(),
+            content: "203.0.113.1".to_string(),
+            record_type: "A".to_string(),
+            proxied: false,
+            ttl: 1,
+            modified_on: None,
+        }
+    }
+
+    fn results() -> Vec<(String, Result<Option<DnsRecord>, FlareSyncError>)> {
+        vec![
+            ("example.com".to_string(), Ok(Some(record("example.com")))),
+            (
+                "flaky.example.com".to_string(),
+                Err(FlareSyncError::Timeout("slow".to_string())),
+            ),
+            ("new.example.com".to_string(), Ok(None)),
+            (
+                "secret.example.com".to_string(),
+                Err(FlareSyncError::Cloudflare(
+                    "Authentication error".to_string(),
+                )),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_check_keeps_records_and_fails_strict_on_problems() {
+        assert_eq!(
+            "Strict".parse::<PrefetchPolicy>().unwrap(),
+            PrefetchPolicy::Strict
+        );
+        assert!("always".parse::<PrefetchPolicy>().is_err());
+
+        let records = check(PrefetchPolicy::Warn, results()).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records["example.com"].as_ref().unwrap().name, "example.com");
+        assert!(records["new.example.com"].is_none());
+        assert!(!records.contains_key("flaky.example.com"));
+
+        assert!(matches!(
+            check(PrefetchPolicy::Strict, results()),
+            Err(FlareSyncError::Config(message)) if message.contains("new.example.com")
+        ));
+        let mut results = results();
+        results.retain(|(domain_name, _)| domain_name != "new.example.com");
+        assert!(matches!(
+            check(PrefetchPolicy::Strict, results),
+            Err(FlareSyncError::Cloudflare(_))
+        ));
+    }
+}
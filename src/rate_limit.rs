@@ -0,0 +1,115 @@
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::{self, Instant};
+use tracing::debug;
+
+/// Cloudflare allows 1200 requests per five minutes per user. 200 a minute plus a full
+/// burst stays well under that in any five-minute window.
+pub const DEFAULT_REQUESTS_PER_MINUTE: u32 = 200;
+
+/// How many seconds' worth of requests may go out back to back after an idle period.
+const BURST_SECONDS: f64 = 10.0;
+
+static LIMITER: Mutex<Option<TokenBucket>> = Mutex::new(None);
+
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    per_second: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(requests_per_minute: u32, now: Instant) -> Self {
+        let mut bucket = Self {
+            capacity: 0.0,
+            per_second: 0.0,
+            tokens: 0.0,
+            refilled_at: now,
+        };
+        bucket.set_rate(requests_per_minute);
+        bucket.tokens = bucket.capacity;
+        bucket
+    }
+
+    fn set_rate(&mut self, requests_per_minute: u32) {
+        self.per_second = f64::from(requests_per_minute.max(1)) / 60.0;
+        self.capacity = (self.per_second * BURST_SECONDS).max(1.0);
+        self.tokens = self.tokens.min(self.capacity);
+    }
+
+    /// Takes a token, or returns how long until one is available.
+    fn take(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now
+            .saturating_duration_since(self.refilled_at)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.refilled_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / self.per_second,
+            ))
+        }
+    }
+}
+
+/// Sets the process-wide Cloudflare request rate. Tokens already saved up are kept, up to
+/// the new burst size.
+pub fn configure(requests_per_minute: u32) {
+    let mut limiter = LIMITER.lock().unwrap();
+    match limiter.as_mut() {
+        Some(bucket) => bucket.set_rate(requests_per_minute),
+        None => *limiter = Some(TokenBucket::new(requests_per_minute, Instant::now())),
+    }
+}
+
+/// Waits until another Cloudflare request may be sent. Every Cloudflare call in the
+/// process shares one bucket, so concurrent updates queue up here instead of tripping
+/// Cloudflare's rate limit.
+pub async fn acquire() {
+    loop {
+        let wait = LIMITER
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| TokenBucket::new(DEFAULT_REQUESTS_PER_MINUTE, Instant::now()))
+            .take(Instant::now());
+        match wait {
+            Ok(()) => return,
+            Err(wait) => {
+                debug!("Cloudflare rate limit reached. Waiting {:?}", wait);
+                time::sleep(wait).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_allows_burst_then_refills() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(60, start);
+        for _ in 0..10 {
+            assert_eq!(bucket.take(start), Ok(()));
+        }
+        assert_eq!(bucket.take(start), Err(Duration::from_secs(1)));
+        assert_eq!(bucket.take(start + Duration::from_secs(1)), Ok(()));
+        assert!(bucket.take(start + Duration::from_secs(1)).is_err());
+        assert_eq!(bucket.take(start + Duration::from_secs(60)), Ok(()));
+        assert_eq!(bucket.tokens, 9.0);
+
+        bucket.set_rate(6);
+        assert_eq!(bucket.tokens, 1.0);
+        assert_eq!(bucket.take(start + Duration::from_secs(60)), Ok(()));
+        assert_eq!(
+            bucket.take(start + Duration::from_secs(60)),
+            Err(Duration::from_secs(10))
+        );
+    }
+}
@@ -0,0 +1,450 @@
+use std::any::TypeId;
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::{Arc, OnceLock, RwLock};
+use tracing::field::{display, DisplayValue, Field, FieldSet, Value, Visit};
+use tracing::span::{self, Attributes, Record};
+use tracing::subscriber::Interest;
+use tracing::{Event, Metadata, Subscriber};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+const REDACTED: &str = "[REDACTED]";
+const MIN_SECRET_LENGTH: usize = 4;
+
+static GLOBAL: OnceLock<Redactor> = OnceLock::new();
+
+/// The redactor every log output uses. Each job adds its secrets when it starts and again
+/// when it reloads its configuration.
+pub fn global() -> &'static Redactor {
+    GLOBAL.get_or_init(Redactor::default)
+}
+
+/// Replaces known secrets in text. Clones share their secrets, so secrets added later, such
+/// as a rotated token from a reloaded configuration, are redacted by every clone.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    secrets: Arc<RwLock<Vec<String>>>,
+}
+
+impl Redactor {
+    pub fn new<I, S>(secrets: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let redactor = Self::default();
+        redactor.add(secrets);
+        redactor
+    }
+
+    /// Adds secrets to redact. Secrets are never removed, since a retired token may still
+    /// show up in a log line written after it was replaced.
+    pub fn add<I, S>(&self, secrets: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut current = self.secrets.write().unwrap();
+        current.extend(
+            secrets
+                .into_iter()
+                .map(Into::into)
+                .filter(|secret| secret.len() >= MIN_SECRET_LENGTH),
+        );
+        current.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        current.dedup();
+    }
+
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for secret in self.secrets.read().unwrap().iter() {
+            if text.contains(secret.as_str()) {
+                text = Cow::Owned(text.replace(secret.as_str(), REDACTED));
+            }
+        }
+        text
+    }
+}
+
+#[derive(Clone)]
+pub struct RedactingMakeWriter<M> {
+    inner: M,
+    redactor: Redactor,
+}
+
+impl<M> RedactingMakeWriter<M> {
+    pub fn new(inner: M, redactor: Redactor) -> Self {
+        Self { inner, redactor }
+    }
+}
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for RedactingMakeWriter<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter {
+            inner: self.inner.make_writer(),
+            redactor: self.redactor.clone(),
+        }
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        RedactingWriter {
+            inner: self.inner.make_writer_for(meta),
+            redactor: self.redactor.clone(),
+        }
+    }
+}
+
+pub struct RedactingWriter<W> {
+    inner: W,
+    redactor: Redactor,
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.inner
+            .write_all(self.redactor.redact(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Wraps a layer that exports fields itself instead of writing formatted lines, such as
+/// journald or OpenTelemetry, and hands it events and span fields with secrets replaced.
+pub struct RedactingLayer<L> {
+    inner: L,
+    redactor: Redactor,
+}
+
+impl<L> RedactingLayer<L> {
+    pub fn new(inner: L, redactor: Redactor) -> Self {
+        Self { inner, redactor }
+    }
+}
+
+enum FieldValue {
+    Str(String),
+    Debug(DisplayValue<String>),
+    I64(i64),
+    U64(u64),
+    I128(i128),
+    U128(u128),
+    F64(f64),
+    Bool(bool),
+}
+
+impl FieldValue {
+    fn as_value(&self) -> &dyn Value {
+        match self {
+            FieldValue::Str(value) => value,
+            FieldValue::Debug(value) => value,
+            FieldValue::I64(value) => value,
+            FieldValue::U64(value) => value,
+            FieldValue::I128(value) => value,
+            FieldValue::U128(value) => value,
+            FieldValue::F64(value) => value,
+            FieldValue::Bool(value) => value,
+        }
+    }
+}
+
+/// A copy of the fields of an event or span, indexed like its [`FieldSet`], with secrets
+/// replaced in every text and `Debug` value.
+struct RedactedFields<'r> {
+    redactor: &'r Redactor,
+    values: Vec<Option<FieldValue>>,
+    redacted: bool,
+}
+
+impl<'r> RedactedFields<'r> {
+    fn new(redactor: &'r Redactor, fields: &FieldSet) -> Self {
+        Self {
+            redactor,
+            values: fields.iter().map(|_| None).collect(),
+            redacted: false,
+        }
+    }
+
+    fn set(&mut self, field: &Field, value: FieldValue) {
+        if let Some(slot) = self.values.get_mut(field.index()) {
+            *slot = Some(value);
+        }
+    }
+
+    fn redact(&mut self, text: &str) -> String {
+        let redacted = self.redactor.redact(text);
+        self.redacted |= matches!(redacted, Cow::Owned(_));
+        redacted.into_owned()
+    }
+
+    fn values(&self) -> Vec<Option<&dyn Value>> {
+        self.values
+            .iter()
+            .map(|value| value.as_ref().map(FieldValue::as_value))
+            .collect()
+    }
+}
+
+impl Visit for RedactedFields<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.set(field, FieldValue::F64(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.set(field, FieldValue::I64(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.set(field, FieldValue::U64(value));
+    }
+
+    fn record_i128(&mut self, field: &Field, value: i128) {
+        self.set(field, FieldValue::I128(value));
+    }
+
+    fn record_u128(&mut self, field: &Field, value: u128) {
+        self.set(field, FieldValue::U128(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.set(field, FieldValue::Bool(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        let value = self.redact(value);
+        self.set(field, FieldValue::Str(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let value = self.redact(&format!("{:?}", value));
+        self.set(field, FieldValue::Debug(display(value)));
+    }
+}
+
+impl<S, L> Layer<S> for RedactingLayer<L>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    L: Layer<S>,
+{
+    fn on_register_dispatch(&self, subscriber: &tracing::Dispatch) {
+        self.inner.on_register_dispatch(subscriber);
+    }
+
+    fn on_layer(&mut self, subscriber: &mut S) {
+        self.inner.on_layer(subscriber);
+    }
+
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        self.inner.register_callsite(metadata)
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, ctx: Context<'_, S>) -> bool {
+        self.inner.enabled(metadata, ctx)
+    }
+
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let metadata = attrs.metadata();
+        let mut fields = RedactedFields::new(&self.redactor, metadata.fields());
+        attrs.record(&mut fields);
+        if !fields.redacted {
+            return self.inner.on_new_span(attrs, id, ctx);
+        }
+        let values = fields.values();
+        let values = metadata.fields().value_set_all(&values);
+        let attrs = match attrs.parent() {
+            _ if attrs.is_contextual() => Attributes::new(metadata, &values),
+            Some(parent) => Attributes::child_of(parent.clone(), metadata, &values),
+            None => Attributes::new_root(metadata, &values),
+        };
+        self.inner.on_new_span(&attrs, id, ctx);
+    }
+
+    fn on_record(&self, id: &span::Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(metadata) = ctx.metadata(id) else {
+            return self.inner.on_record(id, values, ctx);
+        };
+        let mut fields = RedactedFields::new(&self.redactor, metadata.fields());
+        values.record(&mut fields);
+        if !fields.redacted {
+            return self.inner.on_record(id, values, ctx);
+        }
+        let values = fields.values();
+        let values = metadata.fields().value_set_all(&values);
+        self.inner.on_record(id, &Record::new(&values), ctx);
+    }
+
+    fn on_follows_from(&self, id: &span::Id, follows: &span::Id, ctx: Context<'_, S>) {
+        self.inner.on_follows_from(id, follows, ctx);
+    }
+
+    fn event_enabled(&self, event: &Event<'_>, ctx: Context<'_, S>) -> bool {
+        self.inner.event_enabled(event, ctx)
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut fields = RedactedFields::new(&self.redactor, metadata.fields());
+        event.record(&mut fields);
+        if !fields.redacted {
+            return self.inner.on_event(event, ctx);
+        }
+        let values = fields.values();
+        let values = metadata.fields().value_set_all(&values);
+        let event = if event.is_contextual() {
+            Event::new(metadata, &values)
+        } else {
+            Event::new_child_of(event.parent().cloned(), metadata, &values)
+        };
+        self.inner.on_event(&event, ctx);
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        self.inner.on_enter(id, ctx);
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        self.inner.on_exit(id, ctx);
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        self.inner.on_close(id, ctx);
+    }
+
+    fn on_id_change(&self, old: &span::Id, new: &span::Id, ctx: Context<'_, S>) {
+        self.inner.on_id_change(old, new, ctx);
+    }
+
+    fn max_level_hint(&self) -> Option<tracing::level_filters::LevelFilter> {
+        self.inner.max_level_hint()
+    }
+
+    // OpenTelemetry looks itself up through the subscriber, so lookups must reach the inner
+    // layer.
+    unsafe fn downcast_raw(&self, id: TypeId) -> Option<*const ()> {
+        if id == TypeId::of::<Self>() {
+            Some(self as *const Self as *const ())
+        } else {
+            self.inner.downcast_raw(id)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_redacting_writer_scrubs_secrets_from_log_lines() {
+        let captured = Captured::default();
+        let redactor = Redactor::new(["secret-token", "", "abc"]);
+        let sink = captured.clone();
+        let make_writer = RedactingMakeWriter::new(move || sink.clone(), redactor);
+
+        make_writer
+            .make_writer()
+            .write_all(b"Authorization: Bearer secret-token, abc stays\n")
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(captured.0.lock().unwrap().clone()).unwrap(),
+            "Authorization: Bearer [REDACTED], abc stays\n"
+        );
+    }
+
+    #[derive(Clone, Default)]
+    struct FieldCapture(Arc<Mutex<Vec<String>>>);
+
+    impl Visit for FieldCapture {
+        fn record_i64(&mut self, field: &Field, value: i64) {
+            self.0.lock().unwrap().push(format!("{}={}", field, value));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.lock().unwrap().push(format!("{}={}", field, value));
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{}={:?}", field, value));
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for FieldCapture {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _id: &span::Id, _ctx: Context<'_, S>) {
+            attrs.record(&mut self.clone());
+        }
+
+        fn on_record(&self, _id: &span::Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+            values.record(&mut self.clone());
+        }
+
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            event.record(&mut self.clone());
+        }
+    }
+
+    #[test]
+    fn test_redacting_layer_scrubs_fields_for_layers_without_a_writer() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let captured = FieldCapture::default();
+        let subscriber = tracing_subscriber::registry().with(RedactingLayer::new(
+            captured.clone(),
+            Redactor::new(["secret-token"]),
+        ));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!(
+                "job",
+                token = "secret-token",
+                attempt = tracing::field::Empty
+            );
+            span.record("attempt", "secret-token");
+            let _entered = span.enter();
+            tracing::info!(
+                status = 401,
+                error = ?"Bearer secret-token",
+                "Request with secret-token failed"
+            );
+            tracing::info!(status = 200, "Nothing to hide");
+        });
+
+        assert_eq!(
+            *captured.0.lock().unwrap(),
+            vec![
+                "token=[REDACTED]",
+                "attempt=[REDACTED]",
+                "message=Request with [REDACTED] failed",
+                "status=401",
+                "error=\"Bearer [REDACTED]\"",
+                "message=Nothing to hide",
+                "status=200",
+            ]
+        );
+    }
+}
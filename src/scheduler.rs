@@ -0,0 +1,397 @@
+use crate::config::Config;
+use crate::errors::FlareSyncError;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How far the wall clock may run ahead of a sleep before it counts as a suspend or pause.
+pub const CLOCK_JUMP_THRESHOLD: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+struct Entry {
+    domain: String,
+    interval: Duration,
+    next_run: Instant,
+}
+
+/// When the first check runs after start, so a fleet restarted at once does not hit the
+/// IP providers and Cloudflare in the same second.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StartupPolicy {
+    pub delay: Duration,
+    /// Upper bound of a random extra delay, picked once per start.
+    pub splay: Duration,
+    /// Waits one full interval before each domain's first check.
+    pub skip_initial_check: bool,
+}
+
+impl StartupPolicy {
+    pub fn random_splay(&self) -> Duration {
+        let millis = u64::try_from(self.splay.as_millis()).unwrap_or(u64::MAX);
+        if millis == 0 {
+            return Duration::ZERO;
+        }
+        let random = RandomState::new().hash_one(std::process::id());
+        Duration::from_millis(random % (millis + 1))
+    }
+}
+
+/// What happens to checks that fall due while a cycle is still running.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverlapPolicy {
+    /// Run them once, right after the cycle ends.
+    #[default]
+    Queue,
+    /// Drop them and wait for each domain's next regular tick.
+    Skip,
+}
+
+impl OverlapPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OverlapPolicy::Queue => "queue",
+            OverlapPolicy::Skip => "skip",
+        }
+    }
+}
+
+impl FromStr for OverlapPolicy {
+    type Err = FlareSyncError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "queue" => Ok(OverlapPolicy::Queue),
+            "skip" => Ok(OverlapPolicy::Skip),
+            _ => Err(FlareSyncError::Config(
+                "OVERLAP_POLICY must be queue or skip".to_string(),
+            )),
+        }
+    }
+}
+
+/// Tracks when each domain is next due so domains with different intervals can share one
+/// sync loop. Every domain is due immediately after start.
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    entries: Vec<Entry>,
+    cap: Option<Duration>,
+}
+
+impl Scheduler {
+    pub fn new(intervals: impl IntoIterator<Item = (String, Duration)>, now: Instant) -> Self {
+        Self {
+            entries: intervals
+                .into_iter()
+                .map(|(domain, interval)| Entry {
+                    domain,
+                    interval,
+                    next_run: now,
+                })
+                .collect(),
+            cap: None,
+        }
+    }
+
+    pub fn from_config(config: &Config, now: Instant) -> Self {
+        Self::new(
+            config
+                .domain_names
+                .iter()
+                .map(|domain_name| (domain_name.clone(), config.interval_for(domain_name))),
+            now,
+        )
+    }
+
+    /// Moves every domain's first run `policy.delay` plus `splay` after `now`, and one
+    /// more interval later when the initial check is skipped.
+    pub fn delay_start(&mut self, policy: &StartupPolicy, splay: Duration, now: Instant) {
+        for entry in &mut self.entries {
+            entry.next_run = now + policy.delay + splay;
+            if policy.skip_initial_check {
+                entry.next_run += entry.interval;
+            }
+        }
+    }
+
+    /// The domains whose next run has arrived, in configuration order.
+    pub fn due(&self, now: Instant) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.next_run <= now)
+            .map(|entry| entry.domain.clone())
+            .collect()
+    }
+
+    /// The domains that fell due after `since` and by `now`, i.e. while a cycle that started
+    /// at `since` was running.
+    pub fn came_due(&self, since: Instant, now: Instant) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.next_run > since && entry.next_run <= now)
+            .map(|entry| entry.domain.clone())
+            .collect()
+    }
+
+    /// Moves each of `domains` to its first regular tick after `now`, dropping the ticks in
+    /// between.
+    pub fn skip_missed(&mut self, domains: &[String], now: Instant) {
+        for entry in self.entries_mut(domains) {
+            while entry.next_run <= now {
+                entry.next_run += entry.interval;
+            }
+        }
+    }
+
+    /// Schedules each of `domains` one full interval after `now`, or sooner while a cap is
+    /// set.
+    pub fn mark_run(&mut self, domains: &[String], now: Instant) {
+        let cap = self.cap;
+        for entry in self.entries_mut(domains) {
+            let interval = cap.map_or(entry.interval, |cap| cap.min(entry.interval));
+            entry.next_run = now + interval;
+        }
+    }
+
+    /// Limits every domain's interval to `cap`, bringing forward runs scheduled later than
+    /// that. `None` restores the configured intervals from each domain's next run.
+    pub fn set_cap(&mut self, cap: Option<Duration>, now: Instant) {
+        self.cap = cap;
+        if let Some(cap) = cap {
+            for entry in &mut self.entries {
+                entry.next_run = entry.next_run.min(now + cap);
+            }
+        }
+    }
+
+    /// Retries `domains` after `delay` instead of their usual interval.
+    pub fn postpone(&mut self, domains: &[String], delay: Duration, now: Instant) {
+        for entry in self.entries_mut(domains) {
+            entry.next_run = now + delay;
+        }
+    }
+
+    pub fn wake_all(&mut self, now: Instant) {
+        for entry in &mut self.entries {
+            entry.next_run = now;
+        }
+    }
+
+    pub fn time_until_next(&self, now: Instant) -> Duration {
+        self.entries
+            .iter()
+            .map(|entry| entry.next_run.saturating_duration_since(now))
+            .min()
+            .unwrap_or_default()
+    }
+
+    fn entries_mut<'a>(&'a mut self, domains: &'a [String]) -> impl Iterator<Item = &'a mut Entry> {
+        self.entries
+            .iter_mut()
+            .filter(|entry| domains.contains(&entry.domain))
+    }
+}
+
+/// Shortens polling after the public IP changes, since changes often come in bursts
+/// during ISP maintenance, then doubles the interval after each stable cycle until it
+/// reaches `max` again.
+#[derive(Debug, Clone)]
+pub struct AdaptiveInterval {
+    min: Duration,
+    max: Duration,
+    current: Option<Duration>,
+}
+
+impl AdaptiveInterval {
+    pub fn new(min: Duration, max: Duration) -> Self {
+        Self {
+            min,
+            max,
+            current: None,
+        }
+    }
+
+    /// `None` unless `ADAPTIVE_MIN_INTERVAL` is set.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        config
+            .adaptive_min_interval
+            .map(|min_interval| Self::new(min_interval, config.max_interval()))
+    }
+
+    /// Records a completed cycle and returns the cap to apply to the schedule, or `None`
+    /// once polling is back at the configured intervals.
+    pub fn record_cycle(&mut self, ip_changed: bool) -> Option<Duration> {
+        self.current = if ip_changed {
+            Some(self.min)
+        } else {
+            self.current
+                .map(|current| current.saturating_mul(2))
+                .filter(|current| *current < self.max)
+        };
+        self.current.filter(|current| *current < self.max)
+    }
+}
+
+/// How much longer than `slept` the wall clock advanced between `started` and `now`, when
+/// that exceeds [`CLOCK_JUMP_THRESHOLD`]. The monotonic clock behind timers stops while the
+/// system is suspended, so a sleep resumes where it left off and only the wall clock
+/// reveals the gap.
+pub fn clock_jump(slept: Duration, started: SystemTime, now: SystemTime) -> Option<Duration> {
+    let elapsed = now.duration_since(started).ok()?;
+    elapsed
+        .checked_sub(slept)
+        .filter(|gap| *gap > CLOCK_JUMP_THRESHOLD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlapping_checks_are_queued_or_skipped() {
+        let start = Instant::now();
+        let minute = Duration::from_secs(60);
+        let mut scheduler = Scheduler::new(
+            [
+                ("fast.example.com".to_string(), minute),
+                ("slow.example.com".to_string(), minute * 10),
+            ],
+            start,
+        );
+        scheduler.mark_run(&scheduler.due(start), start);
+
+        let cycle_end = start + minute * 3 + Duration::from_secs(30);
+        let overlapped = scheduler.came_due(start + minute / 2, cycle_end);
+        assert_eq!(overlapped, vec!["fast.example.com"]);
+        assert_eq!(scheduler.due(cycle_end), overlapped);
+
+        scheduler.skip_missed(&overlapped, cycle_end);
+        assert!(scheduler.due(cycle_end).is_empty());
+        assert_eq!(
+            scheduler.time_until_next(cycle_end),
+            Duration::from_secs(30)
+        );
+
+        assert_eq!(
+            "Skip".parse::<OverlapPolicy>().unwrap(),
+            OverlapPolicy::Skip
+        );
+        assert!("drop".parse::<OverlapPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_domains_run_on_their_own_intervals() {
+        let start = Instant::now();
+        let minute = Duration::from_secs(60);
+        let mut scheduler = Scheduler::new(
+            [
+                ("critical.example.com".to_string(), minute),
+                ("stable.example.com".to_string(), minute * 60),
+            ],
+            start,
+        );
+
+        let due = scheduler.due(start);
+        assert_eq!(due, ["critical.example.com", "stable.example.com"]);
+        scheduler.mark_run(&due, start);
+        assert!(scheduler.due(start).is_empty());
+        assert_eq!(scheduler.time_until_next(start), minute);
+
+        let later = start + minute;
+        let due = scheduler.due(later);
+        assert_eq!(due, ["critical.example.com"]);
+        scheduler.postpone(&due, Duration::from_secs(30), later);
+        assert_eq!(scheduler.time_until_next(later), Duration::from_secs(30));
+
+        scheduler.wake_all(later);
+        assert_eq!(scheduler.due(later).len(), 2);
+    }
+
+    #[test]
+    fn test_startup_policy_delays_first_run() {
+        let start = Instant::now();
+        let minute = Duration::from_secs(60);
+        let intervals = [
+            ("a.example.com".to_string(), minute * 5),
+            ("b.example.com".to_string(), minute * 10),
+        ];
+        let policy = StartupPolicy {
+            delay: Duration::from_secs(30),
+            splay: minute,
+            skip_initial_check: false,
+        };
+        let splay = policy.random_splay();
+        assert!(splay <= minute);
+        assert_eq!(StartupPolicy::default().random_splay(), Duration::ZERO);
+
+        let mut scheduler = Scheduler::new(intervals.clone(), start);
+        scheduler.delay_start(&policy, Duration::from_secs(15), start);
+        assert!(scheduler.due(start).is_empty());
+        assert_eq!(scheduler.time_until_next(start), Duration::from_secs(45));
+
+        let mut scheduler = Scheduler::new(intervals, start);
+        let skip = StartupPolicy {
+            skip_initial_check: true,
+            ..StartupPolicy::default()
+        };
+        scheduler.delay_start(&skip, Duration::ZERO, start);
+        assert_eq!(scheduler.time_until_next(start), minute * 5);
+        assert_eq!(scheduler.due(start + minute * 5), ["a.example.com"]);
+    }
+
+    #[test]
+    fn test_clock_jump_detects_suspend() {
+        let started = SystemTime::now();
+        let slept = Duration::from_secs(30);
+        assert_eq!(clock_jump(slept, started, started + slept), None);
+        assert_eq!(
+            clock_jump(slept, started, started + Duration::from_secs(90)),
+            None
+        );
+        assert_eq!(
+            clock_jump(slept, started, started + Duration::from_secs(7230)),
+            Some(Duration::from_secs(7200))
+        );
+        assert_eq!(
+            clock_jump(slept, started, started - Duration::from_secs(3600)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_adaptive_interval_backs_off_after_ip_change() {
+        let start = Instant::now();
+        let minute = Duration::from_secs(60);
+        let mut scheduler = Scheduler::new(
+            [
+                ("a.example.com".to_string(), minute * 5),
+                ("b.example.com".to_string(), minute * 10),
+            ],
+            start,
+        );
+        let domains = scheduler.due(start);
+        scheduler.mark_run(&domains, start);
+
+        let mut adaptive = AdaptiveInterval::new(minute, minute * 10);
+        assert_eq!(adaptive.record_cycle(false), None);
+        assert_eq!(adaptive.record_cycle(true), Some(minute));
+        scheduler.set_cap(Some(minute), start);
+        assert_eq!(scheduler.time_until_next(start), minute);
+        scheduler.mark_run(&domains, start);
+        assert_eq!(scheduler.due(start + minute).len(), 2);
+
+        assert_eq!(adaptive.record_cycle(false), Some(minute * 2));
+        assert_eq!(adaptive.record_cycle(false), Some(minute * 4));
+        assert_eq!(adaptive.record_cycle(false), Some(minute * 8));
+        assert_eq!(adaptive.record_cycle(false), None);
+        assert_eq!(adaptive.record_cycle(true), Some(minute));
+
+        scheduler.set_cap(Some(minute * 8), start);
+        scheduler.mark_run(&domains, start);
+        assert_eq!(scheduler.due(start + minute * 5), ["a.example.com"]);
+        assert_eq!(
+            scheduler.time_until_next(start + minute * 5),
+            Duration::ZERO
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use std::time::Duration;
+#[cfg(target_os = "linux")]
+use tracing::debug;
+
+/// Sends a state string such as `READY=1` to the systemd notification socket. Does nothing
+/// unless the service manager set `NOTIFY_SOCKET`.
+#[cfg(target_os = "linux")]
+pub fn notify(state: &str) {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+    let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    let path = path.to_string_lossy();
+    let result = (|| {
+        let address = match path.strip_prefix('@') {
+            Some(name) => SocketAddr::from_abstract_name(name.as_bytes())?,
+            None => SocketAddr::from_pathname(path.as_ref())?,
+        };
+        UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &address)
+    })();
+    if let Err(e) = result {
+        debug!("Failed to notify systemd ({}): {}", state, e);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn notify(_state: &str) {}
+
+pub fn ready(status: &str) {
+    notify(&format!("READY=1\nSTATUS={}", status));
+}
+
+pub fn status(status: &str) {
+    notify(&format!("STATUS={}", status));
+}
+
+pub fn watchdog() {
+    notify("WATCHDOG=1");
+}
+
+pub fn stopping() {
+    notify("STOPPING=1");
+}
+
+/// Awaits `future`, sending `WATCHDOG=1` every [`watchdog_interval`] until it completes,
+/// so a slow but bounded step such as a domain update does not look like a hang.
+pub async fn while_pinging_watchdog<F: std::future::Future>(future: F) -> F::Output {
+    let Some(interval) = watchdog_interval() else {
+        return future.await;
+    };
+    tokio::pin!(future);
+    loop {
+        tokio::select! {
+            output = &mut future => return output,
+            _ = tokio::time::sleep(interval) => watchdog(),
+        }
+    }
+}
+
+/// How often to send `WATCHDOG=1`: half the `WatchdogSec` systemd configured for this
+/// process, or `None` when the watchdog is off.
+pub fn watchdog_interval() -> Option<Duration> {
+    parse_watchdog_interval(
+        std::env::var("WATCHDOG_USEC").ok().as_deref(),
+        std::env::var("WATCHDOG_PID").ok().as_deref(),
+        std::process::id(),
+    )
+}
+
+fn parse_watchdog_interval(
+    usec: Option<&str>,
+    pid: Option<&str>,
+    own_pid: u32,
+) -> Option<Duration> {
+    if pid.is_some_and(|pid| pid.parse() != Ok(own_pid)) {
+        return None;
+    }
+    let usec: u64 = usec?.parse().ok().filter(|usec| *usec > 0)?;
+    Some(Duration::from_micros(usec / 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchdog_interval_is_half_of_watchdog_sec_for_this_process() {
+        assert_eq!(
+            parse_watchdog_interval(Some("60000000"), None, 42),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_watchdog_interval(Some("60000000"), Some("42"), 42),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_watchdog_interval(Some("60000000"), Some("7"), 42),
+            None
+        );
+        assert_eq!(parse_watchdog_interval(Some("0"), None, 42), None);
+        assert_eq!(parse_watchdog_interval(None, None, 42), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_watchdog_is_pinged_while_a_step_runs() {
+        use std::os::unix::net::UnixDatagram;
+
+        let socket_path =
+            std::env::temp_dir().join(format!("flaresync_notify_{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket_path);
+        let socket = UnixDatagram::bind(&socket_path).unwrap();
+        socket.set_nonblocking(true).unwrap();
+
+        let output = {
+            let _guard = crate::test_support::global_lock();
+            std::env::set_var("NOTIFY_SOCKET", &socket_path);
+            std::env::set_var("WATCHDOG_USEC", "100000");
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_time()
+                .build()
+                .unwrap();
+            let output = runtime.block_on(while_pinging_watchdog(async {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                "done"
+            }));
+            std::env::remove_var("NOTIFY_SOCKET");
+            std::env::remove_var("WATCHDOG_USEC");
+            output
+        };
+        assert_eq!(output, "done");
+
+        let mut buf = [0; 64];
+        let mut pings = 0;
+        while let Ok(len) = socket.recv(&mut buf) {
+            if &buf[..len] == b"WATCHDOG=1" {
+                pings += 1;
+            }
+        }
+        assert!(pings >= 3, "expected at least 3 pings, got {}", pings);
+        let _ = std::fs::remove_file(&socket_path);
+    }
+}
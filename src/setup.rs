@@ -0,0 +1,83 @@
+use crate::errors::FlareSyncError;
+use std::path::Path;
+
+/// Parses a selection such as `1,3-4` into zero-based indices of a list of `count` items.
+pub fn parse_selection(input: &str, count: usize) -> Result<Vec<usize>, FlareSyncError> {
+    let invalid = |part: &str| {
+        FlareSyncError::Config(format!(
+            "Invalid selection '{}': expected numbers between 1 and {}",
+            part, count
+        ))
+    };
+    let number = |part: &str| -> Result<usize, FlareSyncError> {
+        match part.trim().parse::<usize>() {
+            Ok(number) if (1..=count).contains(&number) => Ok(number - 1),
+            _ => Err(invalid(part)),
+        }
+    };
+
+    let mut selected = Vec::new();
+    for part in input
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (number(start)?, number(end)?),
+            None => (number(part)?, number(part)?),
+        };
+        if start > end {
+            return Err(invalid(part));
+        }
+        for index in start..=end {
+            if !selected.contains(&index) {
+                selected.push(index);
+            }
+        }
+    }
+    if selected.is_empty() {
+        return Err(FlareSyncError::Config("Nothing selected".to_string()));
+    }
+    Ok(selected)
+}
+
+pub fn config_toml(
+    token_file: &Path,
+    zone_id: &str,
+    domains: &[String],
+) -> Result<String, FlareSyncError> {
+    let mut table = toml::Table::new();
+    table.insert(
+        "cloudflare_api_token_file".to_string(),
+        toml::Value::String(token_file.display().to_string()),
+    );
+    table.insert(
+        "cloudflare_zone_id".to_string(),
+        toml::Value::String(zone_id.to_string()),
+    );
+    table.insert(
+        "domain_name".to_string(),
+        toml::Value::Array(
+            domains
+                .iter()
+                .map(|domain| toml::Value::String(domain.clone()))
+                .collect(),
+        ),
+    );
+    toml::to_string(&table)
+        .map_err(|e| FlareSyncError::Config(format!("Failed to serialize config file: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_selection_ranges_and_duplicates() {
+        assert_eq!(parse_selection("1, 3-4,3", 5).unwrap(), [0, 2, 3]);
+        assert!(parse_selection("0", 5).is_err());
+        assert!(parse_selection("4-2", 5).is_err());
+        assert!(parse_selection("6", 5).is_err());
+        assert!(parse_selection(" ", 5).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use crate::atomic_file;
+use crate::cloudflare::DnsRecord;
+use crate::config::Config;
+#[cfg(feature = "sqlite")]
+use crate::database::Database;
+use crate::errors::FlareSyncError;
+use crate::prefetch::Records;
+use crate::status::RuntimeStatus;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+/// What FlareSync last saw of one domain's A record.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct DomainState {
+    pub record: Option<DnsRecord>,
+    pub last_updated_at: Option<String>,
+}
+
+/// The last public IP and the known records of each domain, saved whenever they change
+/// and loaded at startup.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct SavedState {
+    pub zone_id: Option<String>,
+    pub last_public_ip: Option<String>,
+    #[serde(default)]
+    pub domains: BTreeMap<String, DomainState>,
+}
+
+impl SavedState {
+    /// Reads the state file at `path`. A missing file is an empty state.
+    pub fn load(path: &Path) -> Result<Self, FlareSyncError> {
+        match fs::read(path) {
+            Ok(json) => Ok(serde_json::from_slice(&json)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), FlareSyncError> {
+        atomic_file::write(path, &serde_json::to_vec_pretty(self)?)
+    }
+
+    /// The saved records of `domains`, if they were saved for `zone_id`.
+    pub fn records(&self, zone_id: &str, domains: &[String]) -> Records {
+        if self.zone_id.as_deref() != Some(zone_id) {
+            return Records::new();
+        }
+        domains
+            .iter()
+            .filter_map(|domain_name| {
+                let record = self.domains.get(domain_name)?.record.clone()?;
+                Some((domain_name.clone(), Some(record)))
+            })
+            .collect()
+    }
+
+    /// Copies the last public IP and the last update of each of `domains` into `status`.
+    pub fn restore(&self, status: &mut RuntimeStatus, domains: &[String]) {
+        status.last_public_ip = self.last_public_ip.clone();
+        for domain_name in domains {
+            let last_updated_at = self
+                .domains
+                .get(domain_name)
+                .and_then(|domain| domain.last_updated_at.clone());
+            if last_updated_at.is_some() {
+                status
+                    .domains
+                    .entry(domain_name.clone())
+                    .or_default()
+                    .last_updated_at = last_updated_at;
+            }
+        }
+    }
+
+    /// Returns whether `ip` differs from the saved one.
+    pub fn mark_ip(&mut self, ip: &Ipv4Addr) -> bool {
+        let ip = Some(ip.to_string());
+        let changed = self.last_public_ip != ip;
+        self.last_public_ip = ip;
+        changed
+    }
+
+    /// Saves `record` as the current record of `domain_name` in `zone_id`, also as its
+    /// last update when `updated`. Records of another zone are dropped. Returns whether
+    /// anything changed.
+    pub fn mark_record(
+        &mut self,
+        zone_id: &str,
+        domain_name: &str,
+        record: &DnsRecord,
+        updated: bool,
+    ) -> bool {
+        if self.zone_id.as_deref() != Some(zone_id) {
+            self.zone_id = Some(zone_id.to_string());
+            self.domains.clear();
+        }
+        let domain = self.domains.entry(domain_name.to_string()).or_default();
+        if !updated && domain.record.as_ref() == Some(record) {
+            return false;
+        }
+        domain.record = Some(record.clone());
+        if updated {
+            domain.last_updated_at = Some(chrono::Local::now().to_rfc3339());
+        }
+        true
+    }
+
+    /// Drops the saved record of `domain_name`, so it is fetched again after a restart.
+    /// Returns whether there was one.
+    pub fn forget_record(&mut self, domain_name: &str) -> bool {
+        self.domains
+            .get_mut(domain_name)
+            .and_then(|domain| domain.record.take())
+            .is_some()
+    }
+}
+
+/// Where the state of `config` is saved: its database if `DATABASE_PATH` is set, or else
+/// its state file.
+pub fn location(config: &Config) -> &Path {
+    config
+        .database_path
+        .as_deref()
+        .unwrap_or(&config.state_file_path)
+}
+
+pub fn load(config: &Config) -> Result<SavedState, FlareSyncError> {
+    #[cfg(feature = "sqlite")]
+    if let Some(database) = &config.database_path {
+        return Database::open(database)?.load_state();
+    }
+    SavedState::load(&config.state_file_path)
+}
+
+pub fn save(config: &Config, state: &SavedState) -> Result<(), FlareSyncError> {
+    #[cfg(feature = "sqlite")]
+    if let Some(database) = &config.database_path {
+        return Database::open(database)?.save_state(state);
+    }
+    state.save(&config.state_file_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn record(content: &str) -> DnsRecord {
+        DnsRecord {
+            id: "record-1".to_string(),
+            name: "example.com".to_string(),
+            content: content.to_string(),
+            record_type: "A".to_string(),
+            proxied: false,
+            ttl: 1,
+            modified_on: None,
+        }
+    }
+
+    #[test]
+    fn test_state_round_trips_and_seeds_status() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir()
+            .join(format!(
+                "flaresync_state_test_{}_{}",
+                std::process::id(),
+                unique
+            ))
+            .join("state.json");
+        assert_eq!(SavedState::load(&path).unwrap(), SavedState::default());
+
+        let domains = vec!["example.com".to_string(), "other.example.com".to_string()];
+        let mut state = SavedState::default();
+        assert!(state.mark_ip(&"203.0.113.2".parse().unwrap()));
+        assert!(!state.mark_ip(&"203.0.113.2".parse().unwrap()));
+        assert!(state.mark_record("zone", "example.com", &record("203.0.113.2"), true));
+        assert!(!state.mark_record("zone", "example.com", &record("203.0.113.2"), false));
+        state.save(&path).unwrap();
+
+        let loaded = SavedState::load(&path).unwrap();
+        assert_eq!(loaded, state);
+        let records = loaded.records("zone", &domains);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records["example.com"].as_ref().unwrap().id, "record-1");
+        assert!(loaded.records("other-zone", &domains).is_empty());
+
+        let mut status = RuntimeStatus::new();
+        loaded.restore(&mut status, &domains);
+        assert_eq!(status.last_public_ip.as_deref(), Some("203.0.113.2"));
+        assert!(status.domains["example.com"].last_updated_at.is_some());
+        assert!(!status.domains.contains_key("other.example.com"));
+
+        state.mark_record(
+            "other-zone",
+            "other.example.com",
+            &record("203.0.113.2"),
+            false,
+        );
+        assert!(!state.domains.contains_key("example.com"));
+        assert!(state.forget_record("other.example.com"));
+        assert!(!state.forget_record("other.example.com"));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -1,18 +1,26 @@
+use crate::atomic_file;
 use crate::errors::FlareSyncError;
+use crate::history::HistoryEntry;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
-use std::fs::{self, OpenOptions};
-use std::io::Write;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::fs;
 use std::net::Ipv4Addr;
-use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::path::Path;
+use std::time::Duration;
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct DomainStatus {
     pub last_checked_at: Option<String>,
     pub last_updated_at: Option<String>,
+    #[serde(default)]
+    pub last_success_at: Option<String>,
     pub last_status: String,
     pub last_error: Option<String>,
+    #[serde(default)]
+    pub consecutive_failures: u32,
+    #[serde(default)]
+    pub paused: bool,
 }
 
 impl Default for DomainStatus {
@@ -20,12 +28,40 @@ impl Default for DomainStatus {
         Self {
             last_checked_at: None,
             last_updated_at: None,
+            last_success_at: None,
             last_status: "pending".to_string(),
             last_error: None,
+            consecutive_failures: 0,
+            paused: false,
         }
     }
 }
 
+impl DomainStatus {
+    /// The status to show people: `paused` while the domain is paused, otherwise the
+    /// result of its last check.
+    pub fn display_status(&self) -> &str {
+        if self.paused {
+            "paused"
+        } else {
+            &self.last_status
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct RuntimeStats {
+    pub uptime_seconds: u64,
+    pub total_cycles: u64,
+    pub successful_cycles: u64,
+    pub consecutive_failures: u64,
+    pub total_updates: u64,
+    pub average_cycle_duration_ms: u64,
+    pub total_cycle_duration_ms: u64,
+    #[serde(default)]
+    pub overlapping_checks: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct RuntimeStatus {
     pub started_at: String,
@@ -34,7 +70,17 @@ pub struct RuntimeStatus {
     pub last_ip_check_at: Option<String>,
     pub domains: BTreeMap<String, DomainStatus>,
     pub last_error: Option<String>,
+    pub last_cycle_at: Option<String>,
+    pub last_successful_cycle_at: Option<String>,
+    /// When the first check is due, if `STARTUP_DELAY`, `STARTUP_SPLAY`, or
+    /// `SKIP_INITIAL_CHECK` moved it after startup.
+    #[serde(default)]
+    pub first_check_at: Option<String>,
     pub shutting_down: bool,
+    #[serde(default)]
+    pub paused: bool,
+    #[serde(default)]
+    pub stats: RuntimeStats,
 }
 
 impl RuntimeStatus {
@@ -47,7 +93,27 @@ impl RuntimeStatus {
             last_ip_check_at: None,
             domains: BTreeMap::new(),
             last_error: None,
+            last_cycle_at: None,
+            last_successful_cycle_at: None,
+            first_check_at: None,
             shutting_down: false,
+            paused: false,
+            stats: RuntimeStats::default(),
+        }
+    }
+
+    pub fn mark_paused(&mut self, paused: bool) {
+        self.updated_at = now_timestamp();
+        self.paused = paused;
+    }
+
+    /// Flags exactly the domains in `paused` as paused.
+    pub fn mark_paused_domains(&mut self, paused: &BTreeSet<String>) {
+        for domain in paused {
+            self.domains.entry(domain.clone()).or_default();
+        }
+        for (domain, domain_status) in &mut self.domains {
+            domain_status.paused = paused.contains(domain);
         }
     }
 
@@ -71,11 +137,14 @@ impl RuntimeStatus {
 
         let domain_status = self.domains.entry(domain.to_string()).or_default();
         domain_status.last_checked_at = Some(now.clone());
+        domain_status.last_success_at = Some(now.clone());
         domain_status.last_status = status.to_string();
         if updated {
             domain_status.last_updated_at = Some(now);
+            self.stats.total_updates += 1;
         }
         domain_status.last_error = None;
+        domain_status.consecutive_failures = 0;
         self.last_error = None;
     }
 
@@ -87,42 +156,168 @@ impl RuntimeStatus {
         domain_status.last_checked_at = Some(now);
         domain_status.last_status = "error".to_string();
         domain_status.last_error = Some(error.to_string());
+        domain_status.consecutive_failures += 1;
         self.last_error = Some(error.to_string());
     }
 
+    pub fn mark_cycle_complete(&mut self, success: bool, duration: Duration) {
+        let now = now_timestamp();
+        self.updated_at = now.clone();
+        self.last_cycle_at = Some(now.clone());
+        if success {
+            self.last_successful_cycle_at = Some(now);
+        }
+
+        let stats = &mut self.stats;
+        stats.total_cycles += 1;
+        stats.total_cycle_duration_ms += duration.as_millis() as u64;
+        stats.average_cycle_duration_ms = stats.total_cycle_duration_ms / stats.total_cycles;
+        if success {
+            stats.successful_cycles += 1;
+            stats.consecutive_failures = 0;
+        } else {
+            stats.consecutive_failures += 1;
+        }
+        self.stats.uptime_seconds = self.uptime_seconds(chrono::Local::now());
+    }
+
+    pub fn mark_first_check_at(&mut self, first_check_at: chrono::DateTime<chrono::Local>) {
+        self.updated_at = now_timestamp();
+        self.first_check_at = Some(first_check_at.to_rfc3339());
+    }
+
+    pub fn mark_overlapping_checks(&mut self, count: usize) {
+        self.updated_at = now_timestamp();
+        self.stats.overlapping_checks += count as u64;
+    }
+
     pub fn mark_shutting_down(&mut self) {
         self.updated_at = now_timestamp();
         self.shutting_down = true;
+        self.stats.uptime_seconds = self.uptime_seconds(chrono::Local::now());
     }
 
-    pub fn write_to_path(&self, path: &Path) -> Result<(), FlareSyncError> {
-        if let Some(parent) = path.parent() {
-            if !parent.as_os_str().is_empty() {
-                fs::create_dir_all(parent)?;
+    pub fn stats_at(&self, now: chrono::DateTime<chrono::Local>) -> RuntimeStats {
+        RuntimeStats {
+            uptime_seconds: self.uptime_seconds(now),
+            ..self.stats.clone()
+        }
+    }
+
+    fn uptime_seconds(&self, now: chrono::DateTime<chrono::Local>) -> u64 {
+        chrono::DateTime::parse_from_rfc3339(&self.started_at)
+            .map(|started_at| (now.fixed_offset() - started_at).num_seconds().max(0) as u64)
+            .unwrap_or_default()
+    }
+
+    /// The status of exactly `domain_names`, with a pending entry for any never checked.
+    pub fn for_domains(mut self, domain_names: &[String]) -> Self {
+        self.domains = domain_names
+            .iter()
+            .map(|domain_name| {
+                let domain = self.domains.remove(domain_name).unwrap_or_default();
+                (domain_name.clone(), domain)
+            })
+            .collect();
+        self
+    }
+
+    /// What `flaresync status --json` prints: the status file with `recent_errors` from
+    /// the history added.
+    pub fn report_json(
+        &self,
+        recent_errors: &[&HistoryEntry],
+    ) -> Result<serde_json::Value, FlareSyncError> {
+        let mut value = serde_json::to_value(self)?;
+        value["recent_errors"] = serde_json::to_value(recent_errors)?;
+        Ok(value)
+    }
+
+    /// The text `flaresync status` prints: the IP and cycle summary, a row per domain in
+    /// `domain_names`, and `recent_errors` from the history.
+    pub fn render_report(
+        &self,
+        domain_names: &[String],
+        recent_errors: &[&HistoryEntry],
+    ) -> String {
+        let never = "never";
+        let mut report = String::new();
+        let _ = writeln!(
+            report,
+            "Public IP:       {} (checked {})",
+            self.last_public_ip.as_deref().unwrap_or("unknown"),
+            self.last_ip_check_at.as_deref().unwrap_or(never)
+        );
+        let _ = writeln!(
+            report,
+            "Last cycle:      {}",
+            self.last_cycle_at.as_deref().unwrap_or(never)
+        );
+        let _ = writeln!(
+            report,
+            "Last successful: {}",
+            self.last_successful_cycle_at.as_deref().unwrap_or(never)
+        );
+        let _ = writeln!(
+            report,
+            "State:           {} (status written {})",
+            if self.shutting_down {
+                "stopped"
+            } else {
+                "running"
+            },
+            self.updated_at
+        );
+        if let Some(error) = &self.last_error {
+            let _ = writeln!(report, "Last error:      {}", error);
+        }
+
+        let _ = writeln!(report);
+        let _ = writeln!(
+            report,
+            "{:<32} {:<10} {:<26} {:<26} FAILURES",
+            "DOMAIN", "STATUS", "LAST SUCCESS", "LAST UPDATE"
+        );
+        let pending = DomainStatus::default();
+        for domain_name in domain_names {
+            let domain = self.domains.get(domain_name).unwrap_or(&pending);
+            let _ = writeln!(
+                report,
+                "{:<32} {:<10} {:<26} {:<26} {}",
+                domain_name,
+                domain.display_status(),
+                domain.last_success_at.as_deref().unwrap_or(never),
+                domain.last_updated_at.as_deref().unwrap_or(never),
+                domain.consecutive_failures
+            );
+            if let Some(error) = &domain.last_error {
+                let _ = writeln!(report, "    {}", error);
             }
         }
 
-        let json = serde_json::to_vec_pretty(self)?;
-        let temp_path = temporary_status_path(path);
-
-        let write_result = (|| -> Result<(), FlareSyncError> {
-            let mut temp_file = OpenOptions::new()
-                .write(true)
-                .create_new(true)
-                .open(&temp_path)?;
-            temp_file.write_all(&json)?;
-            temp_file.sync_all()?;
-            drop(temp_file);
-            fs::rename(&temp_path, path)?;
-            Ok(())
-        })();
-
-        if write_result.is_err() {
-            let _ = fs::remove_file(&temp_path);
+        if !recent_errors.is_empty() {
+            let _ = writeln!(report);
+            let _ = writeln!(report, "Recent errors:");
+            for entry in recent_errors {
+                let _ = writeln!(
+                    report,
+                    "  {}  {}  {}",
+                    entry.timestamp,
+                    entry.domain,
+                    entry.error.as_deref().unwrap_or_default()
+                );
+            }
         }
+        report
+    }
+
+    pub fn read_from_path(path: &Path) -> Result<Self, FlareSyncError> {
+        let json = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
 
-        write_result?;
-        Ok(())
+    pub fn write_to_path(&self, path: &Path) -> Result<(), FlareSyncError> {
+        atomic_file::write(path, &serde_json::to_vec_pretty(self)?)
     }
 }
 
@@ -136,27 +331,11 @@ fn now_timestamp() -> String {
     chrono::Local::now().to_rfc3339()
 }
 
-fn temporary_status_path(path: &Path) -> PathBuf {
-    let unique = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|duration| duration.as_nanos())
-        .unwrap_or_default();
-    let file_name = path
-        .file_name()
-        .and_then(|value| value.to_str())
-        .unwrap_or("flaresync-status.json");
-    path.with_file_name(format!(
-        ".{}.{}.{}.tmp",
-        file_name,
-        std::process::id(),
-        unique
-    ))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_json::Value;
+    use std::time::{SystemTime, UNIX_EPOCH};
 
     #[test]
     fn test_runtime_status_records_ip_and_domain_success() {
@@ -171,6 +350,7 @@ mod tests {
         assert_eq!(domain.last_status, "updated");
         assert!(domain.last_checked_at.is_some());
         assert!(domain.last_updated_at.is_some());
+        assert_eq!(domain.last_success_at, domain.last_checked_at);
         assert!(status.last_error.is_none());
     }
 
@@ -192,10 +372,13 @@ mod tests {
         let mut status = RuntimeStatus::new();
         let error = FlareSyncError::Cloudflare("permission denied".to_string());
 
+        status.mark_domain_error("example.com", &error);
         status.mark_domain_error("example.com", &error);
 
         let domain = status.domains.get("example.com").unwrap();
         assert_eq!(domain.last_status, "error");
+        assert_eq!(domain.consecutive_failures, 2);
+        assert!(domain.last_success_at.is_none());
         assert!(domain
             .last_error
             .as_ref()
@@ -208,6 +391,139 @@ mod tests {
             .contains("permission denied"));
     }
 
+    #[test]
+    fn test_runtime_status_marks_paused_domains() {
+        let mut status = RuntimeStatus::new();
+        status.mark_domain_result("example.com", "unchanged", false);
+        status.mark_domain_result("www.example.com", "unchanged", false);
+
+        status.mark_paused_domains(
+            &["www.example.com".to_string(), "new.example.com".to_string()].into(),
+        );
+        assert!(!status.domains["example.com"].paused);
+        assert!(status.domains["www.example.com"].paused);
+        assert!(status.domains["new.example.com"].paused);
+        assert_eq!(status.domains["www.example.com"].last_status, "unchanged");
+
+        status.mark_paused_domains(&BTreeSet::new());
+        assert!(status.domains.values().all(|domain| !domain.paused));
+    }
+
+    #[test]
+    fn test_runtime_status_records_successful_cycle_only() {
+        let mut status = RuntimeStatus::new();
+
+        status.mark_cycle_complete(false, Duration::from_millis(300));
+        assert!(status.last_cycle_at.is_some());
+        assert!(status.last_successful_cycle_at.is_none());
+
+        status.mark_cycle_complete(true, Duration::from_millis(100));
+        assert_eq!(status.last_successful_cycle_at, status.last_cycle_at);
+    }
+
+    #[test]
+    fn test_runtime_status_tracks_cycle_statistics() {
+        let mut status = RuntimeStatus::new();
+
+        status.mark_domain_result("example.com", "updated", true);
+        status.mark_cycle_complete(true, Duration::from_millis(100));
+        status.mark_cycle_complete(false, Duration::from_millis(200));
+        status.mark_cycle_complete(false, Duration::from_millis(600));
+
+        assert_eq!(status.stats.total_cycles, 3);
+        assert_eq!(status.stats.successful_cycles, 1);
+        assert_eq!(status.stats.consecutive_failures, 2);
+        assert_eq!(status.stats.total_updates, 1);
+        assert_eq!(status.stats.average_cycle_duration_ms, 300);
+
+        status.mark_cycle_complete(true, Duration::from_millis(100));
+        assert_eq!(status.stats.consecutive_failures, 0);
+
+        let later = chrono::Local::now() + chrono::TimeDelta::seconds(90);
+        assert!(status.stats_at(later).uptime_seconds >= 89);
+    }
+
+    #[test]
+    fn test_report_lists_configured_domains_and_recent_errors() {
+        let mut status = RuntimeStatus::new();
+        status.mark_ip_check_success(&"203.0.113.10".parse().unwrap());
+        status.mark_domain_result("example.com", "unchanged", false);
+        status.mark_domain_error(
+            "vpn.example.com",
+            &FlareSyncError::Cloudflare("rate limited".to_string()),
+        );
+        status.mark_domain_result("removed.example.com", "updated", true);
+        status.mark_cycle_complete(false, Duration::from_millis(100));
+        let domain_names = vec![
+            "vpn.example.com".to_string(),
+            "example.com".to_string(),
+            "new.example.com".to_string(),
+        ];
+        let status = status.for_domains(&domain_names);
+        assert_eq!(
+            status.domains.keys().collect::<Vec<_>>(),
+            ["example.com", "new.example.com", "vpn.example.com"]
+        );
+
+        let error = HistoryEntry {
+            timestamp: "2024-03-01T12:00:00+00:00".to_string(),
+            domain: "vpn.example.com".to_string(),
+            old_ip: None,
+            new_ip: "203.0.113.10".to_string(),
+            result: "failed".to_string(),
+            error: Some("rate limited".to_string()),
+        };
+        let report = status.render_report(&domain_names, &[&error]);
+        let lines: Vec<&str> = report.lines().collect();
+
+        assert!(lines[0].starts_with("Public IP:       203.0.113.10 (checked "));
+        assert!(lines[3].starts_with("State:           running"));
+        assert!(!report.contains("removed.example.com"));
+        let rows: Vec<&str> = lines
+            .iter()
+            .skip_while(|line| !line.starts_with("DOMAIN"))
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            .filter(|line| !line.starts_with(' '))
+            .map(|line| line.split_whitespace().next().unwrap_or_default())
+            .collect();
+        assert_eq!(rows, ["vpn.example.com", "example.com", "new.example.com"]);
+        let error_line = format!(
+            "\n    {}\n",
+            FlareSyncError::Cloudflare("rate limited".to_string())
+        );
+        assert!(report.contains(&error_line));
+        assert!(report.contains("new.example.com                  pending    never"));
+        assert!(report.ends_with(
+            "Recent errors:\n  2024-03-01T12:00:00+00:00  vpn.example.com  rate limited\n"
+        ));
+    }
+
+    #[test]
+    fn test_report_json_adds_recent_errors_to_the_status() {
+        let mut status = RuntimeStatus::new();
+        status.mark_ip_check_success(&"203.0.113.10".parse().unwrap());
+        status.mark_domain_result("example.com", "unchanged", false);
+        let error = HistoryEntry {
+            timestamp: "2024-03-01T12:00:00+00:00".to_string(),
+            domain: "example.com".to_string(),
+            old_ip: Some("203.0.113.9".to_string()),
+            new_ip: "203.0.113.10".to_string(),
+            result: "failed".to_string(),
+            error: Some("rate limited".to_string()),
+        };
+
+        let value = status.report_json(&[&error]).unwrap();
+        assert_eq!(value["last_public_ip"], "203.0.113.10");
+        assert_eq!(value["domains"]["example.com"]["last_status"], "unchanged");
+        assert_eq!(value["recent_errors"][0]["domain"], "example.com");
+        assert_eq!(value["recent_errors"][0]["error"], "rate limited");
+        assert_eq!(
+            status.report_json(&[]).unwrap()["recent_errors"],
+            serde_json::json!([])
+        );
+    }
+
     #[test]
     fn test_runtime_status_writes_json_file() {
         let _guard = crate::test_support::global_lock();
@@ -0,0 +1,140 @@
+use crate::errors::FlareSyncError;
+use crate::status::RuntimeStatus;
+use chrono::{DateTime, Local, TimeDelta};
+use std::fmt::Write;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryPeriod {
+    Daily,
+    Weekly,
+}
+
+impl SummaryPeriod {
+    fn length(self) -> TimeDelta {
+        match self {
+            SummaryPeriod::Daily => TimeDelta::days(1),
+            SummaryPeriod::Weekly => TimeDelta::weeks(1),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SummaryPeriod::Daily => "daily",
+            SummaryPeriod::Weekly => "weekly",
+        }
+    }
+}
+
+impl FromStr for SummaryPeriod {
+    type Err = FlareSyncError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "daily" => Ok(SummaryPeriod::Daily),
+            "weekly" => Ok(SummaryPeriod::Weekly),
+            _ => Err(FlareSyncError::Config(
+                "SUMMARY_INTERVAL must be daily or weekly".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SummaryReport {
+    period: SummaryPeriod,
+    since: DateTime<Local>,
+    cycles: u64,
+    ip_changes: u64,
+    update_failures: u64,
+    ip_check_failures: u64,
+}
+
+impl SummaryReport {
+    pub fn new(period: SummaryPeriod, now: DateTime<Local>) -> Self {
+        Self {
+            period,
+            since: now,
+            cycles: 0,
+            ip_changes: 0,
+            update_failures: 0,
+            ip_check_failures: 0,
+        }
+    }
+
+    pub fn record_cycle(&mut self) {
+        self.cycles += 1;
+    }
+
+    pub fn record_ip_change(&mut self) {
+        self.ip_changes += 1;
+    }
+
+    pub fn record_update_failure(&mut self) {
+        self.update_failures += 1;
+    }
+
+    pub fn record_ip_check_failure(&mut self) {
+        self.ip_check_failures += 1;
+    }
+
+    pub fn is_due(&self, now: DateTime<Local>) -> bool {
+        now - self.since >= self.period.length()
+    }
+
+    pub fn render(&self, status: &RuntimeStatus) -> String {
+        let mut report = format!(
+            "FlareSync {} summary since {}: {} cycles, {} IP changes, {} update failures, {} IP check failures. Current IP: {}",
+            self.period.label(),
+            self.since.to_rfc3339(),
+            self.cycles,
+            self.ip_changes,
+            self.update_failures,
+            self.ip_check_failures,
+            status.last_public_ip.as_deref().unwrap_or("unknown"),
+        );
+        for (domain, domain_status) in &status.domains {
+            let _ = write!(report, "\n  {}: {}", domain, domain_status.last_status);
+            if let Some(last_updated_at) = &domain_status.last_updated_at {
+                let _ = write!(report, " (last updated {})", last_updated_at);
+            }
+            if let Some(last_error) = &domain_status.last_error {
+                let _ = write!(report, " - {}", last_error);
+            }
+        }
+        report
+    }
+
+    pub fn reset(&mut self, now: DateTime<Local>) {
+        *self = Self::new(self.period, now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_report_is_due_after_period_and_renders_counts() {
+        let start = Local::now();
+        let mut report = SummaryReport::new(SummaryPeriod::Daily, start);
+        report.record_cycle();
+        report.record_cycle();
+        report.record_ip_change();
+        report.record_update_failure();
+
+        assert!(!report.is_due(start + TimeDelta::hours(23)));
+        assert!(report.is_due(start + TimeDelta::hours(24)));
+
+        let mut status = RuntimeStatus::new();
+        status.last_public_ip = Some("203.0.113.7".to_string());
+        status.mark_domain_result("example.com", "updated", true);
+        let rendered = report.render(&status);
+        assert!(rendered.contains("2 cycles, 1 IP changes, 1 update failures, 0 IP check failures"));
+        assert!(rendered.contains("Current IP: 203.0.113.7"));
+        assert!(rendered.contains("\n  example.com: updated (last updated "));
+
+        report.reset(start + TimeDelta::hours(24));
+        assert!(!report.is_due(start + TimeDelta::hours(25)));
+    }
+}
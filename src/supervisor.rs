@@ -0,0 +1,78 @@
+use crate::correlation;
+use crate::errors::FlareSyncError;
+use std::any::Any;
+use std::future::Future;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tracing::Instrument;
+
+/// Runs `future` on its own task so that a panic or a hang inside it becomes an error for
+/// the caller instead of aborting or stalling the loop. The task keeps the current tracing
+/// span and cycle ID, and is aborted when it times out or the returned future is dropped.
+pub async fn supervise<T, F>(name: &str, timeout: Duration, future: F) -> Result<T, FlareSyncError>
+where
+    T: Send + 'static,
+    F: Future<Output = Result<T, FlareSyncError>> + Send + 'static,
+{
+    let future = future.in_current_span();
+    let mut tasks = JoinSet::new();
+    match correlation::current_cycle_id() {
+        Some(cycle_id) => tasks.spawn(correlation::with_cycle_id(cycle_id, future)),
+        None => tasks.spawn(future),
+    };
+
+    match tokio::time::timeout(timeout, tasks.join_next()).await {
+        Ok(Some(Ok(result))) => result,
+        Ok(Some(Err(e))) if e.is_panic() => Err(FlareSyncError::Io(std::io::Error::other(
+            format!("{} panicked: {}", name, panic_message(e.into_panic())),
+        ))),
+        Ok(Some(Err(e))) => Err(FlareSyncError::Io(std::io::Error::other(format!(
+            "{} was cancelled: {}",
+            name, e
+        )))),
+        Ok(None) => unreachable!("the task set holds one task"),
+        Err(_) => Err(FlareSyncError::Timeout(format!(
+            "{} did not finish within {}s",
+            name,
+            timeout.as_secs()
+        ))),
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload.downcast_ref::<&str>().map_or_else(
+            || "unknown panic".to_string(),
+            |message| message.to_string(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_supervise_turns_panics_and_hangs_into_errors() {
+        let result = supervise("ok", Duration::from_secs(1), async { Ok(7) }).await;
+        assert_eq!(result.unwrap(), 7);
+
+        let result: Result<(), _> =
+            supervise("update for a.example.com", Duration::from_secs(1), async {
+                panic!("bad response")
+            })
+            .await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "IO error: update for a.example.com panicked: bad response"
+        );
+
+        let result: Result<(), _> = supervise("stuck", Duration::from_millis(10), async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(())
+        })
+        .await;
+        assert!(matches!(result, Err(FlareSyncError::Timeout(_))));
+    }
+}
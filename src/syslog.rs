@@ -0,0 +1,300 @@
+use crate::errors::FlareSyncError;
+use std::io::{self, Write};
+use std::net::{TcpStream, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use tracing::{Level, Metadata};
+use tracing_subscriber::fmt::MakeWriter;
+
+const APP_NAME: &str = "flaresync";
+#[cfg(unix)]
+const LOCAL_SOCKET_PATH: &str = "/dev/log";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyslogTarget {
+    Local,
+    Udp(String),
+    Tcp(String),
+}
+
+impl FromStr for SyslogTarget {
+    type Err = FlareSyncError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("local") {
+            return Ok(SyslogTarget::Local);
+        }
+        if let Some(address) = value.strip_prefix("udp://") {
+            return Ok(SyslogTarget::Udp(address.to_string()));
+        }
+        if let Some(address) = value.strip_prefix("tcp://") {
+            return Ok(SyslogTarget::Tcp(address.to_string()));
+        }
+        Err(FlareSyncError::Config(
+            "SYSLOG_ADDRESS must be local, udp://host:port, or tcp://host:port".to_string(),
+        ))
+    }
+}
+
+pub fn parse_facility(value: &str) -> Result<u8, FlareSyncError> {
+    let facility = match value.trim().to_ascii_lowercase().as_str() {
+        "user" => 1,
+        "daemon" => 3,
+        "local0" => 16,
+        "local1" => 17,
+        "local2" => 18,
+        "local3" => 19,
+        "local4" => 20,
+        "local5" => 21,
+        "local6" => 22,
+        "local7" => 23,
+        _ => {
+            return Err(FlareSyncError::Config(
+                "SYSLOG_FACILITY must be user, daemon, or local0-local7".to_string(),
+            ))
+        }
+    };
+    Ok(facility)
+}
+
+enum Transport {
+    #[cfg(unix)]
+    Local(UnixDatagram),
+    Udp(UdpSocket),
+    Tcp {
+        address: String,
+        stream: Option<TcpStream>,
+    },
+}
+
+impl Transport {
+    fn connect(target: &SyslogTarget) -> io::Result<Self> {
+        match target {
+            #[cfg(unix)]
+            SyslogTarget::Local => {
+                let socket = UnixDatagram::unbound()?;
+                socket.connect(LOCAL_SOCKET_PATH)?;
+                Ok(Transport::Local(socket))
+            }
+            #[cfg(not(unix))]
+            SyslogTarget::Local => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "local syslog is only available on Unix",
+            )),
+            SyslogTarget::Udp(address) => {
+                let socket = UdpSocket::bind("0.0.0.0:0")?;
+                socket.connect(address)?;
+                Ok(Transport::Udp(socket))
+            }
+            SyslogTarget::Tcp(address) => Ok(Transport::Tcp {
+                address: address.clone(),
+                stream: Some(TcpStream::connect(address)?),
+            }),
+        }
+    }
+
+    fn send(&mut self, message: &str) -> io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Transport::Local(socket) => socket.send(message.as_bytes()).map(|_| ()),
+            Transport::Udp(socket) => socket.send(message.as_bytes()).map(|_| ()),
+            Transport::Tcp { address, stream } => {
+                let frame = format!("{} {}", message.len(), message);
+                if let Some(connected) = stream {
+                    if connected.write_all(frame.as_bytes()).is_ok() {
+                        return Ok(());
+                    }
+                }
+                let mut reconnected = TcpStream::connect(address.as_str())?;
+                let result = reconnected.write_all(frame.as_bytes());
+                *stream = Some(reconnected);
+                result
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SyslogMakeWriter {
+    target: SyslogTarget,
+    facility: u8,
+    hostname: String,
+    transport: Arc<Mutex<Transport>>,
+}
+
+impl SyslogMakeWriter {
+    pub fn connect(target: SyslogTarget, facility: u8) -> Result<Self, FlareSyncError> {
+        let transport = Transport::connect(&target).map_err(|e| {
+            FlareSyncError::Config(format!("Failed to connect to syslog {:?}: {}", target, e))
+        })?;
+        Ok(Self {
+            target,
+            facility,
+            hostname: local_hostname(),
+            transport: Arc::new(Mutex::new(transport)),
+        })
+    }
+}
+
+impl<'a> MakeWriter<'a> for SyslogMakeWriter {
+    type Writer = SyslogWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.writer_for_level(Level::INFO)
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        self.writer_for_level(*meta.level())
+    }
+}
+
+impl SyslogMakeWriter {
+    fn writer_for_level(&self, level: Level) -> SyslogWriter {
+        SyslogWriter {
+            owner: self.clone(),
+            priority: self.facility * 8 + severity(level),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+pub struct SyslogWriter {
+    owner: SyslogMakeWriter,
+    priority: u8,
+    buffer: Vec<u8>,
+}
+
+impl Write for SyslogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for SyslogWriter {
+    fn drop(&mut self) {
+        let text = String::from_utf8_lossy(&self.buffer);
+        let text = text.trim_end();
+        if text.is_empty() {
+            return;
+        }
+
+        let now = chrono::Local::now();
+        let message = match self.owner.target {
+            SyslogTarget::Local => format_rfc3164(self.priority, &now, text),
+            _ => format_rfc5424(self.priority, &now, &self.owner.hostname, text),
+        };
+        if let Ok(mut transport) = self.owner.transport.lock() {
+            if let Err(e) = transport.send(&message) {
+                eprintln!("Failed to send log line to syslog: {}", e);
+            }
+        }
+    }
+}
+
+fn severity(level: Level) -> u8 {
+    match level {
+        Level::ERROR => 3,
+        Level::WARN => 4,
+        Level::INFO => 6,
+        Level::DEBUG | Level::TRACE => 7,
+    }
+}
+
+fn format_rfc5424(
+    priority: u8,
+    timestamp: &chrono::DateTime<chrono::Local>,
+    hostname: &str,
+    message: &str,
+) -> String {
+    format!(
+        "<{}>1 {} {} {} {} - - {}",
+        priority,
+        timestamp.to_rfc3339_opts(chrono::SecondsFormat::Micros, false),
+        hostname,
+        APP_NAME,
+        std::process::id(),
+        message
+    )
+}
+
+fn format_rfc3164(
+    priority: u8,
+    timestamp: &chrono::DateTime<chrono::Local>,
+    message: &str,
+) -> String {
+    format!(
+        "<{}>{} {}[{}]: {}",
+        priority,
+        timestamp.format("%b %e %H:%M:%S"),
+        APP_NAME,
+        std::process::id(),
+        message
+    )
+}
+
+fn local_hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty() && !name.contains(' '))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syslog_target_from_str() {
+        assert_eq!(
+            "local".parse::<SyslogTarget>().unwrap(),
+            SyslogTarget::Local
+        );
+        assert_eq!(
+            "udp://logs.lan:514".parse::<SyslogTarget>().unwrap(),
+            SyslogTarget::Udp("logs.lan:514".to_string())
+        );
+        assert_eq!(
+            "tcp://10.0.0.5:601".parse::<SyslogTarget>().unwrap(),
+            SyslogTarget::Tcp("10.0.0.5:601".to_string())
+        );
+        assert!("logs.lan:514".parse::<SyslogTarget>().is_err());
+    }
+
+    #[test]
+    fn test_format_rfc5424_header() {
+        let timestamp = chrono::Local::now();
+        let message = format_rfc5424(3 * 8 + 6, &timestamp, "router", "IP updated");
+        assert!(message.starts_with("<30>1 "));
+        assert!(message.contains(" router flaresync "));
+        assert!(message.ends_with(" - - IP updated"));
+    }
+
+    #[test]
+    fn test_syslog_writer_sends_udp_datagram_with_level_severity() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = receiver.local_addr().unwrap();
+        let make_writer =
+            SyslogMakeWriter::connect(SyslogTarget::Udp(address.to_string()), 3).unwrap();
+
+        {
+            let mut writer = make_writer.writer_for_level(Level::ERROR);
+            writer.write_all(b"Failed to update example.com\n").unwrap();
+        }
+
+        let mut buffer = [0u8; 512];
+        let len = receiver.recv(&mut buffer).unwrap();
+        let message = String::from_utf8_lossy(&buffer[..len]);
+        assert!(message.starts_with("<27>1 "));
+        assert!(message.ends_with("Failed to update example.com"));
+    }
+}
@@ -0,0 +1,111 @@
+use std::path::PathBuf;
+
+pub const SERVICE_NAME: &str = "flaresync.service";
+pub const TIMER_NAME: &str = "flaresync.timer";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnitOptions {
+    pub executable: PathBuf,
+    pub working_directory: PathBuf,
+    pub config: Option<PathBuf>,
+    pub profile: Option<String>,
+    pub user: Option<String>,
+    /// Run `update` from a timer every this many minutes instead of the `run` loop.
+    pub timer_minutes: Option<u64>,
+}
+
+fn quote(arg: &str) -> String {
+    if arg.contains(char::is_whitespace) || arg.contains('"') {
+        format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        arg.to_string()
+    }
+}
+
+pub fn service_unit(options: &UnitOptions) -> String {
+    let mut command = vec![quote(&options.executable.display().to_string())];
+    if let Some(config) = &options.config {
+        command.push("--config".to_string());
+        command.push(quote(&config.display().to_string()));
+    }
+    if let Some(profile) = &options.profile {
+        command.push("--profile".to_string());
+        command.push(quote(profile));
+    }
+    let oneshot = options.timer_minutes.is_some();
+    command.push(if oneshot { "update" } else { "run" }.to_string());
+
+    let mut unit = String::from(
+        "[Unit]\n\
+         Description=FlareSync Cloudflare DNS updater\n\
+         Wants=network-online.target\n\
+         After=network-online.target\n\
+         \n\
+         [Service]\n",
+    );
+    if oneshot {
+        unit.push_str("Type=oneshot\nSuccessExitStatus=10\n");
+    } else {
+        unit.push_str("Type=notify\nWatchdogSec=10min\nRestart=on-failure\nRestartSec=30\n");
+    }
+    unit.push_str(&format!(
+        "WorkingDirectory={}\nExecStart={}\n",
+        quote(&options.working_directory.display().to_string()),
+        command.join(" ")
+    ));
+    if let Some(user) = &options.user {
+        unit.push_str(&format!("User={}\n", user));
+    }
+    unit.push_str("NoNewPrivileges=true\nPrivateTmp=true\n");
+    if !oneshot {
+        unit.push_str("\n[Install]\nWantedBy=multi-user.target\n");
+    }
+    unit
+}
+
+pub fn timer_unit(minutes: u64) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Run FlareSync every {minutes} minutes\n\
+         \n\
+         [Timer]\n\
+         OnBootSec=1min\n\
+         OnUnitActiveSec={minutes}min\n\
+         Unit={SERVICE_NAME}\n\
+         \n\
+         [Install]\n\
+         WantedBy=timers.target\n"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timer_service_runs_update_with_config() {
+        let options = UnitOptions {
+            executable: PathBuf::from("/usr/local/bin/flaresync"),
+            working_directory: PathBuf::from("/var/lib/flare sync"),
+            config: Some(PathBuf::from("/etc/flaresync.toml")),
+            profile: None,
+            user: Some("flaresync".to_string()),
+            timer_minutes: Some(5),
+        };
+        let unit = service_unit(&options);
+        assert!(unit.contains("Type=oneshot\nSuccessExitStatus=10\n"));
+        assert!(unit.contains("WorkingDirectory=\"/var/lib/flare sync\"\n"));
+        assert!(unit
+            .contains("ExecStart=/usr/local/bin/flaresync --config /etc/flaresync.toml update\n"));
+        assert!(unit.contains("User=flaresync\n"));
+        assert!(!unit.contains("[Install]"));
+        assert!(timer_unit(5).contains("OnUnitActiveSec=5min\n"));
+
+        let daemon = service_unit(&UnitOptions {
+            timer_minutes: None,
+            ..options
+        });
+        assert!(daemon.contains("Type=notify\nWatchdogSec=10min\nRestart=on-failure\n"));
+        assert!(daemon.ends_with("WantedBy=multi-user.target\n"));
+    }
+}
@@ -0,0 +1,130 @@
+use crate::errors::FlareSyncError;
+use opentelemetry::global;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing::Subscriber;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+pub struct Telemetry {
+    tracer_provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider,
+}
+
+impl Telemetry {
+    pub fn init(endpoint: &str) -> Result<Self, FlareSyncError> {
+        let endpoint = endpoint.trim_end_matches('/');
+        let resource = Resource::builder().with_service_name("flaresync").build();
+
+        let span_exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/traces", endpoint))
+            .build()
+            .map_err(|e| FlareSyncError::Config(format!("Invalid OTLP trace exporter: {}", e)))?;
+        let tracer_provider = SdkTracerProvider::builder()
+            .with_batch_exporter(span_exporter)
+            .with_resource(resource.clone())
+            .build();
+
+        let metric_exporter = MetricExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/metrics", endpoint))
+            .build()
+            .map_err(|e| FlareSyncError::Config(format!("Invalid OTLP metric exporter: {}", e)))?;
+        let meter_provider = SdkMeterProvider::builder()
+            .with_periodic_exporter(metric_exporter)
+            .with_resource(resource)
+            .build();
+        global::set_meter_provider(meter_provider.clone());
+
+        Ok(Self {
+            tracer_provider,
+            meter_provider,
+        })
+    }
+
+    pub fn layer<S>(&self) -> impl Layer<S>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer().with_tracer(self.tracer_provider.tracer("flaresync"))
+    }
+
+    pub fn shutdown(&self) {
+        let _ = self.tracer_provider.shutdown();
+        let _ = self.meter_provider.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::mpsc;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// An OTLP endpoint that accepts every request and reports its path.
+    fn collector() -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/", listener.local_addr().unwrap());
+        let (paths, received) = mpsc::channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let paths = paths.clone();
+                std::thread::spawn(move || answer(stream.unwrap(), paths));
+            }
+        });
+        (endpoint, received)
+    }
+
+    fn answer(mut stream: TcpStream, paths: mpsc::Sender<String>) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request_line = String::new();
+        while reader.read_line(&mut request_line).unwrap_or(0) > 0 {
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some(value) = header.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            reader.read_exact(&mut vec![0; content_length]).unwrap();
+            let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+            paths.send(path.to_string()).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .unwrap();
+            request_line.clear();
+        }
+    }
+
+    #[test]
+    fn test_spans_and_metrics_are_exported_to_the_endpoint() {
+        let (endpoint, received) = collector();
+        let telemetry = Telemetry::init(&endpoint).unwrap();
+        let subscriber = tracing_subscriber::registry().with(telemetry.layer());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _cycle = tracing::info_span!("cycle", cycle = 1).entered();
+            tracing::info!("Current public IP: 203.0.113.1");
+        });
+        global::meter("flaresync")
+            .u64_counter("flaresync.test.exports")
+            .build()
+            .add(1, &[]);
+        telemetry.shutdown();
+
+        let mut paths: Vec<String> = received.try_iter().collect();
+        paths.sort();
+        paths.dedup();
+        assert_eq!(paths, ["/v1/metrics", "/v1/traces"]);
+    }
+}
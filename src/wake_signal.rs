@@ -0,0 +1,62 @@
+/// SIGHUP and SIGUSR1 wake the sync loop for an immediate check. The handlers are
+/// installed once at startup so the signals never fall back to their default action.
+#[cfg(unix)]
+pub struct WakeSignal {
+    hangup: tokio::signal::unix::Signal,
+    user1: tokio::signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl WakeSignal {
+    pub fn install() -> std::io::Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        Ok(Self {
+            hangup: signal(SignalKind::hangup())?,
+            user1: signal(SignalKind::user_defined1())?,
+        })
+    }
+
+    pub async fn recv(&mut self) -> &'static str {
+        tokio::select! {
+            _ = self.hangup.recv() => "SIGHUP",
+            _ = self.user1.recv() => "SIGUSR1",
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub struct WakeSignal;
+
+#[cfg(not(unix))]
+impl WakeSignal {
+    pub fn install() -> std::io::Result<Self> {
+        Ok(Self)
+    }
+
+    pub async fn recv(&mut self) -> &'static str {
+        std::future::pending().await
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_sighup_and_sigusr1_wake_the_loop() {
+        let mut wake_signal = WakeSignal::install().unwrap();
+        let pid = std::process::id().to_string();
+
+        for (flag, name) in [("-HUP", "SIGHUP"), ("-USR1", "SIGUSR1")] {
+            let sent = Command::new("kill").args([flag, &pid]).status().unwrap();
+            assert!(sent.success());
+            let received = tokio::time::timeout(Duration::from_secs(5), wake_signal.recv())
+                .await
+                .unwrap();
+            assert_eq!(received, name);
+        }
+    }
+}
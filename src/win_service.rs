@@ -0,0 +1,165 @@
+use crate::errors::FlareSyncError;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::watch;
+use windows_service::service::{
+    ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
+    ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+use windows_service::service_dispatcher;
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+
+pub const SERVICE_NAME: &str = "FlareSync";
+const DISPLAY_NAME: &str = "FlareSync Cloudflare DNS updater";
+const SERVICE_TYPE: ServiceType = ServiceType::OWN_PROCESS;
+
+type ServiceEntry = Box<dyn FnOnce() -> Result<(), String> + Send>;
+
+static ENTRY: Mutex<Option<ServiceEntry>> = Mutex::new(None);
+static RUNNING_AS_SERVICE: AtomicBool = AtomicBool::new(false);
+static STOP: OnceLock<watch::Sender<bool>> = OnceLock::new();
+
+fn stop_sender() -> &'static watch::Sender<bool> {
+    STOP.get_or_init(|| watch::channel(false).0)
+}
+
+/// Resolves once the service control manager has asked the service to stop.
+pub async fn stop_requested() {
+    let mut stop = stop_sender().subscribe();
+    let _ = stop.wait_for(|stopping| *stopping).await;
+}
+
+/// Whether this process was started by the service control manager, in which case
+/// there is no console to log to.
+pub fn is_running_as_service() -> bool {
+    RUNNING_AS_SERVICE.load(Ordering::Relaxed)
+}
+
+fn service_error(action: &str, e: windows_service::Error) -> FlareSyncError {
+    FlareSyncError::Io(std::io::Error::other(format!(
+        "Failed to {} the {} service: {}",
+        action, SERVICE_NAME, e
+    )))
+}
+
+/// Hands the current thread to the service control manager and runs `entry` once the
+/// service has started. Returns after the service stopped.
+pub fn run(entry: ServiceEntry) -> Result<(), FlareSyncError> {
+    *ENTRY.lock().unwrap() = Some(entry);
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+        .map_err(|e| service_error("start the dispatcher for", e))
+}
+
+windows_service::define_windows_service!(ffi_service_main, service_main);
+
+fn service_main(_arguments: Vec<OsString>) {
+    RUNNING_AS_SERVICE.store(true, Ordering::Relaxed);
+    let handler = |control: ServiceControl| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            stop_sender().send_replace(true);
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    };
+    let Ok(status_handle) = service_control_handler::register(SERVICE_NAME, handler) else {
+        return;
+    };
+    let set_state = |state, controls_accepted, exit_code| {
+        let _ = status_handle.set_service_status(ServiceStatus {
+            service_type: SERVICE_TYPE,
+            current_state: state,
+            controls_accepted,
+            exit_code,
+            checkpoint: 0,
+            wait_hint: Duration::default(),
+            process_id: None,
+        });
+    };
+
+    set_state(
+        ServiceState::Running,
+        ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+        ServiceExitCode::NO_ERROR,
+    );
+    let result = match ENTRY.lock().unwrap().take() {
+        Some(entry) => entry(),
+        None => Err("the service entry point was not set".to_string()),
+    };
+    let exit_code = match result {
+        Ok(()) => ServiceExitCode::NO_ERROR,
+        Err(e) => {
+            tracing::error!("FlareSync service stopped: {}", e);
+            ServiceExitCode::ServiceSpecific(1)
+        }
+    };
+    set_state(
+        ServiceState::Stopped,
+        ServiceControlAccept::empty(),
+        exit_code,
+    );
+}
+
+/// Registers FlareSync as an automatically started service that runs this executable
+/// with `launch_arguments`.
+pub fn install(
+    executable: PathBuf,
+    launch_arguments: Vec<OsString>,
+    start: bool,
+) -> Result<(), FlareSyncError> {
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )
+    .map_err(|e| service_error("connect to the service manager to install", e))?;
+    let info = ServiceInfo {
+        name: OsString::from(SERVICE_NAME),
+        display_name: OsString::from(DISPLAY_NAME),
+        service_type: SERVICE_TYPE,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: executable,
+        launch_arguments,
+        dependencies: vec![],
+        account_name: None,
+        account_password: None,
+    };
+    let service = manager
+        .create_service(
+            &info,
+            ServiceAccess::CHANGE_CONFIG | ServiceAccess::START | ServiceAccess::QUERY_STATUS,
+        )
+        .map_err(|e| service_error("install", e))?;
+    service
+        .set_description("Keeps Cloudflare DNS A records pointed at this network's public IP")
+        .map_err(|e| service_error("describe", e))?;
+    if start {
+        service
+            .start::<&str>(&[])
+            .map_err(|e| service_error("start", e))?;
+    }
+    Ok(())
+}
+
+/// Stops the service if it is running and marks it for deletion.
+pub fn uninstall() -> Result<(), FlareSyncError> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+        .map_err(|e| service_error("connect to the service manager to uninstall", e))?;
+    let service = manager
+        .open_service(
+            SERVICE_NAME,
+            ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+        )
+        .map_err(|e| service_error("open", e))?;
+    let status = service
+        .query_status()
+        .map_err(|e| service_error("query", e))?;
+    if status.current_state != ServiceState::Stopped {
+        service.stop().map_err(|e| service_error("stop", e))?;
+    }
+    service.delete().map_err(|e| service_error("delete", e))
+}